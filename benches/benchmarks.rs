@@ -1,5 +1,9 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use auradb::{AuraEngine, Engine, EngineBuilder};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use auradb::config::{CompressionAlgorithm, ValueLogConfig, WalConfig, WalSyncPolicy};
+use auradb::vlog::{VlogReader, VlogWriter};
+use auradb::wal::{self, WalRecord, WalWriter};
+use auradb::{EngineBuilder, Value};
+use std::time::Instant;
 use tempfile::TempDir;
 
 fn basic_operations_benchmark(c: &mut Criterion) {
@@ -30,16 +34,169 @@ fn basic_operations_benchmark(c: &mut Criterion) {
         });
     });
     
+    // get copies the value out of the cache, get_shared hands out the
+    // cached allocation itself
+    engine.put_str("large_key", &"x".repeat(1024 * 1024)).unwrap();
+    c.bench_function("get_large", |b| {
+        b.iter(|| {
+            engine.get_bytes(b"large_key").unwrap();
+        });
+    });
+
+    c.bench_function("get_large_shared", |b| {
+        b.iter(|| {
+            engine.get_shared(b"large_key").unwrap();
+        });
+    });
+    
     c.bench_function("batch_write", |b| {
-        let mut batch = auradb::Batch::new();
-        for i in 0..100 {
-            batch.put(format!("batch_key_{}", i), format!("batch_value_{}", i));
-        }
+        let batch: Vec<(Vec<u8>, Vec<u8>)> = (0..100)
+            .map(|i| (format!("batch_key_{}", i).into_bytes(), format!("batch_value_{}", i).into_bytes()))
+            .collect();
         b.iter(|| {
             engine.write_batch(&batch).unwrap();
         });
     });
 }
 
-criterion_group!(benches, basic_operations_benchmark);
+/// Synchronous WAL appends with small files, so many writes cross a
+/// rotation boundary; compares fresh files against preallocated, recycled ones
+fn wal_rotation_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wal_rotation");
+    for (name, preallocate, recycle_wal) in [("fresh", false, false), ("preallocated_recycled", true, true)] {
+        let temp_dir = TempDir::new().unwrap();
+        let config = WalConfig {
+            wal_path: temp_dir.path().to_path_buf(),
+            max_file_size: 256 * 1024,
+            async_writes: false,
+            preallocate,
+            recycle_wal,
+            ..Default::default()
+        };
+        let mut writer = WalWriter::new(config.clone()).unwrap();
+        let record = WalRecord::Put {
+            key: b"key".to_vec(),
+            value: vec![0u8; 1024],
+            sequence: 0,
+            timestamp: 0,
+        };
+
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                let log_number = writer.log_number();
+                writer.write_record(&record).unwrap();
+                if writer.log_number() != log_number {
+                    wal::retire_logs_before(&config, writer.log_number()).unwrap();
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Synchronous WAL appends of compressible 16KB records with record
+/// compression off and on
+fn wal_compression_benchmark(c: &mut Criterion) {
+    const VALUE_SIZE: usize = 16 * 1024;
+
+    let mut group = c.benchmark_group("wal_compression");
+    group.throughput(Throughput::Bytes(VALUE_SIZE as u64));
+    for (name, compress_records) in [("off", None), ("lz4", Some(CompressionAlgorithm::Lz4))] {
+        let temp_dir = TempDir::new().unwrap();
+        let config = WalConfig {
+            wal_path: temp_dir.path().to_path_buf(),
+            async_writes: false,
+            sync_policy: WalSyncPolicy::Manual,
+            compress_records,
+            ..Default::default()
+        };
+        let mut writer = WalWriter::new(config).unwrap();
+        let value: Vec<u8> = br#"{"user":"auradb","tags":["a","b"],"score":42}"#.iter().copied().cycle().take(VALUE_SIZE).collect();
+        let record = WalRecord::Put {
+            key: b"key".to_vec(),
+            value,
+            sequence: 0,
+            timestamp: 0,
+        };
+
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| writer.write_record(&record).unwrap());
+        });
+        writer.close().unwrap();
+    }
+    group.finish();
+}
+
+/// `put_bytes` throughput with writers on disjoint keys, to show how
+/// writes scale (or contend) across threads
+fn concurrent_put_benchmark(c: &mut Criterion) {
+    const OPS_PER_ITER: u64 = 1600;
+
+    let mut group = c.benchmark_group("concurrent_put");
+    group.throughput(Throughput::Elements(OPS_PER_ITER));
+    for threads in [1u64, 4, 16] {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, &threads| {
+            b.iter_custom(|iters| {
+                let started = Instant::now();
+                for iter in 0..iters {
+                    std::thread::scope(|scope| {
+                        for thread in 0..threads {
+                            let engine = &engine;
+                            scope.spawn(move || {
+                                for i in 0..OPS_PER_ITER / threads {
+                                    let key = format!("t{}_{}_{}", thread, iter, i);
+                                    engine.put_bytes(key.as_bytes(), b"value").unwrap();
+                                }
+                            });
+                        }
+                    });
+                }
+                started.elapsed()
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Uncached reads of 1MB value log values with and without checksum
+/// verification
+fn vlog_verify_benchmark(c: &mut Criterion) {
+    const VALUE_SIZE: usize = 1024 * 1024;
+
+    let temp_dir = TempDir::new().unwrap();
+    let config = ValueLogConfig {
+        vlog_path: temp_dir.path().to_path_buf(),
+        ..Default::default()
+    };
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let vptr = {
+        let _guard = runtime.enter();
+        let mut writer = VlogWriter::new(config).unwrap();
+        let vptr = writer.write_value_sync(Value::new(vec![7u8; VALUE_SIZE])).unwrap();
+        runtime.block_on(writer.close()).unwrap();
+        vptr
+    };
+
+    let mut group = c.benchmark_group("vlog_read_1mb");
+    group.throughput(Throughput::Bytes(VALUE_SIZE as u64));
+    for (name, verify) in [("verified", true), ("unverified", false)] {
+        let mut reader = VlogReader::new(temp_dir.path().to_path_buf()).unwrap();
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| reader.read_value_with_verify(&vptr, verify).unwrap());
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    basic_operations_benchmark,
+    wal_rotation_benchmark,
+    wal_compression_benchmark,
+    concurrent_put_benchmark,
+    vlog_verify_benchmark
+);
 criterion_main!(benches);
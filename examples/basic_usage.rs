@@ -1,4 +1,4 @@
-use auradb::{EngineBuilder, AuraEngine};
+use auradb::EngineBuilder;
 use tempfile::tempdir;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -54,14 +54,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // Batch operations
     println!("\n📦 Performing batch operations...");
-    let mut batch = auradb::Batch::new();
-    for i in 1..=5 {
-        batch.add(auradb::storage::Entry::new(
-            auradb::storage::Key::new(format!("batch_key_{}", i).into_bytes()),
-            auradb::storage::Value::new(format!("batch_value_{}", i).into_bytes()),
-            i as u64,
-        ));
-    }
+    let batch: Vec<(Vec<u8>, Vec<u8>)> = (1..=5)
+        .map(|i| {
+            (
+                format!("batch_key_{}", i).into_bytes(),
+                format!("batch_value_{}", i).into_bytes(),
+            )
+        })
+        .collect();
     engine.write_batch(&batch)?;
     println!("   ✅ Wrote batch with {} operations", batch.len());
     
@@ -1,9 +1,28 @@
-use crate::{error::Result, storage::{Key, Value, Batch, Range}};
+use crate::{error::{Corruption, Error, Result}, storage::{Key, Value, ValuePointer, Entry, Batch, Range, OpType, ChangeEvent, RangeTombstone}};
+use crate::cache::UnifiedCache;
+use crate::metrics::{MetricsCollector, MetricsSnapshot, Operation, OperationLatencies, WriteSizes};
+use crate::comparator::Comparator;
+use crate::compactor::CompactionProgress;
 use crate::config::Config;
-use std::path::PathBuf;
+use crate::filter::PrefixExtractor;
+use crate::io::FileAccess;
+use crate::lsm::{LsmTree, OnVisible, TreeSnapshot, WriteTicket};
+use crate::export::{SnapshotReader, SnapshotWriter};
+use crate::manifest::{MANIFEST_FILE, MANIFEST_VERSION};
+use crate::repair::{RepairOptions, RepairReport};
+use crate::secondary::{self, SecondaryIndex};
+use crate::vlog::{self, ValueStream, VlogReader, VlogWriter};
+use crate::wal::{GroupCommit, WalInfo, WalReader, WalRecord, WalWriter};
+use crate::blocking::SyncEngine;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use std::sync::Arc;
-use parking_lot::RwLock;
+use std::time::Duration;
+use parking_lot::{Mutex, MutexGuard, RwLock};
+use tokio::sync::{broadcast, oneshot};
+use crate::metrics::in_timed_span;
+use tracing::{field, info, trace_span, warn, Span};
 
 /// Main engine trait defining the core KV operations
 #[async_trait::async_trait]
@@ -20,13 +39,14 @@ pub trait Engine: Send + Sync {
     /// Scan a range of keys
     async fn scan(&self, range: Range) -> Result<Vec<(Key, Value)>>;
     
-    /// Write a batch of operations
+    /// Write a batch of operations atomically: as one WAL record, applied
+    /// to the memtable at once, and fsynced first if `batch.sync` is set
     async fn write_batch(&self, batch: &Batch) -> Result<()>;
     
     /// Create a snapshot
     async fn snapshot(&self) -> Result<Snapshot>;
     
-    /// Close the engine
+    /// Close the engine, flushing its memtables to SST files
     async fn close(&self) -> Result<()>;
 }
 
@@ -43,16 +63,77 @@ impl EngineBuilder {
         }
     }
     
-    /// Set the database path
+    /// Set the database path (WAL and value log live underneath it)
     pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Self {
-        self.config.db_path = path.into();
+        self.config = self.config.with_db_path(path.into());
+        self
+    }
+
+    /// Apply `options`: the database path and whether to create a missing
+    /// database or refuse an existing one
+    pub fn options(mut self, options: Options) -> Self {
+        self.config = self
+            .config
+            .with_db_path(options.path)
+            .with_open_flags(options.create_if_missing, options.error_if_exists);
+        self
+    }
+
+    /// Create the database if the path holds none (the default); otherwise
+    /// opening such a path fails
+    pub fn create_if_missing(mut self, create: bool) -> Self {
+        self.config.create_if_missing = create;
+        self
+    }
+
+    /// Fail to open a path that already holds a database
+    pub fn error_if_exists(mut self, error: bool) -> Self {
+        self.config.error_if_exists = error;
+        self
+    }
+
+    /// Set the key comparator used by the memtable, scans and SSTs
+    pub fn comparator(mut self, comparator: Comparator) -> Self {
+        self.config = self.config.with_comparator(comparator);
         self
     }
     
+    /// Set the prefix extractor, enabling prefix Bloom filters for
+    /// `scan_prefix`
+    pub fn prefix_extractor(mut self, extractor: PrefixExtractor) -> Self {
+        self.config = self.config.with_prefix_extractor(extractor);
+        self
+    }
+
+    /// Register a secondary index, maintained by the engine on every write
+    /// and queried with `scan_index`
+    pub fn secondary_index(mut self, index: SecondaryIndex) -> Self {
+        self.config = self.config.with_secondary_index(index);
+        self
+    }
+
+    /// Keep everything in memory, for tests and ephemeral caches. No
+    /// directory is created and no WAL, SST or value log file is written:
+    /// all data stays in the memtable, which never fills up, until
+    /// [`AuraEngine::flush`] drops it or the engine is closed. Nothing
+    /// survives the process, so never use this for data that must.
+    /// Operations that need database files, like checkpoints, SST
+    /// ingestion and manual compaction, fail.
+    pub fn in_memory(mut self) -> Self {
+        self.config = self.config.with_in_memory(true);
+        self
+    }
+
     /// Build the engine
     pub fn build(self) -> Result<AuraEngine> {
         AuraEngine::new(self.config)
     }
+
+    /// Build a blocking engine with its own runtime thread, for callers
+    /// without an async runtime; see [`crate::blocking`]
+    pub fn build_sync(self) -> Result<SyncEngine> {
+        SyncEngine::new(self.config)
+    }
 }
 
 impl Default for EngineBuilder {
@@ -61,221 +142,3957 @@ impl Default for EngineBuilder {
     }
 }
 
+/// A page of scan results and the token to resume after it
+pub type ScanPage = (Vec<(Key, Value)>, Option<Key>);
+
+/// Storage footprint and amplification since the engine was opened
+#[derive(Debug, Clone, Default)]
+pub struct EngineStats {
+    /// Number of SST files
+    pub sst_files: u64,
+    /// Total size of the SST files in bytes
+    pub sst_size: u64,
+    /// Key and value bytes written by users
+    pub user_bytes_written: u64,
+    /// Bytes written to SST files and the value log
+    pub disk_bytes_written: u64,
+    /// Disk bytes written per user byte written
+    pub write_amplification: f64,
+    /// Disk bytes read per byte returned to users
+    pub read_amplification: f64,
+    /// Total SST size per byte of the largest level
+    pub space_amplification: f64,
+    /// Whether writes are stopped, waiting for flushes or compaction
+    pub write_stopped: bool,
+    /// Sealed memtables waiting to be flushed
+    pub immutable_memtables: u64,
+}
+
+/// Approximate memory held by the engine, by component; see
+/// [`AuraEngine::memory_usage`]
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBreakdown {
+    /// Active and sealed memtables
+    pub memtables: usize,
+    /// Cached SST blocks, pinned ones included
+    pub block_cache: usize,
+    /// SST blocks in the compressed block cache tier
+    pub compressed_block_cache: usize,
+    /// Cached value log values
+    pub vlog_cache: usize,
+    /// Bloom filters of live SST files
+    pub filters: usize,
+    /// Learned index models
+    pub learned_indexes: usize,
+}
+
+impl MemoryBreakdown {
+    /// Sum of every component
+    pub fn total(&self) -> usize {
+        self.memtables + self.block_cache + self.compressed_block_cache + self.vlog_cache + self.filters + self.learned_indexes
+    }
+}
+
+/// Outcome of [`AuraEngine::verify_integrity`]
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// SST files checked
+    pub sst_files: u64,
+    /// Value log segments checked
+    pub vlog_segments: u64,
+    /// Value pointers resolved
+    pub value_pointers: u64,
+    /// Damage found, with the file and offset of each
+    pub corruptions: Vec<Corruption>,
+}
+
+impl VerifyReport {
+    /// Whether no corruption was found
+    pub fn is_ok(&self) -> bool {
+        self.corruptions.is_empty()
+    }
+}
+
+/// Per-read options
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    /// Whether to verify value log checksums; `None` uses
+    /// `ValueLogConfig::verify_on_read`. Values served from the value
+    /// log cache were checked, if at all, when they were first read.
+    pub verify_checksums: Option<bool>,
+    /// Whether range iterators yield tombstones, as entries with
+    /// `OpType::Delete`, instead of skipping them. Either way a tombstone
+    /// hides the older versions of its key.
+    pub include_tombstones: bool,
+}
+
+impl ReadOptions {
+    /// Yield (or skip) tombstones from range iterators
+    pub fn with_include_tombstones(mut self, include: bool) -> Self {
+        self.include_tombstones = include;
+        self
+    }
+
+    /// Verify (or skip verifying) value log checksums
+    pub fn with_verify_checksums(mut self, verify: bool) -> Self {
+        self.verify_checksums = Some(verify);
+        self
+    }
+}
+
+/// Pairs written per batch by `import_snapshot`
+const IMPORT_BATCH_SIZE: usize = 1024;
+
+/// Entries whose separated values a snapshot iterator reads from the value
+/// log at a time
+const RESOLVE_BATCH_SIZE: usize = 256;
+
+/// Per-write options
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Keep the value inline in the memtable and SST files even if it
+    /// reaches `ValueLogConfig::separation_threshold`, sparing reads of a
+    /// hot value the value log lookup
+    pub force_inline: bool,
+    /// How long to wait while writes are stopped (every memtable is full,
+    /// or level 0 reached `level0_stop`) before failing with
+    /// `Error::Concurrency("write stalled")`; `None` waits as long as it
+    /// takes
+    pub stall_timeout: Option<Duration>,
+}
+
+impl WriteOptions {
+    /// Keep (or stop keeping) the value inline regardless of its size
+    pub fn with_force_inline(mut self, force_inline: bool) -> Self {
+        self.force_inline = force_inline;
+        self
+    }
+
+    /// Fail instead of waiting longer than `timeout` for writes to resume
+    pub fn with_stall_timeout(mut self, timeout: Duration) -> Self {
+        self.stall_timeout = Some(timeout);
+        self
+    }
+}
+
+/// Events buffered per change subscriber before it starts lagging
+const CHANGE_EVENT_CAPACITY: usize = 1024;
+
 /// Main AuraDB engine implementation
+///
+/// # Read-your-writes
+///
+/// A write is visible to every read on the engine, from any thread, as
+/// soon as the call that made it (`put`, `delete`, `write_batch` and their
+/// variants) returns `Ok`: it is in the memtable by then, and reads check
+/// the memtables before SST files. Visibility does not wait for the WAL.
+/// With `WalConfig::async_writes` the record may still be queued for the
+/// WAL writer thread, and a crash can lose it, but no read in this process
+/// can miss it. Durability is a separate matter; see `WalConfig::sync_policy`
+/// and `Batch::sync`. A batch becomes visible all at once.
+///
+/// # In-memory mode
+///
+/// An engine built with [`EngineBuilder::in_memory`] is not durable at all.
+/// It writes no files, so every write is lost on `flush`, `close` or a
+/// crash. It is meant for tests and caches that can be rebuilt, never for
+/// production data.
 pub struct AuraEngine {
+    /// Shared with the blocking tasks running the async methods
+    state: Arc<EngineState>,
+}
+
+/// Everything an [`AuraEngine`] owns. Dropping the last reference closes
+/// the engine if `close` was not called.
+struct EngineState {
     /// Engine configuration
     config: Config,
-    /// In-memory storage (simplified for now)
-    storage: Arc<RwLock<HashMap<Vec<u8>, Vec<u8>>>>,
+    /// Memtables and SST levels; large values are held as value pointers
+    /// into the value log
+    lsm: Arc<LsmTree>,
+    /// Write-ahead log
+    wal: Mutex<WalWriter>,
+    /// WAL fsyncs writers wait on after releasing `wal`
+    group_commit: Arc<GroupCommit>,
+    /// Value log writer for separated values (taken on close)
+    vlog_writer: Mutex<Option<VlogWriter>>,
+    /// Value log reader used to resolve value pointers, shared with
+    /// snapshots
+    vlog_reader: Arc<Mutex<VlogReader>>,
+    /// Recently read value log values, shared with callers of `get_shared`
+    vlog_cache: Mutex<UnifiedCache>,
+    /// Committed writes, fanned out to `subscribe` receivers
+    changes: broadcast::Sender<ChangeEvent>,
+    /// Latency histograms for each operation type
+    latencies: OperationLatencies,
+    /// Key and value sizes of puts
+    write_sizes: WriteSizes,
     /// Engine status
     closed: Arc<RwLock<bool>>,
 }
 
 impl AuraEngine {
+    /// Another handle on the engine, for work that outlives a borrow of it
+    fn handle(&self) -> AuraEngine {
+        AuraEngine { state: Arc::clone(&self.state) }
+    }
+
     /// Create a new engine instance
     pub fn new(config: Config) -> Result<Self> {
-        // Create directories
-        std::fs::create_dir_all(&config.db_path)
-            .map_err(|e| crate::error::Error::Io(e))?;
-        
-        // Create WAL and value log directories if they don't exist
-        std::fs::create_dir_all(&config.wal.wal_path)
-            .map_err(|e| crate::error::Error::Io(e))?;
-        std::fs::create_dir_all(&config.value_log.vlog_path)
-            .map_err(|e| crate::error::Error::Io(e))?;
+        config.validate().map_err(Error::Config)?;
+
+        if !config.in_memory {
+            Self::prepare_directories(&config)?;
+        }
+        let lsm = LsmTree::open(config.clone())?;
+
+        // An in-memory engine numbers writes but logs none, and keeps every
+        // value inline
+        let (wal, vlog_writer) = if config.in_memory {
+            (WalWriter::in_memory(config.wal.clone()), None)
+        } else {
+            let recovered = Self::recover(&lsm, &config)?;
+            let wal = WalWriter::new(config.wal.clone())?.with_last_sequence(lsm.last_sequence().max(recovered));
+            let (segments, reclaimed) = vlog::remove_empty_segments(&config.value_log.vlog_path)?;
+            if segments > 0 {
+                info!("Removed {} empty value log segments, reclaiming {} bytes", segments, reclaimed);
+            }
+            (wal, Some(VlogWriter::new(config.value_log.clone())?))
+        };
+        let vlog_reader = VlogReader::new(config.value_log.vlog_path.clone())?
+            .with_verify_checksums(config.value_log.verify_on_read)
+            .with_file_access(FileAccess::from_config(&config.performance));
         
-        Ok(Self {
+        let vlog_cache = UnifiedCache::new(config.cache.vlog_cache_size, (&config.cache.eviction_policy).into());
+
+        let state = EngineState {
             config,
-            storage: Arc::new(RwLock::new(HashMap::new())),
+            lsm,
+            group_commit: wal.group_commit(),
+            wal: Mutex::new(wal),
+            vlog_writer: Mutex::new(vlog_writer),
+            vlog_reader: Arc::new(Mutex::new(vlog_reader)),
+            vlog_cache: Mutex::new(vlog_cache),
+            changes: broadcast::channel(CHANGE_EVENT_CAPACITY).0,
+            latencies: OperationLatencies::default(),
+            write_sizes: WriteSizes::default(),
             closed: Arc::new(RwLock::new(false)),
-        })
+        };
+        Ok(Self { state: Arc::new(state) })
     }
-    
-    /// Put a string key-value pair (convenience method)
-    pub fn put_str(&self, key: &str, value: &str) -> Result<()> {
-        let key = Key::new(key.as_bytes().to_vec());
-        let value = Value::new(value.as_bytes().to_vec());
-        
-        let mut storage = self.storage.write();
-        storage.insert(key.data, value.data);
+
+    /// Apply `create_if_missing` and `error_if_exists` to the database at
+    /// `config.db_path`, then create its directories
+    fn prepare_directories(config: &Config) -> Result<()> {
+        // A database exists once its manifest has been written
+        let exists = config.db_path.join(MANIFEST_FILE).exists();
+        if exists && config.error_if_exists {
+            return Err(Error::Config(format!(
+                "Database already exists at {} and error_if_exists is set",
+                config.db_path.display()
+            )));
+        }
+        if !exists && !config.create_if_missing {
+            return Err(Error::Config(format!(
+                "No database at {} and create_if_missing is not set",
+                config.db_path.display()
+            )));
+        }
+
+        std::fs::create_dir_all(&config.db_path)?;
+        std::fs::create_dir_all(&config.wal.wal_path)?;
+        std::fs::create_dir_all(&config.value_log.vlog_path)?;
         Ok(())
     }
-    
-    /// Get a string value by key (convenience method)
-    pub fn get_str(&self, key: &str) -> Result<Option<String>> {
-        let key = Key::new(key.as_bytes().to_vec());
-        
-        let storage = self.storage.read();
-        if let Some(value_data) = storage.get(&key.data) {
-            Ok(Some(String::from_utf8_lossy(value_data).to_string()))
+
+    /// Build the entry for a put, separating the value into the value log
+    /// when it reaches `separation_threshold` unless `options` force it inline
+    /// or the engine is in-memory
+    fn prepare_put(&self, key: Vec<u8>, value: Value, sequence: u64, options: &WriteOptions) -> Result<Entry> {
+        let separate = !options.force_inline
+            && !self.state.config.in_memory
+            && value.is_large(self.state.config.value_log.separation_threshold);
+        self.state.write_sizes.record(key.len(), value.len(), separate);
+        if separate {
+            let vptr = self.write_to_vlog(value)?;
+            Ok(Entry::with_pointer(Key::new(key), vptr, sequence))
         } else {
-            Ok(None)
+            Ok(Entry::new(Key::new(key), value, sequence))
         }
     }
-    
-    /// Delete a string key (convenience method)
-    pub fn delete_str(&self, key: &str) -> Result<()> {
-        let key = Key::new(key.as_bytes().to_vec());
-        
-        let mut storage = self.storage.write();
-        storage.remove(&key.data);
-        Ok(())
+
+    /// Append a value to the value log
+    fn write_to_vlog(&self, value: Value) -> Result<ValuePointer> {
+        let len = value.len() as u64;
+        let vptr = match self.state.vlog_writer.lock().as_mut() {
+            Some(writer) => writer.write_value_sync(value)?,
+            None => return Err(Error::Config("Value log is closed".to_string())),
+        };
+        self.state.lsm.count_separated_value(len);
+        Ok(vptr)
     }
-    
-    /// Scan string keys in a range (convenience method)
-    pub fn scan_str(&self, start: &str, end: &str) -> Result<Vec<(String, String)>> {
-        let start_key = Key::new(start.as_bytes().to_vec());
-        let end_key = Key::new(end.as_bytes().to_vec());
-        
-        let storage = self.storage.read();
-        let mut results = Vec::new();
-        
-        for (key_data, value_data) in storage.iter() {
-            if key_data >= &start_key.data && key_data <= &end_key.data {
-                let key = String::from_utf8_lossy(key_data).to_string();
-                let value = String::from_utf8_lossy(value_data).to_string();
-                results.push((key, value));
-            }
+
+    /// Replay the WAL files not yet covered by SST files into the
+    /// memtable, returning the highest sequence number seen. A record torn
+    /// by a crash ends its file; only a complete record that fails to
+    /// decode is an error. The files stay until the memtable is flushed.
+    fn recover(lsm: &LsmTree, config: &Config) -> Result<u64> {
+        let mut reader = WalReader::new(config.wal.wal_path.clone())?.skip_logs_before(lsm.log_number());
+        let (mut records, mut last_sequence) = (0u64, 0);
+        while let Some(record) = reader.read_next()? {
+            last_sequence = last_sequence.max(record.sequence());
+            lsm.restore(record.into_entries())?;
+            records += 1;
         }
-        
-        Ok(results)
-    }
-    
-    /// Write a batch of key-value pairs
-    pub fn write_batch(&self, batch: &[(Vec<u8>, Vec<u8>)]) -> Result<()> {
-        let mut storage = self.storage.write();
-        for (key, value) in batch {
-            storage.insert(key.clone(), value.clone());
+        if records > 0 {
+            info!("Replayed {} WAL records up to sequence {}", records, last_sequence);
         }
-        Ok(())
+        Ok(last_sequence)
     }
 
-    /// Put a key-value pair using Vec<u8> (for benchmarks)
-    pub fn put_bytes(&self, key: &[u8], value: &[u8]) -> Result<()> {
-        let mut storage = self.storage.write();
-        storage.insert(key.to_vec(), value.to_vec());
+    /// Fail operations issued after `close`
+    fn ensure_open(&self) -> Result<()> {
+        if *self.state.closed.read() {
+            return Err(Error::Config("engine is closed".to_string()));
+        }
         Ok(())
     }
 
-    /// Get a value by key using Vec<u8> (for benchmarks)
-    pub fn get_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
-        let storage = self.storage.read();
-        Ok(storage.get(key).cloned())
+    /// Fail `operation`, which needs database files, on an in-memory engine
+    fn ensure_on_disk(&self, operation: &str) -> Result<()> {
+        if self.state.config.in_memory {
+            return Err(Error::Config(format!("{} is not available on an in-memory engine", operation)));
+        }
+        Ok(())
     }
-}
 
-#[async_trait::async_trait]
-impl Engine for AuraEngine {
-    async fn put(&self, key: Key, value: Value) -> Result<()> {
-        let mut storage = self.storage.write();
-        storage.insert(key.data, value.data);
+    /// Reject empty keys and keys or values over the configured limits
+    fn check_write(&self, key: &[u8], value_len: Option<usize>) -> Result<()> {
+        if key.is_empty() {
+            return Err(Error::EmptyKey);
+        }
+        if secondary::is_index_key(key) {
+            return Err(Error::Config("Keys starting with the secondary index prefix are reserved".to_string()));
+        }
+        if key.len() > self.state.config.max_key_size {
+            return Err(Error::Config(format!(
+                "Key of {} bytes exceeds max_key_size of {} bytes",
+                key.len(),
+                self.state.config.max_key_size
+            )));
+        }
+        if let Some(value_len) = value_len {
+            if value_len > self.state.config.max_value_size {
+                return Err(Error::Config(format!(
+                    "Value of {} bytes exceeds max_value_size of {} bytes",
+                    value_len,
+                    self.state.config.max_value_size
+                )));
+            }
+        }
         Ok(())
     }
-    
-    async fn get(&self, key: &Key) -> Result<Option<Value>> {
-        let storage = self.storage.read();
-        if let Some(value_data) = storage.get(&key.data) {
-            Ok(Some(Value::new(value_data.clone())))
-        } else {
-            Ok(None)
-        }
+
+    /// Log and apply a single put
+    fn put_internal(&self, key: Vec<u8>, value: Value, options: &WriteOptions) -> Result<()> {
+        let span = trace_span!(
+            "put",
+            key_len = key.len(),
+            value_len = value.len(),
+            sequence = field::Empty,
+            duration_us = field::Empty
+        );
+        in_timed_span(&span, || {
+            let _timer = self.state.latencies.start(Operation::Put);
+            self.ensure_open()?;
+            self.check_write(&key, Some(value.len()))?;
+            self.state.lsm.stall_writes(options.stall_timeout)?;
+            if !self.state.config.secondary_indexes.is_empty() {
+                let wal = self.state.lock_wal(true);
+                let updates = self.index_updates(&[(key.as_slice(), Some(value.as_bytes()))])?;
+                let mut entries = vec![self.prepare_put(key, value, 0, options)?];
+                entries.extend(updates);
+                return self.commit_entries(wal, entries, false);
+            }
+            let mut entry = self.prepare_put(key, value, 0, options)?;
+            // Sequence numbers are assigned under the WAL lock so that
+            // records reach the log in sequence order
+            let mut wal = self.state.wal.lock();
+            entry.sequence = wal.next_sequence();
+            Span::current().record("sequence", entry.sequence);
+            let ticket = self.state.lsm.begin_write(entry.sequence);
+            wal.write_record(&WalRecord::from_entry(&entry))?;
+            drop(wal);
+            self.apply_logged(vec![entry], ticket)
+        })
     }
-    
-    async fn delete(&self, key: &Key) -> Result<()> {
-        let mut storage = self.storage.write();
-        storage.remove(&key.data);
-        Ok(())
+
+    /// Log and apply a single delete. If `checked`, the key is looked up
+    /// first, under the WAL lock once the writes in flight are visible, and
+    /// left alone if it is missing. Returns whether a tombstone was written.
+    pub(crate) fn delete_internal(&self, key: Vec<u8>, checked: bool, options: &WriteOptions) -> Result<bool> {
+        let span = trace_span!("delete", key_len = key.len(), sequence = field::Empty, duration_us = field::Empty);
+        in_timed_span(&span, || {
+            let _timer = self.state.latencies.start(Operation::Delete);
+            self.ensure_open()?;
+            self.check_write(&key, None)?;
+            self.state.lsm.stall_writes(options.stall_timeout)?;
+            let mut wal = self.state.lock_wal(checked || !self.state.config.secondary_indexes.is_empty());
+            if checked && self.state.lsm.get(&key)?.is_none_or(|entry| entry.is_delete()) {
+                return Ok(false);
+            }
+            let updates = self.index_updates(&[(key.as_slice(), None)])?;
+            if !updates.is_empty() {
+                let mut entries = vec![Entry::delete(Key::new(key), 0)];
+                entries.extend(updates);
+                return self.commit_entries(wal, entries, false).map(|_| true);
+            }
+            let entry = Entry::delete(Key::new(key), wal.next_sequence());
+            Span::current().record("sequence", entry.sequence);
+            let ticket = self.state.lsm.begin_write(entry.sequence);
+            wal.write_record(&WalRecord::from_entry(&entry))?;
+            drop(wal);
+            self.apply_logged(vec![entry], ticket)?;
+            Ok(true)
+        })
     }
-    
-    async fn scan(&self, range: Range) -> Result<Vec<(Key, Value)>> {
-        let storage = self.storage.read();
-        let mut results = Vec::new();
-        
-        for (key_data, value_data) in storage.iter() {
-            if key_data >= &range.start.data && key_data <= &range.end.data {
-                let key = Key::new(key_data.clone());
-                let value = Value::new(value_data.clone());
-                results.push((key, value));
+
+    /// Prepare and apply the operations of a batch
+    pub(crate) fn write_batch_internal(&self, batch: &Batch, options: &WriteOptions) -> Result<()> {
+        self.ensure_open()?;
+        for entry in &batch.operations {
+            match RangeTombstone::from_entry(entry) {
+                Some(tombstone) => self.check_range_delete(&tombstone)?,
+                None => self.check_write(&entry.key.data, entry.value.as_ref().map(Value::len))?,
             }
         }
-        
-        Ok(results)
-    }
-    
-    async fn write_batch(&self, batch: &Batch) -> Result<()> {
-        let mut storage = self.storage.write();
-        
+        let mut entries = Vec::with_capacity(batch.operations.len());
+        let mut writes = Vec::with_capacity(batch.operations.len());
+
+        // Sequence numbers are assigned by `apply_entries`
         for entry in &batch.operations {
             match entry.op_type {
-                crate::storage::OpType::Put => {
+                // For now, treat merge as put
+                OpType::Put | OpType::Merge => {
                     if let Some(value) = &entry.value {
-                        storage.insert(entry.key.data.clone(), value.data.clone());
+                        entries.push(self.prepare_put(entry.key.data.clone(), value.clone(), 0, options)?);
+                        writes.push((entry.key.data.as_slice(), Some(value.as_bytes())));
                     }
                 }
-                crate::storage::OpType::Delete => {
-                    storage.remove(&entry.key.data);
+                OpType::Delete => {
+                    entries.push(Entry::delete(entry.key.clone(), 0));
+                    writes.push((entry.key.data.as_slice(), None));
                 }
-                crate::storage::OpType::Merge => {
-                    // For now, treat merge as put
-                    if let Some(value) = &entry.value {
-                        storage.insert(entry.key.data.clone(), value.data.clone());
+                // Index entries of the keys covered are found under the WAL lock
+                OpType::RangeDelete => {
+                    if let Some(tombstone) = RangeTombstone::from_entry(entry) {
+                        if !tombstone.is_empty(&self.state.config.comparator) {
+                            entries.push(Entry::range_delete(tombstone.start, tombstone.end, 0));
+                        }
                     }
                 }
             }
         }
-        
-        Ok(())
+
+        self.apply_entries(entries, &writes, batch.sync, options)
     }
-    
-    async fn snapshot(&self) -> Result<Snapshot> {
-        let storage = self.storage.read();
-        let mut snapshot_data = HashMap::new();
-        
-        for (key, value) in storage.iter() {
-            snapshot_data.insert(key.clone(), value.clone());
+
+    /// Log and apply a set of entries as one WAL batch, together with the
+    /// index updates for `writes`, the keys and new values (`None` for
+    /// deletes) the entries hold. With `sync`, the batch is on disk before
+    /// it is applied.
+    fn apply_entries(
+        &self,
+        mut entries: Vec<Entry>,
+        writes: &[(&[u8], Option<&[u8]>)],
+        sync: bool,
+        options: &WriteOptions,
+    ) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
         }
-        
-        Ok(Snapshot {
-            data: snapshot_data,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_millis() as u64,
+        let span = trace_span!("batch", entries = entries.len(), sequence = field::Empty, duration_us = field::Empty);
+        in_timed_span(&span, || {
+            let _timer = self.state.latencies.start(Operation::Batch);
+            self.state.lsm.stall_writes(options.stall_timeout)?;
+            let wal = self.state.lock_wal(!self.state.config.secondary_indexes.is_empty());
+            let covered = self.range_deleted_keys(&entries)?;
+            let mut writes = writes.to_vec();
+            writes.extend(covered.iter().map(|key| (key.as_slice(), None)));
+            entries.extend(self.index_updates(&writes)?);
+            self.commit_entries(wal, entries, sync)
         })
     }
-    
-    async fn close(&self) -> Result<()> {
-        let mut closed = self.closed.write();
-        *closed = true;
+
+    /// Log and apply entries as one WAL batch. The entries are numbered
+    /// here, under the WAL lock, in the order given, and applied once the
+    /// lock is released. With `sync`, the value log and the WAL are
+    /// fsynced before the entries reach the memtable, so a batch readers
+    /// have seen survives a crash; either way recovery finds all of the
+    /// batch or none of it.
+    fn commit_entries(&self, mut wal: MutexGuard<'_, WalWriter>, mut entries: Vec<Entry>, sync: bool) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let first = wal.reserve_sequences(entries.len() as u64);
+        for (entry, sequence) in entries.iter_mut().zip(first..) {
+            entry.sequence = sequence;
+        }
+        Span::current().record("sequence", first);
+        let ticket = self.state.lsm.begin_write(first + entries.len() as u64 - 1);
+        // Separated values go to disk before the pointers logged to them
+        if sync && entries.iter().any(|entry| entry.value_pointer.is_some()) {
+            if let Some(writer) = self.state.vlog_writer.lock().as_ref() {
+                writer.sync_segments()?;
+            }
+        }
+        wal.write_batch(&entries)?;
+        if sync {
+            wal.request_sync()?;
+        }
+        drop(wal);
+        self.apply_logged(entries, ticket)
+    }
+
+    /// Apply entries logged under `ticket` with the WAL lock released, so
+    /// writers sync and insert concurrently: wait for the WAL record to be
+    /// durable if the sync policy or the write asks for it, insert the
+    /// entries and make them visible, delivering their change events in
+    /// sequence order
+    fn apply_logged(&self, entries: Vec<Entry>, ticket: WriteTicket<'_>) -> Result<()> {
+        if let Some(last) = entries.last() {
+            self.state.group_commit.wait(last.sequence)?;
+        }
+        let events: Vec<_> = entries.iter().filter_map(|entry| self.change_event(entry)).collect();
+        self.state.lsm.insert_batch(entries)?;
+        ticket.finish(self.deliver(events));
+        self.maybe_switch_memtable()
+    }
+
+    /// Check a range delete's bounds like a write's keys. With secondary
+    /// indexes, the range must not reach into their reserved key space.
+    fn check_range_delete(&self, tombstone: &RangeTombstone) -> Result<()> {
+        self.check_write(&tombstone.start.data, None)?;
+        self.check_write(&tombstone.end.data, None)?;
+        if !self.state.config.secondary_indexes.is_empty()
+            && tombstone.covers(secondary::INDEX_KEY_PREFIX, &self.state.config.comparator)
+        {
+            return Err(Error::Config("Range deletes must not cover secondary index entries".to_string()));
+        }
         Ok(())
     }
-}
 
-/// Database snapshot
-pub struct Snapshot {
-    /// Snapshot data
-    pub data: HashMap<Vec<u8>, Vec<u8>>,
-    /// Timestamp when snapshot was created
-    pub timestamp: u64,
-}
+    /// Live keys the range deletes among `entries` remove, whose index
+    /// entries must go too; none without secondary indexes. Called with
+    /// the WAL lock held and no write in flight.
+    fn range_deleted_keys(&self, entries: &[Entry]) -> Result<Vec<Vec<u8>>> {
+        let mut keys = Vec::new();
+        if self.state.config.secondary_indexes.is_empty() {
+            return Ok(keys);
+        }
+        for tombstone in entries.iter().filter_map(RangeTombstone::from_entry) {
+            let live = self.state.lsm.scan(&tombstone.start.data, &tombstone.end.data)?;
+            keys.extend(
+                live.into_iter()
+                    .filter(|entry| tombstone.covers(&entry.key.data, &self.state.config.comparator))
+                    .map(|entry| entry.key.data),
+            );
+        }
+        Ok(keys)
+    }
 
-/// Engine options
-#[derive(Debug, Clone)]
-pub struct Options {
-    /// Database path
-    pub path: PathBuf,
-    /// Whether to create directories if they don't exist
-    pub create_if_missing: bool,
-    /// Whether to error if database already exists
-    pub error_if_exists: bool,
-}
+    /// Index entries bringing every secondary index up to date with
+    /// `writes`: a tombstone for each entry the replaced value produced and
+    /// a put for each the new value does. Called with the WAL lock held and
+    /// no write in flight, so the replaced values are the last ones
+    /// committed; a key written twice
+    /// in `writes` replaces its earlier value there.
+    fn index_updates(&self, writes: &[(&[u8], Option<&[u8]>)]) -> Result<Vec<Entry>> {
+        let mut updates = Vec::new();
+        if self.state.config.secondary_indexes.is_empty() {
+            return Ok(updates);
+        }
+        let mut written: HashMap<&[u8], Option<&[u8]>> = HashMap::new();
+        for &(key, value) in writes {
+            let old = match written.insert(key, value) {
+                Some(old) => old.map(<[u8]>::to_vec),
+                None => match self.state.lsm.get(key)? {
+                    Some(entry) => self.resolve_value(&entry, &ReadOptions::default())?,
+                    None => None,
+                },
+            };
+            for index in &self.state.config.secondary_indexes {
+                let old_key = old.as_deref().and_then(|old| index.extract(key, old));
+                let new_key = value.and_then(|value| index.extract(key, value));
+                if old_key == new_key {
+                    continue;
+                }
+                if let Some(old_key) = old_key {
+                    updates.push(Entry::delete(Key::new(index.entry_key(&old_key, key)), 0));
+                }
+                if let Some(new_key) = new_key {
+                    let entry_key = Key::new(index.entry_key(&new_key, key));
+                    updates.push(Entry::new(entry_key, Value::new(Vec::new()), 0));
+                }
+            }
+        }
+        Ok(updates)
+    }
 
-impl Default for Options {
-    fn default() -> Self {
-        Self {
-            path: PathBuf::from("./auradb_data"),
-            create_if_missing: true,
-            error_if_exists: false,
+    /// Event for a write, if anyone is subscribed
+    fn change_event(&self, entry: &Entry) -> Option<ChangeEvent> {
+        if secondary::is_index_key(&entry.key.data) {
+            return None;
+        }
+        (self.state.changes.receiver_count() > 0).then(|| ChangeEvent::from_entry(entry))
+    }
+
+    /// Callback delivering a write's events to subscribers once the write
+    /// is visible. Writes become visible in sequence order, so every
+    /// subscriber sees events in that order.
+    fn deliver(&self, events: Vec<ChangeEvent>) -> Option<OnVisible> {
+        if events.is_empty() {
+            return None;
+        }
+        let changes = self.state.changes.clone();
+        Some(Box::new(move || {
+            for event in events {
+                // Fails only when every receiver has been dropped
+                let _ = changes.send(event);
+            }
+        }))
+    }
+
+    /// Seal the active memtable once it is full, starting a new WAL file
+    /// for the writes that follow
+    fn maybe_switch_memtable(&self) -> Result<()> {
+        if !self.state.lsm.needs_switch() {
+            return Ok(());
+        }
+        let mut wal = self.state.wal.lock();
+        // Another writer may have switched it while this one waited
+        if self.state.lsm.needs_switch() {
+            let next_log_number = wal.rotate()?;
+            self.state.lsm.switch_memtable(next_log_number);
+        }
+        Ok(())
+    }
+
+    /// Look up a key and resolve any value pointer
+    fn get_internal(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.get_with_options(key, &ReadOptions::default())
+    }
+
+    /// Get a value by key with per-read options
+    pub fn get_with_options(&self, key: &[u8], options: &ReadOptions) -> Result<Option<Vec<u8>>> {
+        let span = trace_span!(
+            "get",
+            key_len = key.len(),
+            value_len = field::Empty,
+            tier = field::Empty,
+            duration_us = field::Empty
+        );
+        in_timed_span(&span, || {
+            let _timer = self.state.latencies.start(Operation::Get);
+            self.ensure_open()?;
+            let value = match self.state.lsm.get(key)? {
+                Some(entry) => self.resolve_value(&entry, options)?,
+                None => None,
+            };
+            if let Some(value) = &value {
+                Span::current().record("value_len", value.len());
+            }
+            Ok(value)
+        })
+    }
+
+    /// Resolve an entry to its value bytes, reading the value log if needed
+    fn resolve_value(&self, entry: &Entry, options: &ReadOptions) -> Result<Option<Vec<u8>>> {
+        if entry.value_pointer.is_some() {
+            Ok(self.resolve_shared(entry, options)?.map(|data| data.to_vec()))
+        } else {
+            Ok(entry.value.as_ref().map(|v| v.data.clone()))
+        }
+    }
+
+    /// Resolve an entry to a shared buffer. Separated values come from the
+    /// value log cache, so repeated reads share one allocation.
+    fn resolve_shared(&self, entry: &Entry, options: &ReadOptions) -> Result<Option<Arc<[u8]>>> {
+        let Some(vptr) = &entry.value_pointer else {
+            return Ok(entry.value.as_ref().map(|v| Arc::from(v.as_bytes())));
+        };
+
+        let cache_key = vlog_cache_key(vptr);
+        let cached = self.state.vlog_cache.lock().get(&cache_key);
+        let data = match cached {
+            Some(data) => data,
+            None => {
+                let verify = options.verify_checksums.unwrap_or(self.state.config.value_log.verify_on_read);
+                let data: Arc<[u8]> = self.state.vlog_reader.lock().read_value_with_verify(vptr, verify)?.data.into();
+                self.state.lsm.record_vlog_read(data.len() as u64);
+                self.state.vlog_cache.lock().put(cache_key, Arc::clone(&data))?;
+                data
+            }
+        };
+        self.state.lsm.count_returned_value(data.len() as u64);
+        Ok(Some(data))
+    }
+
+    /// Collect all live pairs within `[start, end]` in comparator order.
+    /// The result is a point-in-time view: writes, and whole batches, that
+    /// commit after the scan starts are not included.
+    pub(crate) fn scan_internal(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let span = trace_span!(
+            "scan",
+            start_len = start.len(),
+            end_len = end.len(),
+            entries = field::Empty,
+            duration_us = field::Empty
+        );
+        in_timed_span(&span, || {
+            let _timer = self.state.latencies.start(Operation::Scan);
+            self.ensure_open()?;
+            let entries = self.state.lsm.scan(start, end)?;
+            let pairs = self.resolve_all(entries)?;
+            Span::current().record("entries", pairs.len());
+            Ok(pairs)
+        })
+    }
+
+    /// Resolve entries to key/value pairs, skipping tombstones. Separated
+    /// values missing from the value log cache are read in one batch.
+    fn resolve_all(&self, entries: Vec<Entry>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut results = Vec::with_capacity(entries.len());
+        // Positions in `results` still waiting for their value log read
+        let mut pending = Vec::new();
+        let mut pointers = Vec::new();
+        {
+            let mut cache = self.state.vlog_cache.lock();
+            for entry in entries.into_iter().filter(|e| !secondary::is_index_key(&e.key.data)) {
+                match entry.value_pointer {
+                    Some(vptr) => match cache.get(&vlog_cache_key(&vptr)) {
+                        Some(data) => {
+                            self.state.lsm.count_returned_value(data.len() as u64);
+                            results.push((entry.key.data, data.to_vec()));
+                        }
+                        None => {
+                            pending.push(results.len());
+                            results.push((entry.key.data, Vec::new()));
+                            pointers.push(vptr);
+                        }
+                    },
+                    None => {
+                        if let Some(value) = entry.value {
+                            results.push((entry.key.data, value.data));
+                        }
+                    }
+                }
+            }
         }
+        if pointers.is_empty() {
+            return Ok(results);
+        }
+
+        let values = self.state.vlog_reader.lock().read_values(&pointers)?;
+        let mut cache = self.state.vlog_cache.lock();
+        for ((i, vptr), value) in pending.into_iter().zip(&pointers).zip(values) {
+            self.state.lsm.record_vlog_read(value.data.len() as u64);
+            self.state.lsm.count_returned_value(value.data.len() as u64);
+            cache.put(vlog_cache_key(vptr), Arc::from(value.as_bytes()))?;
+            results[i].1 = value.data;
+        }
+        Ok(results)
+    }
+    
+    /// Put a string key-value pair (convenience method)
+    pub fn put_str(&self, key: &str, value: &str) -> Result<()> {
+        self.put_internal(key.as_bytes().to_vec(), Value::new(value.as_bytes().to_vec()), &WriteOptions::default())
+    }
+    
+    /// Get a string value by key (convenience method)
+    pub fn get_str(&self, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .get_internal(key.as_bytes())?
+            .map(|value| String::from_utf8_lossy(&value).to_string()))
+    }
+    
+    /// Delete a string key (convenience method)
+    pub fn delete_str(&self, key: &str) -> Result<()> {
+        self.delete_internal(key.as_bytes().to_vec(), false, &WriteOptions::default()).map(|_| ())
+    }
+
+    /// Delete every key in `[start, end)` with a single range tombstone,
+    /// however many keys the range holds. Reads stop seeing the keys at
+    /// once; compaction reclaims their space as the tombstone moves down
+    /// the tree. An empty range (`start >= end`) deletes nothing.
+    pub fn delete_range(&self, start: &Key, end: &Key) -> Result<()> {
+        let mut batch = Batch::new();
+        batch.delete_range(start.clone(), end.clone());
+        self.write_batch_internal(&batch, &WriteOptions::default())
+    }
+
+    /// Delete a key, returning whether it existed. Unlike `delete`, this
+    /// reads the key before deleting it, costing a full point lookup (up
+    /// to one probe per SST level) on top of the write. A missing key is
+    /// left untouched and no tombstone is written.
+    pub fn delete_checked(&self, key: &Key) -> Result<bool> {
+        self.delete_internal(key.data.clone(), true, &WriteOptions::default())
+    }
+
+    /// Byte-slice form of [`AuraEngine::delete_checked`]
+    pub fn delete_bytes_checked(&self, key: &[u8]) -> Result<bool> {
+        self.delete_internal(key.to_vec(), true, &WriteOptions::default())
+    }
+    
+    /// Scan string keys in a range (convenience method)
+    pub fn scan_str(&self, start: &str, end: &str) -> Result<Vec<(String, String)>> {
+        Ok(self
+            .scan_internal(start.as_bytes(), end.as_bytes())?
+            .into_iter()
+            .map(|(key, value)| {
+                (
+                    String::from_utf8_lossy(&key).to_string(),
+                    String::from_utf8_lossy(&value).to_string(),
+                )
+            })
+            .collect())
+    }
+    
+    /// Live pairs whose keys start with `prefix`, in comparator order. With
+    /// a prefix extractor configured, SST files whose prefix filter rules
+    /// the prefix out are skipped.
+    pub fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.ensure_open()?;
+        let entries = self.state.lsm.scan_prefix(prefix)?;
+        self.resolve_all(entries)
+    }
+
+    /// Primary keys whose values the secondary index `name` maps to
+    /// `index_key`, in comparator order of their index entries
+    pub fn scan_index(&self, name: &str, index_key: &[u8]) -> Result<Vec<Vec<u8>>> {
+        self.ensure_open()?;
+        let index = self
+            .state
+            .config
+            .secondary_indexes
+            .iter()
+            .find(|index| index.name() == name)
+            .ok_or_else(|| Error::Config(format!("No secondary index named {}", name)))?;
+        let prefix = index.lookup_prefix(index_key);
+        Ok(self
+            .state
+            .lsm
+            .scan_prefix(&prefix)?
+            .into_iter()
+            .map(|entry| entry.key.data[prefix.len()..].to_vec())
+            .collect())
+    }
+
+    /// Live pairs in `[range.start, range.end]` for which `pred(key, value)`
+    /// holds, in comparator order, up to `range.limit` of them. The
+    /// predicate runs inside the engine as values are resolved, so
+    /// rejected pairs are never copied out. Deleted keys are never passed
+    /// to it.
+    pub fn scan_filtered(
+        &self,
+        range: Range,
+        pred: impl Fn(&[u8], &[u8]) -> bool,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.ensure_open()?;
+        let limit = range.limit.unwrap_or(usize::MAX);
+        let mut results = Vec::new();
+        for entry in self.state.lsm.scan(&range.start.data, &range.end.data)? {
+            if secondary::is_index_key(&entry.key.data) {
+                continue;
+            }
+            if results.len() >= limit {
+                break;
+            }
+            let Some(value) = self.resolve_shared(&entry, &ReadOptions::default())? else {
+                continue;
+            };
+            if pred(&entry.key.data, &value) {
+                results.push((entry.key.data, value.to_vec()));
+            }
+        }
+        Ok(results)
+    }
+
+    /// One page of a range scan: up to `limit` live pairs in
+    /// `[range.start, range.end]` with keys strictly after `after`, plus a
+    /// continuation token. The token is the last key returned, or `None`
+    /// once the range is exhausted; passing it back as `after` resumes
+    /// right behind the previous page. Each page reads a consistent
+    /// snapshot, and since pages are keyed on the last key seen, writes
+    /// between pages cause neither duplicates nor gaps among the keys not
+    /// yet returned.
+    pub fn scan_page(&self, range: Range, after: Option<Key>, limit: usize) -> Result<ScanPage> {
+        self.ensure_open()?;
+        if limit == 0 {
+            return Err(Error::Config("Page limit must be greater than 0".to_string()));
+        }
+
+        let comparator = &self.state.config.comparator;
+        let start = match &after {
+            Some(after) if comparator.compare(&after.data, &range.start.data) != std::cmp::Ordering::Less => &after.data,
+            _ => &range.start.data,
+        };
+        let mut entries = self
+            .state
+            .lsm
+            .scan(start, &range.end.data)?
+            .into_iter()
+            .filter(|e| !secondary::is_index_key(&e.key.data))
+            .filter(|e| {
+                after
+                    .as_ref()
+                    .is_none_or(|after| comparator.compare(&e.key.data, &after.data) == std::cmp::Ordering::Greater)
+            })
+            .take(limit + 1);
+
+        let mut page = Vec::with_capacity(limit);
+        for entry in entries.by_ref().take(limit) {
+            if let Some(value) = self.resolve_value(&entry, &ReadOptions::default())? {
+                page.push((entry.key, Value::new(value)));
+            }
+        }
+        let token = match entries.next() {
+            Some(_) => page.last().map(|(key, _)| key.clone()),
+            None => None,
+        };
+        Ok((page, token))
+    }
+
+    /// Write a batch of key-value pairs
+    pub fn write_batch(&self, batch: &[(Vec<u8>, Vec<u8>)]) -> Result<()> {
+        let options = WriteOptions::default();
+        self.ensure_open()?;
+        for (key, value) in batch {
+            self.check_write(key, Some(value.len()))?;
+        }
+        // Sequence numbers are assigned by `apply_entries`
+        let mut entries = Vec::with_capacity(batch.len());
+        for (key, value) in batch {
+            entries.push(self.prepare_put(key.clone(), Value::new(value.clone()), 0, &options)?);
+        }
+        let writes: Vec<_> = batch.iter().map(|(key, value)| (key.as_slice(), Some(value.as_slice()))).collect();
+        self.apply_entries(entries, &writes, false, &options)
+    }
+
+    /// Put a key-value pair using Vec<u8> (for benchmarks)
+    pub fn put_bytes(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.put_internal(key.to_vec(), Value::new(value.to_vec()), &WriteOptions::default())
+    }
+
+    /// Put a key-value pair with per-write options
+    pub fn put_with_options(&self, key: &[u8], value: &[u8], options: &WriteOptions) -> Result<()> {
+        self.put_internal(key.to_vec(), Value::new(value.to_vec()), options)
+    }
+
+    /// Delete a key with per-write options
+    pub fn delete_with_options(&self, key: &[u8], options: &WriteOptions) -> Result<()> {
+        self.delete_internal(key.to_vec(), false, options).map(|_| ())
+    }
+
+    /// Apply a batch with per-write options
+    pub fn write_batch_with_options(&self, batch: &Batch, options: &WriteOptions) -> Result<()> {
+        self.write_batch_internal(batch, options)
+    }
+
+    /// Put a value read from `reader` without holding it in memory. It is
+    /// copied into a value log segment of its own `stream_chunk_size` bytes
+    /// at a time, and the key is committed once the whole value is on
+    /// disk, so a failed read leaves the old value in place. Streamed
+    /// values are always separated, whatever their size. Unavailable with
+    /// secondary indexes, which need the whole value.
+    pub fn put_stream(&self, key: &Key, reader: impl Read) -> Result<()> {
+        self.put_stream_with_options(key, reader, &WriteOptions::default())
+    }
+
+    /// [`put_stream`](Self::put_stream) with per-write options. Streamed
+    /// values are separated whatever `options.force_inline` says.
+    pub fn put_stream_with_options(&self, key: &Key, reader: impl Read, options: &WriteOptions) -> Result<()> {
+        let span = trace_span!(
+            "put_stream",
+            key_len = key.len(),
+            value_len = field::Empty,
+            sequence = field::Empty,
+            duration_us = field::Empty
+        );
+        in_timed_span(&span, || {
+            let _timer = self.state.latencies.start(Operation::Put);
+            self.ensure_open()?;
+            self.check_write(&key.data, None)?;
+            self.ensure_on_disk("Streaming a value")?;
+            if !self.state.config.secondary_indexes.is_empty() {
+                return Err(Error::Config("Streamed writes are not supported with secondary indexes".to_string()));
+            }
+            self.state.lsm.stall_writes(options.stall_timeout)?;
+            let stream = match self.state.vlog_writer.lock().as_ref() {
+                Some(writer) => writer.start_stream()?,
+                None => return Err(Error::Config("Value log is closed".to_string())),
+            };
+            let vptr = stream.write_from(reader, self.state.config.max_value_size)?;
+            Span::current().record("value_len", vptr.length);
+            // Streamed values are stored uncompressed
+            self.state.write_sizes.record(key.len(), vptr.length as usize, true);
+            self.state.lsm.count_separated_value(vptr.length as u64);
+            let wal = self.state.wal.lock();
+            self.commit_entries(wal, vec![Entry::with_pointer(key.clone(), vptr, 0)], false)
+        })
+    }
+
+    /// Get a value by key using Vec<u8> (for benchmarks)
+    pub fn get_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.get_internal(key)
+    }
+
+    /// Get `len` bytes of a value starting at byte `offset`. The range is
+    /// clamped to the value, so a range running past its end returns fewer
+    /// bytes, or none; a missing key returns `None`. Separated values that
+    /// aren't cached are read in place from the value log, fetching only
+    /// the requested bytes, unless they are compressed and have to be
+    /// decompressed in full. A partial read can't check the checksum,
+    /// which covers the whole value.
+    pub fn get_range(&self, key: &Key, offset: usize, len: usize) -> Result<Option<Vec<u8>>> {
+        let _timer = self.state.latencies.start(Operation::Get);
+        self.ensure_open()?;
+        let Some(entry) = self.state.lsm.get(&key.data)? else {
+            return Ok(None);
+        };
+        let slice = |data: &[u8]| {
+            let start = offset.min(data.len());
+            data[start..start.saturating_add(len).min(data.len())].to_vec()
+        };
+        let Some(vptr) = &entry.value_pointer else {
+            return Ok(entry.value.as_ref().map(|value| slice(&value.data)));
+        };
+
+        if let Some(data) = self.state.vlog_cache.lock().get(&vlog_cache_key(vptr)) {
+            return Ok(Some(slice(&data)));
+        }
+        let data = self.state.vlog_reader.lock().read_value_range(vptr, offset, len)?;
+        self.state.lsm.record_vlog_read(data.len() as u64);
+        Ok(Some(data))
+    }
+
+    /// Get a value as a stream. Separated values are read from the value
+    /// log `stream_chunk_size` bytes at a time rather than loaded whole,
+    /// bypassing the value log cache; their checksum is checked as the
+    /// stream reaches its end if `verify_on_read` is set, failing the last
+    /// read on a mismatch.
+    pub fn get_stream(&self, key: &Key) -> Result<Option<ValueStream>> {
+        let _timer = self.state.latencies.start(Operation::Get);
+        self.ensure_open()?;
+        let Some(entry) = self.state.lsm.get(&key.data)? else {
+            return Ok(None);
+        };
+        let Some(vptr) = &entry.value_pointer else {
+            return Ok(entry.value.map(|value| ValueStream::from_bytes(value.data)));
+        };
+        let value_log = &self.state.config.value_log;
+        let stream = self.state.vlog_reader.lock().read_value_stream(vptr, value_log.verify_on_read, value_log.stream_chunk_size)?;
+        self.state.lsm.record_vlog_read(stream.remaining());
+        Ok(Some(stream))
+    }
+
+    /// Get a value as a shared buffer. Large values are served from the
+    /// value log cache without copying, so concurrent and repeated reads of
+    /// the same value share one allocation.
+    pub fn get_shared(&self, key: &[u8]) -> Result<Option<Arc<[u8]>>> {
+        let _timer = self.state.latencies.start(Operation::Get);
+        self.ensure_open()?;
+        match self.state.lsm.get(key)? {
+            Some(entry) => self.resolve_shared(&entry, &ReadOptions::default()),
+            None => Ok(None),
+        }
+    }
+
+    /// Newest entry for every key in `[range.start, range.end]`, including
+    /// tombstones, with sequence numbers, op types and timestamps intact.
+    /// Unlike `scan`, deletes are not collapsed, which makes the result
+    /// suitable for change capture. Values stored in the value log are
+    /// resolved into `value`; the pointer is kept. All entries come from a
+    /// single consistent snapshot.
+    pub fn scan_entries(&self, range: Range) -> Result<Vec<Entry>> {
+        self.scan_entries_iter(range)?.collect()
+    }
+
+    /// Iterator form of [`scan_entries`](Self::scan_entries). The snapshot
+    /// is taken up front; separated values are read lazily as it advances.
+    pub fn scan_entries_iter(&self, range: Range) -> Result<impl Iterator<Item = Result<Entry>> + '_> {
+        self.scan_entries_with_options(range, &ReadOptions::default().with_include_tombstones(true))
+    }
+
+    /// Newest entry for every key in `[range.start, range.end]`, as
+    /// [`scan_entries_iter`](Self::scan_entries_iter) but with tombstones
+    /// yielded only if `options.include_tombstones` is set. The range
+    /// limit counts yielded entries, so skipped tombstones don't use it up.
+    pub fn scan_entries_with_options(
+        &self,
+        range: Range,
+        options: &ReadOptions,
+    ) -> Result<impl Iterator<Item = Result<Entry>> + '_> {
+        self.ensure_open()?;
+        let entries = self.state.lsm.scan_entries(&range.start.data, &range.end.data)?;
+        let limit = range.limit.unwrap_or(usize::MAX);
+        let include_tombstones = options.include_tombstones;
+        let entries = entries
+            .into_iter()
+            .filter(move |e| !secondary::is_index_key(&e.key.data) && (include_tombstones || !e.is_delete()));
+        let options = options.clone();
+        Ok(entries.take(limit).map(move |mut entry| {
+            if entry.value_pointer.is_some() && !entry.is_delete() {
+                entry.value = self.resolve_value(&entry, &options)?.map(Value::new);
+            }
+            Ok(entry)
+        }))
+    }
+
+    /// Subscribe to committed writes. Each put and delete is delivered once
+    /// it is in the WAL and memtable, in sequence order, to every receiver.
+    /// A receiver that falls more than 1024 events behind gets
+    /// `RecvError::Lagged` and skips ahead; writers never wait on
+    /// subscribers, and dropping a receiver unsubscribes it.
+    pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.state.changes.subscribe()
+    }
+
+    /// Force the active memtable to disk: seal it, if it has data, and
+    /// flush every sealed memtable to level 0 before returning. Safe to
+    /// call concurrently with writes; later writes go to a new memtable.
+    ///
+    /// An in-memory engine has nowhere to flush to: the memtable's data is
+    /// dropped, leaving the engine empty.
+    pub fn flush(&self) -> Result<()> {
+        self.ensure_open()?;
+        self.state.flush_memtables()
+    }
+
+    /// Load externally built SST files without going through the write
+    /// path. Each file is validated (checksums, key order, inline values)
+    /// and linked into the tree in a single manifest edit. Memtable data
+    /// overlapping the files is flushed first, and writes wait until the
+    /// ingestion is done. Data in the files supersedes existing values for
+    /// the same keys. The files themselves are left in place.
+    pub fn ingest_sst(&self, paths: &[PathBuf]) -> Result<()> {
+        self.ensure_open()?;
+        self.ensure_on_disk("SST ingestion")?;
+        let files = paths
+            .iter()
+            .map(|path| self.state.lsm.validate_external_sst(path))
+            .collect::<Result<Vec<_>>>()?;
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        let mut wal = self.state.lock_wal(true);
+        if files.iter().any(|f| self.state.lsm.memtables_overlap(&f.smallest_key, &f.largest_key)) {
+            if !self.state.lsm.active_is_empty() {
+                let next_log_number = wal.rotate()?;
+                self.state.lsm.switch_memtable(next_log_number);
+            }
+            self.state.lsm.flush_immutables()?;
+        }
+
+        let sequence = wal.next_sequence();
+        let max_sequence = files.iter().map(|f| f.max_sequence).max().unwrap_or(0);
+        self.state.lsm.ingest(files, sequence)?;
+        // Later writes must sort after anything the files brought in
+        wal.advance_sequence(max_sequence);
+        self.state.lsm.begin_write(wal.current_sequence()).finish(None);
+        info!("Ingested {} SST files", paths.len());
+        Ok(())
+    }
+
+    /// Atomically replace every key starting with `prefix` (every key for
+    /// `None`) with the contents of externally built SST files. The files
+    /// are validated as for [`ingest_sst`](Self::ingest_sst), the memtables
+    /// are flushed, and the files are installed together with a range
+    /// tombstone just below their sequence, so the old keys disappear in
+    /// the same manifest edit that makes the new ones visible. Compaction
+    /// reclaims the old data later. A prefix requires the bytewise
+    /// comparator, and every key in the files must start with it. With no
+    /// files, the range is simply emptied. As with `ingest_sst`, secondary
+    /// indexes are not updated.
+    pub fn ingest_sst_replacing(&self, prefix: Option<&[u8]>, paths: &[PathBuf]) -> Result<()> {
+        self.ensure_open()?;
+        self.ensure_on_disk("SST ingestion")?;
+        let files = paths
+            .iter()
+            .map(|path| self.state.lsm.validate_external_sst(path))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut wal = self.state.lock_wal(true);
+        // Everything replaced must be in SST files, under the tombstone
+        if !self.state.lsm.active_is_empty() {
+            let next_log_number = wal.rotate()?;
+            self.state.lsm.switch_memtable(next_log_number);
+        }
+        self.state.lsm.flush_immutables()?;
+
+        // The tombstone takes this sequence and the ingested entries the next
+        let sequence = wal.next_sequence();
+        wal.next_sequence();
+        self.state.lsm.ingest_replacing(files, prefix, sequence)?;
+        self.state.lsm.begin_write(wal.current_sequence()).finish(None);
+        info!("Ingested {} SST files replacing their key range", paths.len());
+        Ok(())
+    }
+
+    /// Current sequence, active file and the WAL files on disk with their
+    /// sequence ranges and sizes
+    pub fn wal_info(&self) -> Result<WalInfo> {
+        self.state.wal.lock().info()
+    }
+
+    /// Fsync the WAL, including any records still buffered by the async
+    /// writer, regardless of the configured sync policy
+    pub fn sync_wal(&self) -> Result<()> {
+        self.ensure_open()?;
+        self.state.wal.lock().sync()
+    }
+
+    /// Create a point-in-time checkpoint of the database at `dir`, which
+    /// must be empty or not exist yet, and can then be opened as a
+    /// database of its own.
+    ///
+    /// The memtable is flushed first. SST files and sealed value log
+    /// segments are immutable, so they are hard-linked: the checkpoint takes
+    /// almost no time or space and keeps its files when compaction deletes
+    /// the originals. Segments still being appended to, the WAL tail and the
+    /// manifest are copied. Writes wait while the checkpoint is taken.
+    /// `dir` must be on the same filesystem as the database.
+    pub fn checkpoint(&self, dir: &Path) -> Result<()> {
+        self.ensure_open()?;
+        self.ensure_on_disk("A checkpoint")?;
+        if dir.exists() && std::fs::read_dir(dir)?.next().is_some() {
+            return Err(Error::Config(format!("Checkpoint directory {} is not empty", dir.display())));
+        }
+        self.flush()?;
+
+        let mut wal = self.state.wal.lock();
+        wal.sync()?;
+        std::fs::create_dir_all(dir)?;
+        if !crate::io::same_filesystem(&self.state.config.db_path, dir)? {
+            return Err(Error::Config(format!(
+                "Checkpoint directory {} is on a different filesystem than {}; hard links are impossible",
+                dir.display(),
+                self.state.config.db_path.display()
+            )));
+        }
+
+        let layout = Config::default().with_db_path(dir.to_path_buf());
+        std::fs::create_dir_all(&layout.value_log.vlog_path)?;
+        {
+            let vlog_writer = self.state.vlog_writer.lock();
+            let writer = vlog_writer
+                .as_ref()
+                .ok_or_else(|| Error::Config("Value log is closed".to_string()))?;
+            writer.sync_segments()?;
+            let active = writer.active_segment_ids();
+            for entry in std::fs::read_dir(&self.state.config.value_log.vlog_path)? {
+                let entry = entry?;
+                let name = entry.file_name();
+                let Some(id) = crate::vlog::parse_segment_id(&name.to_string_lossy()) else {
+                    continue;
+                };
+                let target = layout.value_log.vlog_path.join(&name);
+                if active.contains(&id) {
+                    crate::io::copy_synced(&entry.path(), &target)?;
+                } else {
+                    std::fs::hard_link(entry.path(), target)?;
+                }
+            }
+        }
+        crate::io::sync_dir(&layout.value_log.vlog_path)?;
+
+        self.state.lsm.checkpoint(dir)?;
+        drop(wal);
+        info!("Created checkpoint at {}", dir.display());
+        Ok(())
+    }
+
+    /// Compact all data overlapping `[start, end)` down to the bottom level,
+    /// flushing the memtable first. `None` bounds extend the range to the
+    /// whole keyspace. Safe to call concurrently with writes; repeating the
+    /// call once the range is compacted is a no-op.
+    pub fn compact_range(&self, start: Option<&Key>, end: Option<&Key>) -> Result<()> {
+        self.ensure_on_disk("Manual compaction")?;
+        self.flush()?;
+        let (bytes_read, bytes_written) = self
+            .state
+            .lsm
+            .compact_range(start.map(|k| k.data.as_slice()), end.map(|k| k.data.as_slice()))?;
+        info!(
+            "Manual compaction read {} bytes and wrote {} bytes",
+            bytes_read, bytes_written
+        );
+        Ok(())
+    }
+
+    /// Compactions running now, background and manual, in the order they
+    /// started, with how far each has got
+    pub fn compaction_status(&self) -> Vec<CompactionProgress> {
+        self.state.lsm.compaction_status()
+    }
+
+    /// The most recent compactions to finish successfully, oldest first;
+    /// only a bounded number are kept
+    pub fn compaction_history(&self) -> Vec<CompactionProgress> {
+        self.state.lsm.compaction_history()
+    }
+
+    /// Stop background compaction, e.g. for a bulk load. Memtables keep
+    /// being flushed to level 0 while compaction is paused.
+    pub fn pause_compaction(&self) {
+        self.state.lsm.pause_compaction();
+    }
+
+    /// Resume background compaction, catching up on any that became due
+    /// while it was paused
+    pub fn resume_compaction(&self) {
+        self.state.lsm.resume_compaction();
+    }
+
+    /// Apply the settings of `config` that can change while the engine is
+    /// open: the block and value cache sizes and that of the compressed
+    /// block cache tier, if enabled at open (shrinking evicts), the
+    /// compaction triggers, thread count and I/O rate limit, the WAL sync
+    /// policy and the Bloom filter bits of SST files written from now on.
+    /// Paths, the comparator and the checksum algorithms are fixed at open;
+    /// if `config` changes any of them nothing is applied and the error
+    /// names each one. Other settings keep their values until reopening.
+    pub fn reload_config(&self, config: Config) -> Result<()> {
+        self.ensure_open()?;
+        config.validate().map_err(Error::Config)?;
+        let current = &self.state.config;
+        let fixed = [
+            ("db_path", config.db_path != current.db_path),
+            ("wal.wal_path", config.wal.wal_path != current.wal.wal_path),
+            ("value_log.vlog_path", config.value_log.vlog_path != current.value_log.vlog_path),
+            ("sst.sst_path", config.sst.sst_path != current.sst.sst_path),
+            ("in_memory", config.in_memory != current.in_memory),
+            ("comparator", config.comparator.name() != current.comparator.name()),
+            ("wal.checksum", config.wal.checksum != current.wal.checksum),
+            ("value_log.checksum", config.value_log.checksum != current.value_log.checksum),
+            ("sst.checksum", config.sst.checksum != current.sst.checksum),
+            (
+                "value_log.separation_threshold",
+                config.value_log.separation_threshold != current.value_log.separation_threshold,
+            ),
+        ];
+        let changed: Vec<&str> = fixed.iter().filter(|(_, changed)| *changed).map(|(name, _)| *name).collect();
+        if !changed.is_empty() {
+            return Err(Error::Config(format!(
+                "Cannot change {} while the engine is open",
+                changed.join(", ")
+            )));
+        }
+
+        self.state.lsm.reload_config(&config)?;
+        self.state.vlog_cache.lock().set_capacity(config.cache.vlog_cache_size)?;
+        self.state.wal.lock().set_sync_policy(config.wal.sync_policy);
+        Ok(())
+    }
+
+    /// Check the whole database: every block of every live SST file (its
+    /// checksum, key order and Bloom filter), the header and every entry of
+    /// every value log segment, and that every value pointer in the
+    /// memtables and SST files resolves to a value with a valid checksum.
+    /// Damage is listed in the report rather than returned as an error, so
+    /// one bad file does not hide the rest; `Err` means the check itself
+    /// could not run. Separated writes wait while the value log is read.
+    pub fn verify_integrity(&self) -> Result<VerifyReport> {
+        self.ensure_open()?;
+        let mut pointers = Vec::new();
+        let (sst_files, mut corruptions) = self.state.lsm.verify(|source, entry| {
+            if let Some(vptr) = &entry.value_pointer {
+                pointers.push((source.map(Path::to_path_buf), entry.key.data.clone(), vptr.clone()));
+            }
+        });
+
+        let vlog_dir = &self.state.config.value_log.vlog_path;
+        let (vlog_segments, damaged) = if self.state.config.in_memory {
+            (0, Vec::new())
+        } else {
+            // No segment is appended to while it is walked
+            let _writer = self.state.vlog_writer.lock();
+            vlog::verify_segments(vlog_dir, FileAccess::Buffered)?
+        };
+        corruptions.extend(damaged);
+
+        let mut reader = self.state.vlog_reader.lock();
+        for (source, key, vptr) in &pointers {
+            if let Err(e) = reader.read_value_with_verify(vptr, true) {
+                let source = source.as_ref().map_or("a memtable".to_string(), |path| path.display().to_string());
+                corruptions.push(Corruption {
+                    path: vlog::segment_path(vlog_dir, vptr.segment_id).unwrap_or_else(|_| vlog_dir.clone()),
+                    offset: vptr.offset,
+                    message: format!("Value of {:?} in {} does not resolve: {}", String::from_utf8_lossy(key), source, e),
+                });
+            }
+        }
+        Ok(VerifyReport {
+            sst_files,
+            vlog_segments,
+            value_pointers: pointers.len() as u64,
+            corruptions,
+        })
+    }
+
+    /// Load the pairs of a file written by [`Snapshot::export`] through the
+    /// normal write path, in batches, overwriting existing keys. The file
+    /// is read as it is written; a corrupt or truncated file fails the
+    /// import, leaving the batches before the bad record applied.
+    pub fn import_snapshot(&self, path: &Path) -> Result<()> {
+        self.ensure_open()?;
+        let mut batch = Vec::with_capacity(IMPORT_BATCH_SIZE);
+        for pair in SnapshotReader::open(path)? {
+            batch.push(pair?);
+            if batch.len() == IMPORT_BATCH_SIZE {
+                self.write_batch(&batch)?;
+                batch.clear();
+            }
+        }
+        self.write_batch(&batch)
+    }
+
+    /// Version of this AuraDB build
+    pub fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    /// On-disk format version this build writes. Opening a database with a
+    /// newer format fails rather than risk misreading it.
+    pub fn format_version() -> u32 {
+        MANIFEST_VERSION
+    }
+
+    /// On-disk format version of the open database
+    pub fn storage_format_version(&self) -> u32 {
+        self.state.lsm.format_version()
+    }
+
+    /// Upgrade the closed database at `path` from format version `from` to
+    /// `to`; see [`crate::migrate`]. Safe to rerun after an interruption.
+    pub fn migrate(path: &Path, from: u32, to: u32) -> Result<()> {
+        crate::migrate::migrate(path, from, to)
+    }
+
+    /// Salvage what can be read of the closed database at `path`,
+    /// quarantining damaged files and rebuilding the manifest; see
+    /// [`crate::repair`]. Data in damaged files is lost, and the report
+    /// lists it. Never run automatically.
+    pub fn repair(path: &Path, options: RepairOptions) -> Result<RepairReport> {
+        crate::repair::repair(path, options)
+    }
+
+    /// Get the value of an engine property, or `None` if the name is
+    /// unknown. Supported properties:
+    ///
+    /// - `auradb.compaction-paused`: `"1"` if compaction is paused, else `"0"`
+    /// - `auradb.num-files-at-level<N>`: number of SST files in level N
+    /// - `auradb.bytes-at-level<N>` / `auradb.target-bytes-at-level<N>`:
+    ///   total SST size of level N and the size at which it is compacted
+    ///   into the next level (`"none"` for level 0, which is compacted by
+    ///   file count, and for the last level)
+    /// - `auradb.num-immutable-mem-table`: memtables waiting to be flushed
+    /// - `auradb.cur-size-active-mem-table` /
+    ///   `auradb.num-entries-active-mem-table`: memory usage and entry count
+    ///   of the active memtable
+    /// - `auradb.memtable-flush-threshold-bytes` /
+    ///   `auradb.memtable-flush-threshold-entries`: the memory usage and
+    ///   entry count (`"none"` if unlimited) at which the active memtable is
+    ///   flushed, whichever is reached first
+    /// - `auradb.num-compactions-completed`: compactions since open
+    /// - `auradb.running-compactions`: one line per running compaction
+    ///   with its levels, bytes and entries processed so far and elapsed
+    ///   time; empty when none is running
+    /// - `auradb.num-snapshots` / `auradb.oldest-snapshot-sequence`: live
+    ///   [`Snapshot`]s and the sequence number of the oldest (`"0"` if none)
+    /// - `auradb.write-stall`: `"none"`, `"slowdown"` or `"stop"`, from the
+    ///   level 0 file count against `level0_slowdown` / `level0_stop`; writes
+    ///   also stop while `memtable.count` memtables wait to be flushed
+    /// - `auradb.stall-micros`: total time writes were held back
+    /// - `auradb.stall-timeouts`: writes that failed after waiting out
+    ///   their `WriteOptions::stall_timeout`
+    /// - `auradb.compact-read-bytes` / `auradb.compact-write-bytes`: bytes
+    ///   read and written by compactions since open
+    /// - `auradb.write-amplification`, `auradb.read-amplification` and
+    ///   `auradb.space-amplification`: see [`EngineStats`]
+    /// - `auradb.estimate-num-keys`: approximate live key count, see
+    ///   [`Self::estimate_num_keys`]
+    /// - `auradb.active-compaction-strategy`: `"leveled"` or `"tiered"`;
+    ///   the `Flexible` strategy switches between them as the read/write
+    ///   mix changes
+    /// - `auradb.block-cache-hits` / `auradb.block-cache-misses` and
+    ///   `auradb.block-cache-hit-ratio`: SST block cache lookups by point
+    ///   reads, and the fraction that hit (`"0.0000"` before any lookup).
+    ///   Memory-mapped files are not cached.
+    /// - `auradb.compressed-block-cache-hits` /
+    ///   `auradb.compressed-block-cache-misses` and
+    ///   `auradb.compressed-block-cache-hit-ratio`: the same for the
+    ///   compressed tier (`cache.compressed_cache_size`), looked up on block
+    ///   cache misses
+    /// - `auradb.vlog-cache-hits` / `auradb.vlog-cache-misses` and
+    ///   `auradb.vlog-cache-hit-ratio`: the same for the value log cache
+    /// - `auradb.wal-bytes-written` / `auradb.vlog-bytes-written`: bytes
+    ///   appended to WAL and value log files since open, headers included
+    /// - `auradb.sst-logical-bytes-written` /
+    ///   `auradb.sst-physical-bytes-written`: key and value bytes written to
+    ///   SST files since open, and the size of those files on disk
+    /// - `auradb.disk-bytes-read`: SST and value log bytes read from disk
+    ///   since open, i.e. on cache misses, by scans and by compactions
+    /// - `auradb.key-size-histogram` / `auradb.value-size-histogram`: count
+    ///   and percentiles of the key and value sizes of puts since open, see
+    ///   [`HistogramMetric::summary`](crate::metrics::HistogramMetric::summary)
+    /// - `auradb.separated-value-ratio`: fraction of those values written to
+    ///   the value log rather than inline
+    /// - `auradb.cur-size-all-mem-tables`, `auradb.block-cache-usage`,
+    ///   `auradb.compressed-block-cache-usage`, `auradb.vlog-cache-usage`, `auradb.estimate-filter-mem` and
+    ///   `auradb.learned-index-mem`: the components of
+    ///   [`Self::memory_usage`], in bytes
+    pub fn get_property(&self, name: &str) -> Option<String> {
+        match name {
+            "auradb.wal-bytes-written" => Some(self.state.wal.lock().bytes_written().to_string()),
+            "auradb.vlog-bytes-written" => Some(self.vlog_bytes_written().to_string()),
+            "auradb.disk-bytes-read" => Some(self.disk_bytes_read().to_string()),
+            "auradb.vlog-cache-hits" => Some(self.state.vlog_cache.lock().stats().hits.to_string()),
+            "auradb.vlog-cache-misses" => Some(self.state.vlog_cache.lock().stats().misses.to_string()),
+            "auradb.vlog-cache-hit-ratio" => Some(format!("{:.4}", self.state.vlog_cache.lock().stats().hit_ratio())),
+            "auradb.vlog-cache-usage" => Some(self.state.vlog_cache.lock().stats().size.to_string()),
+            "auradb.learned-index-mem" => Some("0".to_string()),
+            "auradb.key-size-histogram" => Some(self.state.write_sizes.keys().to_metric(WriteSizes::KEY_METRIC).summary()),
+            "auradb.value-size-histogram" => {
+                Some(self.state.write_sizes.values().to_metric(WriteSizes::VALUE_METRIC).summary())
+            }
+            "auradb.separated-value-ratio" => Some(format!("{:.4}", self.state.write_sizes.separated_ratio())),
+            _ => self.state.lsm.property(name),
+        }
+    }
+
+    /// Bytes appended to the value log since open
+    fn vlog_bytes_written(&self) -> u64 {
+        self.state.vlog_writer.lock().as_ref().map_or(0, VlogWriter::bytes_written)
+    }
+
+    /// SST and value log bytes read from disk since open
+    fn disk_bytes_read(&self) -> u64 {
+        self.state.lsm.sst_bytes_read() + self.state.vlog_reader.lock().bytes_read()
+    }
+
+    /// Read the SST blocks that may hold keys in `range` into the block
+    /// cache so the first reads after a restart hit it. Stops early once
+    /// the cache is full, and does not block concurrent writes. The range
+    /// limit is ignored; memory-mapped files are not cached.
+    pub fn warm_cache(&self, range: Range) -> Result<()> {
+        self.ensure_open()?;
+        self.state.lsm.warm_cache(Some((&range.start.data, &range.end.data)))
+    }
+
+    /// Read every SST block into the block cache, as far as it fits. Meant
+    /// for datasets that fit in the cache.
+    pub fn warm_cache_all(&self) -> Result<()> {
+        self.ensure_open()?;
+        self.state.lsm.warm_cache(None)
+    }
+
+    /// Keep the SST blocks that may hold keys in `range` in the block
+    /// cache, reading them in if needed, so reads of those keys never go to
+    /// disk. Pinned blocks count against the cache size but are never
+    /// evicted, and files written by later flushes and compactions are
+    /// pinned too. Fails if the pinned blocks would outgrow the cache or
+    /// SST blocks aren't cached (memory-mapped files or no block cache).
+    pub fn pin_range(&self, range: Range) -> Result<()> {
+        self.ensure_open()?;
+        self.state.lsm.pin_range(&range.start.data, &range.end.data)
+    }
+
+    /// Release a range pinned by [`pin_range`](Self::pin_range) with the
+    /// same bounds
+    pub fn unpin_range(&self, range: Range) -> Result<()> {
+        self.ensure_open()?;
+        self.state.lsm.unpin_range(&range.start.data, &range.end.data)
+    }
+
+    /// Approximate number of live keys, computed from file and memtable
+    /// metadata in time proportional to the number of SST files. Exact
+    /// after a full compaction; otherwise keys with versions in several
+    /// levels or memtables are counted more than once, each tombstone is
+    /// assumed to hide exactly one older value, and the estimate may be
+    /// high by up to the number of entries outside the largest level.
+    pub fn estimate_num_keys(&self) -> Result<u64> {
+        self.ensure_open()?;
+        Ok(self.state.lsm.estimate_num_keys())
+    }
+
+    /// Approximate memory held by the engine, itemized by component. Sizes
+    /// count key, value and filter bytes rather than allocator overhead, so
+    /// the total falls somewhat short of the process's resident memory.
+    /// Memory-mapped SST files are not included. Learned indexes don't
+    /// build models yet, so they always report 0.
+    pub fn memory_usage(&self) -> MemoryBreakdown {
+        MemoryBreakdown {
+            memtables: self.state.lsm.memtable_memory(),
+            block_cache: self.state.lsm.block_cache_stats().size,
+            compressed_block_cache: self.state.lsm.compressed_cache_stats().size,
+            vlog_cache: self.state.vlog_cache.lock().stats().size,
+            filters: self.state.lsm.filter_memory(),
+            learned_indexes: 0,
+        }
+    }
+
+    /// Storage footprint and write, read and space amplification. Write
+    /// amplification counts SST and value log bytes (not the WAL) per
+    /// key and value byte written; read amplification counts SST and value
+    /// log bytes read per key and value byte returned, so cache hits bring
+    /// it down; space amplification is the total SST size over the size of
+    /// the largest level. Each is 0 until there is data to compute it.
+    pub fn stats(&self) -> Result<EngineStats> {
+        self.ensure_open()?;
+        let (sst_files, sst_size) = self.state.lsm.sst_footprint();
+        Ok(EngineStats {
+            sst_files,
+            sst_size,
+            user_bytes_written: self.state.lsm.user_bytes_written(),
+            disk_bytes_written: self.state.lsm.disk_bytes_written(),
+            write_amplification: self.state.lsm.write_amplification(),
+            read_amplification: self.state.lsm.read_amplification(),
+            space_amplification: self.state.lsm.space_amplification(),
+            write_stopped: self.state.lsm.is_write_stopped(),
+            immutable_memtables: self.state.lsm.immutable_count() as u64,
+        })
+    }
+
+    /// Latency of `op` at `quantile` (0 to 1, e.g. 0.99), over every such
+    /// operation since the engine opened, including failed ones. `None`
+    /// until the first one completes.
+    pub fn latency_percentile(&self, op: Operation, quantile: f64) -> Option<Duration> {
+        self.state.latencies.percentile(op, quantile)
+    }
+
+    /// Snapshot of the engine's counters, `auradb.block-cache-hits`,
+    /// `auradb.block-cache-misses`, their `auradb.compressed-block-cache-*`
+    /// counterparts, `auradb.vlog-cache-hits`,
+    /// `auradb.vlog-cache-misses` and the I/O byte counters listed under
+    /// [`Self::get_property`], and of its per-operation latency
+    /// histograms in nanoseconds, named like `auradb.get.latency` (see
+    /// [`Operation::metric_name`]). Also has `auradb.key-size` and
+    /// `auradb.value-size` histograms of put sizes in bytes and the
+    /// `auradb.values-separated` counter of values among them written to
+    /// the value log.
+    pub fn metrics(&self) -> Result<MetricsSnapshot> {
+        let block = self.state.lsm.block_cache_stats();
+        let compressed = self.state.lsm.compressed_cache_stats();
+        let vlog = self.state.vlog_cache.lock().stats();
+        let mut collector = MetricsCollector::new();
+        collector.add_counter("auradb.block-cache-hits", block.hits)?;
+        collector.add_counter("auradb.block-cache-misses", block.misses)?;
+        collector.add_counter("auradb.compressed-block-cache-hits", compressed.hits)?;
+        collector.add_counter("auradb.compressed-block-cache-misses", compressed.misses)?;
+        collector.add_counter("auradb.vlog-cache-hits", vlog.hits)?;
+        collector.add_counter("auradb.vlog-cache-misses", vlog.misses)?;
+        collector.add_counter("auradb.wal-bytes-written", self.state.wal.lock().bytes_written())?;
+        collector.add_counter("auradb.vlog-bytes-written", self.vlog_bytes_written())?;
+        collector.add_counter("auradb.sst-logical-bytes-written", self.state.lsm.sst_logical_bytes_written())?;
+        collector.add_counter("auradb.sst-physical-bytes-written", self.state.lsm.sst_physical_bytes_written())?;
+        collector.add_counter("auradb.disk-bytes-read", self.disk_bytes_read())?;
+        for op in Operation::ALL {
+            collector.add_histogram(op.metric_name(), self.state.latencies.histogram(op))?;
+        }
+        collector.add_histogram(WriteSizes::KEY_METRIC, self.state.write_sizes.keys())?;
+        collector.add_histogram(WriteSizes::VALUE_METRIC, self.state.write_sizes.values())?;
+        collector.add_counter(WriteSizes::SEPARATED_METRIC, self.state.write_sizes.separated())?;
+        Ok(collector.snapshot())
+    }
+}
+
+impl EngineState {
+    /// Lock the WAL for a write or a memtable switch. With `settled`,
+    /// first wait for the writes in flight to become visible, for callers
+    /// that read what those writes replace or need them in the memtables.
+    fn lock_wal(&self, settled: bool) -> MutexGuard<'_, WalWriter> {
+        let wal = self.wal.lock();
+        if settled {
+            self.lsm.wait_for_writes();
+        }
+        wal
+    }
+
+    /// [`AuraEngine::flush`] without the open check, for `close` and drop
+    fn flush_memtables(&self) -> Result<()> {
+        if self.config.in_memory {
+            let _wal = self.wal.lock();
+            self.lsm.clear_memtables();
+            return Ok(());
+        }
+        {
+            let mut wal = self.lock_wal(true);
+            if !self.lsm.active_is_empty() {
+                let next_log_number = wal.rotate()?;
+                self.lsm.switch_memtable(next_log_number);
+            }
+        }
+        self.lsm.flush_immutables()
+    }
+}
+
+impl Drop for EngineState {
+    /// Best-effort `close` for an engine dropped without one: flush the
+    /// memtables (persisting the manifest), fsync the WAL and close the
+    /// value log. Every step is synchronous, so this is safe with or
+    /// without a Tokio runtime.
+    fn drop(&mut self) {
+        if *self.closed.read() {
+            return;
+        }
+        info!("AuraEngine dropped without close(); flushing and closing");
+        if let Err(e) = self.flush_memtables() {
+            warn!("Failed to flush memtables on drop, unflushed writes are discarded: {}", e);
+        }
+        *self.closed.write() = true;
+        self.lsm.shutdown();
+        if let Err(e) = self.lsm.save_rl_policy() {
+            warn!("Failed to save RL policy on drop: {}", e);
+        }
+        if let Err(e) = self.wal.lock().close() {
+            warn!("Failed to close WAL on drop, buffered records may be discarded: {}", e);
+        }
+        // The writer's own Drop stops its queues and closes its segments
+        drop(self.vlog_writer.lock().take());
+    }
+}
+
+#[async_trait::async_trait]
+impl Engine for AuraEngine {
+    async fn put(&self, key: Key, value: Value) -> Result<()> {
+        let engine = self.handle();
+        run_blocking(move || engine.put_internal(key.data, value, &WriteOptions::default())).await
+    }
+    
+    async fn get(&self, key: &Key) -> Result<Option<Value>> {
+        let (engine, key) = (self.handle(), key.data.clone());
+        run_blocking(move || Ok(engine.get_internal(&key)?.map(Value::new))).await
+    }
+    
+    async fn delete(&self, key: &Key) -> Result<()> {
+        let (engine, key) = (self.handle(), key.data.clone());
+        run_blocking(move || engine.delete_internal(key, false, &WriteOptions::default()).map(|_| ())).await
+    }
+    
+    async fn scan(&self, range: Range) -> Result<Vec<(Key, Value)>> {
+        let engine = self.handle();
+        run_blocking(move || {
+            Ok(engine
+                .scan_internal(&range.start.data, &range.end.data)?
+                .into_iter()
+                .map(|(key, value)| (Key::new(key), Value::new(value)))
+                .collect())
+        })
+        .await
+    }
+    
+    async fn write_batch(&self, batch: &Batch) -> Result<()> {
+        let (engine, batch) = (self.handle(), batch.clone());
+        run_blocking(move || engine.write_batch_internal(&batch, &WriteOptions::default())).await
+    }
+    
+    async fn snapshot(&self) -> Result<Snapshot> {
+        self.ensure_open()?;
+        Ok(Snapshot {
+            view: self.state.lsm.snapshot(),
+            vlog_reader: Arc::clone(&self.state.vlog_reader),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        })
+    }
+    
+    async fn close(&self) -> Result<()> {
+        {
+            // Mark the engine closed first so new operations fail cleanly;
+            // a second close is a no-op
+            let mut closed = self.state.closed.write();
+            if *closed {
+                return Ok(());
+            }
+            *closed = true;
+        }
+        let state = Arc::clone(&self.state);
+        run_blocking(move || -> Result<()> {
+            // Persist the memtables so reopening starts from SST files
+            // rather than a WAL replay
+            if !state.config.in_memory {
+                state.flush_memtables()?;
+            }
+            state.lsm.shutdown();
+            state.lsm.save_rl_policy()?;
+            state.wal.lock().close()
+        })
+        .await?;
+
+        let writer = self.state.vlog_writer.lock().take();
+        if let Some(mut writer) = writer {
+            writer.close().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Run synchronous engine work (lock waits, WAL fsyncs, value log reads,
+/// write stalls) from an async method: on the runtime's blocking pool, or
+/// on a thread of its own when no Tokio runtime is running, so it never
+/// holds up an executor thread, whatever the runtime flavor. A panic in
+/// `f` is resumed in the caller.
+async fn run_blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    let outcome = match tokio::runtime::Handle::try_current() {
+        Ok(handle) => match handle.spawn_blocking(f).await {
+            Ok(result) => Ok(result),
+            Err(e) if e.is_panic() => Err(e.into_panic()),
+            // The runtime is shutting down and never started the work
+            Err(_) => return Err(Error::Concurrency("runtime shut down".to_string())),
+        },
+        Err(_) => {
+            let (sender, receiver) = oneshot::channel();
+            std::thread::Builder::new()
+                .name("auradb-blocking".to_string())
+                .spawn(move || sender.send(std::panic::catch_unwind(std::panic::AssertUnwindSafe(f))))?;
+            receiver.await.map_err(|_| Error::Concurrency("blocking thread exited".to_string()))?
+        }
+    };
+    outcome.unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+/// Value log cache key for a value pointer
+fn vlog_cache_key(vptr: &ValuePointer) -> Vec<u8> {
+    let mut key = Vec::with_capacity(16);
+    key.extend_from_slice(&vptr.segment_id.to_le_bytes());
+    key.extend_from_slice(&vptr.offset.to_le_bytes());
+    key
+}
+
+/// Database snapshot: a consistent view of the engine as of its creation.
+///
+/// Nothing is copied: the snapshot reads the memtables as of its sequence
+/// number, keeping sealed ones in memory, and pins the SST files then
+/// current, so compactions leave them on disk and its reads keep seeing
+/// the old values until it is dropped; separated values stay in the
+/// value log, which is never garbage collected. The live snapshots are
+/// counted by the `auradb.num-snapshots` property.
+pub struct Snapshot {
+    /// Pinned memtables and SST files
+    view: TreeSnapshot,
+    /// Resolves separated values
+    vlog_reader: Arc<Mutex<VlogReader>>,
+    /// Timestamp when snapshot was created
+    pub timestamp: u64,
+}
+
+impl Snapshot {
+    /// Last sequence number the snapshot includes
+    pub fn sequence(&self) -> u64 {
+        self.view.sequence()
+    }
+
+    /// Value of `key` as of the snapshot
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let entries = self.view.get(key)?.filter(|e| !e.is_delete()).into_iter().collect();
+        Ok(self.resolve(entries)?.pop().map(|(_, value)| value))
+    }
+
+    /// Pairs with keys in `[start, end]` as of the snapshot, in comparator
+    /// order
+    pub fn scan(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.resolve(self.view.scan(start, end)?)
+    }
+
+    /// Iterate over every pair as of the snapshot, in comparator order.
+    /// Entries are merged from the snapshot's memtables and SST files as
+    /// the iteration goes, [`RESOLVE_BATCH_SIZE`] at a time, with the
+    /// separated values of each batch read together, so only one batch is
+    /// held in memory.
+    pub fn iter(&self) -> Result<impl Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + '_> {
+        let mut entries = self.view.iter();
+        let batches = std::iter::from_fn(move || {
+            let batch: Result<Vec<Entry>> = entries.by_ref().take(RESOLVE_BATCH_SIZE).collect();
+            match batch {
+                Ok(batch) if batch.is_empty() => None,
+                Ok(batch) => Some(self.resolve(batch)),
+                Err(e) => Some(Err(e)),
+            }
+        });
+        Ok(batches.flat_map(|batch| {
+            let (pairs, error) = match batch {
+                Ok(pairs) => (pairs, None),
+                Err(e) => (Vec::new(), Some(Err(e))),
+            };
+            pairs.into_iter().map(Ok).chain(error)
+        }))
+    }
+
+    /// Live entries to key/value pairs, reading separated values in one
+    /// batch. Secondary index entries are left out.
+    fn resolve(&self, entries: Vec<Entry>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let entries: Vec<Entry> = entries.into_iter().filter(|e| !secondary::is_index_key(&e.key.data)).collect();
+        let pointers: Vec<ValuePointer> = entries.iter().filter_map(|e| e.value_pointer.clone()).collect();
+        let values = if pointers.is_empty() {
+            Vec::new()
+        } else {
+            self.vlog_reader.lock().read_values(&pointers)?
+        };
+        for value in &values {
+            self.view.count_value_read(value.data.len() as u64);
+        }
+        let mut values = values.into_iter();
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| {
+                let value = match entry.value_pointer {
+                    Some(_) => values.next().map(|v| v.data),
+                    None => entry.value.map(|v| v.data),
+                };
+                value.map(|value| (entry.key.data, value))
+            })
+            .collect())
+    }
+
+    /// Write the snapshot's pairs to a portable file at `path` (see
+    /// [`crate::export`]), record by record as [`Self::iter`] resolves
+    /// them, for loading with [`AuraEngine::import_snapshot`]
+    pub fn export(&self, path: &Path) -> Result<()> {
+        let mut writer = SnapshotWriter::create(path)?;
+        for pair in self.iter()? {
+            let (key, value) = pair?;
+            writer.write(&key, &value)?;
+        }
+        writer.finish()
+    }
+}
+
+/// Engine options
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Database path
+    pub path: PathBuf,
+    /// Whether to create the database if the path holds none
+    pub create_if_missing: bool,
+    /// Whether to error if the database already exists
+    pub error_if_exists: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("./auradb_data"),
+            create_if_missing: true,
+            error_if_exists: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use std::sync::atomic::Ordering;
+    use crate::config::{ChecksumType, WalSyncPolicy};
+
+    #[tokio::test]
+    async fn test_small_values_stay_inline() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+
+        engine.put_bytes(b"small", b"value").unwrap();
+
+        let entry = engine.state.lsm.get(b"small").unwrap().unwrap();
+        assert!(entry.value_pointer.is_none());
+        assert_eq!(engine.get_bytes(b"small").unwrap(), Some(b"value".to_vec()));
+
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_large_values_are_separated() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        let threshold = engine.state.config.value_log.separation_threshold;
+        let large = vec![7u8; threshold];
+
+        engine.put_bytes(b"large", &large).unwrap();
+
+        let entry = engine.state.lsm.get(b"large").unwrap().unwrap();
+        assert!(entry.value.is_none());
+        assert!(entry.value_pointer.is_some());
+        assert_eq!(engine.get_bytes(b"large").unwrap(), Some(large.clone()));
+
+        // Repeated shared reads hand out the same cached buffer
+        let first = engine.get_shared(b"large").unwrap().unwrap();
+        let second = engine.get_shared(b"large").unwrap().unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(&*first, large.as_slice());
+
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_force_inline_keeps_large_values_inline() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        let large = vec![9u8; engine.state.config.value_log.separation_threshold * 2];
+        let inline = WriteOptions::default().with_force_inline(true);
+        let vlog_before = engine.vlog_bytes_written();
+        engine.put_with_options(b"hot", &large, &inline).unwrap();
+
+        let entry = engine.state.lsm.get(b"hot").unwrap().unwrap();
+        assert!(entry.value_pointer.is_none());
+        assert_eq!(engine.vlog_bytes_written(), vlog_before);
+        engine.flush().unwrap();
+        assert_eq!(engine.get_bytes(b"hot").unwrap(), Some(large.clone()));
+        assert_eq!(engine.get_range(&Key::from("hot"), 4, 4).unwrap(), Some(large[4..8].to_vec()));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_streamed_values_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.value_log.stream_chunk_size = 4096;
+        config.max_value_size = 64 * 1024;
+        let engine = AuraEngine::new(config.clone()).unwrap();
+        let value: Vec<u8> = (0..50_000u32).map(|i| (i % 251) as u8).collect();
+
+        engine.put_stream(&Key::from("blob"), value.as_slice()).unwrap();
+        let entry = engine.state.lsm.get(b"blob").unwrap().unwrap();
+        assert_eq!(entry.value_pointer.as_ref().unwrap().length as usize, value.len());
+        let mut read = Vec::new();
+        engine.get_stream(&Key::from("blob")).unwrap().unwrap().read_to_end(&mut read).unwrap();
+        assert_eq!(read, value);
+        assert_eq!(engine.get_bytes(b"blob").unwrap(), Some(value.clone()));
+
+        // Inline values stream too, and missing keys have no stream
+        engine.put_str("small", "inline").unwrap();
+        let mut read = String::new();
+        engine.get_stream(&Key::from("small")).unwrap().unwrap().read_to_string(&mut read).unwrap();
+        assert_eq!(read, "inline");
+        assert!(engine.get_stream(&Key::from("missing")).unwrap().is_none());
+
+        // An oversized stream fails without replacing the value or leaving
+        // its segment behind
+        let segments = || std::fs::read_dir(&config.value_log.vlog_path).unwrap().count();
+        let before = segments();
+        let oversized = std::io::repeat(1).take(config.max_value_size as u64 + 1);
+        assert!(matches!(engine.put_stream(&Key::from("blob"), oversized), Err(Error::Config(_))));
+        assert_eq!(segments(), before);
+        assert_eq!(engine.get_bytes(b"blob").unwrap(), Some(value.clone()));
+        engine.flush().unwrap();
+        engine.close().await.unwrap();
+        drop(engine);
+
+        // The value survives a reopen; damage shows up at the end of the stream
+        let path = crate::vlog::segment_path(&config.value_log.vlog_path, entry.value_pointer.unwrap().segment_id).unwrap();
+        let mut bytes = std::fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xFF;
+        std::fs::write(&path, bytes).unwrap();
+        let engine = AuraEngine::new(config).unwrap();
+        let mut stream = engine.get_stream(&Key::from("blob")).unwrap().unwrap();
+        let err = stream.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scan_entries_keeps_tombstones_and_metadata() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        let large = vec![3u8; engine.state.config.value_log.separation_threshold];
+
+        engine.put_str("a", "1").unwrap();
+        engine.put_bytes(b"b", &large).unwrap();
+        engine.put_str("c", "3").unwrap();
+        engine.flush().unwrap();
+        engine.delete_str("a").unwrap();
+
+        let range = Range::new(Key::from("a"), Key::from("c"));
+        let entries = engine.scan_entries(range.clone()).unwrap();
+        let keys: Vec<&[u8]> = entries.iter().map(|e| e.key.data.as_slice()).collect();
+        assert_eq!(keys, vec![&b"a"[..], b"b", b"c"]);
+        assert!(entries[0].is_delete());
+        assert!(entries[0].sequence > entries[2].sequence);
+        assert_eq!(entries[1].value.as_ref().unwrap().data, large);
+
+        // `scan` over the same range drops the tombstone
+        assert_eq!(engine.scan_str("a", "c").unwrap().len(), 2);
+
+        let limited: Vec<Entry> = engine
+            .scan_entries_iter(range.with_limit(1))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(limited.len(), 1);
+
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scan_entries_with_options_tombstones() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+
+        // The older version of "b" lands in an SST, its tombstone in the memtable
+        engine.put_str("a", "1").unwrap();
+        engine.put_str("b", "2").unwrap();
+        engine.flush().unwrap();
+        engine.delete_str("b").unwrap();
+        engine.put_str("c", "3").unwrap();
+
+        let range = Range::new(Key::from("a"), Key::from("c"));
+        let scan = |options: ReadOptions, range: Range| -> Vec<Entry> {
+            engine.scan_entries_with_options(range, &options).unwrap().collect::<Result<_>>().unwrap()
+        };
+
+        let live = scan(ReadOptions::default(), range.clone());
+        let keys: Vec<&[u8]> = live.iter().map(|e| e.key.data.as_slice()).collect();
+        assert_eq!(keys, vec![&b"a"[..], b"c"]);
+
+        let all = scan(ReadOptions::default().with_include_tombstones(true), range.clone());
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[1].key.data, b"b");
+        assert_eq!(all[1].op_type, OpType::Delete);
+        assert!(all[1].value.is_none());
+
+        // Skipped tombstones don't count against the limit
+        let limited = scan(ReadOptions::default(), Range::new(Key::from("b"), Key::from("c")).with_limit(1));
+        assert_eq!(limited.len(), 1);
+        assert_eq!(limited[0].key.data, b"c");
+
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_see_committed_writes() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        let mut first = engine.subscribe();
+        let mut second = engine.subscribe();
+
+        engine.put_str("k", "v").unwrap();
+        engine.delete_str("k").unwrap();
+
+        for rx in [&mut first, &mut second] {
+            let put = rx.recv().await.unwrap();
+            assert_eq!((put.key.as_slice(), &put.op), (&b"k"[..], &OpType::Put));
+            let delete = rx.recv().await.unwrap();
+            assert_eq!(delete.op, OpType::Delete);
+            assert!(delete.sequence > put.sequence);
+        }
+
+        // A subscriber that falls behind is told how much it missed
+        let mut slow = engine.subscribe();
+        drop(first);
+        for i in 0..CHANGE_EVENT_CAPACITY + 1 {
+            engine.put_str(&format!("key{}", i), "v").unwrap();
+        }
+        assert!(matches!(slow.recv().await, Err(broadcast::error::RecvError::Lagged(1))));
+
+        engine.close().await.unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_writers_read_their_writes_and_publish_in_order() {
+        const THREADS: usize = 8;
+        const WRITES: usize = 100;
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.memtable.max_size = 16 * 1024;
+        config.wal.sync_policy = WalSyncPolicy::EveryWrite;
+        config.wal.async_writes = false;
+        let engine = Arc::new(AuraEngine::new(config.clone()).unwrap());
+
+        let mut events = engine.subscribe();
+        let subscriber = std::thread::spawn(move || {
+            (0..THREADS * WRITES).map(|_| events.blocking_recv().unwrap().sequence).collect::<Vec<_>>()
+        });
+        let writers: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let engine = engine.clone();
+                std::thread::spawn(move || {
+                    for i in 0..WRITES {
+                        let key = format!("t{}_{:03}", t, i);
+                        engine.put_bytes(key.as_bytes(), &[b'v'; 100]).unwrap();
+                        assert!(engine.get_bytes(key.as_bytes()).unwrap().is_some(), "{} not visible", key);
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        // Events arrive in sequence order, with no gaps between writes
+        let sequences = subscriber.join().unwrap();
+        assert!(sequences.windows(2).all(|w| w[1] == w[0] + 1));
+        // Memtables were switched while writers were in flight
+        assert!(engine.state.wal.lock().log_number() > 1);
+
+        // Every write was synced before it returned
+        std::mem::forget(Arc::into_inner(engine).unwrap());
+        let engine = AuraEngine::new(config).unwrap();
+        for t in 0..THREADS {
+            for i in 0..WRITES {
+                let key = format!("t{}_{:03}", t, i);
+                assert!(engine.get_bytes(key.as_bytes()).unwrap().is_some(), "{} lost", key);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ingest_sst() {
+        use crate::sst::SstWriter;
+
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path().join("db")).build().unwrap();
+        engine.put_str("b", "old").unwrap();
+
+        let write_external = |name: &str, keys: &[&str]| {
+            let path = temp_dir.path().join(name);
+            let mut writer = SstWriter::new(&path, &engine.state.config.sst, FileAccess::Buffered).unwrap();
+            for key in keys {
+                writer.add(&Entry::new(Key::from(*key), Value::from("ingested"), 1)).unwrap();
+            }
+            writer.finish().unwrap();
+            path
+        };
+
+        // Overlapping the memtable: flushed, then the file wins
+        let overlapping = write_external("overlap.sst", &["a", "b"]);
+        let disjoint = write_external("disjoint.sst", &["x", "y"]);
+        engine.ingest_sst(&[overlapping, disjoint]).unwrap();
+
+        assert_eq!(engine.get_str("b").unwrap(), Some("ingested".to_string()));
+        assert_eq!(engine.get_str("y").unwrap(), Some("ingested".to_string()));
+        let bottom = format!("auradb.num-files-at-level{}", crate::sst::NUM_LEVELS - 1);
+        assert_eq!(engine.get_property(&bottom), Some("1".to_string()));
+
+        // Writes after ingestion still supersede ingested data
+        engine.put_str("b", "new").unwrap();
+        assert_eq!(engine.get_str("b").unwrap(), Some("new".to_string()));
+
+        let unordered = write_external("unordered.sst", &["m", "k"]);
+        assert!(matches!(engine.ingest_sst(&[unordered]), Err(Error::SstCorruption(_))));
+
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ingest_sst_replacing() {
+        use crate::sst::SstWriter;
+
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path().join("db")).build().unwrap();
+        let write_external = |name: &str, keys: &[&str]| {
+            let path = temp_dir.path().join(name);
+            let mut writer = SstWriter::new(&path, &engine.state.config.sst, FileAccess::Buffered).unwrap();
+            for key in keys {
+                writer.add(&Entry::new(Key::from(*key), Value::from("ingested"), 1)).unwrap();
+            }
+            writer.finish().unwrap();
+            path
+        };
+
+        // Old data in level 1, level 0 and the memtable
+        for key in ["user:1", "user:2", "user:3", "zone:1"] {
+            engine.put_str(key, "old").unwrap();
+        }
+        engine.flush().unwrap();
+        engine.compact_range(None, None).unwrap();
+        engine.put_str("user:4", "old").unwrap();
+        engine.flush().unwrap();
+        engine.put_str("user:5", "old").unwrap();
+
+        let users = write_external("users.sst", &["user:2", "user:9"]);
+        engine.ingest_sst_replacing(Some(b"user:"), &[users]).unwrap();
+        for key in ["user:1", "user:3", "user:4", "user:5"] {
+            assert_eq!(engine.get_str(key).unwrap(), None, "{}", key);
+        }
+        assert_eq!(engine.get_str("user:2").unwrap().as_deref(), Some("ingested"));
+        assert_eq!(engine.get_str("zone:1").unwrap().as_deref(), Some("old"));
+        let keys: Vec<String> = engine.scan_str("a", "z~").unwrap().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["user:2", "user:9", "zone:1"]);
+
+        // Files outside the prefix are refused before anything changes
+        let stray = write_external("stray.sst", &["other"]);
+        assert!(matches!(engine.ingest_sst_replacing(Some(b"user:"), &[stray]), Err(Error::Config(_))));
+
+        // Replacing everything also drops the largest key, which has no successor
+        let all = write_external("all.sst", &["b"]);
+        engine.ingest_sst_replacing(None, &[all]).unwrap();
+        let keys: Vec<String> = engine.scan_str("a", "z~").unwrap().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["b"]);
+
+        // Later writes supersede the ingested data
+        engine.put_str("b", "new").unwrap();
+        assert_eq!(engine.get_str("b").unwrap().as_deref(), Some("new"));
+        engine.compact_range(None, None).unwrap();
+        assert_eq!(engine.scan_str("a", "z~").unwrap(), [("b".to_string(), "new".to_string())]);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_key_and_value_size_limits() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config::default().with_db_path(temp_dir.path().to_path_buf()).with_size_limits(4, 8);
+        let engine = AuraEngine::new(config).unwrap();
+
+        assert!(matches!(engine.put_bytes(b"", b"v"), Err(Error::EmptyKey)));
+        assert!(matches!(engine.delete_str(""), Err(Error::EmptyKey)));
+        match engine.put_bytes(b"toolong", b"v") {
+            Err(Error::Config(msg)) => assert!(msg.contains("max_key_size") && msg.contains("7 bytes")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match engine.write_batch(&[(b"ok".to_vec(), b"v".to_vec()), (b"big".to_vec(), vec![0; 9])]) {
+            Err(Error::Config(msg)) => assert!(msg.contains("max_value_size")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // A rejected batch writes nothing
+        assert_eq!(engine.get_bytes(b"ok").unwrap(), None);
+        engine.put_bytes(b"key", b"value").unwrap();
+
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reads_see_writes_once_they_return() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.wal.async_writes = true;
+        // Memtables seal and flush in the background throughout
+        config.memtable.max_size = 8 * 1024;
+        let engine = AuraEngine::new(config).unwrap();
+        let threshold = engine.state.config.value_log.separation_threshold;
+
+        std::thread::scope(|scope| {
+            for t in 0..4 {
+                let engine = &engine;
+                scope.spawn(move || {
+                    for i in 0..300 {
+                        let key = format!("t{}_{:04}", t, i);
+                        let value = if i % 7 == 0 { vec![i as u8; threshold] } else { key.as_bytes().to_vec() };
+                        engine.put_bytes(key.as_bytes(), &value).unwrap();
+                        assert_eq!(engine.get_bytes(key.as_bytes()).unwrap(), Some(value), "{}", key);
+
+                        if i % 10 == 0 {
+                            let mut batch = Batch::new();
+                            batch.put(Key::from(format!("{}_batch", key).as_str()), Value::from("b"));
+                            batch.delete(Key::from(key.as_str()));
+                            engine.write_batch_internal(&batch, &WriteOptions::default()).unwrap();
+                            assert_eq!(engine.get_bytes(key.as_bytes()).unwrap(), None, "{}", key);
+                            assert_eq!(engine.get_str(&format!("{}_batch", key)).unwrap().as_deref(), Some("b"));
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(engine.scan_str("t0_", "t0_9").unwrap().len(), 300);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_checked_reports_existence() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        engine.put_str("memtable", "1").unwrap();
+        engine.put_str("flushed", "2").unwrap();
+        engine.flush().unwrap();
+        engine.put_str("memtable", "1").unwrap();
+
+        assert!(engine.delete_checked(&Key::from("memtable")).unwrap());
+        assert!(engine.delete_bytes_checked(b"flushed").unwrap());
+        assert!(!engine.delete_checked(&Key::from("memtable")).unwrap());
+        assert!(!engine.delete_bytes_checked(b"flushed").unwrap());
+        assert!(!engine.delete_checked(&Key::from("never")).unwrap());
+        assert_eq!(engine.get_str("memtable").unwrap(), None);
+        assert_eq!(engine.get_str("flushed").unwrap(), None);
+        // No tombstone is written for a missing key
+        assert!(engine.state.lsm.get(b"never").unwrap().is_none());
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_operations_fail_after_close() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        engine.put_str("key", "value").unwrap();
+        engine.close().await.unwrap();
+        engine.close().await.unwrap();
+
+        let is_closed = |result: Result<()>| matches!(result, Err(Error::Config(msg)) if msg == "engine is closed");
+        assert!(is_closed(engine.put_str("key", "value")));
+        assert!(is_closed(engine.get_str("key").map(|_| ())));
+        assert!(is_closed(engine.delete_str("key")));
+        assert!(is_closed(engine.scan_str("a", "z").map(|_| ())));
+        assert!(is_closed(engine.get(&Key::from("key")).await.map(|_| ())));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_blocked_writes_do_not_starve_readers() {
+        let temp_dir = tempdir().unwrap();
+        let engine = Arc::new(EngineBuilder::new().path(temp_dir.path()).build().unwrap());
+        engine.put_str("key", "value").unwrap();
+
+        // Hold the WAL lock so writers block, more of them than workers
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let holder = {
+            let engine = engine.clone();
+            std::thread::spawn(move || {
+                let _wal = engine.state.wal.lock();
+                locked_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+            })
+        };
+        locked_rx.recv().unwrap();
+        let writers: Vec<_> = (0..4)
+            .map(|i| {
+                let engine = engine.clone();
+                tokio::spawn(async move { engine.put(Key::from(format!("w{}", i)), Value::from("v")).await })
+            })
+            .collect();
+
+        let readers: Vec<_> = (0..2000)
+            .map(|_| {
+                let engine = engine.clone();
+                tokio::spawn(async move { engine.get(&Key::from("key")).await })
+            })
+            .collect();
+        let reads = tokio::time::timeout(Duration::from_secs(10), async {
+            for reader in readers {
+                assert_eq!(reader.await.unwrap().unwrap(), Some(Value::from("value")));
+            }
+        });
+        assert!(reads.await.is_ok(), "readers starved behind blocked writers");
+
+        release_tx.send(()).unwrap();
+        holder.join().unwrap();
+        for writer in writers {
+            writer.await.unwrap().unwrap();
+        }
+        assert_eq!(engine.get_str("w3").unwrap(), Some("v".to_string()));
+        engine.close().await.unwrap();
+    }
+
+    #[test]
+    fn test_drop_without_close_persists_writes() {
+        // No runtime: dropping must not rely on one
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        engine.put_str("key", "value").unwrap();
+        engine.put_bytes(b"large", &vec![7u8; 64 * 1024]).unwrap();
+        drop(engine);
+
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        assert_eq!(engine.get_str("key").unwrap().as_deref(), Some("value"));
+        assert_eq!(engine.get_bytes(b"large").unwrap(), Some(vec![7u8; 64 * 1024]));
+    }
+
+    #[tokio::test]
+    async fn test_scan_prefix_with_prefix_filters() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new()
+            .path(temp_dir.path())
+            .prefix_extractor(PrefixExtractor::fixed(4))
+            .build()
+            .unwrap();
+
+        engine.put_str("usr:1", "a").unwrap();
+        engine.put_str("usr:2", "b").unwrap();
+        engine.flush().unwrap();
+        engine.put_str("org:1", "c").unwrap();
+        engine.delete_str("usr:2").unwrap();
+
+        let keys: Vec<Vec<u8>> = engine.scan_prefix(b"usr:").unwrap().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![b"usr:1".to_vec()]);
+        assert_eq!(engine.scan_prefix(b"org:").unwrap().len(), 1);
+        assert!(engine.scan_prefix(b"xyz:").unwrap().is_empty());
+        // Prefixes outside the extractor's domain still scan every file
+        assert_eq!(engine.scan_prefix(b"u").unwrap().len(), 1);
+
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scan_filtered() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        for i in 0..10 {
+            engine.put_str(&format!("k{}", i), if i % 2 == 0 { "even" } else { "odd" }).unwrap();
+        }
+        engine.delete_str("k4").unwrap();
+
+        let even = engine
+            .scan_filtered(Range::new(Key::from("k0"), Key::from("k9")), |_, value| value == b"even")
+            .unwrap();
+        let keys: Vec<&[u8]> = even.iter().map(|(k, _)| k.as_slice()).collect();
+        assert_eq!(keys, vec![&b"k0"[..], b"k2", b"k6", b"k8"]);
+
+        let limited = engine
+            .scan_filtered(Range::new(Key::from("k0"), Key::from("k9")).with_limit(2), |key, _| key > b"k5".as_slice())
+            .unwrap();
+        assert_eq!(limited.len(), 2);
+        assert_eq!(limited[0].0, b"k6");
+
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scan_page_resumes_after_token() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        for i in 0..7 {
+            engine.put_str(&format!("k{}", i), "v").unwrap();
+        }
+        let range = Range::new(Key::from("k0"), Key::from("k9"));
+
+        let (page, token) = engine.scan_page(range.clone(), None, 3).unwrap();
+        let keys: Vec<&[u8]> = page.iter().map(|(k, _)| k.data.as_slice()).collect();
+        assert_eq!(keys, vec![&b"k0"[..], b"k1", b"k2"]);
+        assert_eq!(token, Some(Key::from("k2")));
+
+        // Writes between pages: behind the token they're not revisited,
+        // ahead of it they show up
+        engine.put_str("k1", "changed").unwrap();
+        engine.delete_str("k3").unwrap();
+        engine.put_str("k8", "v").unwrap();
+
+        let mut keys = Vec::new();
+        let mut after = token;
+        while after.is_some() {
+            let (page, token) = engine.scan_page(range.clone(), after, 3).unwrap();
+            keys.extend(page.into_iter().map(|(k, _)| k.data));
+            after = token;
+        }
+        assert_eq!(keys, vec![b"k4".to_vec(), b"k5".to_vec(), b"k6".to_vec(), b"k8".to_vec()]);
+
+        assert!(matches!(engine.scan_page(range, None, 0), Err(Error::Config(_))));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reverse_comparator_orders_scans() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new()
+            .path(temp_dir.path())
+            .comparator(Comparator::reverse())
+            .build()
+            .unwrap();
+
+        for key in ["a", "b", "c", "d"] {
+            engine.put_str(key, key).unwrap();
+        }
+
+        let keys: Vec<String> = engine.scan_str("c", "a").unwrap().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec!["c", "b", "a"]);
+
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_format_version_checked_on_open() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        assert_eq!(engine.storage_format_version(), AuraEngine::format_version());
+        assert!(!AuraEngine::version().is_empty());
+        engine.close().await.unwrap();
+        drop(engine);
+
+        let mut manifest = crate::manifest::Manifest::load(temp_dir.path()).unwrap().unwrap();
+        manifest.version = AuraEngine::format_version() + 1;
+        manifest.save(temp_dir.path()).unwrap();
+        let result = EngineBuilder::new().path(temp_dir.path()).build();
+        assert!(matches!(result, Err(Error::Config(message)) if message.contains("format version")));
+    }
+
+    #[tokio::test]
+    async fn test_comparator_mismatch_on_reopen() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new()
+            .path(temp_dir.path())
+            .comparator(Comparator::reverse())
+            .build()
+            .unwrap();
+        engine.close().await.unwrap();
+        drop(engine);
+
+        let result = EngineBuilder::new().path(temp_dir.path()).build();
+        assert!(matches!(result, Err(Error::Config(_))));
+
+        let reopened = EngineBuilder::new()
+            .path(temp_dir.path())
+            .comparator(Comparator::reverse())
+            .build();
+        assert!(reopened.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_creation_settings_checked_on_reopen() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        AuraEngine::new(config.clone()).unwrap().close().await.unwrap();
+
+        let mut changed = config.clone();
+        changed.sst.checksum = ChecksumType::XxHash3;
+        changed.value_log.separation_threshold *= 2;
+        let err = AuraEngine::new(changed).err().unwrap();
+        let Error::Config(msg) = err else { panic!("expected a config error, got {:?}", err) };
+        assert!(msg.contains("sst.checksum (database uses Crc32, configured XxHash3)"), "{}", msg);
+        assert!(msg.contains("value_log.separation_threshold"), "{}", msg);
+        assert!(!msg.contains("wal.checksum"), "{}", msg);
+
+        // Mutable settings may change freely
+        let mut tuned = config;
+        tuned.cache.block_cache_size /= 2;
+        tuned.wal.sync_policy = WalSyncPolicy::Manual;
+        AuraEngine::new(tuned).unwrap().close().await.unwrap();
+    }
+
+    /// Poll `property` until `done` accepts its value
+    fn wait_for_property(engine: &AuraEngine, property: &str, done: impl Fn(usize) -> bool) -> usize {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        loop {
+            let value: usize = engine.get_property(property).unwrap().parse().unwrap();
+            if done(value) || std::time::Instant::now() > deadline {
+                return value;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume_compaction() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.memtable.max_size = 4 * 1024;
+        config.compaction.triggers.level0_files = 2;
+        let engine = AuraEngine::new(config).unwrap();
+
+        engine.pause_compaction();
+        assert_eq!(engine.get_property("auradb.compaction-paused").as_deref(), Some("1"));
+
+        for i in 0..400 {
+            engine.put_str(&format!("key_{:04}", i), "value").unwrap();
+        }
+        wait_for_property(&engine, "auradb.num-immutable-mem-table", |n| n == 0);
+
+        // Flushes continue while paused, but level 0 is never compacted
+        assert!(engine.get_property("auradb.num-files-at-level0").unwrap().parse::<usize>().unwrap() >= 2);
+        assert_eq!(engine.get_property("auradb.num-files-at-level1").as_deref(), Some("0"));
+        assert_eq!(engine.get_property("auradb.num-compactions-completed").as_deref(), Some("0"));
+
+        engine.resume_compaction();
+        assert_eq!(engine.get_property("auradb.compaction-paused").as_deref(), Some("0"));
+        assert!(wait_for_property(&engine, "auradb.num-files-at-level1", |n| n > 0) > 0);
+
+        assert_eq!(engine.scan_str("key_0000", "key_9999").unwrap().len(), 400);
+        assert_eq!(engine.get_str("key_0123").unwrap(), Some("value".to_string()));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_engine_touches_no_files() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("db");
+        let engine = EngineBuilder::new().path(&path).in_memory().build().unwrap();
+        let large = vec![3u8; engine.state.config.value_log.separation_threshold * 2];
+
+        engine.put_bytes(b"a", b"1").unwrap();
+        engine.put_bytes(b"large", &large).unwrap();
+        let mut batch = Batch::new();
+        batch.put("b", "2");
+        batch.delete(Key::from("a"));
+        engine.write_batch_internal(&batch, &WriteOptions::default()).unwrap();
+        for i in 0..2000 {
+            engine.put_str(&format!("key_{:04}", i), "value").unwrap();
+        }
+        assert!(engine.state.lsm.get(b"large").unwrap().unwrap().value_pointer.is_none());
+        assert_eq!(engine.get_bytes(b"large").unwrap(), Some(large));
+        assert_eq!(engine.get_bytes(b"a").unwrap(), None);
+        assert_eq!(engine.scan_str("key_0000", "key_9999").unwrap().len(), 2000);
+        assert_eq!(engine.get_property("auradb.num-immutable-mem-table").as_deref(), Some("0"));
+        assert!(engine.wal_info().unwrap().segments.is_empty());
+        assert!(engine.checkpoint(&temp_dir.path().join("checkpoint")).is_err());
+
+        // Flushing drops the data instead of writing it anywhere
+        engine.flush().unwrap();
+        assert_eq!(engine.get_str("b").unwrap(), None);
+        engine.put_str("c", "3").unwrap();
+        engine.close().await.unwrap();
+        assert!(!path.exists());
+        assert!(std::fs::read_dir(temp_dir.path()).unwrap().next().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_compaction_status_and_history() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        let engine = AuraEngine::new(config).unwrap();
+        assert!(engine.compaction_status().is_empty());
+        assert_eq!(engine.get_property("auradb.running-compactions").as_deref(), Some(""));
+
+        for round in 0..2 {
+            for i in 0..50 {
+                engine.put_str(&format!("key_{:04}", i), &format!("value_{}", round)).unwrap();
+            }
+            engine.flush().unwrap();
+        }
+        engine.compact_range(None, None).unwrap();
+
+        // The manual compaction merged both level 0 files, dropping the
+        // older version of every key
+        assert!(engine.compaction_status().is_empty());
+        let history = engine.compaction_history();
+        let last = history.last().unwrap();
+        assert_eq!((last.source_level, last.input_files, last.input_entries), (0, 2, 100));
+        assert_eq!(last.bytes_read, last.input_bytes);
+        assert_eq!(last.entries_processed, 100);
+        assert!(last.output_files >= 1 && last.output_bytes > 0);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_keeps_old_values_through_compactions() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        let sst_path = config.sst.sst_path.clone();
+        let large = vec![1u8; config.value_log.separation_threshold];
+        let engine = AuraEngine::new(config).unwrap();
+        let sst_files = || {
+            std::fs::read_dir(&sst_path)
+                .unwrap()
+                .filter(|e| e.as_ref().unwrap().path().extension().is_some_and(|ext| ext == "sst"))
+                .count()
+        };
+        let live_files = |engine: &AuraEngine| {
+            (0..crate::sst::NUM_LEVELS)
+                .map(|level| engine.get_property(&format!("auradb.num-files-at-level{}", level)).unwrap())
+                .map(|n| n.parse::<usize>().unwrap())
+                .sum::<usize>()
+        };
+
+        for i in 0..50 {
+            engine.put_str(&format!("key_{:04}", i), "old").unwrap();
+        }
+        engine.put_bytes(b"large", &large).unwrap();
+        engine.flush().unwrap();
+        engine.put_str("key_0049", "unflushed").unwrap();
+        let snapshot = engine.snapshot().await.unwrap();
+        assert_eq!(engine.get_property("auradb.num-snapshots").as_deref(), Some("1"));
+        assert_eq!(engine.get_property("auradb.oldest-snapshot-sequence"), Some(snapshot.sequence().to_string()));
+
+        for round in 0..3 {
+            for i in 0..50 {
+                engine.put_str(&format!("key_{:04}", i), &format!("new_{}", round)).unwrap();
+            }
+            engine.delete_internal(b"large".to_vec(), false, &WriteOptions::default()).unwrap();
+            engine.flush().unwrap();
+            engine.compact_range(None, None).unwrap();
+        }
+
+        assert_eq!(engine.get_str("key_0000").unwrap().as_deref(), Some("new_2"));
+        assert_eq!(engine.get_bytes(b"large").unwrap(), None);
+        // The bottom compaction dropped the tombstone of "large" although
+        // the snapshot predates it; the snapshot reads its own files
+        assert_eq!(engine.estimate_num_keys().unwrap(), 50);
+        assert_eq!(snapshot.get(b"key_0000").unwrap(), Some(b"old".to_vec()));
+        assert_eq!(snapshot.get(b"key_0049").unwrap(), Some(b"unflushed".to_vec()));
+        assert_eq!(snapshot.get(b"large").unwrap(), Some(large));
+        let pairs = snapshot.scan(b"key_0000", b"key_9999").unwrap();
+        assert_eq!(pairs.len(), 50);
+        assert!(pairs[..49].iter().all(|(_, value)| value == b"old"));
+        // The files the snapshot reads outlived the compactions
+        assert!(sst_files() > live_files(&engine));
+
+        drop(snapshot);
+        assert_eq!(engine.get_property("auradb.num-snapshots").as_deref(), Some("0"));
+        assert_eq!(sst_files(), live_files(&engine));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_ignores_later_writes_to_its_memtables() {
+        let temp_dir = tempdir().unwrap();
+        let engine = AuraEngine::new(Config::default().with_db_path(temp_dir.path().to_path_buf())).unwrap();
+        for i in 0..10 {
+            engine.put_str(&format!("key_{}", i), "old").unwrap();
+        }
+        let snapshot = engine.snapshot().await.unwrap();
+
+        engine.put_str("key_0", "new").unwrap();
+        engine.put_str("key_05", "added").unwrap();
+        engine.delete_internal(b"key_1".to_vec(), false, &WriteOptions::default()).unwrap();
+        engine.delete_range(&Key::new(b"key_7".to_vec()), &Key::new(b"key_9".to_vec())).unwrap();
+        // Sealing the memtable the snapshot reads doesn't drop it
+        engine.flush().unwrap();
+        engine.put_str("key_2", "newer").unwrap();
+
+        assert_eq!(snapshot.get(b"key_0").unwrap(), Some(b"old".to_vec()));
+        assert_eq!(snapshot.get(b"key_05").unwrap(), None);
+        assert_eq!(snapshot.get(b"key_8").unwrap(), Some(b"old".to_vec()));
+        let pairs: Vec<_> = snapshot.iter().unwrap().collect::<Result<_>>().unwrap();
+        let expected: Vec<_> = (0..10).map(|i| (format!("key_{}", i).into_bytes(), b"old".to_vec())).collect();
+        assert_eq!(pairs, expected);
+        assert_eq!(snapshot.scan(b"key_1", b"key_2").unwrap().len(), 2);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_level_targets_grow_from_base_level_size() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.memtable.max_size = 4 * 1024;
+        config.compaction.triggers.level0_files = 2;
+        config.compaction.triggers.base_level_size = 16 * 1024;
+        config.compaction.triggers.level_size_ratio = 2.0;
+        let engine = AuraEngine::new(config).unwrap();
+
+        assert_eq!(engine.get_property("auradb.target-bytes-at-level0").as_deref(), Some("none"));
+        assert_eq!(engine.get_property("auradb.target-bytes-at-level1").as_deref(), Some("16384"));
+        assert_eq!(engine.get_property("auradb.target-bytes-at-level3").as_deref(), Some("65536"));
+        let bottom = format!("auradb.target-bytes-at-level{}", crate::sst::NUM_LEVELS - 1);
+        assert_eq!(engine.get_property(&bottom).as_deref(), Some("none"));
+        assert_eq!(engine.get_property(&format!("auradb.bytes-at-level{}", crate::sst::NUM_LEVELS)), None);
+
+        for i in 0..2000 {
+            engine.put_str(&format!("key_{:05}", i), &format!("value_{:058}", i)).unwrap();
+        }
+        wait_for_property(&engine, "auradb.num-immutable-mem-table", |n| n == 0);
+
+        // Level 1 is kept within its target by pushing data further down
+        let bytes = |level: usize| -> u64 {
+            engine.get_property(&format!("auradb.bytes-at-level{}", level)).unwrap().parse().unwrap()
+        };
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while (bytes(1) > 16 * 1024 || bytes(2) == 0) && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(bytes(1) <= 16 * 1024);
+        assert!(bytes(2) > 0);
+
+        assert_eq!(engine.scan_str("key_00000", "key_99999").unwrap().len(), 2000);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_range_across_levels() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        let key = |i: usize| format!("key_{:03}", i);
+
+        // The oldest versions sit in level 1, newer ones in level 0 and the memtable
+        for i in 0..100 {
+            engine.put_str(&key(i), "level1").unwrap();
+        }
+        engine.flush().unwrap();
+        engine.compact_range(None, None).unwrap();
+        for i in (0..100).step_by(3) {
+            engine.put_str(&key(i), "level0").unwrap();
+        }
+        engine.flush().unwrap();
+        for i in (0..100).step_by(5) {
+            engine.put_str(&key(i), "memtable").unwrap();
+        }
+        assert_eq!(engine.get_property("auradb.num-files-at-level0").as_deref(), Some("1"));
+        assert_eq!(engine.get_property("auradb.num-files-at-level1").as_deref(), Some("1"));
+
+        engine.delete_range(&Key::from(key(10).as_str()), &Key::from(key(90).as_str())).unwrap();
+        engine.put_str(&key(50), "after").unwrap();
+        // An empty range deletes nothing
+        engine.delete_range(&Key::from(key(95).as_str()), &Key::from(key(5).as_str())).unwrap();
+
+        let check = |engine: &AuraEngine| {
+            for i in 0..100 {
+                let value = engine.get_str(&key(i)).unwrap();
+                match i {
+                    50 => assert_eq!(value.as_deref(), Some("after")),
+                    10..90 => assert_eq!(value, None, "{}", key(i)),
+                    _ => assert!(value.is_some(), "{}", key(i)),
+                }
+            }
+            let scanned = engine.scan_str(&key(0), &key(99)).unwrap();
+            assert_eq!(scanned.len(), 21);
+            assert!(scanned.iter().any(|(k, v)| k == &key(50) && v == "after"));
+        };
+        check(&engine);
+
+        // The range tombstone survives a flush and a reopen in its SST
+        engine.flush().unwrap();
+        check(&engine);
+        engine.close().await.unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        check(&engine);
+
+        // Compacting to the bottom level drops the tombstone and what it covers
+        engine.compact_range(None, None).unwrap();
+        check(&engine);
+        let manifest = crate::manifest::Manifest::load(temp_dir.path()).unwrap().unwrap();
+        assert!(manifest.files.iter().all(|f| f.level == 1));
+        assert_eq!(manifest.files.iter().map(|f| f.entry_count).sum::<u64>(), 21);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_compact_range_moves_data_to_bottom_level() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.memtable.max_size = 4 * 1024;
+        let engine = AuraEngine::new(config).unwrap();
+        engine.pause_compaction();
+
+        for i in 0..300 {
+            engine.put_str(&format!("key_{:04}", i), "value").unwrap();
+        }
+        for i in 0..150 {
+            engine.delete_str(&format!("key_{:04}", i)).unwrap();
+        }
+
+        engine.compact_range(None, None).unwrap();
+        assert_eq!(engine.get_property("auradb.num-files-at-level0").as_deref(), Some("0"));
+        assert_eq!(engine.get_property("auradb.num-files-at-level1").as_deref(), Some("1"));
+        let written = engine.get_property("auradb.compact-write-bytes").unwrap();
+        assert_ne!(written, "0");
+
+        // A second call finds nothing left to compact
+        engine.compact_range(None, Some(&Key::new(b"key_0200".to_vec()))).unwrap();
+        assert_eq!(engine.get_property("auradb.compact-write-bytes"), Some(written));
+
+        assert_eq!(engine.get_str("key_0100").unwrap(), None);
+        assert_eq!(engine.scan_str("key_0000", "key_9999").unwrap().len(), 150);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_compaction_splits_output_at_target_file_size() {
+        use rand::RngCore;
+
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.sst.target_file_size = 64 * 1024;
+        let engine = AuraEngine::new(config).unwrap();
+        engine.pause_compaction();
+
+        // 1MB of incompressible inline values into 64KB files
+        let mut rng = rand::thread_rng();
+        let mut value = vec![0u8; 512];
+        for i in 0..2048 {
+            rng.fill_bytes(&mut value);
+            engine.put_bytes(format!("key_{:05}", i).as_bytes(), &value).unwrap();
+        }
+        engine.compact_range(None, None).unwrap();
+
+        let manifest = crate::manifest::Manifest::load(temp_dir.path()).unwrap().unwrap();
+        let mut files = manifest.files.clone();
+        assert!(files.iter().all(|f| f.level == 1));
+        assert!((14..=18).contains(&files.len()), "{} files", files.len());
+        files.sort_by(|a, b| a.smallest_key.cmp(&b.smallest_key));
+        assert_eq!(files[0].smallest_key, b"key_00000");
+        assert_eq!(files.last().unwrap().largest_key, b"key_02047");
+        for pair in files.windows(2) {
+            assert!(pair[0].largest_key < pair[1].smallest_key);
+        }
+        assert_eq!(files.iter().map(|f| f.entry_count).sum::<u64>(), 2048);
+        // Every file but the last stops just past the target
+        for file in &files[..files.len() - 1] {
+            assert!(file.size >= 64 * 1024 && file.size < 72 * 1024, "{} bytes", file.size);
+        }
+
+        assert_eq!(engine.scan_str("key_00000", "key_99999").unwrap().len(), 2048);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_writes_stop_until_level0_drains() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.memtable.max_size = 4 * 1024;
+        config.compaction.triggers.level0_files = 100;
+        config.compaction.triggers.level0_slowdown = 2;
+        config.compaction.triggers.level0_stop = 3;
+        let engine = Arc::new(AuraEngine::new(config).unwrap());
+
+        let writer = {
+            let engine = Arc::clone(&engine);
+            std::thread::spawn(move || {
+                for i in 0..400 {
+                    engine.put_str(&format!("key_{:04}", i), "value").unwrap();
+                }
+            })
+        };
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while engine.get_property("auradb.write-stall").as_deref() != Some("stop") {
+            assert!(std::time::Instant::now() < deadline, "writes never stalled");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(!writer.is_finished());
+
+        // Draining level 0 releases the writer
+        while !writer.is_finished() {
+            assert!(std::time::Instant::now() < deadline, "writer never resumed");
+            engine.compact_range(None, None).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        writer.join().unwrap();
+        assert_ne!(engine.get_property("auradb.stall-micros").as_deref(), Some("0"));
+        assert_eq!(engine.scan_str("key_0000", "key_9999").unwrap().len(), 400);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_async_calls_leave_a_current_thread_runtime_free() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.compaction.triggers.level0_files = 100;
+        config.compaction.triggers.level0_slowdown = 1;
+        config.compaction.triggers.level0_stop = 1;
+        let engine = Arc::new(AuraEngine::new(config).unwrap());
+        for i in 0..100 {
+            engine.put_str(&format!("key_{:03}", i), "value").unwrap();
+        }
+        engine.flush().unwrap();
+        assert!(engine.stats().unwrap().write_stopped);
+
+        // The stopped put waits on a blocking thread, so the only runtime
+        // thread is still free to run thousands of reads next to it
+        let stalled = tokio::spawn({
+            let engine = Arc::clone(&engine);
+            async move { engine.put(Key::from("stalled"), Value::from("1")).await }
+        });
+        let reads: Vec<_> = (0..5000)
+            .map(|i| {
+                let engine = Arc::clone(&engine);
+                tokio::spawn(async move { engine.get(&Key::new(format!("key_{:03}", i % 100).into_bytes())).await })
+            })
+            .collect();
+        for read in reads {
+            assert_eq!(read.await.unwrap().unwrap(), Some(Value::from("value")));
+        }
+        assert!(!stalled.is_finished());
+
+        engine.compact_range(None, None).unwrap();
+        stalled.await.unwrap().unwrap();
+        assert_eq!(engine.get_str("stalled").unwrap().as_deref(), Some("1"));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stall_timeout_fails_stopped_writes() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.compaction.triggers.level0_files = 100;
+        config.compaction.triggers.level0_slowdown = 1;
+        config.compaction.triggers.level0_stop = 1;
+        let engine = AuraEngine::new(config).unwrap();
+        engine.put_str("a", "1").unwrap();
+        engine.flush().unwrap();
+        assert!(engine.stats().unwrap().write_stopped);
+
+        let options = WriteOptions::default().with_stall_timeout(Duration::from_millis(20));
+        let stalled = |result: Result<()>| matches!(result, Err(Error::Concurrency(msg)) if msg == "write stalled");
+        assert!(stalled(engine.put_with_options(b"b", b"2", &options)));
+        assert!(stalled(engine.delete_with_options(b"a", &options)));
+        let mut batch = Batch::new();
+        batch.put("b", "2");
+        assert!(stalled(engine.write_batch_with_options(&batch, &options)));
+        assert!(stalled(engine.put_stream_with_options(&Key::from("b"), &b"2"[..], &options)));
+        assert_eq!(engine.get_property("auradb.stall-timeouts").as_deref(), Some("4"));
+
+        engine.compact_range(None, None).unwrap();
+        assert!(!engine.stats().unwrap().write_stopped);
+        engine.put_with_options(b"b", b"2", &options).unwrap();
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_flush_and_sync_wal() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.wal.sync_policy = crate::config::WalSyncPolicy::Manual;
+        let engine = AuraEngine::new(config).unwrap();
+
+        engine.put_str("a", "1").unwrap();
+        engine.sync_wal().unwrap();
+        let mut reader = crate::wal::WalReader::new(temp_dir.path().join("wal")).unwrap();
+        assert!(matches!(reader.read_next().unwrap(), Some(WalRecord::Put { key, .. }) if key == b"a"));
+
+        engine.flush().unwrap();
+        assert_eq!(engine.get_property("auradb.num-files-at-level0").as_deref(), Some("1"));
+        assert_eq!(engine.get_property("auradb.num-immutable-mem-table").as_deref(), Some("0"));
+        assert_eq!(engine.get_str("a").unwrap(), Some("1".to_string()));
+
+        // Nothing new to flush
+        engine.flush().unwrap();
+        assert_eq!(engine.get_property("auradb.num-files-at-level0").as_deref(), Some("1"));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_writes_reach_the_wal() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.wal.sync_policy = WalSyncPolicy::EveryWrite;
+        config.wal.async_writes = false;
+        let engine = AuraEngine::new(config).unwrap();
+        engine.put_bytes(b"a", b"1").unwrap();
+        engine.put_bytes(b"b", b"2").unwrap();
+        engine.delete_internal(b"a".to_vec(), false, &WriteOptions::default()).unwrap();
+        // Crash rather than close, which would flush and retire the WAL
+        std::mem::forget(engine);
+
+        let mut reader = crate::wal::WalReader::new(temp_dir.path().join("wal")).unwrap();
+        let mut logged = Vec::new();
+        while let Some(record) = reader.read_next().unwrap() {
+            logged.push(record);
+        }
+        assert_eq!(logged.len(), 3);
+        assert!(matches!(&logged[0], WalRecord::Put { key, value, .. } if key == b"a" && value == b"1"));
+        assert!(matches!(&logged[1], WalRecord::Put { key, .. } if key == b"b"));
+        assert!(matches!(&logged[2], WalRecord::Delete { key, .. } if key == b"a"));
+        assert!(logged.windows(2).all(|w| w[0].sequence() < w[1].sequence()));
+    }
+
+    #[tokio::test]
+    async fn test_synced_batch_is_all_or_nothing_after_crash() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.wal.sync_policy = WalSyncPolicy::Manual;
+        let engine = AuraEngine::new(config).unwrap();
+        let large = vec![4u8; engine.state.config.value_log.separation_threshold];
+
+        engine.put_str("before", "0").unwrap();
+        let mut batch = Batch::new();
+        batch.put(Key::from("a"), Value::from("1"));
+        batch.put(Key::from("b"), Value::new(large.clone()));
+        batch.delete(Key::from("before"));
+        batch.sync = true;
+        engine.write_batch_internal(&batch, &WriteOptions::default()).unwrap();
+        // Crash: nothing is flushed or closed
+        std::mem::forget(engine);
+
+        let wal_dir = temp_dir.path().join("wal");
+        let records = || {
+            let mut reader = crate::wal::WalReader::new(wal_dir.clone()).unwrap();
+            let mut records = Vec::new();
+            while let Some(record) = reader.read_next().unwrap() {
+                records.push(record);
+            }
+            records
+        };
+
+        // The synced batch survives whole, its separated value included
+        let logged = records();
+        assert_eq!(logged.len(), 2);
+        let WalRecord::Batch { operations, .. } = &logged[1] else {
+            panic!("expected a batch, got {:?}", logged[1]);
+        };
+        assert_eq!(operations.len(), 3);
+        let Some(WalRecord::PutPointer { value_pointer, .. }) = operations.get(1) else {
+            panic!("expected a separated value, got {:?}", operations[1]);
+        };
+        let mut vlog = VlogReader::new(temp_dir.path().join("vlog")).unwrap();
+        assert_eq!(vlog.read_value(value_pointer).unwrap().data, large);
+
+        // A batch torn by the crash is dropped entirely
+        let mut files: Vec<_> = std::fs::read_dir(&wal_dir).unwrap().map(|e| e.unwrap().path()).collect();
+        files.sort();
+        let path = files.last().unwrap();
+        let len = std::fs::metadata(path).unwrap().len();
+        std::fs::OpenOptions::new().write(true).open(path).unwrap().set_len(len - 10).unwrap();
+        let logged = records();
+        assert_eq!(logged.len(), 1);
+        assert!(matches!(&logged[0], WalRecord::Put { key, .. } if key == b"before"));
+    }
+
+    #[tokio::test]
+    async fn test_reopen_replays_wal_up_to_torn_record() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.wal.sync_policy = WalSyncPolicy::EveryWrite;
+        config.wal.async_writes = false;
+        let engine = AuraEngine::new(config.clone()).unwrap();
+        let large = vec![6u8; engine.state.config.value_log.separation_threshold];
+
+        engine.put_str("a", "1").unwrap();
+        engine.put_str("gone", "x").unwrap();
+        engine.flush().unwrap();
+        engine.put_bytes(b"large", &large).unwrap();
+        let mut batch = Batch::new();
+        batch.put(Key::from("c"), Value::from("3"));
+        batch.delete_range(Key::from("a"), Key::from("b"));
+        engine.write_batch_internal(&batch, &WriteOptions::default()).unwrap();
+        engine.delete_str("gone").unwrap();
+        engine.put_str("torn", "z").unwrap();
+        let last_sequence = engine.state.wal.lock().current_sequence();
+        // Crash, tearing the last record
+        std::mem::forget(engine);
+        let wal_dir = temp_dir.path().join("wal");
+        let mut files: Vec<_> = std::fs::read_dir(&wal_dir).unwrap().map(|e| e.unwrap().path()).collect();
+        files.sort();
+        let path = files.last().unwrap();
+        let len = std::fs::metadata(path).unwrap().len();
+        std::fs::OpenOptions::new().write(true).open(path).unwrap().set_len(len - 3).unwrap();
+
+        let engine = AuraEngine::new(config.clone()).unwrap();
+        assert_eq!(engine.get_str("a").unwrap(), None);
+        assert_eq!(engine.get_bytes(b"large").unwrap(), Some(large.clone()));
+        assert_eq!(engine.get_str("c").unwrap().as_deref(), Some("3"));
+        assert_eq!(engine.get_str("gone").unwrap(), None);
+        assert_eq!(engine.get_str("torn").unwrap(), None);
+        // Numbering resumes after the last intact record
+        assert_eq!(engine.state.wal.lock().current_sequence(), last_sequence - 1);
+
+        // Replayed writes survive another reopen, and reach SST files on
+        // the next flush
+        engine.put_str("d", "4").unwrap();
+        engine.close().await.unwrap();
+        let engine = AuraEngine::new(config).unwrap();
+        assert_eq!(engine.get_str("c").unwrap().as_deref(), Some("3"));
+        assert_eq!(engine.get_str("d").unwrap().as_deref(), Some("4"));
+        engine.flush().unwrap();
+        assert_eq!(engine.get_str("a").unwrap(), None);
+        assert_eq!(engine.get_bytes(b"large").unwrap(), Some(large));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_memory_usage_breakdown() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.performance.memory_mapped = false;
+        let engine = AuraEngine::new(config).unwrap();
+        let large = vec![5u8; engine.state.config.value_log.separation_threshold];
+
+        for i in 0..100 {
+            engine.put_str(&format!("key_{:03}", i), "value").unwrap();
+        }
+        engine.put_bytes(b"large", &large).unwrap();
+        let before = engine.memory_usage();
+        assert!(before.memtables > 0);
+        assert_eq!(before.filters, 0);
+
+        engine.flush().unwrap();
+        engine.get_str("key_042").unwrap();
+        engine.get_bytes(b"large").unwrap();
+        let after = engine.memory_usage();
+        assert!(after.memtables < before.memtables);
+        assert!(after.filters > 0);
+        assert!(after.block_cache > 0);
+        assert!(after.vlog_cache >= large.len());
+        assert_eq!(after.learned_indexes, 0);
+        assert_eq!(
+            after.total(),
+            after.memtables + after.block_cache + after.compressed_block_cache + after.vlog_cache + after.filters
+        );
+
+        let property = |name: &str| engine.get_property(name).unwrap().parse::<usize>().unwrap();
+        assert_eq!(property("auradb.cur-size-all-mem-tables"), after.memtables);
+        assert_eq!(property("auradb.block-cache-usage"), after.block_cache);
+        assert_eq!(property("auradb.vlog-cache-usage"), after.vlog_cache);
+        assert_eq!(property("auradb.estimate-filter-mem"), after.filters);
+        assert_eq!(property("auradb.learned-index-mem"), 0);
+
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_key_and_value_size_histograms() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        let threshold = engine.state.config.value_log.separation_threshold;
+        assert_eq!(engine.get_property("auradb.separated-value-ratio").as_deref(), Some("0.0000"));
+
+        for i in 0..99 {
+            engine.put_bytes(format!("key_{:02}", i).as_bytes(), &[1u8; 10]).unwrap();
+        }
+        engine.put_bytes(b"large_key", &vec![2u8; threshold]).unwrap();
+        engine.delete_str("key_00").unwrap();
+
+        let metrics = engine.metrics().unwrap();
+        let histogram = |name: &str| metrics.histograms.iter().find(|h| h.name == name).unwrap().clone();
+        let keys = histogram("auradb.key-size");
+        let values = histogram("auradb.value-size");
+        assert_eq!(keys.count, 100);
+        assert_eq!((keys.min, keys.max), (6.0, 9.0));
+        assert_eq!(values.p50, 10.0);
+        assert!(values.max >= threshold as f64 * 0.98);
+        assert_eq!(metrics.counter("auradb.values-separated"), Some(1));
+
+        let summary = engine.get_property("auradb.value-size-histogram").unwrap();
+        assert!(summary.starts_with("count=100 min=10 p50=10 "), "{}", summary);
+        assert!(engine.get_property("auradb.key-size-histogram").unwrap().starts_with("count=100 min=6 "));
+        assert_eq!(engine.get_property("auradb.separated-value-ratio").as_deref(), Some("0.0100"));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit_ratio_properties_and_metrics() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.performance.memory_mapped = false;
+        let engine = AuraEngine::new(config).unwrap();
+        assert_eq!(engine.get_property("auradb.block-cache-hit-ratio").as_deref(), Some("0.0000"));
+
+        engine.put_str("a", "1").unwrap();
+        engine.flush().unwrap();
+        for _ in 0..3 {
+            assert_eq!(engine.get_str("a").unwrap(), Some("1".to_string()));
+        }
+
+        // The first read misses and caches the block; the rest hit it
+        assert_eq!(engine.get_property("auradb.block-cache-misses").as_deref(), Some("1"));
+        assert_eq!(engine.get_property("auradb.block-cache-hits").as_deref(), Some("2"));
+        assert_eq!(engine.get_property("auradb.block-cache-hit-ratio").as_deref(), Some("0.6667"));
+
+        let metrics = engine.metrics().unwrap();
+        assert_eq!(metrics.counter("auradb.block-cache-hits"), Some(2));
+        assert_eq!(metrics.counter("auradb.block-cache-misses"), Some(1));
+        assert_eq!(metrics.counter("auradb.vlog-cache-hits"), Some(0));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_warm_cache() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.performance.memory_mapped = false;
+        config.sst.block_size = 64;
+        let engine = AuraEngine::new(config).unwrap();
+        for i in 0..100 {
+            engine.put_str(&format!("k{:03}", i), "value").unwrap();
+        }
+        engine.flush().unwrap();
+
+        engine.warm_cache(Range::new(Key::from("k010"), Key::from("k019"))).unwrap();
+        let warmed = engine.state.lsm.block_cache_stats().size;
+        assert!(warmed > 0);
+        assert_eq!(engine.get_property("auradb.block-cache-misses").as_deref(), Some("0"));
+        for i in 10..20 {
+            engine.get_str(&format!("k{:03}", i)).unwrap().unwrap();
+        }
+        assert_eq!(engine.get_property("auradb.block-cache-misses").as_deref(), Some("0"));
+
+        engine.warm_cache_all().unwrap();
+        assert!(engine.state.lsm.block_cache_stats().size > warmed);
+        engine.get_str("k099").unwrap().unwrap();
+        assert_eq!(engine.get_property("auradb.block-cache-misses").as_deref(), Some("0"));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rl_policy_persists_across_restart() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        engine.close().await.unwrap();
+        assert!(temp_dir.path().join(crate::compactor::RL_POLICY_FILE).exists());
+
+        // The saved policy is picked up again on open
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_flexible_compaction_follows_workload() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.compaction.strategy = crate::config::CompactionStrategy::Flexible;
+        config.compaction.use_rl_agent = false;
+        config.rl_agent.state_update_frequency = 10;
+        let engine = AuraEngine::new(config).unwrap();
+        let strategy = |engine: &AuraEngine| engine.get_property("auradb.active-compaction-strategy").unwrap();
+        let wait_for = |engine: &AuraEngine, expected: &str| {
+            for _ in 0..200 {
+                if strategy(engine) == expected {
+                    return;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            panic!("strategy stayed {}", strategy(engine));
+        };
+        assert_eq!(strategy(&engine), "leveled");
+
+        for i in 0..10 {
+            engine.put_str(&format!("k{}", i), "v").unwrap();
+        }
+        wait_for(&engine, "tiered");
+
+        for i in 0..10 {
+            engine.get_str(&format!("k{}", i)).unwrap();
+        }
+        wait_for(&engine, "leveled");
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_estimate_num_keys() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        for i in 0..100 {
+            engine.put_str(&format!("k{:03}", i), "v").unwrap();
+        }
+        engine.flush().unwrap();
+        for i in 0..10 {
+            engine.delete_str(&format!("k{:03}", i)).unwrap();
+        }
+        engine.flush().unwrap();
+        for i in 100..105 {
+            engine.put_str(&format!("k{:03}", i), "v").unwrap();
+        }
+
+        assert_eq!(engine.estimate_num_keys().unwrap(), 95);
+        assert_eq!(engine.get_property("auradb.estimate-num-keys").as_deref(), Some("95"));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_range() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        let large: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        engine.put_bytes(b"large", &large).unwrap();
+        engine.put_bytes(b"small", b"0123456789").unwrap();
+
+        let read_before = engine.state.vlog_reader.lock().bytes_read();
+        let slice = engine.get_range(&Key::from("large"), 1000, 16).unwrap().unwrap();
+        assert_eq!(slice, &large[1000..1016]);
+        // Only the entry header and the slice came from the file
+        assert!(engine.state.vlog_reader.lock().bytes_read() - read_before < 1024);
+
+        let tail = engine.get_range(&Key::from("large"), large.len() - 4, 100).unwrap().unwrap();
+        assert_eq!(tail, &large[large.len() - 4..]);
+        assert_eq!(engine.get_range(&Key::from("large"), large.len() + 1, 8).unwrap(), Some(Vec::new()));
+        assert_eq!(engine.get_range(&Key::from("small"), 3, 4).unwrap().as_deref(), Some(&b"3456"[..]));
+        assert_eq!(engine.get_range(&Key::from("missing"), 0, 4).unwrap(), None);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_secondary_index() {
+        let temp_dir = tempdir().unwrap();
+        // Values are "<city>:<name>"; index the city
+        let city = SecondaryIndex::new("city", |_, value| {
+            value.iter().position(|&b| b == b':').map(|end| value[..end].to_vec())
+        });
+        let engine = EngineBuilder::new().path(temp_dir.path()).secondary_index(city).build().unwrap();
+        engine.put_str("u1", "paris:ann").unwrap();
+        engine.put_str("u2", "oslo:bob").unwrap();
+        engine.write_batch(&[(b"u3".to_vec(), b"paris:cy".to_vec())]).unwrap();
+        assert_eq!(engine.scan_index("city", b"paris").unwrap(), [b"u1", b"u3"]);
+
+        // Overwrites and deletes remove the stale entries
+        engine.put_str("u1", "oslo:ann").unwrap();
+        engine.delete_str("u3").unwrap();
+        assert!(engine.scan_index("city", b"paris").unwrap().is_empty());
+        assert_eq!(engine.scan_index("city", b"oslo").unwrap(), [b"u1", b"u2"]);
+        assert!(engine.scan_index("city", b"osl").unwrap().is_empty());
+        assert!(engine.scan_index("name", b"oslo").is_err());
+
+        // Index entries stay out of user scans and writes
+        assert_eq!(engine.snapshot().await.unwrap().iter().unwrap().count(), 2);
+        assert!(engine.put_bytes(secondary::INDEX_KEY_PREFIX, b"x").is_err());
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_export_and_import() {
+        let temp_dir = tempdir().unwrap();
+        let source = EngineBuilder::new().path(temp_dir.path().join("source")).build().unwrap();
+        let pairs: Vec<_> = (0..3000)
+            .map(|i| (format!("key{:05}", i).into_bytes(), vec![i as u8; i % 5000]))
+            .collect();
+        source.write_batch(&pairs).unwrap();
+        let snapshot = source.snapshot().await.unwrap();
+        let dump = temp_dir.path().join("dump");
+        snapshot.export(&dump).unwrap();
+        source.close().await.unwrap();
+
+        let target = EngineBuilder::new().path(temp_dir.path().join("target")).build().unwrap();
+        target.import_snapshot(&dump).unwrap();
+        let imported: Vec<_> = target.snapshot().await.unwrap().iter().unwrap().collect::<Result<_>>().unwrap();
+        assert_eq!(imported, snapshot.iter().unwrap().collect::<Result<Vec<_>>>().unwrap());
+        target.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_export_streams_separated_values() {
+        let temp_dir = tempdir().unwrap();
+        let source = EngineBuilder::new().path(temp_dir.path().join("source")).build().unwrap();
+        let threshold = source.state.config.value_log.separation_threshold;
+        // Several resolve batches of values, half flushed to SST files
+        let count = RESOLVE_BATCH_SIZE * 2 + 10;
+        let pairs: Vec<_> = (0..count)
+            .map(|i| (format!("key{:05}", i).into_bytes(), vec![i as u8; threshold + i]))
+            .collect();
+        source.write_batch(&pairs[..count / 2]).unwrap();
+        source.flush().unwrap();
+        source.write_batch(&pairs[count / 2..]).unwrap();
+        assert!(source.state.lsm.get(b"key00000").unwrap().unwrap().value_pointer.is_some());
+        assert!(source.state.lsm.get(&pairs[count - 1].0).unwrap().unwrap().value_pointer.is_some());
+
+        let dump = temp_dir.path().join("dump");
+        source.snapshot().await.unwrap().export(&dump).unwrap();
+        let exported = SnapshotReader::open(&dump).unwrap().collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(exported, pairs);
+        source.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_iter_reads_sst_blocks_as_it_goes() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.sst.block_size = 4096;
+        let engine = AuraEngine::new(config).unwrap();
+        for i in 0..2000 {
+            engine.put_str(&format!("k{:04}", i), &format!("{:0>100}", i * 7919)).unwrap();
+        }
+        engine.flush().unwrap();
+        let (_, sst_size) = engine.state.lsm.sst_footprint();
+
+        let snapshot = engine.snapshot().await.unwrap();
+        let before = engine.state.lsm.sst_bytes_read();
+        let first: Vec<_> = snapshot.iter().unwrap().take(RESOLVE_BATCH_SIZE).collect::<Result<_>>().unwrap();
+        assert_eq!(first.len(), RESOLVE_BATCH_SIZE);
+        assert!(engine.state.lsm.sst_bytes_read() - before < sst_size / 2);
+        assert_eq!(snapshot.iter().unwrap().count(), 2000);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_verify_integrity() {
+        let temp_dir = tempdir().unwrap();
+        // Uncompressed, so every segment holding values is large
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.value_log.compress_values = false;
+        let engine = AuraEngine::new(config).unwrap();
+        for i in 0..20 {
+            engine.put_bytes(format!("key{:02}", i).as_bytes(), &vec![i as u8; 8 * 1024]).unwrap();
+        }
+        engine.flush().unwrap();
+        let report = engine.verify_integrity().unwrap();
+        assert!(report.is_ok(), "{:?}", report.corruptions);
+        assert_eq!((report.sst_files, report.value_pointers), (1, 20));
+        assert!(report.vlog_segments >= 1);
+
+        let flip = |path: &Path, offset: u64| {
+            use std::io::{Seek, SeekFrom, Write};
+            let byte = std::fs::read(path).unwrap()[offset as usize];
+            let mut file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+            file.seek(SeekFrom::Start(offset)).unwrap();
+            file.write_all(&[byte ^ 0xff]).unwrap();
+        };
+
+        // Damage the last value of every segment
+        for entry in std::fs::read_dir(&engine.state.config.value_log.vlog_path).unwrap() {
+            let path = entry.unwrap().path();
+            let len = std::fs::metadata(&path).unwrap().len();
+            if len > 1024 {
+                flip(&path, len - 1);
+            }
+        }
+        let report = engine.verify_integrity().unwrap();
+        assert!(report.corruptions.iter().any(|c| c.message.contains("does not resolve")));
+        assert!(report.corruptions.iter().all(|c| c.path.starts_with(&engine.state.config.value_log.vlog_path)));
+
+        // And the first SST block
+        let sst = std::fs::read_dir(&engine.state.config.sst.sst_path).unwrap().next().unwrap().unwrap().path();
+        flip(&sst, 8);
+        let report = engine.verify_integrity().unwrap();
+        assert!(report.corruptions.iter().any(|c| c.path == sst && c.offset == 0));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_config() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        let engine = AuraEngine::new(config.clone()).unwrap();
+        engine.put_str("key", "value").unwrap();
+        engine.flush().unwrap();
+        assert_eq!(engine.get_property("auradb.write-stall").as_deref(), Some("none"));
+
+        let mut tuned = config.clone();
+        tuned.cache.block_cache_size = 4096;
+        tuned.compaction.triggers.level0_files = 8;
+        tuned.compaction.triggers.level0_slowdown = 1;
+        tuned.wal.sync_policy = crate::config::WalSyncPolicy::Manual;
+        engine.reload_config(tuned).unwrap();
+        assert_eq!(engine.state.lsm.block_cache_stats().capacity, 4096);
+        assert_eq!(engine.get_property("auradb.write-stall").as_deref(), Some("slowdown"));
+
+        let mut moved = config.clone().with_db_path(temp_dir.path().join("elsewhere"));
+        moved.sst.checksum = crate::config::ChecksumType::None;
+        let Err(Error::Config(message)) = engine.reload_config(moved) else {
+            panic!("fixed settings must not change");
+        };
+        assert!(message.contains("db_path") && message.contains("sst.checksum"), "{}", message);
+        assert_eq!(engine.get_str("key").unwrap().as_deref(), Some("value"));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_open_flags() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("db");
+        let open = |create_if_missing, error_if_exists| {
+            EngineBuilder::new()
+                .options(Options {
+                    path: path.clone(),
+                    create_if_missing,
+                    error_if_exists,
+                })
+                .build()
+        };
+
+        assert!(matches!(open(false, false), Err(Error::Config(_))));
+        let engine = open(true, true).unwrap();
+        engine.put_str("key", "value").unwrap();
+        engine.close().await.unwrap();
+
+        // Close flushed the memtable, so nothing is left to replay
+        assert!(matches!(open(true, true), Err(Error::Config(_))));
+        let engine = open(false, false).unwrap();
+        assert!(engine.state.lsm.active_is_empty());
+        assert_eq!(engine.get_str("key").unwrap().as_deref(), Some("value"));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bottom_compaction_drops_tombstones() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        for i in 0..10 {
+            engine.put_str(&format!("k{}", i), "v").unwrap();
+        }
+        engine.flush().unwrap();
+        for i in 0..5 {
+            engine.delete_str(&format!("k{}", i)).unwrap();
+        }
+        engine.flush().unwrap();
+        engine.compact_range(None, None).unwrap();
+
+        // Only the five live keys are left, with no tombstones next to them
+        assert_eq!(engine.estimate_num_keys().unwrap(), 5);
+        engine.close().await.unwrap();
+
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        assert_eq!(engine.get_str("k0").unwrap(), None);
+        assert_eq!(engine.get_str("k5").unwrap().as_deref(), Some("v"));
+        assert_eq!(engine.scan_prefix(b"k").unwrap().len(), 5);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scan_sees_whole_batches_only() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        let done = std::sync::atomic::AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for batch in 0..200 {
+                    let pairs: Vec<_> = (0..10)
+                        .map(|i| (format!("k{:03}{}", batch, i).into_bytes(), b"v".to_vec()))
+                        .collect();
+                    engine.write_batch(&pairs).unwrap();
+                }
+                done.store(true, Ordering::SeqCst);
+            });
+
+            let mut last = 0;
+            while !done.load(Ordering::SeqCst) {
+                let count = engine.scan_str("k", "l").unwrap().len();
+                assert_eq!(count % 10, 0, "scan saw part of a batch");
+                assert!(count >= last);
+                last = count;
+            }
+        });
+        assert_eq!(engine.scan_str("k", "l").unwrap().len(), 2000);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_latency_percentiles() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        assert_eq!(engine.latency_percentile(Operation::Get, 0.99), None);
+        for i in 0..20 {
+            engine.put_str(&format!("k{}", i), "v").unwrap();
+            engine.get_str(&format!("k{}", i)).unwrap();
+        }
+
+        let p50 = engine.latency_percentile(Operation::Get, 0.5).unwrap();
+        let p99 = engine.latency_percentile(Operation::Get, 0.99).unwrap();
+        assert!(p50 > Duration::ZERO && p50 <= p99);
+        assert_eq!(engine.latency_percentile(Operation::Delete, 0.5), None);
+
+        let snapshot = engine.metrics().unwrap();
+        let puts = snapshot.histograms.iter().find(|h| h.name == "auradb.put.latency").unwrap();
+        assert_eq!(puts.count, 20);
+        assert!(puts.p50 <= puts.p99 && puts.p99 <= puts.max);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_amplification_stats() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.performance.memory_mapped = false;
+        let engine = AuraEngine::new(config).unwrap();
+        let stats = engine.stats().unwrap();
+        assert_eq!((stats.write_amplification, stats.read_amplification), (0.0, 0.0));
+
+        for i in 0..100 {
+            engine.put_str(&format!("k{:03}", i), "value").unwrap();
+        }
+        engine.flush().unwrap();
+        engine.get_str("k001").unwrap().unwrap();
+        engine.get_str("k002").unwrap().unwrap();
+
+        let stats = engine.stats().unwrap();
+        assert_eq!(stats.sst_files, 1);
+        assert_eq!(stats.user_bytes_written, 100 * 9);
+        assert!(stats.write_amplification > 0.0);
+        // The first read loads a block, the second hits the cache
+        assert!(stats.read_amplification > 1.0);
+        assert_eq!(stats.space_amplification, 1.0);
+        assert_eq!(engine.get_property("auradb.space-amplification").as_deref(), Some("1.0000"));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_range_scan_reads_only_the_blocks_it_covers() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.sst.block_size = 4096;
+        let engine = AuraEngine::new(config).unwrap();
+        for i in 0..2000 {
+            engine.put_str(&format!("k{:04}", i), &format!("{:0>100}", i * 7919)).unwrap();
+        }
+        engine.flush().unwrap();
+        let (_, sst_size) = engine.state.lsm.sst_footprint();
+
+        let before = engine.state.lsm.sst_bytes_read();
+        let found = engine.state.lsm.scan(b"k1000", b"k1001").unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(engine.state.lsm.sst_bytes_read() - before < sst_size / 10);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_compressed_values_count_their_uncompressed_size() {
+        let temp_dir = tempdir().unwrap();
+        let engine = AuraEngine::new(Config::default().with_db_path(temp_dir.path().to_path_buf())).unwrap();
+        let large = "x".repeat(8 * 1024);
+        engine.put_str("big", &large).unwrap();
+
+        let stored = engine.state.lsm.get(b"big").unwrap().unwrap().value_pointer.unwrap().length as usize;
+        assert!(stored < large.len());
+        assert_eq!(engine.stats().unwrap().user_bytes_written, (3 + large.len()) as u64);
+        // Reading the value back returns as many bytes as were read from
+        // the value log, plus the key
+        assert_eq!(engine.get_str("big").unwrap().unwrap(), large);
+        let amplification = engine.stats().unwrap().read_amplification;
+        assert!(amplification > 0.99 && amplification < 1.0);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_io_byte_counters() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.performance.memory_mapped = false;
+        config.wal.async_writes = false;
+        config.value_log.compress_values = false;
+        let engine = AuraEngine::new(config).unwrap();
+        let large = "x".repeat(4096);
+        for i in 0..10 {
+            engine.put_str(&format!("k{}", i), "v").unwrap();
+        }
+        engine.put_str("big", &large).unwrap();
+        engine.flush().unwrap();
+        let wal = engine.metrics().unwrap().counter("auradb.wal-bytes-written").unwrap();
+        assert!(wal > 0);
+        engine.put_str("k10", "v").unwrap();
+        assert!(engine.metrics().unwrap().counter("auradb.wal-bytes-written").unwrap() > wal);
+
+        let metrics = engine.metrics().unwrap();
+        assert!(metrics.counter("auradb.vlog-bytes-written").unwrap() > 4096);
+        // Ten 3-byte pairs plus the key and pointed-to length of the large value
+        assert_eq!(metrics.counter("auradb.sst-logical-bytes-written"), Some(10 * 3 + 3 + 4096));
+        assert!(metrics.counter("auradb.sst-physical-bytes-written").unwrap() > 0);
+        assert_eq!(metrics.counter("auradb.disk-bytes-read"), Some(0));
+
+        engine.get_str("k1").unwrap().unwrap();
+        engine.get_str("big").unwrap().unwrap();
+        let read: u64 = engine.get_property("auradb.disk-bytes-read").unwrap().parse().unwrap();
+        assert!(read > 4096);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_survives_compaction() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("db");
+        let checkpoint_path = temp_dir.path().join("checkpoint");
+        let engine = EngineBuilder::new().path(&db_path).build().unwrap();
+        let large = "x".repeat(4096);
+        engine.put_str("a", "1").unwrap();
+        engine.put_str("big", &large).unwrap();
+        engine.flush().unwrap();
+        engine.put_str("b", "2").unwrap();
+
+        engine.checkpoint(&checkpoint_path).unwrap();
+        assert!(matches!(engine.checkpoint(&checkpoint_path), Err(Error::Config(_))));
+
+        // Later writes and compactions don't reach the checkpoint
+        engine.put_str("a", "changed").unwrap();
+        engine.compact_range(None, None).unwrap();
+        engine.close().await.unwrap();
+
+        let restored = EngineBuilder::new().path(&checkpoint_path).build().unwrap();
+        assert_eq!(restored.get_str("a").unwrap(), Some("1".to_string()));
+        assert_eq!(restored.get_str("b").unwrap(), Some("2".to_string()));
+        assert_eq!(restored.get_str("big").unwrap(), Some(large));
+        restored.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_files_removed_on_open() {
+        let temp_dir = tempdir().unwrap();
+        let config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        let large = "x".repeat(4096);
+        let engine = AuraEngine::new(config.clone()).unwrap();
+        engine.put_str("a", "1").unwrap();
+        engine.put_str("big", &large).unwrap();
+        engine.flush().unwrap();
+        engine.close().await.unwrap();
+
+        // Leftovers of an interrupted flush and of a segment torn at creation
+        let orphan_sst = config.sst.sst_path.join(crate::sst::sst_file_name(999));
+        let torn_segment = config.value_log.vlog_path.join(format!("vlog_{:016x}_{:016x}.seg", 999, 0));
+        std::fs::write(&orphan_sst, b"partial table").unwrap();
+        std::fs::write(&torn_segment, b"AURA").unwrap();
+
+        let engine = AuraEngine::new(config).unwrap();
+        assert!(!orphan_sst.exists());
+        assert!(!torn_segment.exists());
+        assert_eq!(engine.get_str("a").unwrap(), Some("1".to_string()));
+        assert_eq!(engine.get_str("big").unwrap(), Some(large));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wal_info() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.wal.async_writes = false;
+        let engine = AuraEngine::new(config).unwrap();
+        engine.put_str("a", "1").unwrap();
+        engine.delete_str("a").unwrap();
+
+        let info = engine.wal_info().unwrap();
+        assert_eq!(info.sequence, 2);
+        assert_eq!(info.segments.len(), 1);
+        let active = &info.segments[0];
+        assert_eq!(active.path, info.active_file);
+        assert_eq!((active.first_sequence, active.last_sequence), (1, 2));
+        assert!(!active.closed);
+        assert_eq!(info.total_bytes, active.size);
+
+        // Flushing retires the file holding the flushed writes
+        engine.flush().unwrap();
+        let info = engine.wal_info().unwrap();
+        assert_eq!(info.segments.len(), 1);
+        assert_ne!(info.segments[0].path, active.path);
+        assert_eq!(info.segments[0].last_sequence, 0);
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pin_range() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        config.performance.memory_mapped = false;
+        config.sst.block_size = 64;
+        config.cache.block_cache_size = 4096;
+        let engine = AuraEngine::new(config).unwrap();
+        for i in 0..200 {
+            engine.put_str(&format!("k{:03}", i), "value").unwrap();
+        }
+        engine.flush().unwrap();
+
+        let hot = || Range::new(Key::from("k010"), Key::from("k019"));
+        engine.pin_range(hot()).unwrap();
+        let pinned = engine.state.lsm.block_cache_stats().pinned;
+        assert!(pinned > 0);
+
+        // Scanning every key churns the rest of the cache
+        for i in 0..200 {
+            engine.get_str(&format!("k{:03}", i)).unwrap().unwrap();
+        }
+        let misses = engine.state.lsm.block_cache_stats().misses;
+        for i in 10..20 {
+            engine.get_str(&format!("k{:03}", i)).unwrap().unwrap();
+        }
+        assert_eq!(engine.state.lsm.block_cache_stats().misses, misses);
+
+        // The whole file doesn't fit
+        let all = Range::new(Key::from("k000"), Key::from("k199"));
+        assert!(matches!(engine.pin_range(all), Err(Error::Config(_))));
+        assert_eq!(engine.state.lsm.block_cache_stats().pinned, pinned);
+
+        engine.unpin_range(hot()).unwrap();
+        assert_eq!(engine.state.lsm.block_cache_stats().pinned, 0);
+        assert!(matches!(engine.unpin_range(hot()), Err(Error::Config(_))));
+        engine.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_options_verify_checksums() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        // Stored as is, so the flipped byte is the value's
+        config.value_log.compress_values = false;
+        let large = "x".repeat(4096);
+        let engine = AuraEngine::new(config.clone()).unwrap();
+        engine.put_str("big", &large).unwrap();
+        engine.flush().unwrap();
+        engine.close().await.unwrap();
+
+        // Flip the value's last byte in its segment
+        let segment = std::fs::read_dir(&config.value_log.vlog_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| std::fs::metadata(path).unwrap().len() > 4096)
+            .unwrap();
+        let mut bytes = std::fs::read(&segment).unwrap();
+        *bytes.last_mut().unwrap() = b'y';
+        std::fs::write(&segment, bytes).unwrap();
+
+        let engine = AuraEngine::new(config).unwrap();
+        let unverified = ReadOptions::default().with_verify_checksums(false);
+        let value = engine.get_with_options(b"big", &unverified).unwrap().unwrap();
+        assert_eq!(value.last(), Some(&b'y'));
+        engine.close().await.unwrap();
+
+        let mut config = Config::default().with_db_path(temp_dir.path().to_path_buf());
+        let engine = AuraEngine::new(config.clone()).unwrap();
+        assert!(matches!(engine.get_str("big"), Err(Error::ValueLogCorruption(_))));
+        engine.close().await.unwrap();
+        config.value_log.verify_on_read = false;
+        let engine = AuraEngine::new(config).unwrap();
+        let verified = ReadOptions::default().with_verify_checksums(true);
+        assert!(matches!(engine.get_with_options(b"big", &verified), Err(Error::ValueLogCorruption(_))));
+        assert!(engine.get_bytes(b"big").unwrap().is_some());
+        engine.close().await.unwrap();
     }
 }
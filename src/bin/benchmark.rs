@@ -3,6 +3,8 @@ use hdrhistogram::Histogram;
 use clap::Parser;
 use auradb::AuraEngine;
 use auradb::config::Config;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 /// Benchmark configuration
 #[derive(Parser, Debug)]
@@ -29,14 +31,21 @@ struct Args {
     /// Batch size for batch operations
     #[arg(long, default_value_t = 100)]
     batch_size: usize,
+    /// Seed for generated keys, values and access patterns, or `random`
+    #[arg(long, default_value = "42", value_parser = parse_seed)]
+    seed: u64,
 }
 
-fn random_key(size: usize) -> Vec<u8> {
-    (0..size).map(|_| rand::random::<u8>()).collect()
+/// Parse `--seed`: a number, or `random` for a fresh seed each run
+fn parse_seed(seed: &str) -> Result<u64, String> {
+    if seed.eq_ignore_ascii_case("random") {
+        return Ok(rand::random());
+    }
+    seed.parse().map_err(|_| format!("expected a number or `random`, got `{}`", seed))
 }
 
-fn random_value(size: usize) -> Vec<u8> {
-    (0..size).map(|_| rand::random::<u8>()).collect()
+fn random_bytes(rng: &mut StdRng, size: usize) -> Vec<u8> {
+    (0..size).map(|_| rng.gen::<u8>()).collect()
 }
 
 fn main() {
@@ -50,6 +59,8 @@ fn main() {
     println!("Workload: {}", args.workload);
     println!("Large values: {}", args.large_values);
     println!("Batch size: {}", args.batch_size);
+    println!("Seed: {}", args.seed);
+    let mut rng = StdRng::seed_from_u64(args.seed);
 
     // Engine init
     let config = Config::default();
@@ -68,8 +79,8 @@ fn main() {
             // --- Batch write path ---
             let mut batch = Vec::with_capacity(args.batch_size);
             for _ in 0..args.batch_size.min(args.operations - i) {
-                let key = random_key(args.key_size);
-                let value = random_value(args.value_size);
+                let key = random_bytes(&mut rng, args.key_size);
+                let value = random_bytes(&mut rng, args.value_size);
                 batch.push((key, value));
             }
             
@@ -80,11 +91,12 @@ fn main() {
             i += args.batch_size;
         } else {
             // --- Single op path ---
-            let key = random_key(args.key_size);
-            let value = random_value(args.value_size);
+            let key = random_bytes(&mut rng, args.key_size);
+            let value = random_bytes(&mut rng, args.value_size);
+            let write = rng.gen::<f64>() < 0.33;
             
             let t0 = Instant::now();
-            if rand::random::<f64>() < 0.33 {
+            if write {
                 engine.put_bytes(&key, &value).unwrap();
             } else {
                 let _ = engine.get_bytes(&key);
@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+use clap::{Parser, ValueEnum};
+use auradb::{AuraEngine, Engine};
+use auradb::config::{Config, WalSyncPolicy};
+
+/// Line the writer process prints once every write has returned
+const WRITES_COMPLETE: &str = "writes complete";
+
+/// How the writing engine goes away before the database is reopened
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Shutdown {
+    /// Write in a child process that aborts without running any destructors
+    Abort,
+    /// Drop the engine without calling `close`
+    Drop,
+    /// Close the engine cleanly
+    Close,
+}
+
+/// Durability benchmark: writes keys with the WAL fsynced on every write,
+/// shuts the engine down, reopens it and checks that every key survived
+#[derive(Parser, Debug)]
+#[command(name = "AuraDB Durability Benchmark")]
+struct Args {
+    /// Database path (removed before the run)
+    #[arg(short, long, default_value = "./durability_benchmark_db")]
+    db_path: String,
+    /// Number of keys to write
+    #[arg(short, long, default_value_t = 10000)]
+    operations: usize,
+    /// Key size in bytes
+    #[arg(long, default_value_t = 16)]
+    key_size: usize,
+    /// Value size in bytes
+    #[arg(long, default_value_t = 1024)]
+    value_size: usize,
+    /// How the writing engine shuts down
+    #[arg(long, value_enum, default_value_t = Shutdown::Abort)]
+    shutdown: Shutdown,
+    /// Only write the keys, then abort (used by the `abort` mode)
+    #[arg(long, hide = true)]
+    writer: bool,
+}
+
+/// Engine configuration with the WAL synced before every write returns
+fn durable_config(db_path: &Path) -> Config {
+    let mut config = Config::default().with_db_path(db_path.to_path_buf());
+    config.wal.sync_policy = WalSyncPolicy::EveryWrite;
+    config.wal.async_writes = false;
+    config
+}
+
+/// Key number `i`, zero-padded to `size` bytes
+fn key(i: usize, size: usize) -> Vec<u8> {
+    format!("{:0>width$}", i, width = size).into_bytes()
+}
+
+/// Value for key number `i`, so a value read back can be checked
+fn value(i: usize, size: usize) -> Vec<u8> {
+    i.to_le_bytes().iter().copied().cycle().take(size).collect()
+}
+
+/// Write every key and report the durable write throughput
+fn write_keys(engine: &AuraEngine, args: &Args) {
+    let start = Instant::now();
+    for i in 0..args.operations {
+        engine
+            .put_bytes(&key(i, args.key_size), &value(i, args.value_size))
+            .expect("Write failed");
+    }
+    let duration = start.elapsed().as_secs_f64();
+    println!(" Wrote {} keys in {:.2} s", args.operations, duration);
+    println!(" Durable write throughput: {:.2} ops/sec", args.operations as f64 / duration);
+}
+
+/// Write in a child process that aborts once the writes return, so
+/// nothing beyond what the engine made durable can survive
+fn write_and_abort(args: &Args) {
+    let output = Command::new(std::env::current_exe().expect("Failed to locate benchmark binary"))
+        .args(["--writer", "--db-path", &args.db_path])
+        .args(["--operations", &args.operations.to_string()])
+        .args(["--key-size", &args.key_size.to_string()])
+        .args(["--value-size", &args.value_size.to_string()])
+        .stderr(Stdio::inherit())
+        .output()
+        .expect("Failed to start writer process");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    print!("{}", stdout.replace(&format!("{}\n", WRITES_COMPLETE), ""));
+    if !stdout.lines().any(|line| line == WRITES_COMPLETE) {
+        eprintln!("❌ Writer process failed before finishing its writes ({})", output.status);
+        std::process::exit(1);
+    }
+    println!(" Writer process aborted ({})", output.status);
+}
+
+fn main() {
+    let args = Args::parse();
+    let db_path = PathBuf::from(&args.db_path);
+
+    if args.writer {
+        let engine = AuraEngine::new(durable_config(&db_path)).expect("Failed to create engine");
+        write_keys(&engine, &args);
+        println!("{}", WRITES_COMPLETE);
+        std::process::abort();
+    }
+
+    println!("💾 AuraDB Durability Benchmark");
+    println!("==============================");
+    println!("Database path: {}", args.db_path);
+    println!("Operations: {}", args.operations);
+    println!("Key size: {} bytes", args.key_size);
+    println!("Value size: {} bytes", args.value_size);
+    println!("Shutdown: {:?}", args.shutdown);
+    std::fs::remove_dir_all(&db_path).ok();
+
+    println!("🔄 Writing with fsync on every write...");
+    match args.shutdown {
+        Shutdown::Abort => write_and_abort(&args),
+        Shutdown::Drop => {
+            let engine = AuraEngine::new(durable_config(&db_path)).expect("Failed to create engine");
+            write_keys(&engine, &args);
+            drop(engine);
+        }
+        Shutdown::Close => {
+            let engine = AuraEngine::new(durable_config(&db_path)).expect("Failed to create engine");
+            write_keys(&engine, &args);
+            let runtime = tokio::runtime::Runtime::new().expect("Failed to start runtime");
+            runtime.block_on(engine.close()).expect("Close failed");
+        }
+    }
+
+    println!("🔄 Reopening...");
+    let start = Instant::now();
+    let engine = AuraEngine::new(durable_config(&db_path)).expect("Failed to reopen engine");
+    let recovery = start.elapsed();
+    println!(" Recovery time: {:.2} ms", recovery.as_secs_f64() * 1000.0);
+
+    println!("🔍 Verifying...");
+    let start = Instant::now();
+    let (mut missing, mut corrupt) = (0, 0);
+    for i in 0..args.operations {
+        match engine.get_bytes(&key(i, args.key_size)).expect("Read failed") {
+            None => missing += 1,
+            Some(v) if v != value(i, args.value_size) => corrupt += 1,
+            Some(_) => {}
+        }
+    }
+    println!(" Verified {} keys in {:.2} s", args.operations, start.elapsed().as_secs_f64());
+    println!(" Missing: {} | Wrong value: {}", missing, corrupt);
+
+    // Nothing at all coming back means the engine never recovered the
+    // unflushed writes, not that some of them were lost
+    if args.operations > 0 && missing == args.operations {
+        println!("❌ Recovery unsupported: the reopened engine recovered none of the {} writes", args.operations);
+        std::process::exit(2);
+    }
+    if missing + corrupt > 0 {
+        println!("❌ {} of {} acknowledged writes did not survive", missing + corrupt, args.operations);
+        std::process::exit(1);
+    }
+    println!("✅ All writes survived");
+}
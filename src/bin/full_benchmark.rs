@@ -11,7 +11,7 @@ fn run_workload(label: &str, operations: usize, key_size: usize, value_size: usi
              operations, key_size, value_size, read_ratio * 100.0);
     
     let config = Config::default();
-    let mut db = AuraEngine::new(config).expect("DB init failed");
+    let db = AuraEngine::new(config).expect("DB init failed");
 
     let mut rng = rand::thread_rng();
     let mut keys: Vec<Vec<u8>> = Vec::with_capacity(operations);
@@ -59,7 +59,7 @@ fn run_latency_workload(label: &str, operations: usize, key_size: usize, value_s
 
     // Pre-populate with some data
     println!("   📝 Pre-populating database...");
-    for i in 0..(operations / 10) {
+    for _ in 0..(operations / 10) {
         let key: Vec<u8> = (0..key_size).map(|_| rng.gen::<u8>()).collect();
         let val: Vec<u8> = (0..value_size).map(|_| rng.gen::<u8>()).collect();
         db.put_bytes(&key, &val).expect("Put failed");
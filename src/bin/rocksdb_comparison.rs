@@ -1,9 +1,9 @@
 use std::time::Instant;
 use std::fs;
-use std::path::Path;
 use auradb::AuraEngine;
-use auradb::config::Config;
-use rand::Rng;
+use auradb::config::{Config, WalSyncPolicy};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use clap::Parser;
 
 /// RocksDB Comparison Benchmark
@@ -23,16 +23,29 @@ struct Args {
     #[arg(long, default_value_t = 1024)]
     value_size: usize,
     
-    /// Whether to simulate fsync (durability)
+    /// Whether to fsync the WAL before each write returns
     #[arg(long, default_value_t = false)]
     fsync: bool,
     
     /// Database path
     #[arg(long, default_value = "./rocksdb_comparison_db")]
     db_path: String,
+
+    /// Seed for generated keys, values and access patterns, or `random`
+    #[arg(long, default_value = "42", value_parser = parse_seed)]
+    seed: u64,
+}
+
+/// Parse `--seed`: a number, or `random` for a fresh seed each run
+fn parse_seed(seed: &str) -> Result<u64, String> {
+    if seed.eq_ignore_ascii_case("random") {
+        return Ok(rand::random());
+    }
+    seed.parse().map_err(|_| format!("expected a number or `random`, got `{}`", seed))
 }
 
 /// RocksDB benchmark data from published sources
+#[allow(dead_code)]
 struct RocksDBBenchmark {
     name: String,
     throughput_ops: f64,
@@ -60,27 +73,33 @@ fn run_rocksdb_comparison(args: &Args) {
     println!("This benchmark validates AuraDB against published RocksDB numbers");
     println!("Value size: {} bytes | Operations: {}", args.value_size, args.operations);
     println!("Durability: {}", if args.fsync { "fsync enabled" } else { "fsync disabled" });
+    println!("Seed: {}", args.seed);
+    let mut rng = StdRng::seed_from_u64(args.seed);
     
     // Clean up any existing database
     fs::remove_dir_all(&args.db_path).ok();
     
-    let config = Config::default();
+    let mut config = Config::default().with_db_path(args.db_path.clone().into());
+    if args.fsync {
+        config.wal.sync_policy = WalSyncPolicy::EveryWrite;
+        config.wal.async_writes = false;
+    }
     let mut db = AuraEngine::new(config).expect("Failed to create AuraDB engine");
     
     // Benchmark 1: Bulk Random Writes (RocksDB: ~1M ops/sec)
     println!("\n📊 Benchmark 1: Bulk Random Writes");
     println!("=====================================");
-    let write_result = benchmark_bulk_writes(&mut db, args);
+    let write_result = benchmark_bulk_writes(&mut db, args, &mut rng);
     
     // Benchmark 2: Point Reads (RocksDB: P50 ~450µs, P99 ~1ms)
     println!("\n📊 Benchmark 2: Point Reads");
     println!("==============================");
-    let read_result = benchmark_point_reads(&mut db, args);
+    let read_result = benchmark_point_reads(&mut db, args, &mut rng);
     
     // Benchmark 3: Read-While-Writing (RocksDB: ~7M ops/sec)
     println!("\n📊 Benchmark 3: Read-While-Writing");
     println!("=====================================");
-    let mixed_result = benchmark_read_while_writing(&mut db, args);
+    let _mixed_result = benchmark_read_while_writing(&mut db, args, &mut rng);
     
     // Generate comparison report
     println!("\n\n🎯 COMPETITIVE ANALYSIS REPORT");
@@ -172,8 +191,7 @@ fn run_rocksdb_comparison(args: &Args) {
 }
 
 /// Benchmark bulk random writes
-fn benchmark_bulk_writes(db: &mut AuraEngine, args: &Args) -> BenchmarkResult {
-    let mut rng = rand::thread_rng();
+fn benchmark_bulk_writes(db: &mut AuraEngine, args: &Args, rng: &mut StdRng) -> BenchmarkResult {
     let start = Instant::now();
     
     for i in 0..args.operations {
@@ -182,12 +200,6 @@ fn benchmark_bulk_writes(db: &mut AuraEngine, args: &Args) -> BenchmarkResult {
         
         db.put_bytes(&key, &value).expect("Write failed");
         
-        // Simulate fsync if enabled
-        if args.fsync {
-            // In real implementation, this would be actual fsync
-            std::thread::sleep(std::time::Duration::from_nanos(1000)); // 1µs simulation
-        }
-        
         if i % (args.operations / 10).max(1) == 0 {
             print!("\r   Progress: {}/{} ({:.0}%)", i, args.operations, (i as f64 / args.operations as f64) * 100.0);
         }
@@ -205,8 +217,7 @@ fn benchmark_bulk_writes(db: &mut AuraEngine, args: &Args) -> BenchmarkResult {
 }
 
 /// Benchmark point reads
-fn benchmark_point_reads(db: &mut AuraEngine, args: &Args) -> BenchmarkResult {
-    let mut rng = rand::thread_rng();
+fn benchmark_point_reads(db: &mut AuraEngine, args: &Args, rng: &mut StdRng) -> BenchmarkResult {
     let mut latencies = Vec::new();
     
     // Pre-populate with data
@@ -253,8 +264,7 @@ fn benchmark_point_reads(db: &mut AuraEngine, args: &Args) -> BenchmarkResult {
 }
 
 /// Benchmark read-while-writing
-fn benchmark_read_while_writing(db: &mut AuraEngine, args: &Args) -> BenchmarkResult {
-    let mut rng = rand::thread_rng();
+fn benchmark_read_while_writing(db: &mut AuraEngine, args: &Args, rng: &mut StdRng) -> BenchmarkResult {
     let mut keys = Vec::new();
     let mut latencies = Vec::new();
     
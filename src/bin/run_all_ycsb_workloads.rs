@@ -120,7 +120,7 @@ fn main() {
 /// Run a single YCSB workload and capture results
 fn run_ycsb_workload(workload: char, operations: usize, key_size: usize, value_size: usize) -> WorkloadResult {
     let output = Command::new("cargo")
-        .args(&[
+        .args([
             "run", "--release", "--bin", "ycsb_benchmark",
             "--", "--workload", &workload.to_string(),
             "--operations", &operations.to_string(),
@@ -179,6 +179,7 @@ fn extract_p99_latency(output: &str) -> f64 {
 
 /// Results from a workload execution
 #[derive(Debug)]
+#[allow(dead_code)]
 struct WorkloadResult {
     workload: char,
     operations: usize,
@@ -1,9 +1,11 @@
+use std::collections::BTreeSet;
 use std::time::Instant;
 use std::fs;
 use std::path::Path;
-use auradb::AuraEngine;
+use auradb::{AuraEngine, Key, Range};
 use auradb::config::Config;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use clap::Parser;
 
 /// YCSB-style benchmark configuration
@@ -33,8 +35,110 @@ struct Args {
     /// Database path
     #[arg(long, default_value = "./ycsb_db")]
     db_path: String,
+
+    /// Seed for generated keys, values and access patterns, or `random`
+    #[arg(long, default_value = "42", value_parser = parse_seed)]
+    seed: u64,
+
+    /// Skew of the zipfian and latest distributions, between 0 and 1
+    /// exclusive; higher concentrates more accesses on fewer keys
+    #[arg(long, default_value_t = DEFAULT_ZIPF_THETA)]
+    zipf_theta: f64,
+}
+
+/// Parse `--seed`: a number, or `random` for a fresh seed each run
+fn parse_seed(seed: &str) -> Result<u64, String> {
+    if seed.eq_ignore_ascii_case("random") {
+        return Ok(rand::random());
+    }
+    seed.parse().map_err(|_| format!("expected a number or `random`, got `{}`", seed))
+}
+
+/// Zipfian distribution over `0..n`, where rank 0 is the most popular,
+/// following the YCSB generator (Gray et al., "Quickly Generating
+/// Billion-Record Synthetic Databases"). `n` may grow between draws; the
+/// zeta sum is extended rather than recomputed.
+#[derive(Debug, Clone)]
+struct Zipfian {
+    theta: f64,
+    /// Number of items `zeta_n` covers
+    items: usize,
+    /// Sum of `1 / i^theta` for `i` in `1..=items`
+    zeta_n: f64,
+    zeta_2: f64,
+}
+
+impl Zipfian {
+    fn new(theta: f64) -> Self {
+        Self {
+            theta,
+            items: 0,
+            zeta_n: 0.0,
+            zeta_2: 1.0 + 0.5f64.powf(theta),
+        }
+    }
+
+    /// Draw a rank in `0..n`; `n` must be positive
+    fn next(&mut self, rng: &mut impl Rng, n: usize) -> usize {
+        if n != self.items {
+            if n < self.items {
+                self.items = 0;
+                self.zeta_n = 0.0;
+            }
+            self.zeta_n += (self.items + 1..=n).map(|i| 1.0 / (i as f64).powf(self.theta)).sum::<f64>();
+            self.items = n;
+        }
+
+        let u: f64 = rng.gen();
+        let uz = u * self.zeta_n;
+        if uz < 1.0 {
+            return 0;
+        }
+        if uz < self.zeta_2 {
+            return 1.min(n - 1);
+        }
+        let alpha = 1.0 / (1.0 - self.theta);
+        let eta = (1.0 - (2.0 / n as f64).powf(1.0 - self.theta)) / (1.0 - self.zeta_2 / self.zeta_n);
+        ((n as f64 * (eta * u - eta + 1.0).powf(alpha)) as usize).min(n - 1)
+    }
+}
+
+/// How a workload picks which existing key to access
+#[derive(Debug, Clone)]
+enum KeyChooser {
+    /// Every key equally likely
+    Uniform,
+    /// Zipfian over insertion order: the first keys loaded are hottest
+    Zipfian(Zipfian),
+    /// Zipfian over recency: the newest keys are hottest
+    Latest(Zipfian),
+}
+
+impl KeyChooser {
+    fn new(distribution: &str, theta: f64) -> Self {
+        match distribution {
+            "zipfian" => Self::Zipfian(Zipfian::new(theta)),
+            "latest" => Self::Latest(Zipfian::new(theta)),
+            _ => Self::Uniform,
+        }
+    }
+
+    /// Index of the key to access among `len` keys; `len` must be positive
+    fn next(&mut self, rng: &mut impl Rng, len: usize) -> usize {
+        match self {
+            Self::Uniform => rng.gen_range(0..len),
+            Self::Zipfian(zipfian) => zipfian.next(rng, len),
+            Self::Latest(zipfian) => len - 1 - zipfian.next(rng, len),
+        }
+    }
 }
 
+/// Zipfian skew used by the YCSB reference implementation
+const DEFAULT_ZIPF_THETA: f64 = 0.99;
+
+/// Longest scan issued by workload E, in records
+const MAX_SCAN_LENGTH: usize = 100;
+
 /// YCSB Workload Definitions
 #[derive(Debug, Clone)]
 struct YCSBWorkload {
@@ -116,10 +220,15 @@ struct CustomWorkloadRunner {
     operations: usize,
     key_size: usize,
     value_size: usize,
+    seed: u64,
+    chooser: KeyChooser,
+    /// Distribution of scan lengths, shortest most likely
+    scan_lengths: Zipfian,
 }
 
 impl CustomWorkloadRunner {
-    fn new(workload: YCSBWorkload, operations: usize, key_size: usize, value_size: usize) -> Self {
+    fn new(workload: YCSBWorkload, operations: usize, key_size: usize, value_size: usize, seed: u64, theta: f64) -> Self {
+        let chooser = KeyChooser::new(&workload.distribution, theta);
         let config = Config::default();
         let db = AuraEngine::new(config).expect("Failed to create AuraDB engine");
         
@@ -129,12 +238,19 @@ impl CustomWorkloadRunner {
             operations,
             key_size,
             value_size,
+            seed,
+            chooser,
+            scan_lengths: Zipfian::new(theta),
         }
     }
 
+    #[allow(unused_assignments)]
     fn run(&mut self) -> WorkloadResult {
-        let mut rng = rand::thread_rng();
+        let mut rng = StdRng::seed_from_u64(self.seed);
         let mut keys: Vec<Vec<u8>> = Vec::new();
+        // Key order, to bound each scan to the records it should return
+        let mut sorted_keys: BTreeSet<Vec<u8>> = BTreeSet::new();
+        let mut scanned_records = 0;
         let mut latencies = Vec::new();
         
         let mut read_count = 0;
@@ -146,7 +262,7 @@ impl CustomWorkloadRunner {
         let start = Instant::now();
 
         // Pre-populate with some data for reads/updates
-        if self.workload.read_ratio > 0.0 || self.workload.update_ratio > 0.0 {
+        if self.workload.insert_ratio < 1.0 {
             let pre_populate_count = (self.operations / 10).max(1000);
             println!("   📝 Pre-populating database with {} keys...", pre_populate_count);
             
@@ -154,6 +270,7 @@ impl CustomWorkloadRunner {
                 let key = self.generate_key(&mut rng);
                 let value = self.generate_value(&mut rng);
                 self.db.put_bytes(&key, &value).expect("Pre-population failed");
+                sorted_keys.insert(key.clone());
                 keys.push(key);
             }
         }
@@ -170,7 +287,7 @@ impl CustomWorkloadRunner {
             if rand_val < (cumulative + self.workload.read_ratio) {
                 // Read operation
                 if !keys.is_empty() {
-                    let key_idx = rng.gen_range(0..keys.len());
+                    let key_idx = self.chooser.next(&mut rng, keys.len());
                     let key = &keys[key_idx];
                     let _ = self.db.get_bytes(key);
                     read_count += 1;
@@ -179,10 +296,10 @@ impl CustomWorkloadRunner {
             } else if rand_val < (cumulative + self.workload.update_ratio) {
                 // Update operation
                 if !keys.is_empty() {
-                    let key_idx = rng.gen_range(0..keys.len());
+                    let key_idx = self.chooser.next(&mut rng, keys.len());
                     let key = &keys[key_idx];
                     let value = self.generate_value(&mut rng);
-                    self.db.put_bytes(key, &value);
+                    self.db.put_bytes(key, &value).expect("Put failed");
                     update_count += 1;
                 }
                 cumulative += self.workload.update_ratio;
@@ -190,29 +307,32 @@ impl CustomWorkloadRunner {
                 // Insert operation
                 let key = self.generate_key(&mut rng);
                 let value = self.generate_value(&mut rng);
-                self.db.put_bytes(&key, &value);
+                self.db.put_bytes(&key, &value).expect("Put failed");
+                sorted_keys.insert(key.clone());
                 keys.push(key);
                 insert_count += 1;
                 cumulative += self.workload.insert_ratio;
             } else if rand_val < (cumulative + self.workload.scan_ratio) {
-                // Scan operation (simplified as range read)
+                // Scan operation: a range read of 1 to MAX_SCAN_LENGTH
+                // records from an existing key
                 if !keys.is_empty() {
-                    let start_idx = rng.gen_range(0..keys.len().saturating_sub(10));
-                    let end_idx = (start_idx + 10).min(keys.len());
-                    for j in start_idx..end_idx {
-                        let _ = self.db.get_bytes(&keys[j]);
-                    }
+                    let start = &keys[self.chooser.next(&mut rng, keys.len())];
+                    let length = 1 + self.scan_lengths.next(&mut rng, MAX_SCAN_LENGTH);
+                    let end = sorted_keys.range(start.clone()..).take(length).last().unwrap_or(start);
+                    let range = Range::new(Key::new(start.clone()), Key::new(end.clone()));
+                    let (records, _) = self.db.scan_page(range, None, length).expect("Scan failed");
+                    scanned_records += records.len();
                     scan_count += 1;
                 }
                 cumulative += self.workload.scan_ratio;
             } else if rand_val < (cumulative + self.workload.read_modify_write_ratio) {
                 // Read-modify-write operation
                 if !keys.is_empty() {
-                    let key_idx = rng.gen_range(0..keys.len());
+                    let key_idx = self.chooser.next(&mut rng, keys.len());
                     let key = &keys[key_idx];
                     let _ = self.db.get_bytes(key);
                     let value = self.generate_value(&mut rng);
-                    self.db.put_bytes(key, &value);
+                    self.db.put_bytes(key, &value).expect("Put failed");
                     rmw_count += 1;
                 }
                 cumulative += self.workload.read_modify_write_ratio;
@@ -247,6 +367,7 @@ impl CustomWorkloadRunner {
             update_count,
             insert_count,
             scan_count,
+            scanned_records,
             rmw_count,
             latency_p50: p50,
             latency_p95: p95,
@@ -276,6 +397,7 @@ struct WorkloadResult {
     update_count: usize,
     insert_count: usize,
     scan_count: usize,
+    scanned_records: usize,
     rmw_count: usize,
     latency_p50: u64,
     latency_p95: u64,
@@ -294,6 +416,9 @@ impl WorkloadResult {
         println!("   ✏️  Updates: {} ({:.1}%)", self.update_count, (self.update_count as f64 / self.total_operations as f64) * 100.0);
         println!("   ➕ Inserts: {} ({:.1}%)", self.insert_count, (self.insert_count as f64 / self.total_operations as f64) * 100.0);
         println!("   🔍 Scans: {} ({:.1}%)", self.scan_count, (self.scan_count as f64 / self.total_operations as f64) * 100.0);
+        if self.scan_count > 0 {
+            println!("      Records per scan: {:.1}", self.scanned_records as f64 / self.scan_count as f64);
+        }
         println!("   🔄 Read-Modify-Writes: {} ({:.1}%)", self.rmw_count, (self.rmw_count as f64 / self.total_operations as f64) * 100.0);
         println!("\n⏱ Latency Percentiles:");
         println!("   p50:   {} ns ({:.2} µs)", self.latency_p50, self.latency_p50 as f64 / 1000.0);
@@ -335,6 +460,11 @@ fn main() {
     println!("Value size: {} bytes", args.value_size);
     println!("Threads: {}", args.threads);
     println!("Database path: {}", args.db_path);
+    println!("Seed: {}", args.seed);
+    if !(args.zipf_theta > 0.0 && args.zipf_theta < 1.0) {
+        eprintln!("❌ --zipf-theta must be between 0 and 1 exclusive, got {}", args.zipf_theta);
+        std::process::exit(1);
+    }
 
     // Get workload definition
     let workload = match YCSBWorkload::from_name(&args.workload) {
@@ -352,12 +482,15 @@ fn main() {
     println!("   Scan ratio: {:.1}%", workload.scan_ratio * 100.0);
     println!("   Read-Modify-Write ratio: {:.1}%", workload.read_modify_write_ratio * 100.0);
     println!("   Distribution: {}", workload.distribution);
+    if workload.distribution != "uniform" {
+        println!("   Zipfian theta: {}", args.zipf_theta);
+    }
 
     // Clean up any existing database
     fs::remove_dir_all(&args.db_path).ok();
     
     println!("\n🔧 Running YCSB workload...");
-    let mut runner = CustomWorkloadRunner::new(workload.clone(), args.operations, args.key_size, args.value_size);
+    let mut runner = CustomWorkloadRunner::new(workload.clone(), args.operations, args.key_size, args.value_size, args.seed, args.zipf_theta);
     let result = runner.run();
     result.print_summary();
     
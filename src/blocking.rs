@@ -0,0 +1,188 @@
+//! Blocking engine for callers without an async runtime
+//!
+//! [`SyncEngine`] wraps an [`AuraEngine`] for CLI tools and embedded use.
+//! It starts a dedicated thread, `auradb-runtime`, running a
+//! single-threaded Tokio runtime. The engine's background tasks, such as
+//! the value log write queues, run on that thread. Every method blocks the
+//! calling thread: reads and writes run directly on it, inside the
+//! runtime's context, and only the final close waits on the runtime
+//! thread. The engine is `Sync`, so several threads can share one
+//! `SyncEngine`.
+//!
+//! Don't call a `SyncEngine` from inside another Tokio runtime. Closing it
+//! there panics, because it blocks on a runtime. Async code should use
+//! [`AuraEngine`] and the [`Engine`] trait.
+
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+use tracing::warn;
+
+use crate::api::{AuraEngine, Engine, WriteOptions};
+use crate::config::Config;
+use crate::error::Result;
+use crate::storage::{Batch, Key};
+
+/// An [`AuraEngine`] behind purely blocking methods, with its own runtime
+/// thread; see the [module docs](self)
+pub struct SyncEngine {
+    /// The wrapped engine; taken on close
+    engine: Option<AuraEngine>,
+    /// Handle to the runtime on the background thread
+    handle: Handle,
+    /// Stops the runtime thread when sent or dropped
+    shutdown: Option<oneshot::Sender<()>>,
+    /// The runtime thread
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SyncEngine {
+    /// Start the runtime thread and open the engine on it
+    pub fn new(config: Config) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let handle = runtime.handle().clone();
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        let thread = std::thread::Builder::new().name("auradb-runtime".to_string()).spawn(move || {
+            runtime.block_on(async {
+                let _ = stopped.await;
+            });
+        })?;
+
+        let mut engine = Self {
+            engine: None,
+            handle,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        };
+        // Background tasks spawned while opening land on the runtime thread
+        let opened = {
+            let _guard = engine.handle.enter();
+            AuraEngine::new(config)
+        };
+        engine.engine = Some(opened?);
+        Ok(engine)
+    }
+
+    /// Open or create the database at `path` with the default configuration
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        Self::new(Config::default().with_db_path(path.into()))
+    }
+
+    /// Run `f` on the calling thread against the engine, in the runtime's
+    /// context
+    fn with_engine<T>(&self, f: impl FnOnce(&AuraEngine) -> T) -> T {
+        let _guard = self.handle.enter();
+        f(self.engine())
+    }
+
+    /// The wrapped engine, for methods without a blocking form here. Its
+    /// synchronous methods can be called directly; don't call async ones.
+    pub fn engine(&self) -> &AuraEngine {
+        self.engine.as_ref().expect("engine is open until close")
+    }
+
+    /// Put a key-value pair
+    pub fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.with_engine(|engine| engine.put_bytes(key, value))
+    }
+
+    /// Get the value of a key
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.with_engine(|engine| engine.get_bytes(key))
+    }
+
+    /// Delete a key
+    pub fn delete(&self, key: &[u8]) -> Result<()> {
+        self.with_engine(|engine| engine.delete_internal(key.to_vec(), false, &WriteOptions::default()).map(|_| ()))
+    }
+
+    /// Delete every key in `[start, end)`
+    pub fn delete_range(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        self.with_engine(|engine| engine.delete_range(&Key::new(start.to_vec()), &Key::new(end.to_vec())))
+    }
+
+    /// Live pairs with keys in `[start, end]`, in comparator order
+    pub fn scan(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.with_engine(|engine| engine.scan_internal(start, end))
+    }
+
+    /// Apply a batch of puts and deletes atomically
+    pub fn write_batch(&self, batch: &Batch) -> Result<()> {
+        self.with_engine(|engine| engine.write_batch_internal(batch, &WriteOptions::default()))
+    }
+
+    /// Flush the memtables to level 0
+    pub fn flush(&self) -> Result<()> {
+        self.with_engine(AuraEngine::flush)
+    }
+
+    /// Close the engine, then stop the runtime thread
+    pub fn close(mut self) -> Result<()> {
+        self.shutdown()
+    }
+
+    /// Close the engine, if still open, and stop and join the runtime thread
+    fn shutdown(&mut self) -> Result<()> {
+        let closed = match self.engine.take() {
+            Some(engine) => self.handle.block_on(async move { engine.close().await }),
+            None => Ok(()),
+        };
+        drop(self.shutdown.take());
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                warn!("AuraDB runtime thread panicked");
+            }
+        }
+        closed
+    }
+}
+
+impl Drop for SyncEngine {
+    fn drop(&mut self) {
+        if let Err(e) = self.shutdown() {
+            warn!("Failed to close engine on drop: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Value;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sync_engine_without_runtime() {
+        let temp_dir = tempdir().unwrap();
+        let engine = SyncEngine::open(temp_dir.path()).unwrap();
+        let large = vec![7u8; Config::default().value_log.separation_threshold];
+
+        engine.put(b"a", b"1").unwrap();
+        engine.put(b"b", &large).unwrap();
+        let mut batch = Batch::new();
+        batch.put(Key::from("c"), Value::from("3"));
+        batch.delete(Key::from("a"));
+        engine.write_batch(&batch).unwrap();
+        engine.delete(b"missing").unwrap();
+
+        assert_eq!(engine.get(b"a").unwrap(), None);
+        assert_eq!(engine.get(b"b").unwrap(), Some(large.clone()));
+        let keys: Vec<Vec<u8>> = engine.scan(b"a", b"z").unwrap().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![b"b".to_vec(), b"c".to_vec()]);
+
+        engine.flush().unwrap();
+        engine.close().unwrap();
+
+        // Dropping without close still closes the engine
+        let engine = SyncEngine::open(temp_dir.path()).unwrap();
+        assert_eq!(engine.get(b"b").unwrap(), Some(large));
+        assert_eq!(engine.get(b"c").unwrap(), Some(b"3".to_vec()));
+        engine.put(b"d", b"4").unwrap();
+        engine.flush().unwrap();
+        drop(engine);
+        let engine = SyncEngine::open(temp_dir.path()).unwrap();
+        assert_eq!(engine.get(b"d").unwrap(), Some(b"4".to_vec()));
+    }
+}
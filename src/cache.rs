@@ -1,10 +1,15 @@
 //! Cache module for block and value log caching
 //! 
-//! This module will implement unified caching with multiple eviction policies.
-//! 
-//! Planned for M2 milestone.
+//! [`UnifiedCache`] is a byte-budgeted cache whose entries are shared
+//! `Arc<[u8]>` buffers, so a hit hands out the cached allocation instead of
+//! copying it. Eviction is least-recently-used; the other policies in
+//! [`EvictionPolicy`] currently fall back to LRU. Pinned entries count
+//! against the capacity but are never evicted.
 
 use crate::error::{Error, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 /// Cache eviction policy
 #[derive(Debug, Clone)]
@@ -17,46 +22,257 @@ pub enum EvictionPolicy {
     TinyLfu,
 }
 
+impl From<&crate::config::EvictionPolicy> for EvictionPolicy {
+    fn from(policy: &crate::config::EvictionPolicy) -> Self {
+        match policy {
+            crate::config::EvictionPolicy::Lru => EvictionPolicy::Lru,
+            crate::config::EvictionPolicy::Arc => EvictionPolicy::Arc,
+            crate::config::EvictionPolicy::TinyLfu => EvictionPolicy::TinyLfu,
+        }
+    }
+}
+
+/// Key and data of an entry evicted from the cache
+pub type Evicted = (Vec<u8>, Arc<[u8]>);
+
 /// Cache entry
 #[derive(Debug, Clone)]
 pub struct CacheEntry {
     /// Entry key
     pub key: Vec<u8>,
     /// Entry data
-    pub data: Vec<u8>,
+    pub data: Arc<[u8]>,
     /// Access count
     pub access_count: u64,
-    /// Last access time
+    /// Last access time (logical clock)
     pub last_access: u64,
+    /// Outstanding pins; pinned entries are left out of the recency order
+    pub pins: u32,
 }
 
 /// Unified cache for SST blocks and vlog pages
+#[derive(Debug)]
 pub struct UnifiedCache {
-    // TODO: Implement cache functionality
+    /// Capacity in bytes
+    capacity: usize,
+    /// Eviction policy
+    policy: EvictionPolicy,
+    /// Cached entries
+    entries: HashMap<Vec<u8>, CacheEntry>,
+    /// Keys ordered by last access, oldest first
+    recency: BTreeMap<u64, Vec<u8>>,
+    /// Logical clock for recency
+    clock: u64,
+    /// Bytes currently cached
+    size: usize,
+    /// Bytes of pinned entries
+    pinned: usize,
+    /// Hit count
+    hits: AtomicU64,
+    /// Miss count
+    misses: AtomicU64,
 }
 
 impl UnifiedCache {
-    /// Create a new unified cache
-    pub fn new(_capacity: usize, _policy: EvictionPolicy) -> Self {
-        Self {}
+    /// Create a new unified cache holding up to `capacity` bytes
+    pub fn new(capacity: usize, policy: EvictionPolicy) -> Self {
+        Self {
+            capacity,
+            policy,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            size: 0,
+            pinned: 0,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Eviction policy the cache was configured with
+    pub fn policy(&self) -> &EvictionPolicy {
+        &self.policy
     }
     
-    /// Get an entry from cache
-    pub fn get(&mut self, _key: &[u8]) -> Option<Vec<u8>> {
-        // TODO: Implement
-        None
+    /// Get an entry from cache, sharing its buffer
+    pub fn get(&mut self, key: &[u8]) -> Option<Arc<[u8]>> {
+        self.clock += 1;
+        let Some(entry) = self.entries.get_mut(key) else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        if entry.pins == 0 {
+            self.recency.remove(&entry.last_access);
+            self.recency.insert(self.clock, entry.key.clone());
+        }
+        entry.last_access = self.clock;
+        entry.access_count += 1;
+        Some(Arc::clone(&entry.data))
     }
     
-    /// Put an entry into cache
-    pub fn put(&mut self, _key: Vec<u8>, _data: Vec<u8>) -> Result<()> {
-        // TODO: Implement
+    /// Remove an unpinned entry and hand it out, counting the lookup like
+    /// [`get`](Self::get)
+    pub fn take(&mut self, key: &[u8]) -> Option<Arc<[u8]>> {
+        let data = self.entries.get(key).filter(|entry| entry.pins == 0).map(|entry| Arc::clone(&entry.data));
+        match data {
+            Some(_) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                self.remove(key);
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        data
+    }
+
+    /// Put an entry into cache, evicting old entries to make room. Entries
+    /// larger than the room left beside the pinned ones are not cached, and
+    /// pinned entries keep their data.
+    pub fn put(&mut self, key: Vec<u8>, data: Arc<[u8]>) -> Result<()> {
+        self.put_evicting(key, data).map(|_| ())
+    }
+
+    /// [`put`](Self::put), returning the entries evicted to make room
+    pub fn put_evicting(&mut self, key: Vec<u8>, data: Arc<[u8]>) -> Result<Vec<Evicted>> {
+        if self.entries.get(&key).is_some_and(|entry| entry.pins > 0) {
+            return Ok(Vec::new());
+        }
+        self.remove(&key);
+        if data.len() > self.capacity - self.pinned {
+            return Ok(Vec::new());
+        }
+        let evicted = self.evict_for(data.len());
+
+        self.clock += 1;
+        self.size += data.len();
+        self.recency.insert(self.clock, key.clone());
+        self.entries.insert(
+            key.clone(),
+            CacheEntry {
+                key,
+                data,
+                access_count: 0,
+                last_access: self.clock,
+                pins: 0,
+            },
+        );
+        Ok(evicted)
+    }
+
+    /// Evict unpinned entries, least recently used first, until `len` more
+    /// bytes fit or only pinned entries are left, returning them
+    fn evict_for(&mut self, len: usize) -> Vec<Evicted> {
+        let mut evicted = Vec::new();
+        while self.size + len > self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.size -= entry.data.len();
+                evicted.push((entry.key, entry.data));
+            }
+        }
+        evicted
+    }
+
+    /// Change the capacity, evicting down to it when shrinking. Fails,
+    /// leaving the cache unchanged, if the pinned entries alone would not
+    /// fit.
+    pub fn set_capacity(&mut self, capacity: usize) -> Result<()> {
+        if self.pinned > capacity {
+            return Err(Error::Config(format!(
+                "Cannot shrink the cache to {} bytes while {} bytes are pinned",
+                capacity, self.pinned
+            )));
+        }
+        self.capacity = capacity;
+        self.evict_for(0);
+        Ok(())
+    }
+
+    /// Pin `key` so eviction skips it, caching the data returned by `load`
+    /// first if the key is absent. Pins nest; each needs a matching
+    /// [`unpin`](Self::unpin). Fails, leaving the cache unchanged, rather
+    /// than let pinned entries outgrow the capacity.
+    pub fn pin(&mut self, key: Vec<u8>, load: impl FnOnce() -> Result<Arc<[u8]>>) -> Result<()> {
+        let len = match self.entries.get(&key) {
+            Some(entry) if entry.pins > 0 => 0,
+            Some(entry) => entry.data.len(),
+            None => {
+                let data = load()?;
+                self.check_pinnable(data.len())?;
+                let len = data.len();
+                self.put(key.clone(), data)?;
+                len
+            }
+        };
+        self.check_pinnable(len)?;
+        let entry = self.entries.get_mut(&key).expect("entry was just cached");
+        if entry.pins == 0 {
+            self.recency.remove(&entry.last_access);
+            self.pinned += len;
+        }
+        entry.pins += 1;
+        Ok(())
+    }
+
+    /// Drop one pin on `key`; once none are left it is evictable again.
+    /// Keys that aren't pinned are left alone.
+    pub fn unpin(&mut self, key: &[u8]) {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return;
+        };
+        if entry.pins == 0 {
+            return;
+        }
+        entry.pins -= 1;
+        if entry.pins == 0 {
+            self.pinned -= entry.data.len();
+            self.clock += 1;
+            entry.last_access = self.clock;
+            self.recency.insert(self.clock, entry.key.clone());
+        }
+    }
+
+    /// Fail if pinning `len` more bytes would exceed the capacity
+    fn check_pinnable(&self, len: usize) -> Result<()> {
+        if self.pinned + len > self.capacity {
+            return Err(Error::Config(format!(
+                "Pinning {} more bytes would exceed the cache capacity of {} bytes ({} already pinned)",
+                len, self.capacity, self.pinned
+            )));
+        }
         Ok(())
     }
+
+    /// Whether `key` is cached, without counting a lookup or refreshing it
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Remove an entry, pinned or not
+    pub fn remove(&mut self, key: &[u8]) {
+        if let Some(entry) = self.entries.remove(key) {
+            if entry.pins > 0 {
+                self.pinned -= entry.data.len();
+            } else {
+                self.recency.remove(&entry.last_access);
+            }
+            self.size -= entry.data.len();
+        }
+    }
     
     /// Get cache statistics
     pub fn stats(&self) -> CacheStats {
-        // TODO: Implement
-        CacheStats::default()
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            size: self.size,
+            pinned: self.pinned,
+            capacity: self.capacity,
+        }
     }
 }
 
@@ -69,10 +285,24 @@ pub struct CacheStats {
     pub misses: u64,
     /// Current size
     pub size: usize,
+    /// Bytes of pinned entries, included in `size`
+    pub pinned: usize,
     /// Capacity
     pub capacity: usize,
 }
 
+impl CacheStats {
+    /// Fraction of lookups that hit, or 0 before the first lookup
+    pub fn hit_ratio(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,4 +313,62 @@ mod tests {
         let stats = cache.stats();
         assert_eq!(stats.capacity, 1024);
     }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let mut cache = UnifiedCache::new(10, EvictionPolicy::Lru);
+        cache.put(b"a".to_vec(), Arc::from(&[1u8; 4][..])).unwrap();
+        cache.put(b"b".to_vec(), Arc::from(&[2u8; 4][..])).unwrap();
+        assert!(cache.get(b"a").is_some());
+
+        cache.put(b"c".to_vec(), Arc::from(&[3u8; 4][..])).unwrap();
+        assert!(cache.get(b"b").is_none());
+        assert!(cache.get(b"a").is_some());
+        assert_eq!(cache.stats().size, 8);
+
+        // Hits share the cached allocation
+        let first = cache.get(b"c").unwrap();
+        assert!(Arc::ptr_eq(&first, &cache.get(b"c").unwrap()));
+    }
+
+    #[test]
+    fn test_set_capacity_evicts_when_shrinking() {
+        let mut cache = UnifiedCache::new(12, EvictionPolicy::Lru);
+        let block = |byte: u8| Arc::from(&[byte; 4][..]);
+        cache.pin(b"a".to_vec(), || Ok(block(1))).unwrap();
+        cache.put(b"b".to_vec(), block(2)).unwrap();
+        cache.put(b"c".to_vec(), block(3)).unwrap();
+
+        cache.set_capacity(8).unwrap();
+        assert_eq!(cache.stats().size, 8);
+        assert!(cache.contains(b"a") && !cache.contains(b"b") && cache.contains(b"c"));
+        assert!(matches!(cache.set_capacity(3), Err(Error::Config(_))));
+        assert_eq!(cache.stats().capacity, 8);
+
+        cache.set_capacity(16).unwrap();
+        cache.put(b"d".to_vec(), block(4)).unwrap();
+        assert_eq!(cache.stats().size, 12);
+    }
+
+    #[test]
+    fn test_pinned_entries_survive_eviction() {
+        let mut cache = UnifiedCache::new(10, EvictionPolicy::Lru);
+        let block = |byte: u8| Arc::from(&[byte; 4][..]);
+        cache.pin(b"a".to_vec(), || Ok(block(1))).unwrap();
+        cache.put(b"b".to_vec(), block(2)).unwrap();
+        cache.put(b"c".to_vec(), block(3)).unwrap();
+        cache.put(b"d".to_vec(), block(4)).unwrap();
+        assert!(cache.contains(b"a"));
+        assert_eq!(cache.stats().pinned, 4);
+
+        // A second pin of the remaining room fits; a third doesn't
+        cache.pin(b"d".to_vec(), || unreachable!()).unwrap();
+        assert!(matches!(cache.pin(b"e".to_vec(), || Ok(block(5))), Err(Error::Config(_))));
+        assert!(!cache.contains(b"e"));
+
+        cache.unpin(b"a");
+        cache.put(b"f".to_vec(), block(6)).unwrap();
+        assert!(!cache.contains(b"a"));
+        assert_eq!(cache.stats().pinned, 4);
+    }
 }
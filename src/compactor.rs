@@ -1,14 +1,66 @@
 //! Compaction module for LSM tree management
 //! 
-//! This module will implement flexible LSM (FLSM) with tiered/leveled compaction,
-//! RL-driven policy selection, and I/O rate limiting.
-//! 
-//! Planned for M2-M3 milestones.
+//! Level 0 holds freshly flushed SST files whose key ranges may overlap.
+//! Once it accumulates `triggers.level0_files` files they are merged with
+//! the overlapping files of level 1. Deeper levels are compacted into the
+//! next one when they outgrow
+//! `base_level_size * level_size_ratio^(level - 1)`,
+//! picking files round-robin so every key range is eventually rewritten.
+//! Compactions keep only the newest version of each key, and drop
+//! tombstones too once no deeper level overlaps the inputs, since nothing
+//! older is left for them to hide.
+//!
+//! Compaction can be paused (e.g. during bulk loads); memtable flushes keep
+//! running while it is paused.
+//!
+//! Every level that needs compacting gets a score, its file count or size
+//! over its limit in percent, and the highest score runs first. Scores are
+//! recomputed from the current version before each pick, so an overflowing
+//! level 0 that is stalling writes preempts a routine compaction deeper
+//! down. Tasks queued with [`CompactionManager::schedule_task`] compete on
+//! the same scale.
+//!
+//! Up to `max_threads` compactions run at once. A task is only picked if
+//! it shares no input file with a running one and, where the two touch a
+//! common level, their key ranges are disjoint, so concurrent outputs
+//! never overlap within a level. Finished tasks are handed back with
+//! [`CompactionManager::finish_task`].
+//!
+//! Under the tiered strategy level 0 collects `TIERED_RUN_FACTOR` times
+//! more files (staying below the slowdown trigger) and deeper levels may
+//! grow that much larger before they are compacted, trading read cost for
+//! less rewriting. The `Flexible` strategy switches between leveled and
+//! tiered at runtime. A switch only changes which compaction is picked
+//! next, so levels 1 and deeper never overlap whichever is active.
+//!
+//! Every compaction run is tracked while it runs: the code running it
+//! updates a [`CompactionTracker`] as it reads inputs, merges entries and
+//! finishes output files, and [`CompactionManager::progress`] reports
+//! them. The last `HISTORY_SIZE` compactions to finish are kept for
+//! [`CompactionManager::history`].
 
+use crate::comparator::Comparator;
+use crate::config::{CompactionConfig, CompactionStrategy as ConfigStrategy, CompactionTriggers, RlAgentConfig};
 use crate::error::{Error, Result};
+use crate::iterator::MergeIterator;
+use crate::sst::{SstFile, Version, NUM_LEVELS};
+use crate::storage::Entry;
+use parking_lot::{Mutex, RwLock};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 
 /// Compaction strategy type
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CompactionStrategy {
     /// Leveled compaction (RocksDB-style)
     Leveled,
@@ -27,87 +79,1178 @@ pub struct CompactionTask {
     pub source_level: u32,
     /// Target level
     pub target_level: u32,
-    /// Input files
-    pub input_files: Vec<String>,
-    /// Output file
-    pub output_file: String,
-    /// Priority
+    /// Input files from the source and target levels
+    pub input_files: Vec<SstFile>,
+    /// Priority (higher is more urgent)
     pub priority: u32,
 }
 
+impl CompactionTask {
+    /// File numbers of the inputs
+    pub fn input_ids(&self) -> Vec<u64> {
+        self.input_files.iter().map(|f| f.id).collect()
+    }
+}
+
+/// Finished compactions kept for [`CompactionManager::history`]
+const HISTORY_SIZE: usize = 64;
+
+/// Progress of a running compaction, or the totals of a finished one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactionProgress {
+    /// Task ID
+    pub id: u64,
+    /// Source level
+    pub source_level: u32,
+    /// Target level
+    pub target_level: u32,
+    /// Number of input files
+    pub input_files: usize,
+    /// Total size of the input files
+    pub input_bytes: u64,
+    /// Input file bytes read so far
+    pub bytes_read: u64,
+    /// Entries in the input files
+    pub input_entries: u64,
+    /// Input entries merged so far, whether written out or dropped
+    pub entries_processed: u64,
+    /// Output files finished so far
+    pub output_files: u64,
+    /// Size of the output files finished so far
+    pub output_bytes: u64,
+    /// Time since the compaction started; how long it took once finished
+    pub elapsed: Duration,
+}
+
+impl CompactionProgress {
+    /// One-line summary, as listed by the `auradb.running-compactions`
+    /// property
+    pub fn summary(&self) -> String {
+        format!(
+            "id={} level={}->{} files={} read={}/{} entries={}/{} output_files={} output_bytes={} elapsed_ms={}",
+            self.id,
+            self.source_level,
+            self.target_level,
+            self.input_files,
+            self.bytes_read,
+            self.input_bytes,
+            self.entries_processed,
+            self.input_entries,
+            self.output_files,
+            self.output_bytes,
+            self.elapsed.as_millis()
+        )
+    }
+}
+
+/// Counters of a running compaction, updated by the code running it
+#[derive(Debug)]
+pub struct CompactionTracker {
+    /// Task ID
+    id: u64,
+    /// Source level
+    source_level: u32,
+    /// Target level
+    target_level: u32,
+    /// Number of input files
+    input_files: usize,
+    /// Total size of the input files
+    input_bytes: u64,
+    /// Entries in the input files
+    input_entries: u64,
+    /// When the compaction started
+    started: Instant,
+    /// Input file bytes read so far
+    bytes_read: AtomicU64,
+    /// Input entries merged so far
+    entries_processed: AtomicU64,
+    /// Output files finished so far
+    output_files: AtomicU64,
+    /// Size of the output files finished so far
+    output_bytes: AtomicU64,
+}
+
+impl CompactionTracker {
+    fn new(task: &CompactionTask) -> Self {
+        Self {
+            id: task.id,
+            source_level: task.source_level,
+            target_level: task.target_level,
+            input_files: task.input_files.len(),
+            input_bytes: task.input_files.iter().map(|f| f.size).sum(),
+            input_entries: task.input_files.iter().map(|f| f.entry_count).sum(),
+            started: Instant::now(),
+            bytes_read: AtomicU64::new(0),
+            entries_processed: AtomicU64::new(0),
+            output_files: AtomicU64::new(0),
+            output_bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// Count an input file read in full
+    pub fn add_read(&self, bytes: u64) {
+        self.bytes_read.fetch_add(bytes, AtomicOrdering::Relaxed);
+    }
+
+    /// Count an input entry merged
+    pub fn add_processed(&self) {
+        self.entries_processed.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+
+    /// Count a finished output file
+    pub fn add_output(&self, bytes: u64) {
+        self.output_files.fetch_add(1, AtomicOrdering::Relaxed);
+        self.output_bytes.fetch_add(bytes, AtomicOrdering::Relaxed);
+    }
+
+    /// Progress so far
+    pub fn progress(&self) -> CompactionProgress {
+        CompactionProgress {
+            id: self.id,
+            source_level: self.source_level,
+            target_level: self.target_level,
+            input_files: self.input_files,
+            input_bytes: self.input_bytes,
+            bytes_read: self.bytes_read.load(AtomicOrdering::Relaxed),
+            input_entries: self.input_entries,
+            entries_processed: self.entries_processed.load(AtomicOrdering::Relaxed),
+            output_files: self.output_files.load(AtomicOrdering::Relaxed),
+            output_bytes: self.output_bytes.load(AtomicOrdering::Relaxed),
+            elapsed: self.started.elapsed(),
+        }
+    }
+}
+
+/// Task waiting in the compaction queue: highest priority first, then
+/// oldest first
+#[derive(Debug)]
+struct QueuedTask(CompactionTask);
+
+impl QueuedTask {
+    fn key(&self) -> (u32, Reverse<u64>) {
+        (self.0.priority, Reverse(self.0.id))
+    }
+}
+
+impl PartialEq for QueuedTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for QueuedTask {}
+
+impl PartialOrd for QueuedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedTask {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// How much more data the tiered strategy lets each level collect
+const TIERED_RUN_FACTOR: usize = 4;
+
 /// Compaction manager for orchestrating LSM compaction
 pub struct CompactionManager {
-    // TODO: Implement compaction management functionality
+    /// Compaction configuration; triggers, thread count and rate limit can
+    /// change at runtime
+    config: RwLock<CompactionConfig>,
+    /// Key ordering
+    comparator: Comparator,
+    /// Whether background compaction is paused
+    paused: AtomicBool,
+    /// Next task ID
+    next_task_id: AtomicU64,
+    /// Number of compactions completed
+    completed: AtomicU64,
+    /// Bytes read from compaction inputs
+    bytes_read: AtomicU64,
+    /// Bytes written to compaction outputs
+    bytes_written: AtomicU64,
+    /// Largest key compacted last in each level, for round-robin picking
+    compact_pointers: Mutex<Vec<Option<Vec<u8>>>>,
+    /// Whether compactions are currently picked tiered-style
+    tiered: AtomicBool,
+    /// Scheduled tasks waiting to run
+    queue: Mutex<BinaryHeap<QueuedTask>>,
+    /// Picked tasks that have not finished yet
+    running: Mutex<Vec<CompactionTask>>,
+    /// Counters of the compactions being run, manual ones included
+    tracked: Mutex<Vec<Arc<CompactionTracker>>>,
+    /// Last compactions to finish, oldest first
+    history: Mutex<VecDeque<CompactionProgress>>,
 }
 
 impl CompactionManager {
     /// Create a new compaction manager
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(config: CompactionConfig, comparator: Comparator) -> Self {
+        let tiered = matches!(config.strategy, ConfigStrategy::Tiered);
+        Self {
+            config: RwLock::new(config),
+            comparator,
+            paused: AtomicBool::new(false),
+            next_task_id: AtomicU64::new(1),
+            completed: AtomicU64::new(0),
+            bytes_read: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+            compact_pointers: Mutex::new(vec![None; NUM_LEVELS]),
+            tiered: AtomicBool::new(tiered),
+            queue: Mutex::new(BinaryHeap::new()),
+            running: Mutex::new(Vec::new()),
+            tracked: Mutex::new(Vec::new()),
+            history: Mutex::new(VecDeque::with_capacity(HISTORY_SIZE)),
+        }
     }
-    
-    /// Schedule a compaction task
-    pub fn schedule_task(&mut self, _task: CompactionTask) -> Result<()> {
-        // TODO: Implement
-        Ok(())
+
+    /// Whether the strategy is picked at runtime
+    pub fn is_flexible(&self) -> bool {
+        matches!(self.config.read().strategy, ConfigStrategy::Flexible)
     }
-    
-    /// Get pending compaction tasks
+
+    /// Strategy compactions are currently picked with: leveled or tiered
+    pub fn active_strategy(&self) -> CompactionStrategy {
+        if self.tiered.load(AtomicOrdering::Relaxed) {
+            CompactionStrategy::Tiered
+        } else {
+            CompactionStrategy::Leveled
+        }
+    }
+
+    /// Pick later compactions with `strategy`. `Flexible` leaves the
+    /// active strategy as is. Returns whether the strategy changed.
+    pub fn set_active_strategy(&self, strategy: CompactionStrategy) -> bool {
+        let tiered = match strategy {
+            CompactionStrategy::Leveled => false,
+            CompactionStrategy::Tiered => true,
+            CompactionStrategy::Flexible => return false,
+        };
+        let previous = self.active_strategy();
+        if self.tiered.swap(tiered, AtomicOrdering::Relaxed) == tiered {
+            return false;
+        }
+        info!("Compaction strategy switched from {:?} to {:?}", previous, strategy);
+        true
+    }
+
+    /// Take the triggers, thread count and I/O rate limit from `config`;
+    /// the strategy and RL settings stay as opened
+    pub fn update_config(&self, config: &CompactionConfig) {
+        let mut current = self.config.write();
+        current.triggers = config.triggers.clone();
+        current.max_threads = config.max_threads;
+        current.io_rate_limit = config.io_rate_limit;
+    }
+
+    /// Current compaction triggers
+    pub fn triggers(&self) -> CompactionTriggers {
+        self.config.read().triggers.clone()
+    }
+
+    /// Most compactions allowed to run at once
+    pub fn max_threads(&self) -> usize {
+        self.config.read().max_threads.max(1)
+    }
+
+    /// Level 0 file count that triggers a compaction into level 1
+    fn level0_trigger(&self) -> usize {
+        let triggers = &self.config.read().triggers;
+        let base = triggers.level0_files.max(1);
+        if self.tiered.load(AtomicOrdering::Relaxed) {
+            (base * TIERED_RUN_FACTOR).min(triggers.level0_slowdown.saturating_sub(1)).max(base)
+        } else {
+            base
+        }
+    }
+
+    /// Stop picking new compactions. A compaction already running finishes.
+    pub fn pause(&self) {
+        self.paused.store(true, AtomicOrdering::SeqCst);
+    }
+
+    /// Allow compactions to be picked again
+    pub fn resume(&self) {
+        self.paused.store(false, AtomicOrdering::SeqCst);
+    }
+
+    /// Whether compaction is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(AtomicOrdering::SeqCst)
+    }
+
+    /// Number of compactions completed
+    pub fn completed_compactions(&self) -> u64 {
+        self.completed.load(AtomicOrdering::Relaxed)
+    }
+
+    /// Total bytes read by compactions
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(AtomicOrdering::Relaxed)
+    }
+
+    /// Total bytes written by compactions
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(AtomicOrdering::Relaxed)
+    }
+
+    /// Start tracking the progress of `task`, which is about to run
+    pub fn start_tracking(&self, task: &CompactionTask) -> Arc<CompactionTracker> {
+        let tracker = Arc::new(CompactionTracker::new(task));
+        self.tracked.lock().push(Arc::clone(&tracker));
+        tracker
+    }
+
+    /// Stop tracking a compaction that has finished, adding it to the
+    /// history if it succeeded
+    pub fn stop_tracking(&self, tracker: &Arc<CompactionTracker>, succeeded: bool) {
+        self.tracked.lock().retain(|tracked| !Arc::ptr_eq(tracked, tracker));
+        if succeeded {
+            let mut history = self.history.lock();
+            if history.len() == HISTORY_SIZE {
+                history.pop_front();
+            }
+            history.push_back(tracker.progress());
+        }
+    }
+
+    /// Progress of the compactions running now, in the order they started
+    pub fn progress(&self) -> Vec<CompactionProgress> {
+        self.tracked.lock().iter().map(|tracker| tracker.progress()).collect()
+    }
+
+    /// The last compactions to finish successfully, oldest first
+    pub fn history(&self) -> Vec<CompactionProgress> {
+        self.history.lock().iter().cloned().collect()
+    }
+
+    /// Record a finished compaction
+    pub fn record_completed(&self, bytes_read: u64, bytes_written: u64) {
+        self.completed.fetch_add(1, AtomicOrdering::Relaxed);
+        self.bytes_read.fetch_add(bytes_read, AtomicOrdering::Relaxed);
+        self.bytes_written.fetch_add(bytes_written, AtomicOrdering::Relaxed);
+    }
+
+    /// Size limit of a level (level 1 and deeper): `base_level_size` for
+    /// level 1, growing by `level_size_ratio` per level below it
+    pub fn max_bytes_for_level(&self, level: usize) -> u64 {
+        let triggers = &self.config.read().triggers;
+        let exponent = level.saturating_sub(1) as i32;
+        (triggers.base_level_size as f64 * triggers.level_size_ratio.powi(exponent)) as u64
+    }
+
+    /// Queue a task to run once no more urgent compaction is needed. Tasks
+    /// whose inputs have been compacted away by then are dropped.
+    pub fn schedule_task(&self, task: CompactionTask) {
+        self.queue.lock().push(QueuedTask(task));
+    }
+
+    /// Queued tasks, most urgent first
     pub fn get_pending_tasks(&self) -> Vec<CompactionTask> {
-        // TODO: Implement
-        Vec::new()
+        let mut tasks: Vec<_> = self.queue.lock().iter().map(|queued| queued.0.clone()).collect();
+        tasks.sort_by_key(|task| Reverse((task.priority, Reverse(task.id))));
+        tasks
     }
-    
-    /// Run compaction tasks
-    pub fn run_compaction(&mut self) -> Result<()> {
-        // TODO: Implement
-        Ok(())
+
+    /// Levels that need compacting with their priority: how far the level
+    /// is over its limit, in percent of the limit
+    fn level_scores(&self, version: &Version) -> Vec<(usize, u32)> {
+        let mut scores = Vec::new();
+        let level0 = version.level(0).len();
+        let trigger = self.level0_trigger();
+        if level0 > 0 && level0 >= trigger {
+            scores.push((0, (level0 * 100 / trigger) as u32));
+        }
+
+        let size_factor = if self.tiered.load(AtomicOrdering::Relaxed) { TIERED_RUN_FACTOR as u64 } else { 1 };
+        for level in 1..NUM_LEVELS - 1 {
+            let size = version.level_size(level);
+            let max_bytes = self.max_bytes_for_level(level).saturating_mul(size_factor);
+            if size > max_bytes {
+                let score = size.saturating_mul(100) / max_bytes.max(1);
+                scores.push((level, score.min(u32::MAX as u64) as u32));
+            }
+        }
+        scores
+    }
+
+    /// Pick the most urgent compaction for `version` that can run next to
+    /// the ones already running, if any is needed: the highest scoring
+    /// level or queued task, with shallower levels winning ties between
+    /// levels and queued tasks winning ties with levels. The task counts
+    /// as running until passed to [`finish_task`](Self::finish_task).
+    /// Returns `None` while compaction is paused or `max_threads` tasks
+    /// are running.
+    pub fn pick_compaction(&self, version: &Version) -> Option<CompactionTask> {
+        if self.is_paused() {
+            return None;
+        }
+        let mut running = self.running.lock();
+        if running.len() >= self.max_threads() {
+            return None;
+        }
+
+        let mut queue = self.queue.lock();
+        let live: HashSet<u64> = version.files().map(|t| t.meta.id).collect();
+        queue.retain(|queued| queued.0.input_files.iter().all(|f| live.contains(&f.id)));
+        let mut waiting = std::mem::take(&mut *queue).into_sorted_vec();
+        waiting.reverse();
+        let mut levels = self.level_scores(version);
+        levels.sort_by_key(|&(level, priority)| Reverse((priority, Reverse(level))));
+        let mut levels = levels.into_iter().peekable();
+
+        let mut picked = None;
+        let mut next = 0;
+        while picked.is_none() {
+            let level_priority = levels.peek().map(|&(_, priority)| priority);
+            if next < waiting.len() && level_priority.is_none_or(|priority| waiting[next].0.priority >= priority) {
+                if self.conflicts(&waiting[next].0, &running) {
+                    next += 1;
+                } else {
+                    picked = Some(waiting.remove(next).0);
+                }
+            } else if let Some((level, priority)) = levels.next() {
+                picked = self.level_task(version, level, priority, &running);
+            } else {
+                break;
+            }
+        }
+        queue.extend(waiting);
+
+        let task = picked?;
+        running.push(task.clone());
+        Some(task)
+    }
+
+    /// Mark a task returned by [`pick_compaction`](Self::pick_compaction)
+    /// as finished, whether it succeeded or not
+    pub fn finish_task(&self, task: &CompactionTask) {
+        self.running.lock().retain(|running| running.id != task.id);
+    }
+
+    /// Number of picked tasks that have not finished
+    pub fn running_tasks(&self) -> usize {
+        self.running.lock().len()
+    }
+
+    /// Whether `task` cannot run alongside the `running` tasks: it shares
+    /// an input file with one, or touches a level one of them touches
+    /// within an overlapping key range
+    fn conflicts(&self, task: &CompactionTask, running: &[CompactionTask]) -> bool {
+        if task.input_files.is_empty() {
+            return false;
+        }
+        let (smallest, largest) = self.key_range(task.input_files.iter());
+        running.iter().any(|other| {
+            if task.input_files.iter().any(|f| other.input_files.iter().any(|o| o.id == f.id)) {
+                return true;
+            }
+            let levels = [other.source_level, other.target_level];
+            if other.input_files.is_empty()
+                || !(levels.contains(&task.source_level) || levels.contains(&task.target_level))
+            {
+                return false;
+            }
+            let (other_smallest, other_largest) = self.key_range(other.input_files.iter());
+            self.comparator.compare(&smallest, &other_largest) != Ordering::Greater
+                && self.comparator.compare(&other_smallest, &largest) != Ordering::Greater
+        })
+    }
+
+    /// Compaction of `level` into the next level that does not conflict
+    /// with the `running` tasks
+    fn level_task(
+        &self,
+        version: &Version,
+        level: usize,
+        priority: u32,
+        running: &[CompactionTask],
+    ) -> Option<CompactionTask> {
+        if level == 0 {
+            let level0 = version.level(0);
+            let (smallest, largest) = self.key_range(level0.iter().map(|t| &t.meta));
+            let mut inputs: Vec<SstFile> = level0.iter().map(|t| t.meta.clone()).collect();
+            inputs.extend(
+                version
+                    .overlapping(1, &smallest, &largest, &self.comparator)
+                    .iter()
+                    .map(|t| t.meta.clone()),
+            );
+            let task = self.task(0, 1, inputs, priority);
+            return (!self.conflicts(&task, running)).then_some(task);
+        }
+
+        // Round-robin from the compact pointer, skipping files a running
+        // task holds
+        let files = version.level(level);
+        let mut pointers = self.compact_pointers.lock();
+        let start = pointers[level].as_ref().map_or(0, |pointer| {
+            files.partition_point(|t| self.comparator.compare(&t.meta.largest_key, pointer) != Ordering::Greater)
+        });
+        for table in files[start..].iter().chain(&files[..start]) {
+            let mut inputs = vec![table.meta.clone()];
+            inputs.extend(
+                version
+                    .overlapping(level + 1, &table.meta.smallest_key, &table.meta.largest_key, &self.comparator)
+                    .iter()
+                    .map(|t| t.meta.clone()),
+            );
+            let task = self.task(level as u32, level as u32 + 1, inputs, priority);
+            if !self.conflicts(&task, running) {
+                pointers[level] = Some(table.meta.largest_key.clone());
+                return Some(task);
+            }
+        }
+        None
+    }
+
+    /// Whether no level below the task's target overlaps its inputs, so its
+    /// output holds the oldest data for those keys and tombstones can go
+    pub fn is_bottommost(&self, version: &Version, task: &CompactionTask) -> bool {
+        if task.input_files.is_empty() {
+            return true;
+        }
+        let (smallest, largest) = self.key_range(task.input_files.iter());
+        (task.target_level as usize + 1..NUM_LEVELS)
+            .all(|level| version.overlapping(level, &smallest, &largest, &self.comparator).is_empty())
+    }
+
+    /// Build a manual compaction of `inputs` from `level` into the next
+    /// level, adding the next level's overlapping files
+    pub fn manual_task(&self, version: &Version, level: usize, mut inputs: Vec<SstFile>) -> CompactionTask {
+        let (smallest, largest) = self.key_range(inputs.iter());
+        inputs.extend(
+            version
+                .overlapping(level + 1, &smallest, &largest, &self.comparator)
+                .iter()
+                .map(|t| t.meta.clone()),
+        );
+        self.task(level as u32, level as u32 + 1, inputs, u32::MAX)
+    }
+
+    fn task(&self, source_level: u32, target_level: u32, input_files: Vec<SstFile>, priority: u32) -> CompactionTask {
+        CompactionTask {
+            id: self.next_task_id.fetch_add(1, AtomicOrdering::Relaxed),
+            source_level,
+            target_level,
+            input_files,
+            priority,
+        }
+    }
+
+    /// Smallest and largest key across a non-empty set of files
+    fn key_range<'a>(&self, mut files: impl Iterator<Item = &'a SstFile>) -> (Vec<u8>, Vec<u8>) {
+        let first = files.next().expect("key range of no files");
+        let mut smallest = &first.smallest_key;
+        let mut largest = &first.largest_key;
+        for file in files {
+            if self.comparator.compare(&file.smallest_key, smallest) == Ordering::Less {
+                smallest = &file.smallest_key;
+            }
+            if self.comparator.compare(&file.largest_key, largest) == Ordering::Greater {
+                largest = &file.largest_key;
+            }
+        }
+        (smallest.clone(), largest.clone())
     }
 }
 
-/// RL agent for compaction policy selection
+/// Merge sorted runs into one run in comparator order, keeping only the
+/// newest entry (highest sequence) for each key. Tombstones are kept so they
+/// continue to shadow older data in deeper levels.
+pub fn merge_entries(inputs: Vec<Vec<Entry>>, comparator: &Comparator) -> Vec<Entry> {
+    MergeIterator::new(inputs.into_iter().map(Vec::into_iter), comparator).collect()
+}
+
+/// Workload features the RL agent picks a strategy from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentState {
+    /// Files in level 0
+    pub level0_files: usize,
+    /// Fraction of recent operations that were writes, from 0 to 1
+    pub write_fraction: f64,
+}
+
+impl AgentState {
+    /// Row of the Q-table for this state
+    fn bucket(&self) -> usize {
+        let level0 = self.level0_files.min(LEVEL0_BUCKETS - 1);
+        let writes = ((self.write_fraction.clamp(0.0, 1.0) * WRITE_BUCKETS as f64) as usize).min(WRITE_BUCKETS - 1);
+        level0 * WRITE_BUCKETS + writes
+    }
+}
+
+/// One step of experience: the strategy chosen in `state`, the reward it
+/// earned and the state that followed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transition {
+    /// State the action was chosen in
+    pub state: AgentState,
+    /// Strategy chosen
+    pub action: CompactionStrategy,
+    /// Reward observed
+    pub reward: f64,
+    /// State after the action
+    pub next_state: AgentState,
+}
+
+/// File in the database directory the engine keeps the RL policy in
+pub const RL_POLICY_FILE: &str = "RL_POLICY";
+
+/// Current saved policy format version
+const POLICY_VERSION: u32 = 1;
+
+/// On-disk form of a learned policy
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedPolicy {
+    /// Format version
+    version: u32,
+    /// Number of state buckets
+    states: usize,
+    /// Number of actions
+    actions: usize,
+    /// Actions selected so far
+    steps: u64,
+    /// Expected reward per state and action
+    q_table: Vec<Vec<f64>>,
+}
+
+/// Level 0 file counts the agent distinguishes (the last bucket is "or more")
+const LEVEL0_BUCKETS: usize = 8;
+/// Write fraction buckets the agent distinguishes
+const WRITE_BUCKETS: usize = 4;
+/// Strategies the agent chooses between, indexed like Q-table columns
+const ACTIONS: [CompactionStrategy; 3] = [
+    CompactionStrategy::Leveled,
+    CompactionStrategy::Tiered,
+    CompactionStrategy::Flexible,
+];
+/// Weight of future rewards in Q-learning updates
+const DISCOUNT: f64 = 0.9;
+
+/// RL agent for compaction policy selection.
+///
+/// A tabular Q-learning agent: [`Self::observe_state`] records the
+/// workload, [`Self::select_action`] picks a strategy epsilon-greedily and
+/// [`Self::update_policy`] learns from the reward that choice earned.
+/// Epsilon follows the configured [`crate::config::ExplorationSchedule`]
+/// over the step count, which [`Self::steps`] and [`Self::with_steps`]
+/// carry across restarts.
+///
+/// With `offline_training`, [`Self::open`] first learns from the
+/// transitions recorded in `training_data_path`, one JSON
+/// [`Transition`] per line; with `record_training_data`, every rewarded
+/// action is appended to that file.
 pub struct RlCompactionAgent {
-    // TODO: Implement RL agent functionality
+    /// Agent configuration
+    config: RlAgentConfig,
+    /// Expected reward of each action, per state bucket
+    q_table: Vec<[f64; ACTIONS.len()]>,
+    /// Actions selected so far
+    steps: u64,
+    /// Most recently observed state
+    state: AgentState,
+    /// State and action index awaiting a reward
+    pending: Option<(AgentState, usize)>,
+    /// Training data file live transitions are appended to
+    recorder: Option<BufWriter<File>>,
+    /// Exploration randomness
+    rng: StdRng,
 }
 
 impl RlCompactionAgent {
-    /// Create a new RL agent
+    /// Create a new RL agent with the default configuration
     pub fn new() -> Self {
-        Self {}
+        Self::with_config(RlAgentConfig::default())
+    }
+
+    /// Create a new RL agent
+    pub fn with_config(config: RlAgentConfig) -> Self {
+        Self {
+            config,
+            q_table: vec![[0.0; ACTIONS.len()]; LEVEL0_BUCKETS * WRITE_BUCKETS],
+            steps: 0,
+            state: AgentState::default(),
+            pending: None,
+            recorder: None,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Create an agent for the engine: resume the policy saved at
+    /// `policy_path` if there is a compatible one, then train on the
+    /// recorded transitions if `offline_training` is set and open the
+    /// training data file for appending if `record_training_data` is set
+    pub fn open(config: RlAgentConfig, policy_path: Option<&Path>) -> Result<Self> {
+        let path = config.training_data_path.clone();
+        let (offline, record) = (config.offline_training, config.record_training_data);
+        let mut agent = Self::with_config(config);
+        if let Some(policy_path) = policy_path {
+            agent.load(policy_path)?;
+        }
+        if let Some(path) = path {
+            if offline && path.exists() {
+                let trained = agent.train_from_file(&path)?;
+                debug!("RL agent trained on {} recorded transitions", trained);
+            }
+            if record {
+                let file = OpenOptions::new().create(true).append(true).open(&path)?;
+                agent.recorder = Some(BufWriter::new(file));
+            }
+        } else if offline || record {
+            return Err(Error::Config("RL offline training and recording need a training_data_path".to_string()));
+        }
+        Ok(agent)
+    }
+
+    /// Atomically write the learned policy and step count to `path`
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let policy = SavedPolicy {
+            version: POLICY_VERSION,
+            states: self.q_table.len(),
+            actions: ACTIONS.len(),
+            steps: self.steps,
+            q_table: self.q_table.iter().map(|row| row.to_vec()).collect(),
+        };
+        let data = serde_json::to_vec(&policy)
+            .map_err(|e| Error::RlAgent(format!("Failed to encode policy: {}", e)))?;
+        let tmp_path = path.with_extension("tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Replace the policy and step count with those saved at `path`.
+    /// Returns false, leaving the agent unchanged, if there is no saved
+    /// policy or it was saved in an incompatible format.
+    pub fn load(&mut self, path: &Path) -> Result<bool> {
+        if !path.exists() {
+            return Ok(false);
+        }
+        let data = std::fs::read(path)?;
+        let policy = match serde_json::from_slice::<SavedPolicy>(&data) {
+            Ok(policy)
+                if policy.version == POLICY_VERSION
+                    && policy.states == self.q_table.len()
+                    && policy.actions == ACTIONS.len()
+                    && policy.q_table.len() == policy.states
+                    && policy.q_table.iter().all(|row| row.len() == policy.actions) =>
+            {
+                policy
+            }
+            Ok(policy) => {
+                warn!(
+                    "Ignoring RL policy {} saved in format version {} ({} states, {} actions)",
+                    path.display(),
+                    policy.version,
+                    policy.states,
+                    policy.actions
+                );
+                return Ok(false);
+            }
+            Err(e) => {
+                warn!("Ignoring unreadable RL policy {}: {}", path.display(), e);
+                return Ok(false);
+            }
+        };
+        for (row, saved) in self.q_table.iter_mut().zip(&policy.q_table) {
+            row.copy_from_slice(saved);
+        }
+        self.steps = policy.steps;
+        Ok(true)
+    }
+
+    /// Learn from every transition in a newline-delimited JSON trace,
+    /// returning how many were applied. Blank lines are skipped.
+    pub fn train_from_file(&mut self, path: &Path) -> Result<usize> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut trained = 0;
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let transition: Transition = serde_json::from_str(&line).map_err(|e| {
+                Error::RlAgent(format!("Invalid transition on line {} of {}: {}", line_number + 1, path.display(), e))
+            })?;
+            self.learn(&transition);
+            trained += 1;
+        }
+        Ok(trained)
+    }
+
+    /// Apply one Q-learning update
+    pub fn learn(&mut self, transition: &Transition) {
+        let action = ACTIONS.iter().position(|a| *a == transition.action).unwrap_or(0);
+        let next = &self.q_table[transition.next_state.bucket()];
+        let target = transition.reward + DISCOUNT * next[best_action(next)];
+        let q = &mut self.q_table[transition.state.bucket()][action];
+        *q += self.config.learning_rate * (target - *q);
+    }
+
+    /// Flush recorded transitions to the training data file
+    pub fn flush_recording(&mut self) -> Result<()> {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Resume the exploration schedule after `steps` earlier steps
+    pub fn with_steps(mut self, steps: u64) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    /// Actions selected so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Current exploration rate
+    pub fn epsilon(&self) -> f64 {
+        self.config
+            .exploration_schedule
+            .epsilon(self.config.exploration_rate, self.steps)
     }
     
     /// Observe current state
-    pub fn observe_state(&mut self) -> Result<()> {
-        // TODO: Implement
+    pub fn observe_state(&mut self, state: AgentState) -> Result<()> {
+        self.state = state;
         Ok(())
     }
     
-    /// Select action based on current state
-    pub fn select_action(&self) -> CompactionStrategy {
-        // TODO: Implement
-        CompactionStrategy::Leveled
+    /// Select action based on current state: a random strategy with
+    /// probability epsilon, else the one with the highest expected reward
+    pub fn select_action(&mut self) -> CompactionStrategy {
+        let action = if self.rng.gen::<f64>() < self.epsilon() {
+            self.rng.gen_range(0..ACTIONS.len())
+        } else {
+            best_action(&self.q_table[self.state.bucket()])
+        };
+        self.steps += 1;
+        self.pending = Some((self.state, action));
+        ACTIONS[action]
     }
     
-    /// Update policy based on reward
-    pub fn update_policy(&mut self, _reward: f64) -> Result<()> {
-        // TODO: Implement
+    /// Update policy based on the reward for the last selected action,
+    /// observed in the current state
+    pub fn update_policy(&mut self, reward: f64) -> Result<()> {
+        let Some((state, action)) = self.pending.take() else {
+            return Ok(());
+        };
+        let transition = Transition {
+            state,
+            action: ACTIONS[action],
+            reward,
+            next_state: self.state,
+        };
+        self.learn(&transition);
+        if let Some(recorder) = &mut self.recorder {
+            serde_json::to_writer(&mut *recorder, &transition)
+                .map_err(|e| Error::RlAgent(format!("Failed to record transition: {}", e)))?;
+            recorder.write_all(b"\n")?;
+        }
         Ok(())
     }
 }
 
+/// Index of the highest value, preferring the earliest on ties
+fn best_action(values: &[f64; ACTIONS.len()]) -> usize {
+    (1..values.len()).fold(0, |best, i| if values[i] > values[best] { i } else { best })
+}
+
+impl Default for RlCompactionAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::ExplorationSchedule;
+    use crate::storage::{Key, Value};
     
     #[test]
     fn test_compaction_manager_creation() {
-        let manager = CompactionManager::new();
+        let manager = CompactionManager::new(CompactionConfig::default(), Comparator::default());
+        assert!(manager.pick_compaction(&Version::default()).is_none());
+
+        manager.pause();
+        assert!(manager.is_paused());
+        manager.resume();
+        assert!(!manager.is_paused());
+    }
+
+    #[test]
+    fn test_progress_and_bounded_history() {
+        let manager = CompactionManager::new(CompactionConfig::default(), Comparator::default());
+        let file = |id, size, entry_count| SstFile {
+            id,
+            path: Default::default(),
+            size,
+            level: 0,
+            entry_count,
+            deletion_count: 0,
+            smallest_key: Vec::new(),
+            largest_key: Vec::new(),
+            bloom_fpr: None,
+        };
+        let task = |id| CompactionTask {
+            id,
+            source_level: 0,
+            target_level: 1,
+            input_files: vec![file(1, 100, 10), file(2, 50, 5)],
+            priority: 0,
+        };
+
+        let tracker = manager.start_tracking(&task(1));
+        tracker.add_read(100);
+        tracker.add_processed();
+        tracker.add_output(80);
+        let running = manager.progress();
+        assert_eq!(running.len(), 1);
+        assert_eq!((running[0].input_files, running[0].input_bytes, running[0].input_entries), (2, 150, 15));
+        assert_eq!((running[0].bytes_read, running[0].entries_processed), (100, 1));
+        assert_eq!((running[0].output_files, running[0].output_bytes), (1, 80));
+        assert!(running[0].summary().starts_with("id=1 level=0->1 files=2 read=100/150"));
+
+        // Failed runs leave no history; successful ones are kept, newest last
+        manager.stop_tracking(&tracker, false);
+        assert!(manager.progress().is_empty());
+        assert!(manager.history().is_empty());
+        for id in 0..HISTORY_SIZE as u64 + 5 {
+            let tracker = manager.start_tracking(&task(id));
+            manager.stop_tracking(&tracker, true);
+        }
+        let history = manager.history();
+        assert_eq!(history.len(), HISTORY_SIZE);
+        assert_eq!(history[0].id, 5);
+        assert_eq!(history.last().unwrap().id, HISTORY_SIZE as u64 + 4);
+    }
+
+    #[test]
+    fn test_urgent_level0_task_preempts_queued_task() {
+        use crate::io::FileAccess;
+        use crate::config::SstConfig;
+        use crate::sst::{sst_file_name, SstManager, SstWriter, VersionEdit};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sst = SstManager::new(temp_dir.path().to_path_buf(), FileAccess::Buffered, Comparator::default());
+        let write = |id: u64, level: u32| {
+            let path = temp_dir.path().join(sst_file_name(id));
+            let mut writer = SstWriter::new(&path, &SstConfig::default(), FileAccess::Buffered).unwrap();
+            writer.add(&Entry::new(Key::from(format!("key_{}", id).as_str()), Value::from("v"), id)).unwrap();
+            let mut meta = writer.finish().unwrap();
+            meta.level = level;
+            meta
+        };
+        let added: Vec<_> = (1..=5).map(|id| write(id, if id == 5 { 1 } else { 0 })).collect();
+        sst.apply(VersionEdit { added: added.clone(), removed: Vec::new() }).unwrap();
+
+        let manager = CompactionManager::new(CompactionConfig::default(), Comparator::default());
+        let queued = manager.task(1, 2, vec![added[4].clone()], 10);
+        manager.schedule_task(queued.clone());
+
+        let task = manager.pick_compaction(&sst.current()).unwrap();
+        assert_eq!(task.source_level, 0);
+        assert_eq!(task.input_files.len(), 4);
+        assert!(task.priority > queued.priority);
+        assert_eq!(manager.get_pending_tasks().len(), 1);
+
+        // Once level 0 is compacted the queued task is next
+        sst.apply(VersionEdit { added: Vec::new(), removed: vec![1, 2, 3, 4] }).unwrap();
+        assert_eq!(manager.pick_compaction(&sst.current()).unwrap().id, queued.id);
         assert!(manager.get_pending_tasks().is_empty());
+        assert!(manager.pick_compaction(&sst.current()).is_none());
+    }
+
+    #[test]
+    fn test_disjoint_compactions_run_concurrently() {
+        use crate::config::SstConfig;
+        use crate::io::FileAccess;
+        use crate::sst::{sst_file_name, SstManager, SstReader, SstWriter, VersionEdit};
+        use std::sync::Barrier;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sst = SstManager::new(temp_dir.path().to_path_buf(), FileAccess::Buffered, Comparator::default());
+        let write = |id: u64, prefix: &str| {
+            let path = temp_dir.path().join(sst_file_name(id));
+            let mut writer = SstWriter::new(&path, &SstConfig::default(), FileAccess::Buffered).unwrap();
+            for i in 0..10u64 {
+                let key = Key::from(format!("{}_{}", prefix, i).as_str());
+                writer.add(&Entry::new(key, Value::from("v"), id * 100 + i)).unwrap();
+            }
+            let mut meta = writer.finish().unwrap();
+            meta.level = 1;
+            meta
+        };
+        let added = vec![write(1, "a"), write(2, "m")];
+        sst.apply(VersionEdit { added: added.clone(), removed: Vec::new() }).unwrap();
+
+        // A tiny size limit puts level 1 over it
+        let mut config = CompactionConfig::default();
+        config.triggers.base_level_size = 1;
+        let manager = CompactionManager::new(config, Comparator::default());
+        let picked = Barrier::new(3);
+        let checked = Barrier::new(3);
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..2)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let task = manager.pick_compaction(&sst.current()).unwrap();
+                        picked.wait();
+                        checked.wait();
+                        let runs = task
+                            .input_files
+                            .iter()
+                            .map(|f| SstReader::open(&f.path, FileAccess::Buffered).unwrap().entries().unwrap())
+                            .collect();
+                        let merged = merge_entries(runs, &Comparator::default());
+                        manager.finish_task(&task);
+                        (task, merged)
+                    })
+                })
+                .collect();
+
+            picked.wait();
+            assert_eq!(manager.running_tasks(), 2);
+            // Both files are held, so nothing else can start
+            manager.schedule_task(manager.manual_task(&sst.current(), 1, vec![added[0].clone()]));
+            assert!(manager.pick_compaction(&sst.current()).is_none());
+            checked.wait();
+
+            let mut outputs: Vec<_> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+            outputs.sort_by_key(|(task, _)| task.input_files[0].id);
+            for ((task, merged), prefix) in outputs.iter().zip(["a", "m"]) {
+                assert_eq!(task.input_files.len(), 1);
+                assert_eq!(merged.len(), 10);
+                assert!(merged.iter().all(|e| e.key.data.starts_with(prefix.as_bytes())));
+            }
+        });
+        assert_eq!(manager.running_tasks(), 0);
+        assert_eq!(manager.pick_compaction(&sst.current()).unwrap().priority, u32::MAX);
+    }
+
+    #[test]
+    fn test_merge_keeps_newest_entry() {
+        let entry = |key: &[u8], value: &[u8], seq| Entry::new(Key::new(key.to_vec()), Value::new(value.to_vec()), seq);
+        let older = vec![entry(b"a", b"old", 1), entry(b"b", b"old", 2)];
+        let newer = vec![entry(b"b", b"new", 5), Entry::delete(Key::new(b"c".to_vec()), 6)];
+
+        let merged = merge_entries(vec![older, newer], &Comparator::default());
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[1].value.as_ref().unwrap().data, b"new");
+        assert!(merged[2].is_delete());
     }
     
     #[test]
     fn test_rl_agent_creation() {
-        let agent = RlCompactionAgent::new();
+        let mut agent = RlCompactionAgent::with_config(RlAgentConfig {
+            exploration_rate: 0.0,
+            ..RlAgentConfig::default()
+        });
         assert!(matches!(agent.select_action(), CompactionStrategy::Leveled));
+
+        // A rewarded strategy becomes the greedy choice
+        agent.pending = Some((AgentState::default(), 1));
+        agent.update_policy(1.0).unwrap();
+        assert!(matches!(agent.select_action(), CompactionStrategy::Tiered));
+    }
+
+    #[test]
+    fn test_exploration_decays_with_steps() {
+        let config = RlAgentConfig {
+            exploration_schedule: ExplorationSchedule::LinearDecay { from: 1.0, to: 0.1, over_steps: 10 },
+            ..RlAgentConfig::default()
+        };
+        let mut agent = RlCompactionAgent::with_config(config.clone());
+        let mut last = agent.epsilon();
+        assert_eq!(last, 1.0);
+        for _ in 0..10 {
+            agent.select_action();
+            assert!(agent.epsilon() < last);
+            last = agent.epsilon();
+        }
+        assert!((agent.epsilon() - 0.1).abs() < 1e-9);
+        agent.select_action();
+        assert!((agent.epsilon() - 0.1).abs() < 1e-9);
+
+        // The schedule resumes where a restarted agent left off
+        let resumed = RlCompactionAgent::with_config(config).with_steps(agent.steps());
+        assert_eq!(resumed.epsilon(), agent.epsilon());
+
+        let exponential = ExplorationSchedule::ExponentialDecay { from: 0.5, decay: 0.5 };
+        assert_eq!(exponential.epsilon(0.1, 2), 0.125);
+    }
+
+    #[test]
+    fn test_offline_training_from_recorded_trace() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("trace.ndjson");
+        let config = RlAgentConfig {
+            exploration_rate: 0.0,
+            learning_rate: 0.5,
+            training_data_path: Some(path.clone()),
+            ..RlAgentConfig::default()
+        };
+
+        // Record a session in which tiered compaction paid off
+        let mut recorder = RlCompactionAgent::open(RlAgentConfig {
+            record_training_data: true,
+            ..config.clone()
+        }, None)
+        .unwrap();
+        for _ in 0..5 {
+            recorder.pending = Some((AgentState::default(), 1));
+            recorder.update_policy(1.0).unwrap();
+        }
+        recorder.flush_recording().unwrap();
+
+        let mut fresh = RlCompactionAgent::open(config.clone(), None).unwrap();
+        assert_eq!(fresh.select_action(), CompactionStrategy::Leveled);
+
+        let mut trained = RlCompactionAgent::open(RlAgentConfig {
+            offline_training: true,
+            ..config
+        }, None)
+        .unwrap();
+        assert_eq!(trained.select_action(), CompactionStrategy::Tiered);
+
+        std::fs::write(&path, "{not json}\n").unwrap();
+        assert!(matches!(trained.train_from_file(&path), Err(Error::RlAgent(_))));
+    }
+
+    #[test]
+    fn test_policy_save_and_load() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(RL_POLICY_FILE);
+        let config = RlAgentConfig {
+            exploration_rate: 0.0,
+            ..RlAgentConfig::default()
+        };
+
+        let mut agent = RlCompactionAgent::with_config(config.clone());
+        agent.pending = Some((AgentState::default(), 2));
+        agent.update_policy(1.0).unwrap();
+        agent.select_action();
+        agent.save(&path).unwrap();
+
+        let mut restored = RlCompactionAgent::open(config.clone(), Some(&path)).unwrap();
+        assert_eq!(restored.steps(), agent.steps());
+        assert_eq!(restored.select_action(), CompactionStrategy::Flexible);
+
+        // A policy from another format version is ignored
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, saved.replace("\"version\":1", "\"version\":99")).unwrap();
+        let mut fresh = RlCompactionAgent::with_config(config);
+        assert!(!fresh.load(&path).unwrap());
+        assert_eq!(fresh.steps(), 0);
     }
 }
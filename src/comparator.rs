@@ -0,0 +1,90 @@
+//! Key comparators
+//!
+//! A [`Comparator`] defines the order of keys in the memtable, scans and
+//! SST files. Its name is recorded in the manifest so a database can only
+//! be reopened with the comparator it was created with.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+/// Comparison function shared by a [`Comparator`]
+pub type CompareFn = dyn Fn(&[u8], &[u8]) -> Ordering + Send + Sync;
+
+/// A named key ordering
+#[derive(Clone)]
+pub struct Comparator {
+    /// Name persisted in the manifest
+    name: Arc<str>,
+    /// Comparison function
+    compare: Arc<CompareFn>,
+}
+
+impl Comparator {
+    /// Name of the default lexicographic comparator
+    pub const BYTEWISE: &'static str = "auradb.BytewiseComparator";
+    /// Name of the built-in reverse lexicographic comparator
+    pub const REVERSE_BYTEWISE: &'static str = "auradb.ReverseBytewiseComparator";
+
+    /// Create a comparator from a name and a comparison function.
+    ///
+    /// The name identifies the ordering on disk, so it must change whenever
+    /// the function's ordering changes.
+    pub fn new<F>(name: impl Into<String>, compare: F) -> Self
+    where
+        F: Fn(&[u8], &[u8]) -> Ordering + Send + Sync + 'static,
+    {
+        Self {
+            name: Arc::from(name.into()),
+            compare: Arc::new(compare),
+        }
+    }
+
+    /// Plain lexicographic byte order (the default)
+    pub fn bytewise() -> Self {
+        Self::new(Self::BYTEWISE, |a, b| a.cmp(b))
+    }
+
+    /// Reverse lexicographic byte order
+    pub fn reverse() -> Self {
+        Self::new(Self::REVERSE_BYTEWISE, |a, b| b.cmp(a))
+    }
+
+    /// Comparator name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Compare two keys
+    pub fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        (self.compare)(a, b)
+    }
+}
+
+impl Default for Comparator {
+    fn default() -> Self {
+        Self::bytewise()
+    }
+}
+
+impl fmt::Debug for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Comparator").field("name", &self.name).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_comparators() {
+        let bytewise = Comparator::bytewise();
+        let reverse = Comparator::reverse();
+
+        assert_eq!(bytewise.compare(b"a", b"b"), Ordering::Less);
+        assert_eq!(reverse.compare(b"a", b"b"), Ordering::Greater);
+        assert_eq!(reverse.compare(b"a", b"a"), Ordering::Equal);
+        assert_ne!(bytewise.name(), reverse.name());
+    }
+}
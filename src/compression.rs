@@ -0,0 +1,64 @@
+//! Block compression
+//!
+//! Codecs for each [`CompressionAlgorithm`]. Callers record the algorithm
+//! next to the data it compressed, so readers decode whatever a file was
+//! written with.
+
+use crate::config::CompressionAlgorithm;
+use crate::error::{Error, Result};
+
+/// Zstd level used where none is configured
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// Compress `data` with `algo`, using Zstd's default level
+pub fn compress(data: &[u8], algo: CompressionAlgorithm) -> Result<Vec<u8>> {
+    compress_with_level(data, algo, DEFAULT_ZSTD_LEVEL)
+}
+
+/// Compress `data` with `algo`. `level` applies to Zstd only; its frames
+/// record what decoding needs, so any level decompresses the same way.
+pub fn compress_with_level(data: &[u8], algo: CompressionAlgorithm, level: i32) -> Result<Vec<u8>> {
+    match algo {
+        CompressionAlgorithm::None => Ok(data.to_vec()),
+        CompressionAlgorithm::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+        CompressionAlgorithm::Zstd => Ok(zstd::bulk::compress(data, level)?),
+        CompressionAlgorithm::Snappy => Err(unsupported(algo)),
+    }
+}
+
+/// Reverse [`compress`]. Callers report failures as corruption of the
+/// file the data came from.
+pub fn decompress(data: &[u8], algo: CompressionAlgorithm) -> Result<Vec<u8>> {
+    match algo {
+        CompressionAlgorithm::None => Ok(data.to_vec()),
+        CompressionAlgorithm::Lz4 => lz4_flex::decompress_size_prepended(data)
+            .map_err(|e| Error::Unknown(format!("LZ4 decompression failed: {}", e))),
+        CompressionAlgorithm::Zstd => zstd::stream::decode_all(data)
+            .map_err(|e| Error::Unknown(format!("Zstd decompression failed: {}", e))),
+        CompressionAlgorithm::Snappy => Err(unsupported(algo)),
+    }
+}
+
+/// Whether this build has a codec for `algo`
+pub fn is_supported(algo: CompressionAlgorithm) -> bool {
+    algo != CompressionAlgorithm::Snappy
+}
+
+fn unsupported(algo: CompressionAlgorithm) -> Error {
+    Error::Config(format!("{:?} compression is not supported by this build", algo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lz4_round_trip() {
+        let data = b"auradb ".repeat(500);
+        let compressed = compress(&data, CompressionAlgorithm::Lz4).unwrap();
+        assert!(compressed.len() < data.len() / 10);
+        assert_eq!(decompress(&compressed, CompressionAlgorithm::Lz4).unwrap(), data);
+        assert!(decompress(&data[..10], CompressionAlgorithm::Lz4).is_err());
+        assert!(compress(&data, CompressionAlgorithm::Snappy).is_err());
+    }
+}
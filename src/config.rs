@@ -1,3 +1,6 @@
+use crate::comparator::Comparator;
+use crate::filter::PrefixExtractor;
+use crate::secondary::SecondaryIndex;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -33,6 +36,30 @@ pub struct Config {
     
     /// Performance tuning
     pub performance: PerformanceConfig,
+
+    /// Largest key accepted by writes, in bytes
+    pub max_key_size: usize,
+
+    /// Largest value accepted by writes, in bytes
+    pub max_value_size: usize,
+
+    /// Whether opening a path without a database creates one
+    pub create_if_missing: bool,
+
+    /// Whether opening a path that already holds a database fails
+    pub error_if_exists: bool,
+
+    /// Keep everything in memtables, with no WAL, SST or value log files;
+    /// nothing survives `close`. For tests and ephemeral caches only.
+    pub in_memory: bool,
+
+    /// Key comparator (recorded in the manifest by name)
+    #[serde(skip)]
+    pub comparator: Comparator,
+
+    /// Secondary indexes the engine maintains on every write
+    #[serde(skip)]
+    pub secondary_indexes: Vec<SecondaryIndex>,
 }
 
 impl Default for Config {
@@ -48,6 +75,13 @@ impl Default for Config {
             learned_index: LearnedIndexConfig::default(),
             rl_agent: RlAgentConfig::default(),
             performance: PerformanceConfig::default(),
+            max_key_size: 8 * 1024, // 8KB
+            max_value_size: 256 * 1024 * 1024, // 256MB
+            create_if_missing: true,
+            error_if_exists: false,
+            in_memory: false,
+            comparator: Comparator::default(),
+            secondary_indexes: Vec::new(),
         }
     }
 }
@@ -63,8 +97,29 @@ pub struct WalConfig {
     pub async_writes: bool,
     /// WAL sync policy
     pub sync_policy: WalSyncPolicy,
-    /// WAL buffer size in bytes
+    /// Capacity of a WAL file's write buffer in bytes; raised to
+    /// `group_flush_bytes` if smaller, so only the group flush limits
+    /// decide when records are written out
     pub buffer_size: usize,
+    /// Records accumulated before they are written out to the WAL file
+    pub group_flush_records: usize,
+    /// Bytes of records accumulated before they are written out
+    pub group_flush_bytes: usize,
+    /// Longest a record waits to be written out when neither limit is
+    /// reached, in milliseconds. The sync writer checks it on each write;
+    /// the async writer also wakes up for it.
+    pub group_flush_interval_ms: u64,
+    /// Whether to preallocate `max_file_size` bytes for each new WAL file
+    pub preallocate: bool,
+    /// Whether to reuse obsolete WAL files instead of deleting them
+    pub recycle_wal: bool,
+    /// Checksum algorithm for new WAL files
+    pub checksum: ChecksumType,
+    /// Compress record payloads with this algorithm. Each record is
+    /// tagged with the algorithm it was written with.
+    pub compress_records: Option<CompressionAlgorithm>,
+    /// Records smaller than this many bytes are written uncompressed
+    pub compression_threshold: usize,
 }
 
 impl Default for WalConfig {
@@ -75,6 +130,14 @@ impl Default for WalConfig {
             async_writes: true,
             sync_policy: WalSyncPolicy::EveryWrite,
             buffer_size: 64 * 1024, // 64KB
+            group_flush_records: 1000,
+            group_flush_bytes: 1024 * 1024, // 1MB
+            group_flush_interval_ms: 10,
+            preallocate: true,
+            recycle_wal: false,
+            checksum: ChecksumType::Crc32,
+            compress_records: None,
+            compression_threshold: 256,
         }
     }
 }
@@ -101,7 +164,8 @@ pub struct ValueLogConfig {
     pub max_segment_size: u64,
     /// Value size threshold for separation (bytes)
     pub separation_threshold: usize,
-    /// Number of parallel write queues
+    /// Number of parallel write queues, each a background task with a
+    /// segment of its own, and of segments synchronous writes rotate over
     pub write_queues: usize,
     /// Value log cache size in bytes
     pub cache_size: usize,
@@ -109,6 +173,21 @@ pub struct ValueLogConfig {
     pub compress_values: bool,
     /// Compression algorithm
     pub compression_algorithm: CompressionAlgorithm,
+    /// Zstd level for values, from 1 (fastest) to 19 (smallest); other
+    /// algorithms ignore it
+    #[serde(default = "default_compression_level")]
+    pub compression_level: i32,
+    /// Checksum algorithm for new segments
+    pub checksum: ChecksumType,
+    /// Whether value reads verify checksums unless the read's options
+    /// say otherwise
+    pub verify_on_read: bool,
+    /// Bytes buffered at a time by streamed value writes and reads
+    pub stream_chunk_size: usize,
+}
+
+fn default_compression_level() -> i32 {
+    crate::compression::DEFAULT_ZSTD_LEVEL
 }
 
 impl Default for ValueLogConfig {
@@ -121,6 +200,10 @@ impl Default for ValueLogConfig {
             cache_size: 64 * 1024 * 1024, // 64MB
             compress_values: true,
             compression_algorithm: CompressionAlgorithm::Lz4,
+            compression_level: crate::compression::DEFAULT_ZSTD_LEVEL,
+            checksum: ChecksumType::Crc32,
+            verify_on_read: true,
+            stream_chunk_size: 1024 * 1024, // 1MB
         }
     }
 }
@@ -138,6 +221,60 @@ pub enum CompressionAlgorithm {
     Snappy,
 }
 
+impl CompressionAlgorithm {
+    /// On-disk tag for this algorithm
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+
+    /// Decode an on-disk tag
+    pub fn from_u8(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(CompressionAlgorithm::None),
+            1 => Some(CompressionAlgorithm::Lz4),
+            2 => Some(CompressionAlgorithm::Zstd),
+            3 => Some(CompressionAlgorithm::Snappy),
+            _ => None,
+        }
+    }
+}
+
+/// Checksum algorithms for on-disk data.
+///
+/// The algorithm is recorded in each file's header or footer, so files are
+/// always verified with the algorithm they were written with.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Copy, Default)]
+pub enum ChecksumType {
+    /// CRC32 (the default)
+    #[default]
+    Crc32,
+    /// xxHash3, truncated to 32 bits (faster on large blocks)
+    XxHash3,
+    /// No checksums; verification is skipped
+    None,
+}
+
+impl ChecksumType {
+    /// On-disk tag for this algorithm
+    pub fn as_u8(self) -> u8 {
+        match self {
+            ChecksumType::Crc32 => 0,
+            ChecksumType::XxHash3 => 1,
+            ChecksumType::None => 2,
+        }
+    }
+
+    /// Decode an on-disk tag
+    pub fn from_u8(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(ChecksumType::Crc32),
+            1 => Some(ChecksumType::XxHash3),
+            2 => Some(ChecksumType::None),
+            _ => None,
+        }
+    }
+}
+
 /// Memtable configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemtableConfig {
@@ -149,6 +286,10 @@ pub struct MemtableConfig {
     pub count: usize,
     /// Flush threshold (percentage of max_size)
     pub flush_threshold: f64,
+    /// Flush once the memtable holds this many entries, even if it is below
+    /// the byte threshold
+    #[serde(default)]
+    pub max_entries: Option<usize>,
 }
 
 impl Default for MemtableConfig {
@@ -158,6 +299,7 @@ impl Default for MemtableConfig {
             implementation: MemtableImpl::SkipList,
             count: 2,
             flush_threshold: 0.8, // 80%
+            max_entries: None,
         }
     }
 }
@@ -178,18 +320,62 @@ pub enum MemtableImpl {
 pub struct SstConfig {
     /// SST directory path
     pub sst_path: PathBuf,
-    /// Target file size in bytes
+    /// Target file size in bytes. Compaction starts a new output file once
+    /// one reaches it; flushes write a memtable to a single file.
     pub target_file_size: u64,
     /// Block size in bytes
     pub block_size: usize,
+    /// Keys between restart points in a data block. Keys after a restart
+    /// point store only what they don't share with the previous key, so
+    /// larger intervals make blocks smaller but point lookups decode more.
+    pub block_restart_interval: usize,
     /// Whether to use Bloom filters
     pub use_bloom_filters: bool,
     /// Bloom filter bits per key
     pub bloom_bits_per_key: f64,
+    /// Target Bloom filter false positive rate; when set, it overrides
+    /// `bloom_bits_per_key` with the bits it takes
+    #[serde(default)]
+    pub bloom_fpr: Option<f64>,
     /// Whether to use Ribbon filters
     pub use_ribbon_filters: bool,
     /// Compression algorithm for SST blocks
     pub compression: CompressionAlgorithm,
+    /// Zstd level for SST blocks, from 1 (fastest) to 19 (smallest); other
+    /// algorithms ignore it
+    #[serde(default = "default_compression_level")]
+    pub compression_level: i32,
+    /// Overrides of `compression` and `compression_level` as `(level,
+    /// algorithm, zstd level)`; each applies to its level and every deeper
+    /// one without an override of its own. Empty to use `compression` for
+    /// all.
+    #[serde(default)]
+    pub compression_per_level: Vec<(u32, CompressionAlgorithm, i32)>,
+    /// Checksum algorithm for new SST files
+    pub checksum: ChecksumType,
+    /// Whether to build prefix Bloom filters with `prefix_extractor`
+    pub prefix_bloom: bool,
+    /// Maps keys to the prefixes prefix filters and scans work on
+    #[serde(skip)]
+    pub prefix_extractor: Option<PrefixExtractor>,
+}
+
+impl SstConfig {
+    /// Compression algorithm and zstd level for SST files written at
+    /// `level`: the override for the nearest level at or above it, if any
+    pub fn compression_for_level(&self, level: u32) -> (CompressionAlgorithm, i32) {
+        self.compression_per_level
+            .iter()
+            .filter(|(from, ..)| *from <= level)
+            .max_by_key(|(from, ..)| *from)
+            .map_or((self.compression, self.compression_level), |&(_, algo, zstd_level)| (algo, zstd_level))
+    }
+
+    /// Bloom filter bits per key: from `bloom_fpr` if set, otherwise
+    /// `bloom_bits_per_key`
+    pub fn bloom_bits(&self) -> f64 {
+        self.bloom_fpr.map_or(self.bloom_bits_per_key, crate::filter::bits_per_key_for_fpr)
+    }
 }
 
 impl Default for SstConfig {
@@ -198,10 +384,17 @@ impl Default for SstConfig {
             sst_path: PathBuf::from("./auradb_data/sst"),
             target_file_size: 64 * 1024 * 1024, // 64MB
             block_size: 64 * 1024, // 64KB
+            block_restart_interval: 16,
             use_bloom_filters: true,
             bloom_bits_per_key: 10.0,
+            bloom_fpr: None,
             use_ribbon_filters: false,
             compression: CompressionAlgorithm::Lz4,
+            compression_level: crate::compression::DEFAULT_ZSTD_LEVEL,
+            compression_per_level: Vec::new(),
+            checksum: ChecksumType::Crc32,
+            prefix_bloom: false,
+            prefix_extractor: None,
         }
     }
 }
@@ -249,8 +442,16 @@ pub enum CompactionStrategy {
 pub struct CompactionTriggers {
     /// Level 0 file count threshold
     pub level0_files: usize,
-    /// Level size ratio threshold
+    /// Level 0 file count at which writes are slowed down
+    pub level0_slowdown: usize,
+    /// Level 0 file count at which writes stop until compaction catches up
+    pub level0_stop: usize,
+    /// Size limit of each level relative to the level above it
     pub level_size_ratio: f64,
+    /// Size limit of level 1 in bytes; level N may hold
+    /// `base_level_size * level_size_ratio^(N - 1)` before it is compacted
+    /// into the next level
+    pub base_level_size: u64,
     /// Write amplification threshold
     pub write_amplification: f64,
 }
@@ -259,7 +460,10 @@ impl Default for CompactionTriggers {
     fn default() -> Self {
         Self {
             level0_files: 4,
+            level0_slowdown: 20,
+            level0_stop: 36,
             level_size_ratio: 10.0,
+            base_level_size: 256 * 1024 * 1024,
             write_amplification: 5.0,
         }
     }
@@ -270,6 +474,10 @@ impl Default for CompactionTriggers {
 pub struct CacheConfig {
     /// Block cache size in bytes
     pub block_cache_size: usize,
+    /// Size in bytes of a second block cache tier holding blocks evicted
+    /// from the block cache, compressed; 0 disables it
+    #[serde(default)]
+    pub compressed_cache_size: usize,
     /// Value log cache size in bytes
     pub vlog_cache_size: usize,
     /// Cache eviction policy
@@ -282,6 +490,7 @@ impl Default for CacheConfig {
     fn default() -> Self {
         Self {
             block_cache_size: 256 * 1024 * 1024, // 256MB
+            compressed_cache_size: 0,
             vlog_cache_size: 64 * 1024 * 1024, // 64MB
             eviction_policy: EvictionPolicy::Arc,
             unified_cache: true,
@@ -356,13 +565,17 @@ pub struct RlAgentConfig {
     pub enabled: bool,
     /// Learning rate
     pub learning_rate: f64,
-    /// Exploration rate (epsilon)
+    /// Exploration rate (epsilon) used by [`ExplorationSchedule::Constant`]
     pub exploration_rate: f64,
+    /// How epsilon changes as the agent takes steps
+    pub exploration_schedule: ExplorationSchedule,
     /// State update frequency
     pub state_update_frequency: usize,
-    /// Whether to use offline training
+    /// Whether to train on the transitions in `training_data_path` at startup
     pub offline_training: bool,
-    /// Training data path
+    /// Whether to append live transitions to `training_data_path`
+    pub record_training_data: bool,
+    /// Training data path (newline-delimited JSON transitions)
     pub training_data_path: Option<PathBuf>,
 }
 
@@ -372,13 +585,56 @@ impl Default for RlAgentConfig {
             enabled: true,
             learning_rate: 0.01,
             exploration_rate: 0.1,
+            exploration_schedule: ExplorationSchedule::Constant,
             state_update_frequency: 1000,
             offline_training: false,
+            record_training_data: false,
             training_data_path: None,
         }
     }
 }
 
+/// Exploration rate of the RL agent over its lifetime
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ExplorationSchedule {
+    /// Always explore with `exploration_rate`
+    #[default]
+    Constant,
+    /// Fall linearly from `from` to `to` over `over_steps` steps, then stay at `to`
+    LinearDecay {
+        /// Initial epsilon
+        from: f64,
+        /// Final epsilon
+        to: f64,
+        /// Steps taken to reach `to`
+        over_steps: u64,
+    },
+    /// Start at `from` and multiply by `decay` every step
+    ExponentialDecay {
+        /// Initial epsilon
+        from: f64,
+        /// Per-step factor, in (0, 1]
+        decay: f64,
+    },
+}
+
+impl ExplorationSchedule {
+    /// Epsilon after `step` steps; `base` is the constant exploration rate
+    pub fn epsilon(&self, base: f64, step: u64) -> f64 {
+        match *self {
+            ExplorationSchedule::Constant => base,
+            ExplorationSchedule::LinearDecay { from, to, over_steps } => {
+                if step >= over_steps {
+                    to
+                } else {
+                    from + (to - from) * (step as f64 / over_steps as f64)
+                }
+            }
+            ExplorationSchedule::ExponentialDecay { from, decay } => from * decay.powf(step as f64),
+        }
+    }
+}
+
 /// Performance tuning configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceConfig {
@@ -392,6 +648,13 @@ pub struct PerformanceConfig {
     pub memory_mapped: bool,
     /// NUMA awareness
     pub numa_aware: bool,
+    /// Whether to tell the OS how SST files are read (`posix_fadvise`):
+    /// random access for point lookups, sequential for scans and
+    /// compactions, whose blocks are then dropped from the page cache.
+    /// Ignored for direct I/O, memory-mapped files and on platforms other
+    /// than Linux.
+    #[serde(default)]
+    pub use_fadvise: bool,
 }
 
 impl Default for PerformanceConfig {
@@ -402,6 +665,7 @@ impl Default for PerformanceConfig {
             direct_io: false,
             memory_mapped: true,
             numa_aware: false,
+            use_fadvise: false,
         }
     }
 }
@@ -412,12 +676,56 @@ impl Config {
         Self::default()
     }
 
-    /// Set the database path
+    /// Set the database path, placing the WAL, value log and SST
+    /// directories underneath it
     pub fn with_db_path(mut self, path: PathBuf) -> Self {
+        self.wal.wal_path = path.join("wal");
+        self.value_log.vlog_path = path.join("vlog");
+        self.sst.sst_path = path.join("sst");
         self.db_path = path;
         self
     }
 
+    /// Set the largest key and value sizes accepted by writes
+    pub fn with_size_limits(mut self, max_key_size: usize, max_value_size: usize) -> Self {
+        self.max_key_size = max_key_size;
+        self.max_value_size = max_value_size;
+        self
+    }
+
+    /// Set whether opening creates a missing database and whether it fails
+    /// on an existing one
+    pub fn with_open_flags(mut self, create_if_missing: bool, error_if_exists: bool) -> Self {
+        self.create_if_missing = create_if_missing;
+        self.error_if_exists = error_if_exists;
+        self
+    }
+
+    /// Set whether the engine keeps everything in memory, touching no files
+    pub fn with_in_memory(mut self, in_memory: bool) -> Self {
+        self.in_memory = in_memory;
+        self
+    }
+
+    /// Set the prefix extractor and enable prefix Bloom filters
+    pub fn with_prefix_extractor(mut self, extractor: PrefixExtractor) -> Self {
+        self.sst.prefix_extractor = Some(extractor);
+        self.sst.prefix_bloom = true;
+        self
+    }
+
+    /// Set the key comparator
+    pub fn with_comparator(mut self, comparator: Comparator) -> Self {
+        self.comparator = comparator;
+        self
+    }
+
+    /// Add a secondary index
+    pub fn with_secondary_index(mut self, index: SecondaryIndex) -> Self {
+        self.secondary_indexes.push(index);
+        self
+    }
+
     /// Set WAL configuration
     pub fn with_wal(mut self, wal: WalConfig) -> Self {
         self.wal = wal;
@@ -477,18 +785,91 @@ impl Config {
         if self.wal.max_file_size == 0 {
             return Err("WAL max file size must be greater than 0".to_string());
         }
+        if self.wal.group_flush_records == 0 || self.wal.group_flush_bytes == 0 {
+            return Err("WAL group flush limits must be greater than 0".to_string());
+        }
         if self.value_log.max_segment_size == 0 {
             return Err("Value log max segment size must be greater than 0".to_string());
         }
         if self.memtable.max_size == 0 {
             return Err("Memtable max size must be greater than 0".to_string());
         }
+        if self.memtable.max_entries == Some(0) {
+            return Err("Memtable max entries must be greater than 0".to_string());
+        }
         if self.sst.target_file_size == 0 {
             return Err("SST target file size must be greater than 0".to_string());
         }
+        if self.sst.block_restart_interval == 0 {
+            return Err("SST block restart interval must be at least 1".to_string());
+        }
+        if self.sst.bloom_fpr.is_some_and(|fpr| !(fpr > 0.0 && fpr < 1.0)) {
+            return Err("Bloom filter false positive rate must be between 0 and 1".to_string());
+        }
+        if self.sst.prefix_bloom && self.sst.prefix_extractor.is_none() {
+            return Err("Prefix Bloom filters require a prefix extractor".to_string());
+        }
+        for (i, index) in self.secondary_indexes.iter().enumerate() {
+            if index.name().is_empty() || index.name().contains('\0') {
+                return Err("Secondary index names must be non-empty and free of NUL bytes".to_string());
+            }
+            if self.secondary_indexes[..i].iter().any(|other| other.name() == index.name()) {
+                return Err(format!("Duplicate secondary index name: {}", index.name()));
+            }
+        }
+        if self.compaction.triggers.level0_slowdown > self.compaction.triggers.level0_stop {
+            return Err("Level 0 slowdown trigger must not exceed the stop trigger".to_string());
+        }
+        if self.compaction.triggers.base_level_size == 0 {
+            return Err("Base level size must be greater than 0".to_string());
+        }
+        let epsilon_ok = |e: f64| (0.0..=1.0).contains(&e);
+        let schedule_ok = match self.rl_agent.exploration_schedule {
+            ExplorationSchedule::Constant => epsilon_ok(self.rl_agent.exploration_rate),
+            ExplorationSchedule::LinearDecay { from, to, .. } => epsilon_ok(from) && epsilon_ok(to),
+            ExplorationSchedule::ExponentialDecay { from, decay } => epsilon_ok(from) && decay > 0.0 && decay <= 1.0,
+        };
+        if !schedule_ok {
+            return Err("RL exploration rates must be between 0 and 1, and decay in (0, 1]".to_string());
+        }
+        if (self.rl_agent.offline_training || self.rl_agent.record_training_data)
+            && self.rl_agent.training_data_path.is_none()
+        {
+            return Err("RL offline training and recording need a training data path".to_string());
+        }
+        let overrides = self.sst.compression_per_level.iter().map(|&(_, algo, zstd_level)| (algo, zstd_level));
+        let mut sst_compression = std::iter::once((self.sst.compression, self.sst.compression_level)).chain(overrides);
+        if let Some((algo, _)) = sst_compression.clone().find(|(algo, _)| !crate::compression::is_supported(*algo)) {
+            return Err(format!("{:?} SST compression is not supported", algo));
+        }
+        if !sst_compression.all(|(_, zstd_level)| (1..=19).contains(&zstd_level)) {
+            return Err("SST compression levels must be between 1 and 19".to_string());
+        }
+        if let Some(algo) = self.wal.compress_records.filter(|algo| !crate::compression::is_supported(*algo)) {
+            return Err(format!("{:?} WAL record compression is not supported", algo));
+        }
+        if self.value_log.compress_values && !crate::compression::is_supported(self.value_log.compression_algorithm) {
+            return Err(format!("{:?} value log compression is not supported", self.value_log.compression_algorithm));
+        }
+        if !(1..=19).contains(&self.value_log.compression_level) {
+            return Err("Value log compression level must be between 1 and 19".to_string());
+        }
+        if self.value_log.write_queues == 0 {
+            return Err("Value log write queues must be greater than 0".to_string());
+        }
+        if self.value_log.stream_chunk_size == 0 {
+            return Err("Value log stream chunk size must be greater than 0".to_string());
+        }
         if self.cache.block_cache_size == 0 {
             return Err("Block cache size must be greater than 0".to_string());
         }
+        // Keys and values are stored behind 32-bit length prefixes
+        if self.max_key_size == 0 || self.max_key_size > u32::MAX as usize {
+            return Err("Max key size must be between 1 and 4GB".to_string());
+        }
+        if self.max_value_size == 0 || self.max_value_size > u32::MAX as usize {
+            return Err("Max value size must be between 1 and 4GB".to_string());
+        }
         Ok(())
     }
 }
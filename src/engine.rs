@@ -192,10 +192,7 @@ impl EngineExt for AuraEngine {
     }
     
     async fn compact(&self) -> Result<()> {
-        // TODO: Implement compaction
-        // For now, just flush the memtable
-        self.flush_memtable().await?;
-        Ok(())
+        self.compact_range(None, None)
     }
     
     async fn backup(&self, _path: &std::path::Path) -> Result<()> {
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use thiserror::Error;
 
 /// Result type for AuraDB operations
@@ -12,6 +13,9 @@ pub enum Error {
     #[error("Bincode error: {0}")]
     Bincode(#[from] bincode::Error),
 
+    #[error("Key must not be empty")]
+    EmptyKey,
+
     #[error("Key not found: {0}")]
     KeyNotFound(String),
 
@@ -27,6 +31,9 @@ pub enum Error {
     #[error("Value log corruption: {0}")]
     ValueLogCorruption(String),
 
+    #[error("Snapshot file corruption: {0}")]
+    SnapshotCorruption(String),
+
     #[error("Compaction error: {0}")]
     Compaction(String),
 
@@ -52,6 +59,17 @@ pub enum Error {
     Unknown(String),
 }
 
+/// Damage found by an integrity check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Corruption {
+    /// File holding the damaged data
+    pub path: PathBuf,
+    /// Byte offset of the damaged record or block within the file
+    pub offset: u64,
+    /// What is wrong
+    pub message: String,
+}
+
 impl From<&str> for Error {
     fn from(s: &str) -> Self {
         Error::Unknown(s.to_string())
@@ -0,0 +1,200 @@
+//! Bloom filters and key prefix extractors
+//!
+//! Each SST can carry a whole-key Bloom filter, consulted by point lookups,
+//! and a prefix Bloom filter over the prefixes a [`PrefixExtractor`] takes
+//! from its keys, consulted by prefix scans. A negative answer from either
+//! means the file holds no matching key and need not be read.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+/// Prefix function shared by a [`PrefixExtractor`]
+pub type ExtractFn = dyn Fn(&[u8]) -> Option<&[u8]> + Send + Sync;
+
+/// A named function mapping keys to the prefix they are filtered by.
+///
+/// Returning `None` puts a key outside the extractor's domain: it is left
+/// out of prefix filters, and scans for such a prefix read every file. If
+/// the extractor maps a prefix `p` to `Some(q)`, every key starting with
+/// `p` must map to `Some(q)` as well.
+#[derive(Clone)]
+pub struct PrefixExtractor {
+    /// Name recorded in each SST's filter block
+    name: Arc<str>,
+    /// Extraction function
+    extract: Arc<ExtractFn>,
+}
+
+impl PrefixExtractor {
+    /// Create an extractor from a name and a prefix function. The name
+    /// must change whenever the function does; filters built under another
+    /// name are ignored.
+    pub fn new(name: impl Into<String>, extract: fn(&[u8]) -> Option<&[u8]>) -> Self {
+        Self {
+            name: Arc::from(name.into()),
+            extract: Arc::new(extract),
+        }
+    }
+
+    /// The first `len` bytes of each key; shorter keys are out of domain
+    pub fn fixed(len: usize) -> Self {
+        Self {
+            name: Arc::from(format!("auradb.FixedPrefix.{}", len)),
+            extract: Arc::new(move |key: &[u8]| key.get(..len)),
+        }
+    }
+
+    /// Extractor name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Prefix of `key`, or `None` if it is outside the domain
+    pub fn extract<'a>(&self, key: &'a [u8]) -> Option<&'a [u8]> {
+        (self.extract)(key)
+    }
+}
+
+impl fmt::Debug for PrefixExtractor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrefixExtractor").field("name", &self.name).finish()
+    }
+}
+
+/// Hash used for every filter probe
+fn filter_hash(key: &[u8]) -> u64 {
+    xxhash_rust::xxh3::xxh3_64(key)
+}
+
+/// An immutable Bloom filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BloomFilter {
+    /// Bit array
+    bits: Vec<u8>,
+    /// Probes per key
+    num_probes: u32,
+}
+
+/// Bits per key a Bloom filter needs for a false positive rate of `fpr`,
+/// with the optimal number of probes: `-ln(fpr) / ln(2)^2`
+pub fn bits_per_key_for_fpr(fpr: f64) -> f64 {
+    -fpr.ln() / (std::f64::consts::LN_2 * std::f64::consts::LN_2)
+}
+
+impl BloomFilter {
+    /// Whether `key` may have been added. False positives are possible,
+    /// false negatives are not.
+    pub fn may_contain(&self, key: &[u8]) -> bool {
+        let num_bits = self.bits.len() as u64 * 8;
+        if num_bits == 0 {
+            return true;
+        }
+        probes(filter_hash(key), self.num_probes).all(|probe| {
+            let bit = probe % num_bits;
+            self.bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0
+        })
+    }
+
+    /// Expected false positive rate once `num_keys` keys have been added:
+    /// `(1 - e^(-k n / m))^k` for `k` probes into `m` bits
+    pub fn false_positive_rate(&self, num_keys: u64) -> f64 {
+        let num_bits = self.bits.len() as f64 * 8.0;
+        if num_bits == 0.0 {
+            return 1.0;
+        }
+        let k = self.num_probes as f64;
+        (1.0 - (-k * num_keys as f64 / num_bits).exp()).powf(k)
+    }
+
+    /// Bytes held by the bit array
+    pub fn memory_usage(&self) -> usize {
+        self.bits.len()
+    }
+}
+
+/// Bit positions (before reduction) probed for a hash, by double hashing
+fn probes(hash: u64, num_probes: u32) -> impl Iterator<Item = u64> {
+    let h1 = hash & 0xFFFF_FFFF;
+    let h2 = (hash >> 32) | 1;
+    (0..num_probes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)))
+}
+
+/// Collects key hashes and builds a [`BloomFilter`] sized for them
+#[derive(Debug)]
+pub struct BloomFilterBuilder {
+    hashes: Vec<u64>,
+    bits_per_key: f64,
+}
+
+impl BloomFilterBuilder {
+    /// Create a builder targeting `bits_per_key` bits for each key added
+    pub fn new(bits_per_key: f64) -> Self {
+        Self {
+            hashes: Vec::new(),
+            bits_per_key: bits_per_key.max(1.0),
+        }
+    }
+
+    /// Add a key
+    pub fn add(&mut self, key: &[u8]) {
+        self.hashes.push(filter_hash(key));
+    }
+
+    /// Build the filter
+    pub fn finish(self) -> BloomFilter {
+        let num_bits = ((self.hashes.len() as f64 * self.bits_per_key) as u64).max(64);
+        let num_bytes = num_bits.div_ceil(8) as usize;
+        let num_bits = num_bytes as u64 * 8;
+        // k = ln 2 * bits per key minimizes the false positive rate
+        let num_probes = ((self.bits_per_key * std::f64::consts::LN_2).round() as u32).clamp(1, 30);
+
+        let mut bits = vec![0u8; num_bytes];
+        for hash in self.hashes {
+            for probe in probes(hash, num_probes) {
+                let bit = probe % num_bits;
+                bits[(bit / 8) as usize] |= 1 << (bit % 8);
+            }
+        }
+        BloomFilter { bits, num_probes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter_has_no_false_negatives() {
+        let mut builder = BloomFilterBuilder::new(10.0);
+        for i in 0..1000 {
+            builder.add(format!("key_{}", i).as_bytes());
+        }
+        let filter = builder.finish();
+
+        assert!((0..1000).all(|i| filter.may_contain(format!("key_{}", i).as_bytes())));
+        let false_positives = (1000..11000)
+            .filter(|i| filter.may_contain(format!("key_{}", i).as_bytes()))
+            .count();
+        assert!(false_positives < 300, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_bits_per_key_for_fpr() {
+        let bits = bits_per_key_for_fpr(0.01);
+        assert!((bits - 9.585).abs() < 0.01, "{}", bits);
+        let mut builder = BloomFilterBuilder::new(bits);
+        for i in 0..1000 {
+            builder.add(format!("key_{}", i).as_bytes());
+        }
+        let fpr = builder.finish().false_positive_rate(1000);
+        assert!(fpr > 0.005 && fpr < 0.015, "{}", fpr);
+    }
+
+    #[test]
+    fn test_fixed_prefix_extractor() {
+        let extractor = PrefixExtractor::fixed(3);
+        assert_eq!(extractor.extract(b"user42"), Some(&b"use"[..]));
+        assert_eq!(extractor.extract(b"us"), None);
+    }
+}
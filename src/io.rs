@@ -0,0 +1,484 @@
+//! File access helpers shared by the SST and value log layers
+//!
+//! Files can be accessed through the OS page cache (buffered), memory
+//! mapped when `PerformanceConfig::memory_mapped` is set, or, when
+//! `PerformanceConfig::direct_io` is set, with `O_DIRECT` so that data is
+//! only cached once in AuraDB's own block cache. Direct I/O requires
+//! offsets, lengths and memory buffers aligned to [`DIRECT_IO_ALIGNMENT`];
+//! the types here take care of that so callers can read arbitrary ranges.
+//! Buffered files take `posix_fadvise` access pattern hints through
+//! [`RandomAccessFile::advise`] when `PerformanceConfig::use_fadvise` is set.
+
+use crate::config::PerformanceConfig;
+use crate::error::Result;
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Alignment required for direct I/O offsets, lengths and buffers
+pub const DIRECT_IO_ALIGNMENT: usize = 4096;
+
+/// Size of the chunks a direct writer hands to the kernel
+const DIRECT_WRITE_CHUNK: usize = 256 * DIRECT_IO_ALIGNMENT;
+
+/// How data files are opened and read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileAccess {
+    /// Regular reads and writes through the OS page cache
+    #[default]
+    Buffered,
+    /// `O_DIRECT` reads and writes that bypass the OS page cache
+    Direct,
+    /// Reads served from a read-only memory mapping; writes are buffered.
+    ///
+    /// Only suitable for immutable files: a mapped file must not be
+    /// truncated while a reader holds it, or accesses fault with `SIGBUS`.
+    Mmap,
+}
+
+impl FileAccess {
+    /// Pick the access mode from the performance configuration.
+    ///
+    /// Direct I/O takes precedence over memory mapping since the two
+    /// cannot be combined.
+    pub fn from_config(config: &PerformanceConfig) -> Self {
+        if config.direct_io {
+            FileAccess::Direct
+        } else if config.memory_mapped {
+            FileAccess::Mmap
+        } else {
+            FileAccess::Buffered
+        }
+    }
+}
+
+/// Open `options` on `path`, using `O_DIRECT` when supported.
+///
+/// Returns the file and whether direct I/O is actually in effect. Platforms
+/// and filesystems that reject `O_DIRECT` (e.g. tmpfs) fall back to buffered
+/// access with a warning.
+fn open_with_access(options: &OpenOptions, path: &Path, access: FileAccess) -> Result<(File, bool)> {
+    if access == FileAccess::Direct {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            let mut direct = options.clone();
+            direct.custom_flags(libc::O_DIRECT);
+            match direct.open(path) {
+                Ok(file) => return Ok((file, true)),
+                Err(e) => warn!(
+                    "Direct I/O unavailable for {} ({}), falling back to buffered I/O",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        warn!(
+            "Direct I/O is not supported on this platform, using buffered I/O for {}",
+            path.display()
+        );
+    }
+    Ok((options.open(path)?, false))
+}
+
+/// Fsync a directory so that entries created, renamed or removed in it
+/// survive a crash. File data needs its own fsync; this only persists the
+/// names. A no-op on platforms that can't open directories.
+pub fn sync_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Whether `a` and `b` are on the same filesystem, so files can be hard
+/// linked from one to the other. Always true on non-Unix platforms.
+pub fn same_filesystem(a: &Path, b: &Path) -> Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Ok(std::fs::metadata(a)?.dev() == std::fs::metadata(b)?.dev())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (a, b);
+        Ok(true)
+    }
+}
+
+/// Copy `from` to `to` and fsync the copy
+pub fn copy_synced(from: &Path, to: &Path) -> Result<()> {
+    std::fs::copy(from, to)?;
+    File::open(to)?.sync_all()?;
+    Ok(())
+}
+
+/// Fsync the directory containing `path`
+pub fn sync_parent_dir(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => sync_dir(parent),
+        _ => sync_dir(Path::new(".")),
+    }
+}
+
+/// Reserve disk space for `len` bytes of `file` without changing its size,
+/// so appends up to that length don't have to allocate blocks. A no-op on
+/// platforms without `fallocate`.
+pub fn preallocate(file: &File, len: u64) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        // SAFETY: the descriptor is owned by `file` and stays open for the call
+        let ret = unsafe { libc::fallocate(file.as_raw_fd(), libc::FALLOC_FL_KEEP_SIZE, 0, len as libc::off_t) };
+        if ret != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = (file, len);
+    Ok(())
+}
+
+/// Expected access pattern for a range of a file, passed to the OS as a
+/// `posix_fadvise` hint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Advice {
+    /// No particular pattern; the OS default readahead
+    Normal,
+    /// Reads in ascending offset order; read ahead aggressively
+    Sequential,
+    /// Reads at scattered offsets; don't read ahead
+    Random,
+    /// The range won't be read again soon; drop it from the page cache
+    DontNeed,
+}
+
+/// Heap buffer whose usable region starts at an aligned address
+struct AlignedBuffer {
+    raw: Vec<u8>,
+    start: usize,
+    len: usize,
+}
+
+impl AlignedBuffer {
+    /// Allocate a zeroed buffer of `len` bytes aligned to [`DIRECT_IO_ALIGNMENT`]
+    fn new(len: usize) -> Self {
+        let raw = vec![0u8; len + DIRECT_IO_ALIGNMENT];
+        let misalignment = raw.as_ptr() as usize % DIRECT_IO_ALIGNMENT;
+        let start = (DIRECT_IO_ALIGNMENT - misalignment) % DIRECT_IO_ALIGNMENT;
+        Self { raw, start, len }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.raw[self.start..self.start + self.len]
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.raw[self.start..self.start + self.len]
+    }
+}
+
+fn align_down(value: u64) -> u64 {
+    value - value % DIRECT_IO_ALIGNMENT as u64
+}
+
+fn align_up(value: usize) -> usize {
+    value.div_ceil(DIRECT_IO_ALIGNMENT) * DIRECT_IO_ALIGNMENT
+}
+
+/// Positional read, retrying until `buf` is full or end of file.
+/// Returns the number of bytes read.
+fn read_full_at(file: &File, buf: &mut [u8], offset: u64) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        #[cfg(unix)]
+        let n = std::os::unix::fs::FileExt::read_at(file, &mut buf[filled..], offset + filled as u64)?;
+        #[cfg(windows)]
+        let n = std::os::windows::fs::FileExt::seek_read(file, &mut buf[filled..], offset + filled as u64)?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(filled)
+}
+
+/// Read-only file supporting positional reads at arbitrary offsets
+#[derive(Debug)]
+pub struct RandomAccessFile {
+    file: File,
+    path: PathBuf,
+    direct: bool,
+    /// Mapping held for the lifetime of the reader in mmap mode
+    mmap: Option<Mmap>,
+    len: u64,
+}
+
+impl RandomAccessFile {
+    /// Open a file for reading
+    pub fn open(path: &Path, access: FileAccess) -> Result<Self> {
+        let mut options = OpenOptions::new();
+        options.read(true);
+        let (file, direct) = open_with_access(&options, path, access)?;
+        let len = file.metadata()?.len();
+
+        let mmap = if access == FileAccess::Mmap && len > 0 {
+            // SAFETY: mapped files are immutable once written and are never
+            // truncated while a reader is open; see `FileAccess::Mmap`.
+            Some(unsafe { Mmap::map(&file)? })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            direct,
+            mmap,
+            len,
+        })
+    }
+
+    /// File path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// File length in bytes at the time it was opened
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the file is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether reads bypass the OS page cache
+    pub fn is_direct(&self) -> bool {
+        self.direct
+    }
+
+    /// Whether reads are served from a memory mapping
+    pub fn is_mapped(&self) -> bool {
+        self.mmap.is_some()
+    }
+
+    /// Hint how `len` bytes from `offset` (the rest of the file if `len` is
+    /// 0) will be read. Only buffered files go through the page cache the
+    /// hint tunes, so this is a no-op for direct and memory-mapped access,
+    /// and on platforms other than Linux. Failures are ignored.
+    pub fn advise(&self, advice: Advice, offset: u64, len: u64) {
+        if self.direct || self.mmap.is_some() {
+            return;
+        }
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::io::AsRawFd;
+            let advice = match advice {
+                Advice::Normal => libc::POSIX_FADV_NORMAL,
+                Advice::Sequential => libc::POSIX_FADV_SEQUENTIAL,
+                Advice::Random => libc::POSIX_FADV_RANDOM,
+                Advice::DontNeed => libc::POSIX_FADV_DONTNEED,
+            };
+            // SAFETY: the descriptor is owned by `self.file` and stays open for the call
+            let ret = unsafe {
+                libc::posix_fadvise(self.file.as_raw_fd(), offset as libc::off_t, len as libc::off_t, advice)
+            };
+            if ret != 0 {
+                warn!("posix_fadvise failed for {}: {}", self.path.display(), std::io::Error::from_raw_os_error(ret));
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = (advice, offset, len);
+    }
+
+    /// Read exactly `len` bytes starting at `offset`, borrowing straight
+    /// from the mapping when the file is memory mapped
+    pub fn read_bytes(&self, offset: u64, len: usize) -> Result<Cow<'_, [u8]>> {
+        match &self.mmap {
+            Some(mmap) => {
+                let start = offset as usize;
+                start
+                    .checked_add(len)
+                    .and_then(|end| mmap.get(start..end))
+                    .map(Cow::Borrowed)
+                    .ok_or_else(|| self.short_read(offset, len))
+            }
+            None => self.read_at(offset, len).map(Cow::Owned),
+        }
+    }
+
+    /// Read exactly `len` bytes starting at `offset`
+    pub fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        if let Some(mmap) = &self.mmap {
+            let start = offset as usize;
+            return start
+                .checked_add(len)
+                .and_then(|end| mmap.get(start..end))
+                .map(<[u8]>::to_vec)
+                .ok_or_else(|| self.short_read(offset, len));
+        }
+
+        if !self.direct {
+            let mut buf = vec![0u8; len];
+            if read_full_at(&self.file, &mut buf, offset)? < len {
+                return Err(self.short_read(offset, len));
+            }
+            return Ok(buf);
+        }
+
+        // Widen the request to aligned boundaries, then copy out the
+        // requested window
+        let aligned_offset = align_down(offset);
+        let skip = (offset - aligned_offset) as usize;
+        let mut buf = AlignedBuffer::new(align_up(skip + len));
+        let read = read_full_at(&self.file, buf.as_mut_slice(), aligned_offset)?;
+        if read < skip + len {
+            return Err(self.short_read(offset, len));
+        }
+        Ok(buf.as_slice()[skip..skip + len].to_vec())
+    }
+
+    fn short_read(&self, offset: u64, len: usize) -> crate::error::Error {
+        crate::error::Error::Io(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!(
+                "Short read of {} bytes at offset {} in {}",
+                len,
+                offset,
+                self.path.display()
+            ),
+        ))
+    }
+}
+
+/// Append-only file used to write immutable data files such as SSTs
+pub struct WritableFile {
+    file: File,
+    direct: bool,
+    /// Pending bytes not yet handed to the kernel (direct mode only)
+    pending: Vec<u8>,
+    /// Total bytes appended
+    len: u64,
+}
+
+impl WritableFile {
+    /// Create (or truncate) a file for writing. Memory-mapped access only
+    /// affects reads, so `FileAccess::Mmap` writes are buffered.
+    pub fn create(path: &Path, access: FileAccess) -> Result<Self> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        let (file, direct) = open_with_access(&options, path, access)?;
+        Ok(Self {
+            file,
+            direct,
+            pending: Vec::new(),
+            len: 0,
+        })
+    }
+
+    /// Bytes appended so far
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether nothing has been appended yet
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append bytes to the file
+    pub fn append(&mut self, data: &[u8]) -> Result<()> {
+        self.len += data.len() as u64;
+        if !self.direct {
+            self.file.write_all(data)?;
+            return Ok(());
+        }
+
+        self.pending.extend_from_slice(data);
+        if self.pending.len() >= DIRECT_WRITE_CHUNK {
+            let full = self.pending.len() - self.pending.len() % DIRECT_IO_ALIGNMENT;
+            self.write_aligned(full)?;
+        }
+        Ok(())
+    }
+
+    /// Write the first `len` pending bytes (a multiple of the alignment)
+    fn write_aligned(&mut self, len: usize) -> Result<()> {
+        let mut buf = AlignedBuffer::new(len);
+        buf.as_mut_slice().copy_from_slice(&self.pending[..len]);
+        self.file.write_all(buf.as_slice())?;
+        self.pending.drain(..len);
+        Ok(())
+    }
+
+    /// Flush remaining data and fsync the file
+    pub fn finish(mut self) -> Result<()> {
+        if self.direct && !self.pending.is_empty() {
+            // Pad the tail to the alignment, then trim the padding off
+            let padded = align_up(self.pending.len());
+            self.pending.resize(padded, 0);
+            self.write_aligned(padded)?;
+            self.file.set_len(self.len)?;
+        }
+        self.file.sync_all()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_unaligned_reads_with_direct_io() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("data");
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut writer = WritableFile::create(&path, FileAccess::Direct).unwrap();
+        writer.append(&data[..123]).unwrap();
+        writer.append(&data[123..]).unwrap();
+        writer.finish().unwrap();
+
+        let file = RandomAccessFile::open(&path, FileAccess::Direct).unwrap();
+        assert_eq!(file.len(), data.len() as u64);
+        assert_eq!(file.read_at(4095, 10).unwrap(), &data[4095..4105]);
+        assert_eq!(file.read_at(17, 9000).unwrap(), &data[17..9017]);
+        assert_eq!(file.read_at(19_990, 10).unwrap(), &data[19_990..]);
+        assert!(file.read_at(19_995, 10).is_err());
+    }
+
+    #[test]
+    fn test_sync_dir() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("data");
+        std::fs::write(&path, b"data").unwrap();
+        sync_parent_dir(&path).unwrap();
+        assert!(sync_dir(&temp_dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_mapped_reads_borrow_from_mapping() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("data");
+        std::fs::write(&path, b"hello mapped world").unwrap();
+
+        let file = RandomAccessFile::open(&path, FileAccess::Mmap).unwrap();
+        assert!(file.is_mapped());
+        assert!(matches!(file.read_bytes(6, 6).unwrap(), Cow::Borrowed(b"mapped")));
+        assert_eq!(file.read_at(13, 5).unwrap(), b"world");
+        assert!(file.read_bytes(13, 6).is_err());
+
+        // Lengths that overflow the end offset are short reads, not panics
+        assert!(file.read_bytes(6, usize::MAX).is_err());
+        assert!(file.read_at(6, usize::MAX).is_err());
+    }
+}
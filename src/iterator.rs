@@ -0,0 +1,269 @@
+//! Merging iterator over sorted runs
+//!
+//! Reads and compactions see the same key in several places: the active
+//! and immutable memtables and the SST files of every level. Each source
+//! yields its entries in comparator order with at most one entry per key.
+//! [`MergeIterator`] combines them into one ordered stream through a
+//! min-heap holding the next entry of every source, ordered by key and
+//! then by descending sequence, so the first entry popped for a key is its
+//! newest version and the rest are skipped.
+//!
+//! Range tombstones cover keys in sources other than their own, so they
+//! are flattened up front into a [`RangeDelSkyline`]: disjoint fragments
+//! of the key space, each holding the newest sequence deleting it. The
+//! merge drops every key whose newest version is older than the fragment
+//! it falls in.
+
+use crate::comparator::Comparator;
+use crate::storage::{Entry, RangeTombstone};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Next entry of one source, ordered so the heap pops the smallest key
+/// and, within a key, the highest sequence first
+struct HeapEntry<'a> {
+    /// Entry at the head of the source
+    entry: Entry,
+    /// Index of the source; lower indexes win sequence ties
+    source: usize,
+    /// Key order
+    comparator: &'a Comparator,
+}
+
+impl HeapEntry<'_> {
+    /// Order in which entries leave the heap
+    fn merge_order(&self, other: &Self) -> Ordering {
+        self.comparator
+            .compare(&self.entry.key.data, &other.entry.key.data)
+            .then_with(|| other.entry.sequence.cmp(&self.entry.sequence))
+            .then_with(|| self.source.cmp(&other.source))
+    }
+}
+
+impl PartialEq for HeapEntry<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.merge_order(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry<'_> {}
+
+impl PartialOrd for HeapEntry<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapEntry<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `BinaryHeap` is a max-heap
+        other.merge_order(self)
+    }
+}
+
+/// Range tombstones flattened into disjoint fragments of the key space,
+/// each with the sequence of the newest tombstone covering it
+pub struct RangeDelSkyline {
+    /// Fragment bounds in comparator order; fragment `i` is
+    /// `[bounds[i], bounds[i + 1])`
+    bounds: Vec<Vec<u8>>,
+    /// Newest covering sequence of each fragment, if any covers it
+    sequences: Vec<Option<u64>>,
+    /// Key order
+    comparator: Comparator,
+}
+
+impl RangeDelSkyline {
+    /// Flatten `tombstones`, in any order
+    pub fn new(tombstones: impl IntoIterator<Item = RangeTombstone>, comparator: &Comparator) -> Self {
+        let tombstones: Vec<_> = tombstones.into_iter().filter(|t| !t.is_empty(comparator)).collect();
+        let mut bounds: Vec<Vec<u8>> =
+            tombstones.iter().flat_map(|t| [t.start.data.clone(), t.end.data.clone()]).collect();
+        bounds.sort_by(|a, b| comparator.compare(a, b));
+        bounds.dedup_by(|a, b| comparator.compare(a, b) == Ordering::Equal);
+
+        let mut sequences = vec![None; bounds.len().saturating_sub(1)];
+        let position = |key: &[u8]| bounds.partition_point(|bound| comparator.compare(bound, key) == Ordering::Less);
+        for tombstone in &tombstones {
+            for sequence in &mut sequences[position(&tombstone.start.data)..position(&tombstone.end.data)] {
+                *sequence = (*sequence).max(Some(tombstone.sequence));
+            }
+        }
+        Self {
+            bounds,
+            sequences,
+            comparator: comparator.clone(),
+        }
+    }
+
+    /// Whether no key is covered
+    pub fn is_empty(&self) -> bool {
+        self.sequences.iter().all(Option::is_none)
+    }
+
+    /// Sequence of the newest tombstone covering `key`, if any
+    pub fn covering_sequence(&self, key: &[u8]) -> Option<u64> {
+        let after = self
+            .bounds
+            .partition_point(|bound| self.comparator.compare(bound, key) != Ordering::Greater);
+        self.sequences.get(after.checked_sub(1)?).copied().flatten()
+    }
+}
+
+/// Merges sorted sources into one stream in comparator order, yielding
+/// only the newest entry (highest sequence) for each key.
+///
+/// Tombstones are yielded by default, since compactions must keep them to
+/// shadow older data in deeper levels; after
+/// [`skip_tombstones`](Self::skip_tombstones) deleted keys are left out
+/// entirely, along with the older versions their tombstones hide. Keys
+/// whose newest version predates a range tombstone given with
+/// [`with_range_tombstones`](Self::with_range_tombstones) are always left
+/// out; the range tombstone stands for them.
+pub struct MergeIterator<'a, I: Iterator<Item = Entry>> {
+    /// Sources, each sorted with at most one entry per key
+    sources: Vec<I>,
+    /// Head entry of every source that is not exhausted
+    heap: BinaryHeap<HeapEntry<'a>>,
+    /// Key order
+    comparator: &'a Comparator,
+    /// Whether to yield tombstones
+    tombstones: bool,
+    /// Range tombstones hiding older versions, if any
+    range_deletes: Option<RangeDelSkyline>,
+}
+
+impl<'a, I: Iterator<Item = Entry>> MergeIterator<'a, I> {
+    /// Merge `sources`, each sorted by `comparator`. When two sources hold
+    /// the same key at the same sequence, the earlier source wins.
+    pub fn new(sources: impl IntoIterator<Item = I>, comparator: &'a Comparator) -> Self {
+        let mut iter = Self {
+            sources: sources.into_iter().collect(),
+            heap: BinaryHeap::new(),
+            comparator,
+            tombstones: true,
+            range_deletes: None,
+        };
+        for source in 0..iter.sources.len() {
+            iter.advance(source);
+        }
+        iter
+    }
+
+    /// Leave deleted keys out of the stream
+    pub fn skip_tombstones(mut self) -> Self {
+        self.tombstones = false;
+        self
+    }
+
+    /// Leave out keys whose newest version is older than the newest range
+    /// tombstone in `skyline` covering them
+    pub fn with_range_tombstones(mut self, skyline: RangeDelSkyline) -> Self {
+        self.range_deletes = (!skyline.is_empty()).then_some(skyline);
+        self
+    }
+
+    /// Push the next entry of `source`, if any, onto the heap
+    fn advance(&mut self, source: usize) {
+        if let Some(entry) = self.sources[source].next() {
+            self.heap.push(HeapEntry {
+                entry,
+                source,
+                comparator: self.comparator,
+            });
+        }
+    }
+}
+
+impl<I: Iterator<Item = Entry>> Iterator for MergeIterator<'_, I> {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Entry> {
+        loop {
+            let newest = self.heap.pop()?;
+            self.advance(newest.source);
+            // Older versions of the key sit at the top of the heap
+            while let Some(top) = self.heap.peek() {
+                if self.comparator.compare(&top.entry.key.data, &newest.entry.key.data) != Ordering::Equal {
+                    break;
+                }
+                let shadowed = self.heap.pop().expect("peeked entry");
+                self.advance(shadowed.source);
+            }
+            let covered = self.range_deletes.as_ref().is_some_and(|skyline| {
+                skyline
+                    .covering_sequence(&newest.entry.key.data)
+                    .is_some_and(|sequence| newest.entry.sequence < sequence)
+            });
+            if !covered && (self.tombstones || !newest.entry.is_delete()) {
+                return Some(newest.entry);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Key, Value};
+
+    fn put(key: &str, value: &str, sequence: u64) -> Entry {
+        Entry::new(Key::from(key), Value::from(value), sequence)
+    }
+
+    fn delete(key: &str, sequence: u64) -> Entry {
+        Entry::delete(Key::from(key), sequence)
+    }
+
+    #[test]
+    fn test_merge_yields_newest_version() {
+        let comparator = Comparator::default();
+        let memtable = vec![put("b", "new", 7), delete("d", 8)];
+        let level0 = vec![put("a", "a", 3), put("b", "mid", 5), put("e", "e", 4)];
+        let level1 = vec![put("b", "old", 1), put("c", "c", 2), put("d", "d", 2)];
+        let sources = || vec![memtable.clone().into_iter(), level0.clone().into_iter(), level1.clone().into_iter()];
+
+        let merged: Vec<_> = MergeIterator::new(sources(), &comparator).collect();
+        let keys: Vec<_> = merged.iter().map(|e| e.key.data.clone()).collect();
+        assert_eq!(keys, [b"a", b"b", b"c", b"d", b"e"]);
+        assert_eq!(merged[1].value.as_ref().unwrap().data, b"new");
+        assert!(merged[3].is_delete());
+
+        // The tombstone hides the older live version of "d"
+        let live: Vec<_> = MergeIterator::new(sources(), &comparator).skip_tombstones().collect();
+        let keys: Vec<_> = live.iter().map(|e| e.key.data.clone()).collect();
+        assert_eq!(keys, [b"a", b"b", b"c", b"e"]);
+    }
+
+    #[test]
+    fn test_range_tombstones_hide_older_versions() {
+        let comparator = Comparator::default();
+        let tombstone = |start: &str, end: &str, sequence| RangeTombstone::new(Key::from(start), Key::from(end), sequence);
+        let skyline = RangeDelSkyline::new([tombstone("b", "e", 5), tombstone("d", "g", 3)], &comparator);
+        assert_eq!(skyline.covering_sequence(b"a"), None);
+        assert_eq!(skyline.covering_sequence(b"b"), Some(5));
+        assert_eq!(skyline.covering_sequence(b"d"), Some(5));
+        assert_eq!(skyline.covering_sequence(b"e"), Some(3));
+        assert_eq!(skyline.covering_sequence(b"g"), None);
+
+        // Sources needn't hold the tombstones that cover their keys
+        let newer = vec![put("c", "new", 6), put("f", "f", 2)];
+        let older = vec![put("a", "a", 1), put("c", "old", 1), put("d", "d", 4), put("e", "e", 4)];
+        let merged: Vec<_> = MergeIterator::new([newer.into_iter(), older.into_iter()], &comparator)
+            .with_range_tombstones(skyline)
+            .collect();
+        let keys: Vec<_> = merged.iter().map(|e| e.key.data.clone()).collect();
+        assert_eq!(keys, [b"a", b"c", b"e"]);
+        assert_eq!(merged[1].value.as_ref().unwrap().data, b"new");
+    }
+
+    #[test]
+    fn test_merge_respects_comparator() {
+        let comparator = Comparator::reverse();
+        let newer = vec![put("c", "new", 9), put("a", "a", 9)];
+        let older = vec![put("c", "old", 1), put("b", "b", 1)];
+        let merged: Vec<_> = MergeIterator::new([newer.into_iter(), older.into_iter()], &comparator).collect();
+        let values: Vec<_> = merged.iter().map(|e| e.value.as_ref().unwrap().data.clone()).collect();
+        assert_eq!(values, [b"new".to_vec(), b"b".to_vec(), b"a".to_vec()]);
+    }
+}
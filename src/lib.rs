@@ -28,16 +28,41 @@
 pub mod error;
 pub mod storage;
 pub mod config;
+pub mod comparator;
+pub mod manifest;
+pub mod io;
+pub mod checksum;
+pub mod compression;
+pub mod filter;
+pub mod cache;
+pub mod metrics;
+pub mod wal;
+pub mod vlog;
+pub mod sst;
+pub mod memtable;
+pub mod iterator;
+pub mod export;
+pub mod secondary;
+pub mod compactor;
+pub mod lsm;
+pub mod migrate;
+pub mod repair;
 pub mod api;
+pub mod blocking;
 
 // Re-export main types
-pub use api::{Engine, EngineBuilder, AuraEngine};
-pub use storage::{Key, Value, ValuePointer, Entry, Batch, Range};
+pub use api::{Engine, EngineBuilder, AuraEngine, EngineStats, MemoryBreakdown, ReadOptions, VerifyReport, WriteOptions};
+pub use blocking::SyncEngine;
+pub use comparator::Comparator;
+pub use filter::PrefixExtractor;
+pub use repair::{RepairOptions, RepairReport};
+pub use secondary::SecondaryIndex;
+pub use storage::{Key, Value, ValuePointer, Entry, Batch, Range, ChangeEvent};
 pub use error::{Error, Result};
 
 /// Common imports for the crate
 pub mod prelude {
     pub use crate::{Engine, EngineBuilder, AuraEngine};
-    pub use crate::{Key, Value, ValuePointer, Entry, Batch, Range};
+    pub use crate::{Key, Value, ValuePointer, Entry, Batch, Range, ChangeEvent};
     pub use crate::{Error, Result};
 }
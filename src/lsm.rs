@@ -0,0 +1,1923 @@
+//! LSM tree: memtables, SST levels and the background worker
+//!
+//! Writes go to the active memtable. When it reaches its flush threshold
+//! the engine rotates the WAL and seals the memtable; a background thread
+//! flushes sealed memtables to level 0 SST files, records them in the
+//! manifest, deletes the WAL files they covered and then runs any
+//! compactions the [`CompactionManager`] picks.
+//!
+//! Scans and compactions combine their sources with a
+//! [`MergeIterator`], which keeps the newest version of each key. SST
+//! files take part through block iterators that seek with the file's
+//! index and decode a block only once the merge reaches it. Range
+//! tombstones from every source are flattened into a [`RangeDelSkyline`]
+//! first, so the merge also drops the keys they cover. Compactions carry
+//! range tombstones into their outputs, split at the output file
+//! boundaries, until they reach the bottom of the tree.
+//!
+//! A [`TreeSnapshot`] holds the memtables and pins the SST version
+//! current when it was taken, so the files a compaction replaces stay on
+//! disk until the last snapshot reading them is dropped. That is all that
+//! isolates snapshots from compactions: a snapshot never reads compaction
+//! outputs, so compactions keep only the newest version of each key and
+//! drop tombstones at the bottom of the tree whatever snapshots are live.
+//! Snapshot sequence numbers are registered with the tree only for the
+//! `auradb.oldest-snapshot-sequence` property.
+//!
+//! An in-memory tree (`Config::in_memory`) never seals its memtable, has
+//! no manifest on disk and runs no background worker; all data stays in
+//! the active memtable until the engine drops it.
+
+use crate::cache::{CacheStats, UnifiedCache};
+use crate::comparator::Comparator;
+use crate::compactor::{
+    AgentState, CompactionManager, CompactionProgress, CompactionStrategy, CompactionTask, CompactionTracker,
+    RlCompactionAgent, RL_POLICY_FILE,
+};
+use crate::config::{Config, SstConfig};
+use crate::error::{Corruption, Error, Result};
+use crate::io::FileAccess;
+use crate::iterator::{MergeIterator, RangeDelSkyline};
+use crate::manifest::{CreationSettings, Manifest};
+use crate::memtable::{create_memtable, Memtable};
+use crate::sst::{sst_file_name, SstFile, SstManager, SstReader, SstWriter, TableHandle, Version, VersionEdit, NUM_LEVELS};
+use crate::storage::{Entry, Key, RangeTombstone};
+use crate::wal;
+use parking_lot::{Condvar, Mutex, RwLock};
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::atomic::AtomicU64;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use crate::metrics::in_timed_span;
+use tracing::{debug, debug_span, error, field, info, Span};
+
+/// Prefix of the per-level file count property
+const NUM_FILES_AT_LEVEL: &str = "auradb.num-files-at-level";
+
+/// Prefix of the per-level size property
+const BYTES_AT_LEVEL: &str = "auradb.bytes-at-level";
+
+/// Prefix of the per-level size limit property
+const TARGET_BYTES_AT_LEVEL: &str = "auradb.target-bytes-at-level";
+
+/// Delay added to each write per level 0 file above the slowdown trigger
+const SLOWDOWN_DELAY_PER_FILE: Duration = Duration::from_millis(1);
+
+/// How often a stopped writer re-checks the level 0 file count
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Write backpressure derived from the level 0 file count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WriteStall {
+    /// Writes proceed normally
+    None,
+    /// Writes are delayed; holds the number of files over the trigger
+    Slowdown(usize),
+    /// Writes wait until compaction drains level 0
+    Stop,
+}
+
+/// A sealed memtable waiting to be flushed
+struct ImmutableMemtable {
+    memtable: Arc<Memtable>,
+    /// First WAL log number not covered by this memtable
+    next_log_number: u64,
+}
+
+/// An external SST file that passed validation for ingestion
+#[derive(Debug, Clone)]
+pub struct ExternalSst {
+    /// Path of the file outside the database
+    pub path: PathBuf,
+    /// Smallest key in the file
+    pub smallest_key: Vec<u8>,
+    /// Largest key in the file
+    pub largest_key: Vec<u8>,
+    /// Number of entries in the file
+    pub entry_count: u64,
+    /// Number of tombstones in the file
+    pub deletion_count: u64,
+    /// Highest sequence number in the file
+    pub max_sequence: u64,
+    /// Expected false positive rate of the file's key Bloom filter, if any
+    pub bloom_fpr: Option<f64>,
+}
+
+/// Byte counters behind the amplification metrics
+#[derive(Debug, Default)]
+struct ByteCounters {
+    /// Key and value bytes accepted from writes
+    user_written: AtomicU64,
+    /// SST bytes written by memtable flushes
+    flush_written: AtomicU64,
+    /// Value bytes written to the value log
+    vlog_written: AtomicU64,
+    /// SST bytes read by scans
+    scan_read: AtomicU64,
+    /// Value log bytes read on value cache misses
+    vlog_read: AtomicU64,
+    /// Key and value bytes of live entries returned by reads, separated
+    /// values at their uncompressed length
+    returned: AtomicU64,
+    /// Key and value bytes of entries written to SST files
+    sst_logical_written: AtomicU64,
+    /// Size of the SST files written
+    sst_physical_written: AtomicU64,
+}
+
+/// Logical size of an entry: its key plus its value, wherever it is
+/// stored. Only the stored length of a separated value is known here,
+/// which is smaller than its own when it was compressed.
+fn user_bytes(entry: &Entry) -> u64 {
+    let value = match (&entry.value, &entry.value_pointer) {
+        (_, Some(vptr)) => vptr.length as u64,
+        (Some(value), None) => value.data.len() as u64,
+        (None, None) => 0,
+    };
+    entry.key.data.len() as u64 + value
+}
+
+/// Bytes a read returns for an entry straight from the tree: its key and
+/// any inline value. The engine counts a separated value through
+/// [`LsmTree::count_returned_value`] once it has read and decompressed it.
+fn returned_bytes(entry: &Entry) -> u64 {
+    match &entry.value_pointer {
+        Some(_) => entry.key.data.len() as u64,
+        None => user_bytes(entry),
+    }
+}
+
+/// `numerator / denominator`, or 0 if the denominator is 0
+fn ratio(numerator: u64, denominator: u64) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+/// Smallest key, in bytewise order, greater than every key starting with
+/// `prefix`, if any is
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|&byte| byte != 0xff)?;
+    let mut successor = prefix[..=last].to_vec();
+    successor[last] += 1;
+    Some(successor)
+}
+
+/// First error a source of a merge hit, shared with the sources
+type ErrorSlot = Rc<Cell<Option<Error>>>;
+
+/// End `source` at its first error, leaving the error in `slot`
+fn until_error<'a>(source: impl Iterator<Item = Result<Entry>> + 'a, slot: &ErrorSlot) -> Box<dyn Iterator<Item = Entry> + 'a> {
+    let slot = Rc::clone(slot);
+    Box::new(source.map_while(move |entry| entry.map_err(|e| slot.set(Some(e))).ok()))
+}
+
+/// Newest version of each key across memtables and SST files, merged as
+/// it is consumed; see [`LsmTree::merge_iter`]. Tombstones are yielded.
+/// An SST block that can't be read ends the iteration with its error.
+pub struct TreeIter<'a> {
+    merged: MergeIterator<'a, Box<dyn Iterator<Item = Entry> + 'a>>,
+    error: ErrorSlot,
+    failed: bool,
+}
+
+impl Iterator for TreeIter<'_> {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Result<Entry>> {
+        if self.failed {
+            return None;
+        }
+        let entry = self.merged.next();
+        // A source that failed dropped out of the merge, so nothing from
+        // here on can be trusted to be the newest version
+        if let Some(e) = self.error.take() {
+            self.failed = true;
+            return Some(Err(e));
+        }
+        entry.map(Ok)
+    }
+}
+
+/// The active memtable and the sealed ones, oldest first
+struct MemtableSet {
+    active: Arc<Memtable>,
+    immutables: VecDeque<ImmutableMemtable>,
+}
+
+/// Callback run when a write becomes visible, such as delivering its
+/// change events
+pub type OnVisible = Box<dyn FnOnce() + Send>;
+
+/// A write logged to the WAL that reads can't see yet
+struct PendingWrite {
+    /// Last sequence number the write took
+    last_sequence: u64,
+    /// Whether the write is done with the memtable, applied or failed
+    done: bool,
+    on_visible: Option<OnVisible>,
+}
+
+/// A write between its WAL record and its visibility to reads, from
+/// [`LsmTree::begin_write`]. Dropping it unfinished, as a failed write
+/// does, lets the writes queued behind it become visible.
+pub struct WriteTicket<'a> {
+    tree: &'a LsmTree,
+    last_sequence: u64,
+    finished: bool,
+}
+
+impl WriteTicket<'_> {
+    /// Mark the write as in the memtable and wait until it is visible,
+    /// which takes every earlier write being visible too. `on_visible`
+    /// runs first; the callbacks of all writes run in sequence order.
+    pub fn finish(mut self, on_visible: Option<OnVisible>) {
+        self.finished = true;
+        self.tree.complete_write(self.last_sequence, on_visible, true);
+    }
+}
+
+impl Drop for WriteTicket<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.tree.complete_write(self.last_sequence, None, false);
+        }
+    }
+}
+
+/// A point-in-time view of an [`LsmTree`]: the memtables and the SST
+/// version current when it was taken, read as of its sequence number.
+/// Holding them keeps sealed memtables in memory and, the version being
+/// pinned, keeps compactions from deleting the files it reads. Its
+/// sequence number stays registered with the tree until it is dropped.
+pub struct TreeSnapshot {
+    tree: Arc<LsmTree>,
+    /// Last sequence number the view includes
+    sequence: u64,
+    /// Memtables, newest first
+    memtables: Vec<Arc<Memtable>>,
+    version: Arc<Version>,
+}
+
+impl TreeSnapshot {
+    /// Last sequence number the view includes
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Newest entry for `key` as of the snapshot; a tombstone if it was
+    /// deleted
+    pub fn get(&self, key: &[u8]) -> Result<Option<Entry>> {
+        let lookup = Key::new(key.to_vec());
+        for memtable in &self.memtables {
+            if let Some(entry) = memtable.get_at(&lookup, self.sequence)? {
+                return Ok(Some(entry));
+            }
+        }
+        self.version.get(key, &self.tree.comparator)
+    }
+
+    /// Live entries with keys in `[start, end]` as of the snapshot, in
+    /// comparator order
+    pub fn scan(&self, start: &[u8], end: &[u8]) -> Result<Vec<Entry>> {
+        let comparator = &self.tree.comparator;
+        let entries = self.merge(Some(start), |table| table.overlaps(start, end, comparator));
+        entries.take_while(|entry| entry.as_ref().map_or(true, |e| comparator.compare(&e.key.data, end) != Ordering::Greater)).collect()
+    }
+
+    /// Count a separated value of `len` uncompressed bytes read from the
+    /// value log and returned by a read of the snapshot
+    pub fn count_value_read(&self, len: u64) {
+        self.tree.record_vlog_read(len);
+        self.tree.count_returned_value(len);
+    }
+
+    /// Every live entry as of the snapshot, in comparator order
+    pub fn scan_all(&self) -> Result<Vec<Entry>> {
+        self.iter().collect()
+    }
+
+    /// Iterate over every live entry as of the snapshot in comparator
+    /// order, merging the memtables and SST files as it goes
+    pub fn iter(&self) -> impl Iterator<Item = Result<Entry>> + '_ {
+        self.merge(None, |_| true)
+    }
+
+    /// Live entries from `start` on, from the files `read_table` accepts
+    fn merge<'a>(&'a self, start: Option<&[u8]>, read_table: impl Fn(&TableHandle) -> bool) -> impl Iterator<Item = Result<Entry>> + 'a {
+        let tree = &self.tree;
+        tree.record_op(false);
+        tree.merge_iter(&self.memtables, &self.version, self.sequence, start, read_table)
+            .filter(|entry| entry.as_ref().map_or(true, |e| !e.is_delete()))
+            .inspect(|entry| {
+                if let Ok(entry) = entry {
+                    tree.bytes.returned.fetch_add(returned_bytes(entry), AtomicOrdering::Relaxed);
+                }
+            })
+    }
+}
+
+impl Drop for TreeSnapshot {
+    fn drop(&mut self) {
+        self.tree.release_snapshot(self.sequence);
+    }
+}
+
+/// LSM tree shared between the engine and its background worker
+pub struct LsmTree {
+    /// Engine configuration
+    config: Config,
+    /// Key ordering
+    comparator: Comparator,
+    /// Memtables
+    memtables: RwLock<MemtableSet>,
+    /// SST files per level
+    sst: SstManager,
+    /// Persistent metadata; also serializes version changes
+    manifest: Mutex<Manifest>,
+    /// Compaction picking and pause state
+    compaction: CompactionManager,
+    /// Serializes memtable flushes
+    flush_lock: Mutex<()>,
+    /// Held shared by background compactions, which the compaction manager
+    /// keeps off each other's files, and exclusively by manual compactions,
+    /// ingestion and checkpoints
+    compaction_lock: RwLock<()>,
+    /// Set when the worker has work to look at
+    pending: Mutex<bool>,
+    /// Wakes the worker
+    wakeup: Condvar,
+    /// Signalled whenever the set of SST files changes
+    version_changed: Condvar,
+    /// Lock paired with `version_changed`
+    version_lock: Mutex<()>,
+    /// Total time writers spent stalled, in microseconds
+    stall_micros: AtomicU64,
+    /// Writes that gave up waiting out a stop
+    stall_timeouts: AtomicU64,
+    /// Set when the worker should exit
+    shutdown: AtomicBool,
+    /// Background worker thread
+    worker: Mutex<Option<JoinHandle<()>>>,
+    /// RL compaction agent, if enabled
+    rl_agent: Option<Mutex<RlCompactionAgent>>,
+    /// Reads and writes since the flexible strategy was last reconsidered
+    window_ops: AtomicU64,
+    /// Writes among `window_ops`
+    window_writes: AtomicU64,
+    /// Compactions completed when the current window started
+    window_compactions: AtomicU64,
+    /// Bytes written and read, for the amplification metrics
+    bytes: ByteCounters,
+    /// Settings for new SST files; the Bloom filter bits can change at
+    /// runtime
+    sst_config: RwLock<SstConfig>,
+    /// Sequence numbers of the live snapshots, with how many share each
+    snapshots: Mutex<BTreeMap<u64, usize>>,
+    /// Last sequence number reads see. Writers insert into the memtable
+    /// concurrently, and a write becomes visible once it and every write
+    /// before it are in, so reads skip entries still being inserted and
+    /// see a batch all at once.
+    visible_sequence: AtomicU64,
+    /// Writes not yet visible, in sequence order
+    pending_writes: Mutex<VecDeque<PendingWrite>>,
+    /// Signalled whenever `visible_sequence` advances
+    writes_visible: Condvar,
+}
+
+impl LsmTree {
+    /// Open the tree described by the manifest in `config.db_path`, creating
+    /// a new manifest for a fresh database, and start the background worker.
+    /// An in-memory tree starts empty and touches no files.
+    pub fn open(config: Config) -> Result<Arc<Self>> {
+        let comparator = config.comparator.clone();
+        if !config.in_memory {
+            std::fs::create_dir_all(&config.sst.sst_path)?;
+        }
+
+        let loaded = if config.in_memory { None } else { Manifest::load(&config.db_path)? };
+        let manifest = match loaded {
+            Some(mut manifest) => {
+                manifest.check_config(&config)?;
+                if manifest.settings.is_none() {
+                    manifest.settings = Some(CreationSettings::from_config(&config));
+                    manifest.save(&config.db_path)?;
+                }
+                manifest
+            }
+            None => {
+                let manifest = Manifest::new(comparator.name()).with_settings(CreationSettings::from_config(&config));
+                if !config.in_memory {
+                    manifest.save(&config.db_path)?;
+                }
+                manifest
+            }
+        };
+
+        let mut sst = SstManager::new(
+            config.sst.sst_path.clone(),
+            FileAccess::from_config(&config.performance),
+            comparator.clone(),
+        )
+        .with_fadvise(config.performance.use_fadvise);
+        if config.cache.block_cache_size > 0 {
+            sst = sst.with_block_cache(UnifiedCache::new(
+                config.cache.block_cache_size,
+                (&config.cache.eviction_policy).into(),
+            ));
+            if config.cache.compressed_cache_size > 0 {
+                sst = sst.with_compressed_cache(UnifiedCache::new(
+                    config.cache.compressed_cache_size,
+                    (&config.cache.eviction_policy).into(),
+                ));
+            }
+        }
+        sst.apply(VersionEdit {
+            added: manifest.files.clone(),
+            removed: Vec::new(),
+        })?;
+        if !config.in_memory {
+            let (orphans, reclaimed) = sst.remove_orphans()?;
+            if orphans > 0 {
+                info!("Removed {} orphaned SST files, reclaiming {} bytes", orphans, reclaimed);
+            }
+        }
+
+        // An in-memory tree has nothing to compact
+        let rl_agent = if config.rl_agent.enabled && !config.in_memory {
+            let policy_path = config.db_path.join(RL_POLICY_FILE);
+            Some(Mutex::new(RlCompactionAgent::open(config.rl_agent.clone(), Some(&policy_path))?))
+        } else {
+            None
+        };
+
+        let manifest_last_sequence = manifest.last_sequence;
+        let tree = Arc::new(Self {
+            memtables: RwLock::new(MemtableSet {
+                active: Arc::new(Self::new_memtable(&config)),
+                immutables: VecDeque::new(),
+            }),
+            compaction: CompactionManager::new(config.compaction.clone(), comparator.clone()),
+            sst,
+            manifest: Mutex::new(manifest),
+            flush_lock: Mutex::new(()),
+            compaction_lock: RwLock::new(()),
+            pending: Mutex::new(true),
+            wakeup: Condvar::new(),
+            version_changed: Condvar::new(),
+            version_lock: Mutex::new(()),
+            stall_micros: AtomicU64::new(0),
+            stall_timeouts: AtomicU64::new(0),
+            shutdown: AtomicBool::new(false),
+            worker: Mutex::new(None),
+            rl_agent,
+            window_ops: AtomicU64::new(0),
+            window_writes: AtomicU64::new(0),
+            window_compactions: AtomicU64::new(0),
+            bytes: ByteCounters::default(),
+            sst_config: RwLock::new(config.sst.clone()),
+            snapshots: Mutex::new(BTreeMap::new()),
+            visible_sequence: AtomicU64::new(manifest_last_sequence),
+            pending_writes: Mutex::new(VecDeque::new()),
+            writes_visible: Condvar::new(),
+            comparator,
+            config,
+        });
+
+        if tree.config.in_memory {
+            return Ok(tree);
+        }
+        let worker = Arc::clone(&tree);
+        let handle = std::thread::Builder::new()
+            .name("auradb-bg".to_string())
+            .spawn(move || worker.background_loop())?;
+        *tree.worker.lock() = Some(handle);
+        Ok(tree)
+    }
+
+    fn new_memtable(config: &Config) -> Memtable {
+        create_memtable(
+            config.memtable.implementation.clone(),
+            config.comparator.clone(),
+            config.memtable.max_size,
+            config.memtable.flush_threshold,
+        )
+        .with_max_entries(config.memtable.max_entries)
+    }
+
+    /// Highest sequence number persisted in SST files
+    pub fn last_sequence(&self) -> u64 {
+        self.manifest.lock().last_sequence
+    }
+
+    /// First WAL log number whose records may not be in SST files yet
+    pub fn log_number(&self) -> u64 {
+        self.manifest.lock().log_number
+    }
+
+    /// On-disk format version recorded in the manifest
+    pub fn format_version(&self) -> u32 {
+        self.manifest.lock().version
+    }
+
+    /// Register a write that took the sequence numbers up to
+    /// `last_sequence`. Writers call this holding the WAL lock, right
+    /// after taking their sequence numbers, so the pending writes queue up
+    /// in sequence order; the entries become visible through the ticket
+    /// once inserted.
+    pub fn begin_write(&self, last_sequence: u64) -> WriteTicket<'_> {
+        self.pending_writes.lock().push_back(PendingWrite {
+            last_sequence,
+            done: false,
+            on_visible: None,
+        });
+        WriteTicket {
+            tree: self,
+            last_sequence,
+            finished: false,
+        }
+    }
+
+    /// Mark a pending write done and make the done writes at the head of
+    /// the queue visible, running their callbacks in order. With `wait`,
+    /// return only once the write itself is visible.
+    fn complete_write(&self, last_sequence: u64, on_visible: Option<OnVisible>, wait: bool) {
+        let mut pending = self.pending_writes.lock();
+        if let Ok(index) = pending.binary_search_by_key(&last_sequence, |write| write.last_sequence) {
+            pending[index].done = true;
+            pending[index].on_visible = on_visible;
+        }
+        let mut visible = None;
+        while pending.front().is_some_and(|write| write.done) {
+            if let Some(write) = pending.pop_front() {
+                if let Some(on_visible) = write.on_visible {
+                    on_visible();
+                }
+                visible = Some(write.last_sequence);
+            }
+        }
+        if let Some(sequence) = visible {
+            self.publish(sequence);
+            self.writes_visible.notify_all();
+        }
+        while wait && self.visible_sequence() < last_sequence {
+            self.writes_visible.wait(&mut pending);
+        }
+    }
+
+    /// Wait until every pending write is visible. Callers hold the WAL
+    /// lock, so no write can start meanwhile and the memtables then hold
+    /// everything logged.
+    pub fn wait_for_writes(&self) {
+        let mut pending = self.pending_writes.lock();
+        while !pending.is_empty() {
+            self.writes_visible.wait(&mut pending);
+        }
+    }
+
+    /// Insert an entry into the active memtable. Inserts share the
+    /// memtable lock, so they run concurrently with each other and with
+    /// reads; the entry becomes visible through its write's ticket.
+    pub fn insert(&self, entry: Entry) -> Result<()> {
+        self.count_write(&entry);
+        self.memtables.read().active.insert(entry)
+    }
+
+    /// Insert entries into the active memtable. Their write's ticket makes
+    /// them visible together, so a read sees either all of them or none.
+    pub fn insert_batch(&self, entries: impl IntoIterator<Item = Entry>) -> Result<()> {
+        let memtables = self.memtables.read();
+        for entry in entries {
+            self.count_write(&entry);
+            memtables.active.insert(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Put entries replayed from the WAL back into the active memtable.
+    /// They were counted when first written, so they aren't counted again.
+    pub fn restore(&self, entries: impl IntoIterator<Item = Entry>) -> Result<()> {
+        let memtables = self.memtables.read();
+        for entry in entries {
+            let sequence = entry.sequence;
+            memtables.active.insert(entry)?;
+            self.publish(sequence);
+        }
+        Ok(())
+    }
+
+    /// Make the writes up to `sequence` visible to reads
+    fn publish(&self, sequence: u64) {
+        self.visible_sequence.fetch_max(sequence, AtomicOrdering::Release);
+    }
+
+    /// Last sequence number reads see
+    pub fn visible_sequence(&self) -> u64 {
+        self.visible_sequence.load(AtomicOrdering::Acquire)
+    }
+
+    /// Count a write towards the operation mix and the bytes written. A
+    /// separated value's user bytes were counted by
+    /// [`Self::count_separated_value`]; its pointer only has the stored,
+    /// possibly compressed, length.
+    fn count_write(&self, entry: &Entry) {
+        self.record_op(true);
+        let user = match &entry.value_pointer {
+            Some(vptr) => {
+                self.bytes.vlog_written.fetch_add(vptr.length as u64, AtomicOrdering::Relaxed);
+                entry.key.data.len() as u64
+            }
+            None => user_bytes(entry),
+        };
+        self.bytes.user_written.fetch_add(user, AtomicOrdering::Relaxed);
+    }
+
+    /// Count the uncompressed length of a value written to the value log
+    /// towards the user bytes written
+    pub fn count_separated_value(&self, len: u64) {
+        self.bytes.user_written.fetch_add(len, AtomicOrdering::Relaxed);
+    }
+
+    /// Newest entry for `key`, which may be a tombstone
+    pub fn get(&self, key: &[u8]) -> Result<Option<Entry>> {
+        self.record_op(false);
+        let entry = self.find(key)?;
+        if let Some(entry) = entry.as_ref().filter(|e| !e.is_delete()) {
+            self.bytes.returned.fetch_add(returned_bytes(entry), AtomicOrdering::Relaxed);
+        }
+        Ok(entry)
+    }
+
+    /// Newest visible entry for `key` in the memtables, then the SST
+    /// files. The tier it came from is recorded in the current span's
+    /// `tier` field.
+    fn find(&self, key: &[u8]) -> Result<Option<Entry>> {
+        let lookup = Key::new(key.to_vec());
+        let sequence = self.visible_sequence();
+        {
+            let memtables = self.memtables.read();
+            if let Some(entry) = memtables.active.get_at(&lookup, sequence)? {
+                Span::current().record("tier", "memtable");
+                return Ok(Some(entry));
+            }
+            for immutable in memtables.immutables.iter().rev() {
+                if let Some(entry) = immutable.memtable.get_at(&lookup, sequence)? {
+                    Span::current().record("tier", "immutable-memtable");
+                    return Ok(Some(entry));
+                }
+            }
+        }
+        let found = self.sst.current().get_with_level(key, &self.comparator)?;
+        Ok(found.map(|(entry, level)| {
+            Span::current().record("tier", format!("level-{}", level).as_str());
+            entry
+        }))
+    }
+
+    /// Newest live entries with keys in `[start, end]`, in comparator order
+    pub fn scan(&self, start: &[u8], end: &[u8]) -> Result<Vec<Entry>> {
+        let mut entries = self.collect_range(Some((start, end)))?;
+        entries.retain(|e| !e.is_delete());
+        Ok(entries)
+    }
+
+    /// Newest entry for every key in `[start, end]`, tombstones included,
+    /// in comparator order
+    pub fn scan_entries(&self, start: &[u8], end: &[u8]) -> Result<Vec<Entry>> {
+        self.collect_range(Some((start, end)))
+    }
+
+    /// Every newest live entry, in comparator order
+    pub fn scan_all(&self) -> Result<Vec<Entry>> {
+        let mut entries = self.collect_range(None)?;
+        entries.retain(|e| !e.is_delete());
+        Ok(entries)
+    }
+
+    /// Newest live entries whose keys start with `prefix`, in comparator
+    /// order. SST files whose prefix filter rules the prefix out are
+    /// skipped without being read.
+    pub fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<Entry>> {
+        let extractor = self.config.sst.prefix_extractor.as_ref();
+        let read_table = |table: &TableHandle| extractor.is_none_or(|extractor| table.reader().may_contain_prefix(prefix, extractor));
+        // Only in bytewise order are the keys with a prefix contiguous,
+        // starting at the prefix itself
+        let mut entries = if self.comparator.name() == Comparator::BYTEWISE {
+            self.collect_newest(Some(prefix), |key| key.starts_with(prefix), |_| true, read_table)?
+        } else {
+            self.collect_newest(None, |_| true, |key| key.starts_with(prefix), read_table)?
+        };
+        entries.retain(|e| !e.is_delete());
+        Ok(entries)
+    }
+
+    /// Merge all sources, optionally restricted to an inclusive key range
+    fn collect_range(&self, range: Option<(&[u8], &[u8])>) -> Result<Vec<Entry>> {
+        match range {
+            Some((start, end)) => self.collect_newest(
+                Some(start),
+                |key| self.comparator.compare(key, end) != Ordering::Greater,
+                |_| true,
+                |table| table.overlaps(start, end, &self.comparator),
+            ),
+            None => self.collect_newest(None, |_| true, |_| true, |_| true),
+        }
+    }
+
+    /// Merge every memtable with the SST files `read_table` accepts from
+    /// `start` on, keeping the newest entry per key, and collect those
+    /// matching `include` until a key falls outside `within`.
+    ///
+    /// The merge reads the memtables as of the visible sequence and the
+    /// SST version current when it started; see [`Self::current_view`].
+    /// The pinned version keeps its SST files readable even if a
+    /// compaction replaces them meanwhile.
+    fn collect_newest(
+        &self,
+        start: Option<&[u8]>,
+        within: impl Fn(&[u8]) -> bool,
+        include: impl Fn(&[u8]) -> bool,
+        read_table: impl Fn(&TableHandle) -> bool,
+    ) -> Result<Vec<Entry>> {
+        self.record_op(false);
+        let (memtables, sequence, version) = self.current_view();
+        let mut entries = Vec::new();
+        for entry in self.merge_iter(&memtables, &version, sequence, start, read_table) {
+            let entry = entry?;
+            if !within(&entry.key.data) {
+                break;
+            }
+            if include(&entry.key.data) {
+                entries.push(entry);
+            }
+        }
+        let returned: u64 = entries.iter().filter(|e| !e.is_delete()).map(returned_bytes).sum();
+        self.bytes.returned.fetch_add(returned, AtomicOrdering::Relaxed);
+        Ok(entries)
+    }
+
+    /// The memtables, newest first, the visible sequence and the current
+    /// SST version, taken under the memtable lock, which flushes need
+    /// exclusively, so together they hold every write up to the sequence
+    fn current_view(&self) -> (Vec<Arc<Memtable>>, u64, Arc<Version>) {
+        let memtables = self.memtables.read();
+        let immutables = memtables.immutables.iter().rev().map(|m| Arc::clone(&m.memtable));
+        let newest_first = std::iter::once(Arc::clone(&memtables.active)).chain(immutables).collect();
+        (newest_first, self.visible_sequence(), self.sst.current())
+    }
+
+    /// Merge `memtables`, newest first, as of `sequence` with the files of
+    /// `version` that `read_table` accepts, from `start` on. Files are
+    /// read a block at a time as the merge reaches them, counted as scan
+    /// reads; the range tombstones of every memtable and file apply.
+    fn merge_iter<'a>(
+        &'a self,
+        memtables: &'a [Arc<Memtable>],
+        version: &'a Version,
+        sequence: u64,
+        start: Option<&[u8]>,
+        read_table: impl Fn(&TableHandle) -> bool,
+    ) -> TreeIter<'a> {
+        let error = ErrorSlot::default();
+        let mut sources = Vec::new();
+        let mut tombstones = Vec::new();
+        for memtable in memtables {
+            sources.push(memtable.iter_from(start, sequence));
+            tombstones.extend(memtable.range_tombstones_at(sequence));
+        }
+        // Even files `read_table` skips may hold tombstones covering keys
+        tombstones.extend(version.range_tombstones().cloned());
+        for table in version.files().filter(|table| read_table(table)) {
+            let blocks = table.reader().iter_from(start).with_read_counter(&self.bytes.scan_read);
+            sources.push(until_error(blocks, &error));
+        }
+        let skyline = RangeDelSkyline::new(tombstones, &self.comparator);
+        TreeIter {
+            merged: MergeIterator::new(sources, &self.comparator).with_range_tombstones(skyline),
+            error,
+            failed: false,
+        }
+    }
+
+    /// Take a point-in-time view of the tree as of the visible sequence:
+    /// the memtables and the current SST version, pinned. The sequence
+    /// stays registered until the view is dropped.
+    pub fn snapshot(self: &Arc<Self>) -> TreeSnapshot {
+        self.record_op(false);
+        let (memtables, sequence, version) = self.current_view();
+        *self.snapshots.lock().entry(sequence).or_insert(0) += 1;
+        TreeSnapshot {
+            tree: Arc::clone(self),
+            sequence,
+            memtables,
+            version,
+        }
+    }
+
+    /// Deregister one snapshot at `sequence`
+    fn release_snapshot(&self, sequence: u64) {
+        let mut snapshots = self.snapshots.lock();
+        if let Some(count) = snapshots.get_mut(&sequence) {
+            *count -= 1;
+            if *count == 0 {
+                snapshots.remove(&sequence);
+            }
+        }
+    }
+
+    /// Sequence number of the oldest live snapshot, if any
+    pub fn oldest_snapshot(&self) -> Option<u64> {
+        self.snapshots.lock().keys().next().copied()
+    }
+
+    /// Number of live snapshots
+    pub fn num_snapshots(&self) -> usize {
+        self.snapshots.lock().values().sum()
+    }
+
+    /// Whether the active memtable has reached its flush threshold; never
+    /// in an in-memory tree, whose memtable grows without bound
+    pub fn needs_switch(&self) -> bool {
+        !self.config.in_memory && self.memtables.read().active.should_flush()
+    }
+
+    /// Drop every memtable's contents. An in-memory tree does this instead
+    /// of flushing, having nowhere to write. Called with the WAL lock held.
+    pub fn clear_memtables(&self) {
+        self.wait_for_writes();
+        let mut memtables = self.memtables.write();
+        memtables.active = Arc::new(Self::new_memtable(&self.config));
+        memtables.immutables.clear();
+    }
+
+    /// Seal the active memtable and schedule its flush. `next_log_number`
+    /// is the WAL file new writes go to, so every older WAL file can be
+    /// deleted once the sealed memtable is on disk. Called with the WAL
+    /// lock held; the writes still going into the memtable finish first.
+    pub fn switch_memtable(&self, next_log_number: u64) {
+        self.wait_for_writes();
+        {
+            let mut memtables = self.memtables.write();
+            let sealed = std::mem::replace(&mut memtables.active, Arc::new(Self::new_memtable(&self.config)));
+            memtables.immutables.push_back(ImmutableMemtable {
+                memtable: sealed,
+                next_log_number,
+            });
+        }
+        self.schedule();
+    }
+
+    /// Apply the runtime-tunable settings of `config`: the block cache
+    /// and compressed tier sizes, the compaction triggers, thread count and I/O rate limit, and
+    /// the Bloom filter bits of SST files written from now on
+    pub fn reload_config(&self, config: &Config) -> Result<()> {
+        self.sst.set_block_cache_capacity(config.cache.block_cache_size)?;
+        self.sst.set_compressed_cache_capacity(config.cache.compressed_cache_size)?;
+        self.compaction.update_config(&config.compaction);
+        {
+            let mut sst_config = self.sst_config.write();
+            sst_config.bloom_bits_per_key = config.sst.bloom_bits_per_key;
+            sst_config.bloom_fpr = config.sst.bloom_fpr;
+        }
+        // Lowered triggers may make a compaction due, raised ones may lift
+        // a stall
+        self.schedule();
+        let _guard = self.version_lock.lock();
+        self.version_changed.notify_all();
+        Ok(())
+    }
+
+    /// Progress of the compactions running now
+    pub fn compaction_status(&self) -> Vec<CompactionProgress> {
+        self.compaction.progress()
+    }
+
+    /// The last compactions to finish, oldest first
+    pub fn compaction_history(&self) -> Vec<CompactionProgress> {
+        self.compaction.history()
+    }
+
+    /// Stop picking new compactions
+    pub fn pause_compaction(&self) {
+        self.compaction.pause();
+    }
+
+    /// Allow compactions again and check whether one is due
+    pub fn resume_compaction(&self) {
+        self.compaction.resume();
+        self.schedule();
+    }
+
+    /// Value of a named engine property, or `None` for unknown names
+    pub fn property(&self, name: &str) -> Option<String> {
+        match name {
+            "auradb.compaction-paused" => Some(if self.compaction.is_paused() { "1" } else { "0" }.to_string()),
+            "auradb.num-snapshots" => Some(self.num_snapshots().to_string()),
+            "auradb.oldest-snapshot-sequence" => Some(self.oldest_snapshot().unwrap_or(0).to_string()),
+            "auradb.num-immutable-mem-table" => Some(self.memtables.read().immutables.len().to_string()),
+            "auradb.cur-size-active-mem-table" => Some(self.memtables.read().active.memory_usage().to_string()),
+            "auradb.num-entries-active-mem-table" => Some(self.memtables.read().active.len().to_string()),
+            "auradb.memtable-flush-threshold-bytes" => {
+                Some(self.memtables.read().active.flush_threshold_bytes().to_string())
+            }
+            "auradb.memtable-flush-threshold-entries" => Some(
+                self.memtables.read().active.max_entries().map_or_else(|| "none".to_string(), |max| max.to_string()),
+            ),
+            "auradb.num-compactions-completed" => Some(self.compaction.completed_compactions().to_string()),
+            "auradb.running-compactions" => Some(
+                self.compaction.progress().iter().map(CompactionProgress::summary).collect::<Vec<_>>().join("\n"),
+            ),
+            "auradb.write-stall" => Some(
+                match self.write_stall() {
+                    WriteStall::None => "none",
+                    WriteStall::Slowdown(_) => "slowdown",
+                    WriteStall::Stop => "stop",
+                }
+                .to_string(),
+            ),
+            "auradb.stall-micros" => Some(self.stall_micros.load(AtomicOrdering::Relaxed).to_string()),
+            "auradb.stall-timeouts" => Some(self.stall_timeouts.load(AtomicOrdering::Relaxed).to_string()),
+            "auradb.compact-read-bytes" => Some(self.compaction.bytes_read().to_string()),
+            "auradb.compact-write-bytes" => Some(self.compaction.bytes_written().to_string()),
+            "auradb.active-compaction-strategy" => Some(
+                match self.compaction.active_strategy() {
+                    CompactionStrategy::Tiered => "tiered",
+                    _ => "leveled",
+                }
+                .to_string(),
+            ),
+            "auradb.write-amplification" => Some(format!("{:.4}", self.write_amplification())),
+            "auradb.read-amplification" => Some(format!("{:.4}", self.read_amplification())),
+            "auradb.space-amplification" => Some(format!("{:.4}", self.space_amplification())),
+            "auradb.estimate-num-keys" => Some(self.estimate_num_keys().to_string()),
+            "auradb.sst-logical-bytes-written" => Some(self.sst_logical_bytes_written().to_string()),
+            "auradb.sst-physical-bytes-written" => Some(self.sst_physical_bytes_written().to_string()),
+            "auradb.block-cache-hits" => Some(self.block_cache_stats().hits.to_string()),
+            "auradb.block-cache-misses" => Some(self.block_cache_stats().misses.to_string()),
+            "auradb.block-cache-hit-ratio" => Some(format!("{:.4}", self.block_cache_stats().hit_ratio())),
+            "auradb.cur-size-all-mem-tables" => Some(self.memtable_memory().to_string()),
+            "auradb.block-cache-usage" => Some(self.block_cache_stats().size.to_string()),
+            "auradb.compressed-block-cache-hits" => Some(self.compressed_cache_stats().hits.to_string()),
+            "auradb.compressed-block-cache-misses" => Some(self.compressed_cache_stats().misses.to_string()),
+            "auradb.compressed-block-cache-hit-ratio" => {
+                Some(format!("{:.4}", self.compressed_cache_stats().hit_ratio()))
+            }
+            "auradb.compressed-block-cache-usage" => Some(self.compressed_cache_stats().size.to_string()),
+            "auradb.estimate-filter-mem" => Some(self.sst.filter_memory().to_string()),
+            _ => {
+                if let Some(level) = name.strip_prefix(BYTES_AT_LEVEL) {
+                    let level: usize = level.parse().ok()?;
+                    return (level < NUM_LEVELS).then(|| self.sst.current().level_size(level).to_string());
+                }
+                if let Some(level) = name.strip_prefix(TARGET_BYTES_AT_LEVEL) {
+                    // Level 0 is compacted by file count, and the last level never
+                    let level: usize = level.parse().ok()?;
+                    return (level < NUM_LEVELS).then(|| {
+                        if level == 0 || level == NUM_LEVELS - 1 {
+                            "none".to_string()
+                        } else {
+                            self.compaction.max_bytes_for_level(level).to_string()
+                        }
+                    });
+                }
+                let level: usize = name.strip_prefix(NUM_FILES_AT_LEVEL)?.parse().ok()?;
+                (level < NUM_LEVELS).then(|| self.sst.current().level(level).len().to_string())
+            }
+        }
+    }
+
+    /// Load the SST blocks covering `[start, end]` (every block if `range`
+    /// is `None`) into the block cache, newest levels first, stopping once
+    /// the cache is full. Works on the current version without holding
+    /// any lock writers need.
+    pub fn warm_cache(&self, range: Option<(&[u8], &[u8])>) -> Result<()> {
+        let version = self.sst.current();
+        for table in version.files() {
+            if range.is_some_and(|(start, end)| !table.overlaps(start, end, &self.comparator)) {
+                continue;
+            }
+            if !table.reader().warm_blocks(range)? {
+                debug!("Block cache full; stopped warming");
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Pin the SST blocks that may hold keys in `[start, end]` in the block
+    /// cache; see [`SstManager::pin_range`]
+    pub fn pin_range(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        self.sst.pin_range(start, end)
+    }
+
+    /// Release a range pinned by [`pin_range`](Self::pin_range)
+    pub fn unpin_range(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        self.sst.unpin_range(start, end)
+    }
+
+    /// Approximate number of live keys, from the SST entry and tombstone
+    /// counts and the memtable sizes, without reading any data.
+    ///
+    /// Each tombstone is assumed to shadow one older entry, so it takes two
+    /// entries off the total. Keys with live versions in more than one
+    /// level or memtable are counted once per version, so the estimate is
+    /// exact for a fully compacted database and otherwise high by up to the
+    /// number of entries outside the largest level. It is never below the
+    /// live entries of that level, whose keys are unique.
+    pub fn estimate_num_keys(&self) -> u64 {
+        let memtable_entries: u64 = {
+            let memtables = self.memtables.read();
+            memtables.active.len() as u64
+                + memtables.immutables.iter().map(|m| m.memtable.len() as u64).sum::<u64>()
+        };
+        let version = self.sst.current();
+        let live = |files: &mut dyn Iterator<Item = &Arc<TableHandle>>| {
+            let (entries, deletions) =
+                files.fold((0u64, 0u64), |(e, d), t| (e + t.meta.entry_count, d + t.meta.deletion_count));
+            entries.saturating_sub(2 * deletions)
+        };
+        let largest_level = (1..NUM_LEVELS)
+            .map(|level| live(&mut version.level(level).iter()))
+            .max()
+            .unwrap_or(0);
+        let total = live(&mut version.files());
+        (total + memtable_entries).max(largest_level)
+    }
+
+    /// Number of live SST files and their total size in bytes
+    pub fn sst_footprint(&self) -> (u64, u64) {
+        let version = self.sst.current();
+        let files = version.files().count() as u64;
+        (files, version.files().map(|t| t.meta.size).sum())
+    }
+
+    /// Count value log bytes read to serve a user read
+    pub fn record_vlog_read(&self, bytes: u64) {
+        self.bytes.vlog_read.fetch_add(bytes, AtomicOrdering::Relaxed);
+    }
+
+    /// Count the uncompressed length of a separated value returned by a
+    /// read towards the bytes returned
+    pub fn count_returned_value(&self, len: u64) {
+        self.bytes.returned.fetch_add(len, AtomicOrdering::Relaxed);
+    }
+
+    /// Key and value bytes accepted from writes since open
+    pub fn user_bytes_written(&self) -> u64 {
+        self.bytes.user_written.load(AtomicOrdering::Relaxed)
+    }
+
+    /// Bytes written to SST files and the value log since open
+    pub fn disk_bytes_written(&self) -> u64 {
+        self.bytes.flush_written.load(AtomicOrdering::Relaxed)
+            + self.compaction.bytes_written()
+            + self.bytes.vlog_written.load(AtomicOrdering::Relaxed)
+    }
+
+    /// Key and value bytes of the entries in the SST files written since
+    /// open by flushes, compactions and ingestion
+    pub fn sst_logical_bytes_written(&self) -> u64 {
+        self.bytes.sst_logical_written.load(AtomicOrdering::Relaxed)
+    }
+
+    /// Size on disk of the SST files counted by
+    /// [`sst_logical_bytes_written`](Self::sst_logical_bytes_written),
+    /// including block framing, filters and indexes
+    pub fn sst_physical_bytes_written(&self) -> u64 {
+        self.bytes.sst_physical_written.load(AtomicOrdering::Relaxed)
+    }
+
+    /// SST bytes read from disk since open: blocks read by point lookups
+    /// on block cache misses and by scans, and compaction inputs
+    pub fn sst_bytes_read(&self) -> u64 {
+        self.sst.bytes_read()
+            + self.bytes.scan_read.load(AtomicOrdering::Relaxed)
+            + self.compaction.bytes_read()
+    }
+
+    /// Bytes written to disk by flushes, compactions and the value log per
+    /// byte written by users, or 0 before the first write. WAL writes are
+    /// not counted.
+    pub fn write_amplification(&self) -> f64 {
+        ratio(self.disk_bytes_written(), self.user_bytes_written())
+    }
+
+    /// SST and value log bytes read per byte returned by gets and scans,
+    /// or 0 before any read returned data. Block cache hits and
+    /// memtable reads cost nothing, so this can be below 1.
+    pub fn read_amplification(&self) -> f64 {
+        let physical = self.sst.bytes_read()
+            + self.bytes.scan_read.load(AtomicOrdering::Relaxed)
+            + self.bytes.vlog_read.load(AtomicOrdering::Relaxed);
+        ratio(physical, self.bytes.returned.load(AtomicOrdering::Relaxed))
+    }
+
+    /// Total SST size per byte in the largest level, the usual stand-in
+    /// for live data, or 0 when there are no SST files. Value log garbage
+    /// is not included.
+    pub fn space_amplification(&self) -> f64 {
+        let version = self.sst.current();
+        let largest = (0..NUM_LEVELS).map(|level| version.level_size(level)).max().unwrap_or(0);
+        ratio(self.sst.total_size(), largest)
+    }
+
+    /// Memory used by the active and sealed memtables
+    pub fn memtable_memory(&self) -> usize {
+        let memtables = self.memtables.read();
+        memtables.active.memory_usage()
+            + memtables.immutables.iter().map(|immutable| immutable.memtable.memory_usage()).sum::<usize>()
+    }
+
+    /// Bytes of Bloom filters held in memory by live SST files
+    pub fn filter_memory(&self) -> usize {
+        self.sst.filter_memory()
+    }
+
+    /// Block cache statistics; all zero when the cache is disabled
+    pub fn block_cache_stats(&self) -> CacheStats {
+        self.sst.block_cache_stats().unwrap_or_default()
+    }
+
+    /// Compressed block cache tier statistics; all zero when the tier is
+    /// disabled. Its lookups are the block cache's misses.
+    pub fn compressed_cache_stats(&self) -> CacheStats {
+        self.sst.compressed_cache_stats().unwrap_or_default()
+    }
+
+    /// Current backpressure. Writes stop while `memtable.count` memtables
+    /// are full and waiting for a flush. Level 0 stalls are lifted while
+    /// compaction is paused, since level 0 could not drain anyway.
+    fn write_stall(&self) -> WriteStall {
+        let max_immutables = self.config.memtable.count.max(2) - 1;
+        if self.memtables.read().immutables.len() >= max_immutables {
+            return WriteStall::Stop;
+        }
+        if self.compaction.is_paused() {
+            return WriteStall::None;
+        }
+        let triggers = self.compaction.triggers();
+        let level0 = self.sst.current().level(0).len();
+        if level0 >= triggers.level0_stop {
+            WriteStall::Stop
+        } else if level0 >= triggers.level0_slowdown {
+            WriteStall::Slowdown(level0 - triggers.level0_slowdown + 1)
+        } else {
+            WriteStall::None
+        }
+    }
+
+    /// Whether writes are currently stopped, by full memtables or level 0
+    pub fn is_write_stopped(&self) -> bool {
+        self.write_stall() == WriteStall::Stop
+    }
+
+    /// Number of sealed memtables waiting to be flushed
+    pub fn immutable_count(&self) -> usize {
+        self.memtables.read().immutables.len()
+    }
+
+    /// Apply write backpressure: sleep in proportion to how far level 0 is
+    /// over the slowdown trigger, or block while writes are stopped. With a
+    /// `timeout`, a stopped write gives up after that long with
+    /// `Error::Concurrency("write stalled")`.
+    pub fn stall_writes(&self, timeout: Option<Duration>) -> Result<()> {
+        let started = Instant::now();
+        let mut result = Ok(());
+        match self.write_stall() {
+            WriteStall::None => return Ok(()),
+            WriteStall::Slowdown(overage) => std::thread::sleep(SLOWDOWN_DELAY_PER_FILE * overage as u32),
+            WriteStall::Stop => {
+                self.schedule();
+                let deadline = timeout.map(|timeout| started + timeout);
+                let mut guard = self.version_lock.lock();
+                while self.write_stall() == WriteStall::Stop && !self.is_shutting_down() {
+                    let wait = match deadline {
+                        Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                            Some(left) if !left.is_zero() => left.min(STOP_POLL_INTERVAL),
+                            _ => {
+                                self.stall_timeouts.fetch_add(1, AtomicOrdering::Relaxed);
+                                result = Err(Error::Concurrency("write stalled".to_string()));
+                                break;
+                            }
+                        },
+                        None => STOP_POLL_INTERVAL,
+                    };
+                    self.version_changed.wait_for(&mut guard, wait);
+                }
+            }
+        }
+        self.stall_micros
+            .fetch_add(started.elapsed().as_micros() as u64, AtomicOrdering::Relaxed);
+        result
+    }
+
+    /// Wake the background worker
+    fn schedule(&self) {
+        *self.pending.lock() = true;
+        self.wakeup.notify_one();
+    }
+
+    /// Stop the background worker and wait for it to exit
+    pub fn shutdown(&self) {
+        self.shutdown.store(true, AtomicOrdering::SeqCst);
+        self.schedule();
+        let handle = self.worker.lock().take();
+        if let Some(handle) = handle {
+            if handle.join().is_err() {
+                error!("AuraDB background worker panicked");
+            }
+        }
+    }
+
+    /// Check every SST file of the current version with
+    /// [`SstReader::verify`]. Each entry read, and each entry still in a
+    /// memtable, is passed to `visit` with the file holding it (`None` for
+    /// memtables). Returns the number of files checked and the damage found.
+    pub fn verify(&self, mut visit: impl FnMut(Option<&Path>, &Entry)) -> (u64, Vec<Corruption>) {
+        let version = {
+            let memtables = self.memtables.read();
+            let immutables = memtables.immutables.iter().map(|immutable| &*immutable.memtable);
+            for memtable in std::iter::once(&*memtables.active).chain(immutables) {
+                for entry in memtable.iter() {
+                    visit(None, &entry);
+                }
+            }
+            self.sst.current()
+        };
+        let mut corruptions = Vec::new();
+        for table in version.files() {
+            corruptions.extend(table.reader().verify(|entry| visit(Some(&table.meta.path), entry)));
+        }
+        (version.files().count() as u64, corruptions)
+    }
+
+    /// Hard-link the live SST files into the checkpoint at `dir` (laid out
+    /// like a database created there), copy the WAL files it still needs
+    /// and save a copy of the manifest naming them. Flushes and compactions
+    /// wait meanwhile, so nothing is deleted or retired halfway. The caller
+    /// holds off writes.
+    pub fn checkpoint(&self, dir: &Path) -> Result<()> {
+        let _flush = self.flush_lock.lock();
+        let _compaction = self.compaction_lock.write();
+        let manifest = self.manifest.lock().clone();
+        let layout = Config::default().with_db_path(dir.to_path_buf());
+
+        std::fs::create_dir_all(&layout.sst.sst_path)?;
+        for file in &manifest.files {
+            std::fs::hard_link(self.sst.file_path(file.id), layout.sst.sst_path.join(sst_file_name(file.id)))?;
+        }
+        crate::io::sync_dir(&layout.sst.sst_path)?;
+
+        std::fs::create_dir_all(&layout.wal.wal_path)?;
+        for entry in std::fs::read_dir(&self.config.wal.wal_path)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if wal::parse_log_number(&name.to_string_lossy()).is_some_and(|n| n >= manifest.log_number) {
+                crate::io::copy_synced(&entry.path(), &layout.wal.wal_path.join(&name))?;
+            }
+        }
+        crate::io::sync_dir(&layout.wal.wal_path)?;
+
+        // Written last: a checkpoint without a manifest is plainly incomplete
+        manifest.save(dir)
+    }
+
+    /// Save the RL agent's policy to the database directory so the next
+    /// open resumes it, and flush any recorded transitions
+    pub fn save_rl_policy(&self) -> Result<()> {
+        if let Some(agent) = &self.rl_agent {
+            let mut agent = agent.lock();
+            agent.flush_recording()?;
+            agent.save(&self.config.db_path.join(RL_POLICY_FILE))?;
+        }
+        Ok(())
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        self.shutdown.load(AtomicOrdering::SeqCst)
+    }
+
+    fn background_loop(&self) {
+        loop {
+            {
+                let mut pending = self.pending.lock();
+                while !*pending {
+                    self.wakeup.wait(&mut pending);
+                }
+                *pending = false;
+            }
+            if self.is_shutting_down() {
+                break;
+            }
+            if let Err(e) = self.background_work() {
+                error!("Background flush/compaction failed: {}", e);
+            }
+        }
+    }
+
+    /// Count an operation towards the flexible strategy's workload window,
+    /// waking the worker once the window is full
+    fn record_op(&self, write: bool) {
+        if !self.compaction.is_flexible() {
+            return;
+        }
+        if write {
+            self.window_writes.fetch_add(1, AtomicOrdering::Relaxed);
+        }
+        let ops = self.window_ops.fetch_add(1, AtomicOrdering::Relaxed) + 1;
+        if ops == self.config.rl_agent.state_update_frequency.max(1) as u64 {
+            self.schedule();
+        }
+    }
+
+    /// Under the flexible strategy, choose leveled or tiered compaction
+    /// for the workload window that just filled up. The RL agent decides
+    /// if `compaction.use_rl_agent` is set, learning from how the last
+    /// choice fared; otherwise write-heavy windows switch to tiered and
+    /// read-heavy ones back to leveled.
+    fn update_compaction_strategy(&self) -> Result<()> {
+        let frequency = self.config.rl_agent.state_update_frequency.max(1) as u64;
+        if !self.compaction.is_flexible() || self.window_ops.load(AtomicOrdering::Relaxed) < frequency {
+            return Ok(());
+        }
+        let ops = self.window_ops.swap(0, AtomicOrdering::Relaxed).max(1);
+        let writes = self.window_writes.swap(0, AtomicOrdering::Relaxed).min(ops);
+        let completed = self.compaction.completed_compactions();
+        let compactions = completed - self.window_compactions.swap(completed, AtomicOrdering::Relaxed);
+        let write_fraction = writes as f64 / ops as f64;
+        let state = AgentState {
+            level0_files: self.sst.current().level(0).len(),
+            write_fraction,
+        };
+
+        let heuristic = if write_fraction >= 0.9 {
+            CompactionStrategy::Tiered
+        } else if write_fraction <= 0.5 {
+            CompactionStrategy::Leveled
+        } else {
+            self.compaction.active_strategy()
+        };
+        let strategy = match self.rl_agent.as_ref().filter(|_| self.config.compaction.use_rl_agent) {
+            Some(agent) => {
+                // Reads pay for every level 0 file, writes for every compaction
+                let reward =
+                    -((1.0 - write_fraction) * state.level0_files as f64 + write_fraction * compactions as f64);
+                let mut agent = agent.lock();
+                agent.observe_state(state)?;
+                agent.update_policy(reward)?;
+                match agent.select_action() {
+                    CompactionStrategy::Flexible => heuristic,
+                    strategy => strategy,
+                }
+            }
+            None => heuristic,
+        };
+        self.compaction.set_active_strategy(strategy);
+        Ok(())
+    }
+
+    /// Flush every sealed memtable, then compact until nothing is due,
+    /// on up to `compaction.max_threads` threads
+    fn background_work(&self) -> Result<()> {
+        self.update_compaction_strategy()?;
+        self.flush_immutables()?;
+        if !self.compact_next()? {
+            return Ok(());
+        }
+        std::thread::scope(|scope| {
+            let helpers: Vec<_> = (1..self.compaction.max_threads())
+                .map(|_| scope.spawn(|| self.compact_until_done()))
+                .collect();
+            let mut result = self.compact_until_done();
+            for helper in helpers {
+                let helper = helper.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                result = result.and(helper);
+            }
+            result
+        })
+    }
+
+    /// Run compactions until none can be picked
+    fn compact_until_done(&self) -> Result<()> {
+        while self.compact_next()? {}
+        Ok(())
+    }
+
+    /// Pick and run one compaction, then flush any sealed memtables.
+    /// Returns false if no compaction could be picked.
+    fn compact_next(&self) -> Result<bool> {
+        if self.is_shutting_down() {
+            return Ok(false);
+        }
+        {
+            let _guard = self.compaction_lock.read();
+            let Some(task) = self.compaction.pick_compaction(&self.sst.current()) else {
+                return Ok(false);
+            };
+            let result = self.run_compaction(&task);
+            self.compaction.finish_task(&task);
+            result?;
+        }
+        self.flush_immutables()?;
+        Ok(true)
+    }
+
+    /// Whether the active memtable holds no entries
+    pub fn active_is_empty(&self) -> bool {
+        self.memtables.read().active.is_empty()
+    }
+
+    /// Flush every sealed memtable to level 0 on the calling thread
+    pub fn flush_immutables(&self) -> Result<()> {
+        while self.flush_oldest()? {}
+        Ok(())
+    }
+
+    /// Compact every file overlapping `[start, end)` down to the deepest
+    /// non-empty level; `None` leaves that side of the range unbounded.
+    /// Runs on the calling thread, even while background compaction is
+    /// paused. Returns the bytes read and written.
+    pub fn compact_range(&self, start: Option<&[u8]>, end: Option<&[u8]>) -> Result<(u64, u64)> {
+        let _guard = self.compaction_lock.write();
+        let in_range = |meta: &SstFile| {
+            start.is_none_or(|start| self.comparator.compare(&meta.largest_key, start) != Ordering::Less)
+                && end.is_none_or(|end| self.comparator.compare(&meta.smallest_key, end) == Ordering::Less)
+        };
+
+        let bottom = {
+            let version = self.sst.current();
+            (1..NUM_LEVELS).rev().find(|&level| !version.level(level).is_empty()).unwrap_or(1)
+        };
+
+        let (mut bytes_read, mut bytes_written) = (0, 0);
+        for level in 0..bottom {
+            let version = self.sst.current();
+            let files = version.level(level);
+            if !files.iter().any(|t| in_range(&t.meta)) {
+                continue;
+            }
+
+            // Level 0 files overlap each other, so they move down together;
+            // otherwise an older file left behind would shadow newer data
+            let inputs: Vec<SstFile> = files
+                .iter()
+                .filter(|t| level == 0 || in_range(&t.meta))
+                .map(|t| t.meta.clone())
+                .collect();
+            let task = self.compaction.manual_task(&version, level, inputs);
+            drop(version);
+
+            let (read, written) = self.run_compaction(&task)?;
+            bytes_read += read;
+            bytes_written += written;
+        }
+        Ok((bytes_read, bytes_written))
+    }
+
+    /// Read an external SST file in full, verifying its checksums, that its
+    /// keys are strictly ascending in this tree's order, and that every
+    /// value is inline (the database's value log can't resolve pointers
+    /// from elsewhere)
+    pub fn validate_external_sst(&self, path: &Path) -> Result<ExternalSst> {
+        let reader = SstReader::open(path, FileAccess::Buffered)?;
+        let entries = reader.entries()?;
+        let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+            return Err(Error::Config(format!("Cannot ingest empty SST {}", path.display())));
+        };
+
+        for pair in entries.windows(2) {
+            if self.comparator.compare(&pair[0].key.data, &pair[1].key.data) != Ordering::Less {
+                return Err(Error::SstCorruption(format!(
+                    "Keys out of order in {} for comparator {}",
+                    path.display(),
+                    self.comparator.name()
+                )));
+            }
+        }
+        if entries.iter().any(|e| e.value_pointer.is_some()) {
+            return Err(Error::Config(format!(
+                "Cannot ingest {}: it holds value log pointers",
+                path.display()
+            )));
+        }
+
+        Ok(ExternalSst {
+            path: path.to_path_buf(),
+            smallest_key: first.key.data.clone(),
+            largest_key: last.key.data.clone(),
+            entry_count: entries.len() as u64,
+            deletion_count: entries.iter().filter(|e| e.is_delete()).count() as u64,
+            max_sequence: entries.iter().map(|e| e.sequence).max().unwrap_or(0),
+            bloom_fpr: reader.bloom_fpr(),
+        })
+    }
+
+    /// Whether any memtable holds a key, or a range tombstone covering
+    /// one, in `[smallest, largest]`
+    pub fn memtables_overlap(&self, smallest: &[u8], largest: &[u8]) -> bool {
+        let in_range = |entry: &Entry| {
+            self.comparator.compare(&entry.key.data, smallest) != Ordering::Less
+                && self.comparator.compare(&entry.key.data, largest) != Ordering::Greater
+        };
+        let overlaps = |tombstone: &RangeTombstone| {
+            self.comparator.compare(&tombstone.start.data, largest) != Ordering::Greater
+                && self.comparator.compare(&tombstone.end.data, smallest) == Ordering::Greater
+        };
+        let memtables = self.memtables.read();
+        std::iter::once(&*memtables.active)
+            .chain(memtables.immutables.iter().map(|m| &*m.memtable))
+            .any(|memtable| memtable.iter().any(|e| in_range(&e)) || memtable.range_tombstones().iter().any(overlaps))
+    }
+
+    /// Add validated external files to the tree in one manifest edit. The
+    /// caller must have flushed any memtable data they overlap and must
+    /// keep writes out until this returns.
+    ///
+    /// A file that overlaps nothing is hard-linked (or copied) into the
+    /// bottom level as is. Otherwise it goes just above the shallowest
+    /// level it overlaps, or into level 0, and is rewritten with every
+    /// entry at `sequence` so it supersedes the data it covers.
+    pub fn ingest(&self, mut files: Vec<ExternalSst>, sequence: u64) -> Result<()> {
+        self.sort_external(&mut files)?;
+
+        let _guard = self.compaction_lock.write();
+        let version = self.sst.current();
+        let mut added = Vec::with_capacity(files.len());
+        let mut last_sequence = 0;
+        for file in &files {
+            let overlapping = (0..NUM_LEVELS).find(|&level| {
+                !version
+                    .overlapping(level, &file.smallest_key, &file.largest_key, &self.comparator)
+                    .is_empty()
+            });
+            let meta = match overlapping {
+                None => {
+                    last_sequence = last_sequence.max(file.max_sequence);
+                    self.link_sst(file, NUM_LEVELS as u32 - 1)?
+                }
+                Some(level) => {
+                    last_sequence = last_sequence.max(sequence);
+                    let entries = SstReader::open(&file.path, FileAccess::Buffered)?.entries()?;
+                    let rewritten = entries.into_iter().map(|mut entry| {
+                        entry.sequence = sequence;
+                        entry
+                    });
+                    self.write_sst(rewritten, &[], level.saturating_sub(1) as u32)?
+                }
+            };
+            debug!("Ingested {} into level {}", file.path.display(), meta.level);
+            added.push(meta);
+        }
+        drop(version);
+
+        self.commit(VersionEdit { added, removed: Vec::new() }, |manifest| {
+            manifest.last_sequence = manifest.last_sequence.max(last_sequence);
+        })
+    }
+
+    /// Replace every key starting with `prefix` (every key for `None`)
+    /// with the entries of validated external files, in one manifest edit.
+    /// The caller must have flushed every memtable and must keep writes
+    /// out until this returns.
+    ///
+    /// The files are merged and rewritten with every entry at
+    /// `sequence + 1`, along with a range tombstone at `sequence` over the
+    /// replaced keys, into the level just above the shallowest one they
+    /// overlap. Older data in the range is shadowed once the edit commits
+    /// and reclaimed by later compactions. A range with no key past it
+    /// ends at the tree's largest key, which a point tombstone covers.
+    /// Prefixes need the bytewise comparator, which keeps the keys sharing
+    /// one together, and every ingested key must start with the prefix.
+    pub fn ingest_replacing(&self, mut files: Vec<ExternalSst>, prefix: Option<&[u8]>, sequence: u64) -> Result<()> {
+        if prefix.is_some() && self.comparator.name() != Comparator::BYTEWISE {
+            return Err(Error::Config("Replacing a key prefix requires the bytewise comparator".to_string()));
+        }
+        self.sort_external(&mut files)?;
+
+        let mut entries = Vec::new();
+        for file in &files {
+            for mut entry in SstReader::open(&file.path, FileAccess::Buffered)?.entries()? {
+                if prefix.is_some_and(|prefix| !entry.key.data.starts_with(prefix)) {
+                    return Err(Error::Config(format!(
+                        "Cannot ingest {}: it holds keys outside the replaced prefix",
+                        file.path.display()
+                    )));
+                }
+                entry.sequence = sequence + 1;
+                entries.push(entry);
+            }
+        }
+
+        let _guard = self.compaction_lock.write();
+        let version = self.sst.current();
+        let by_key = |a: &&Vec<u8>, b: &&Vec<u8>| self.comparator.compare(a, b);
+        let largest = version.files().map(|t| &t.meta.largest_key).max_by(by_key).cloned();
+        let (start, end) = match prefix {
+            Some(prefix) => (Some(prefix.to_vec()), prefix_successor(prefix)),
+            None => (version.files().map(|t| &t.meta.smallest_key).min_by(by_key).cloned(), None),
+        };
+        let mut tombstones = Vec::new();
+        let mut last = Vec::new();
+        if let Some(start) = start {
+            let end = match (end, largest) {
+                (Some(end), _) => Some(end),
+                (None, Some(largest)) if self.comparator.compare(&largest, &start) != Ordering::Less => {
+                    last.push(Entry::delete(Key::new(largest.clone()), sequence));
+                    Some(largest)
+                }
+                (None, _) => None,
+            };
+            if let Some(end) = end {
+                let tombstone = RangeTombstone::new(Key::new(start), Key::new(end), sequence);
+                if !tombstone.is_empty(&self.comparator) {
+                    tombstones.push(tombstone);
+                }
+            }
+        }
+
+        // Above every level holding a key the files or the tombstones touch
+        let bounds = entries
+            .iter()
+            .chain(&last)
+            .map(|e| &e.key.data)
+            .chain(tombstones.iter().flat_map(|t| [&t.start.data, &t.end.data]));
+        let (smallest, largest) = (bounds.clone().min_by(by_key), bounds.max_by(by_key));
+        let level = match smallest.zip(largest) {
+            Some((smallest, largest)) => (0..NUM_LEVELS)
+                .find(|&level| !version.overlapping(level, smallest, largest, &self.comparator).is_empty())
+                .map_or(NUM_LEVELS - 1, |level| level.saturating_sub(1)),
+            None => return Ok(()),
+        };
+        drop(version);
+
+        let merged = MergeIterator::new([entries.into_iter(), last.into_iter()], &self.comparator);
+        let added = self.write_ssts(merged, &tombstones, level as u32, |_| {})?;
+        debug!("Ingested {} files replacing their key range into level {}", files.len(), level);
+        self.commit(VersionEdit { added, removed: Vec::new() }, |manifest| {
+            manifest.last_sequence = manifest.last_sequence.max(sequence + 1);
+        })
+    }
+
+    /// Sort external files by key, rejecting any that overlap
+    fn sort_external(&self, files: &mut [ExternalSst]) -> Result<()> {
+        files.sort_by(|a, b| self.comparator.compare(&a.smallest_key, &b.smallest_key));
+        for pair in files.windows(2) {
+            if self.comparator.compare(&pair[0].largest_key, &pair[1].smallest_key) != Ordering::Less {
+                return Err(Error::Config(format!(
+                    "Cannot ingest overlapping files {} and {}",
+                    pair[0].path.display(),
+                    pair[1].path.display()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Hard-link an external file into the SST directory, copying it when
+    /// linking fails (e.g. across filesystems)
+    fn link_sst(&self, file: &ExternalSst, level: u32) -> Result<SstFile> {
+        let id = {
+            let mut manifest = self.manifest.lock();
+            manifest.next_file_number += 1;
+            manifest.next_file_number - 1
+        };
+        let path = self.sst.file_path(id);
+        if std::fs::hard_link(&file.path, &path).is_err() {
+            std::fs::copy(&file.path, &path)?;
+            std::fs::File::open(&path)?.sync_all()?;
+        }
+        crate::io::sync_parent_dir(&path)?;
+
+        Ok(SstFile {
+            id,
+            size: std::fs::metadata(&path)?.len(),
+            path,
+            level,
+            entry_count: file.entry_count,
+            deletion_count: file.deletion_count,
+            smallest_key: file.smallest_key.clone(),
+            largest_key: file.largest_key.clone(),
+            bloom_fpr: file.bloom_fpr,
+        })
+    }
+
+    /// Flush the oldest sealed memtable to level 0. Returns false if there
+    /// was nothing to flush.
+    fn flush_oldest(&self) -> Result<bool> {
+        let _guard = self.flush_lock.lock();
+        let (memtable, next_log_number) = match self.memtables.read().immutables.front() {
+            Some(immutable) => (Arc::clone(&immutable.memtable), immutable.next_log_number),
+            None => return Ok(false),
+        };
+        let span = debug_span!("flush", entries = memtable.len(), bytes_written = field::Empty, duration_us = field::Empty);
+        in_timed_span(&span, || self.flush_memtable(&memtable, next_log_number))?;
+        Ok(true)
+    }
+
+    /// Write `memtable`, the oldest sealed memtable, to level 0 and retire
+    /// the WAL files it covered
+    fn flush_memtable(&self, memtable: &Memtable, next_log_number: u64) -> Result<()> {
+
+        let added = if memtable.is_empty() {
+            Vec::new()
+        } else {
+            vec![self.write_sst(memtable.iter(), &memtable.range_tombstones(), 0)?]
+        };
+        let last_sequence = memtable
+            .iter()
+            .map(|e| e.sequence)
+            .chain(memtable.range_tombstones().iter().map(|t| t.sequence))
+            .max()
+            .unwrap_or(0);
+        let flushed: u64 = added.iter().map(|f| f.size).sum();
+        Span::current().record("bytes_written", flushed);
+        self.bytes.flush_written.fetch_add(flushed, AtomicOrdering::Relaxed);
+        debug!("Flushed memtable of {} entries to level 0", memtable.len());
+
+        self.commit(VersionEdit { added, removed: Vec::new() }, |manifest| {
+            manifest.log_number = manifest.log_number.max(next_log_number);
+            manifest.last_sequence = manifest.last_sequence.max(last_sequence);
+        })?;
+        self.memtables.write().immutables.pop_front();
+        {
+            let _guard = self.version_lock.lock();
+            self.version_changed.notify_all();
+        }
+        wal::retire_logs_before(&self.config.wal, next_log_number)?;
+        Ok(())
+    }
+
+    /// Merge the task's inputs into files of about `sst.target_file_size`
+    /// in the target level, dropping the keys their range tombstones
+    /// cover, and all tombstones if no deeper level overlaps them.
+    /// The caller holds `compaction_lock`, exclusively unless the task came
+    /// from the compaction manager. Returns the bytes read and written.
+    fn run_compaction(&self, task: &CompactionTask) -> Result<(u64, u64)> {
+        let span = debug_span!(
+            "compaction",
+            source_level = task.source_level,
+            target_level = task.target_level,
+            input_files = task.input_files.len(),
+            bytes_read = field::Empty,
+            bytes_written = field::Empty,
+            duration_us = field::Empty
+        );
+        let tracker = self.compaction.start_tracking(task);
+        let result = in_timed_span(&span, || self.merge_task(task, &tracker));
+        self.compaction.stop_tracking(&tracker, result.is_ok());
+        result
+    }
+
+    /// Body of [`Self::run_compaction`]
+    fn merge_task(&self, task: &CompactionTask, tracker: &CompactionTracker) -> Result<(u64, u64)> {
+        let removed = task.input_ids();
+        let version = self.sst.current();
+        let error = ErrorSlot::default();
+        let mut inputs = Vec::with_capacity(removed.len());
+        let mut tombstones = Vec::new();
+        for table in version.files().filter(|t| removed.contains(&t.meta.id)) {
+            inputs.push(until_error(table.reader().iter_from(None), &error).inspect(|_| tracker.add_processed()));
+            tombstones.extend_from_slice(table.reader().range_tombstones());
+            tracker.add_read(table.meta.size);
+        }
+        // Only compactions holding a level between the target and the
+        // deeper levels could add to them, and those conflict with this one
+        let bottommost = self.compaction.is_bottommost(&version, task);
+
+        let skyline = RangeDelSkyline::new(tombstones.iter().cloned(), &self.comparator);
+        let mut merged = MergeIterator::new(inputs, &self.comparator).with_range_tombstones(skyline);
+        // At the bottom nothing older is left for a tombstone to hide. Live
+        // snapshots read the pinned input files, not the outputs.
+        if bottommost {
+            merged = merged.skip_tombstones();
+            tombstones.clear();
+        }
+        let added = self.write_ssts(merged, &tombstones, task.target_level, |file| tracker.add_output(file.size))?;
+        // An input that failed to read ended early, leaving its remaining
+        // keys out of the outputs
+        if let Some(e) = error.take() {
+            for file in &added {
+                std::fs::remove_file(&file.path).ok();
+            }
+            return Err(e);
+        }
+        debug!(
+            "Compacted {} files from level {} into {} in level {}",
+            removed.len(),
+            task.source_level,
+            added.len(),
+            task.target_level
+        );
+
+        let bytes_read = task.input_files.iter().map(|f| f.size).sum();
+        let bytes_written = added.iter().map(|f| f.size).sum();
+        self.commit(VersionEdit { added, removed }, |_| {})?;
+        Span::current().record("bytes_read", bytes_read).record("bytes_written", bytes_written);
+        self.compaction.record_completed(bytes_read, bytes_written);
+        Ok((bytes_read, bytes_written))
+    }
+
+    /// Write sorted entries and range tombstones to new SST files at
+    /// `level`, starting the next file once one reaches
+    /// `sst.target_file_size`. Entries hold distinct keys, so files never
+    /// share a key; each file keeps the part of every tombstone up to the
+    /// next file's first key. No file is written for neither entries nor
+    /// tombstones. `finished` is called with each file once written.
+    fn write_ssts(
+        &self,
+        entries: impl Iterator<Item = Entry>,
+        tombstones: &[RangeTombstone],
+        level: u32,
+        mut finished: impl FnMut(&SstFile),
+    ) -> Result<Vec<SstFile>> {
+        let target_size = self.sst_config.read().target_file_size;
+        let mut entries = entries.peekable();
+        let mut files = Vec::new();
+        let mut lower = None;
+        while entries.peek().is_some() || (files.is_empty() && !tombstones.is_empty()) {
+            let file = self.write_sst_until(&mut entries, tombstones, lower.as_deref(), level, target_size)?;
+            finished(&file);
+            files.push(file);
+            lower = entries.peek().map(|e| e.key.data.clone());
+        }
+        Ok(files)
+    }
+
+    /// Write sorted entries and range tombstones to a new SST file at `level`
+    fn write_sst(
+        &self,
+        entries: impl Iterator<Item = Entry>,
+        tombstones: &[RangeTombstone],
+        level: u32,
+    ) -> Result<SstFile> {
+        self.write_sst_until(&mut entries.peekable(), tombstones, None, level, u64::MAX)
+    }
+
+    /// Write sorted entries to a new SST file at `level` until it reaches
+    /// `max_size` bytes, leaving the rest in `entries`, along with the part
+    /// of each range tombstone from `lower` (`None` for unbounded) up to
+    /// the first entry left
+    fn write_sst_until<I: Iterator<Item = Entry>>(
+        &self,
+        entries: &mut std::iter::Peekable<I>,
+        tombstones: &[RangeTombstone],
+        lower: Option<&[u8]>,
+        level: u32,
+        max_size: u64,
+    ) -> Result<SstFile> {
+        let id = {
+            let mut manifest = self.manifest.lock();
+            manifest.next_file_number += 1;
+            manifest.next_file_number - 1
+        };
+        let path = self.sst.file_path(id);
+        let mut writer = {
+            let config = self.sst_config.read();
+            SstWriter::new(&path, &config, self.sst.access())?
+                .with_compression(config.compression_for_level(level))
+                .with_comparator(self.comparator.clone())
+        };
+        let mut logical = 0;
+        for entry in entries.by_ref() {
+            logical += user_bytes(&entry);
+            writer.add(&entry)?;
+            if writer.estimated_size() >= max_size {
+                break;
+            }
+        }
+        let upper = entries.peek().map(|e| e.key.data.clone());
+        for tombstone in tombstones {
+            if let Some(clipped) = tombstone.clip(lower, upper.as_deref(), &self.comparator) {
+                writer.add_range_tombstone(clipped);
+            }
+        }
+        let mut file = writer.finish()?;
+        self.bytes.sst_logical_written.fetch_add(logical, AtomicOrdering::Relaxed);
+        self.bytes.sst_physical_written.fetch_add(file.size, AtomicOrdering::Relaxed);
+        file.id = id;
+        file.level = level;
+        Ok(file)
+    }
+
+    /// Install a version edit and persist it to the manifest
+    fn commit(&self, edit: VersionEdit, update: impl FnOnce(&mut Manifest)) -> Result<()> {
+        let mut manifest = self.manifest.lock();
+        self.sst.apply(edit)?;
+        manifest.files = self.sst.all_files();
+        update(&mut manifest);
+        manifest.save(&self.config.db_path)?;
+        drop(manifest);
+
+        let _guard = self.version_lock.lock();
+        self.version_changed.notify_all();
+        Ok(())
+    }
+}
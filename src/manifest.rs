@@ -0,0 +1,254 @@
+//! Database manifest
+//!
+//! The manifest records the invariants a database was created with (its
+//! comparator and [`CreationSettings`]) and the current set of SST files, along with the WAL log number and sequence up
+//! to which their contents are durable. It is stored as JSON in `<db_path>/MANIFEST` and replaced atomically by
+//! writing a temporary file and renaming it over the old one.
+//!
+//! The manifest's `version` is the database's on-disk format version. A
+//! build opens databases from [`MIN_MANIFEST_VERSION`] up to
+//! [`MANIFEST_VERSION`]; newer ones are refused before anything else in
+//! the manifest is read, since their layout is unknown, and older ones
+//! must first be upgraded with [`crate::migrate::migrate`].
+
+use crate::config::{ChecksumType, Config};
+use crate::error::{Error, Result};
+use crate::sst::SstFile;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Manifest file name inside the database directory
+pub const MANIFEST_FILE: &str = "MANIFEST";
+
+/// Current manifest format version (see [`crate::migrate`] for the
+/// history)
+pub const MANIFEST_VERSION: u32 = 2;
+
+/// Oldest manifest format version this build opens without migration
+pub const MIN_MANIFEST_VERSION: u32 = 2;
+
+/// The one field every manifest version shares
+#[derive(Deserialize)]
+struct VersionStamp {
+    version: u32,
+}
+
+/// Persistent database metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Manifest format version
+    pub version: u32,
+    /// Name of the key comparator
+    pub comparator: String,
+    /// Settings fixed at creation; absent in manifests written before they
+    /// were recorded, which adopt the settings they are next opened with
+    #[serde(default)]
+    pub settings: Option<CreationSettings>,
+    /// Next SST file number to allocate
+    #[serde(default = "first_file_number")]
+    pub next_file_number: u64,
+    /// Highest sequence number flushed to SST files
+    #[serde(default)]
+    pub last_sequence: u64,
+    /// WAL files numbered below this are fully flushed
+    #[serde(default)]
+    pub log_number: u64,
+    /// Live SST files with their levels
+    #[serde(default)]
+    pub files: Vec<SstFile>,
+}
+
+fn first_file_number() -> u64 {
+    1
+}
+
+impl Manifest {
+    /// Create a manifest for a new database
+    pub fn new(comparator: impl Into<String>) -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            comparator: comparator.into(),
+            settings: None,
+            next_file_number: first_file_number(),
+            last_sequence: 0,
+            log_number: 0,
+            files: Vec::new(),
+        }
+    }
+
+    /// Record the creation settings
+    pub fn with_settings(mut self, settings: CreationSettings) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
+    /// Fail with an error naming every creation-time setting `config`
+    /// changes
+    pub fn check_config(&self, config: &Config) -> Result<()> {
+        let mut mismatches = Vec::new();
+        if self.comparator != config.comparator.name() {
+            mismatches.push(format!(
+                "comparator (database uses '{}', configured '{}')",
+                self.comparator,
+                config.comparator.name()
+            ));
+        }
+        if let Some(settings) = &self.settings {
+            mismatches.extend(settings.mismatches(&CreationSettings::from_config(config)));
+        }
+        if mismatches.is_empty() {
+            return Ok(());
+        }
+        Err(Error::Config(format!(
+            "Database at {} was created with different settings: {}",
+            config.db_path.display(),
+            mismatches.join("; ")
+        )))
+    }
+
+    /// Load the manifest from `db_path`, if one exists, failing if this
+    /// build cannot open its format version
+    pub fn load(db_path: &Path) -> Result<Option<Self>> {
+        let path = db_path.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read(&path)?;
+        let stamp: VersionStamp = serde_json::from_slice(&data)
+            .map_err(|e| Error::Config(format!("Invalid manifest {}: {}", path.display(), e)))?;
+        check_version(stamp.version, db_path)?;
+        Self::decode(&data, &path).map(Some)
+    }
+
+    /// Load the manifest from `db_path` whatever its format version, for
+    /// migration. Versions up to the current one share a layout.
+    pub(crate) fn load_any_version(db_path: &Path) -> Result<Option<Self>> {
+        let path = db_path.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::decode(&fs::read(&path)?, &path).map(Some)
+    }
+
+    /// Parse a manifest read from `path`
+    fn decode(data: &[u8], path: &Path) -> Result<Self> {
+        serde_json::from_slice(data).map_err(|e| Error::Config(format!("Invalid manifest {}: {}", path.display(), e)))
+    }
+
+    /// Atomically write the manifest to `db_path`
+    pub fn save(&self, db_path: &Path) -> Result<()> {
+        let tmp_path = db_path.join(format!("{}.tmp", MANIFEST_FILE));
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::Config(format!("Failed to encode manifest: {}", e)))?;
+
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, db_path.join(MANIFEST_FILE))?;
+        crate::io::sync_dir(db_path)?;
+        Ok(())
+    }
+}
+
+/// Settings a database keeps for its lifetime. Reopening with different
+/// ones is refused rather than mixing formats within one database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreationSettings {
+    /// Checksum algorithm of WAL files
+    pub wal_checksum: ChecksumType,
+    /// Checksum algorithm of value log segments
+    pub value_log_checksum: ChecksumType,
+    /// Checksum algorithm of SST files
+    pub sst_checksum: ChecksumType,
+    /// Value size at which values are separated into the value log
+    pub separation_threshold: usize,
+}
+
+impl CreationSettings {
+    /// The creation settings `config` asks for
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            wal_checksum: config.wal.checksum,
+            value_log_checksum: config.value_log.checksum,
+            sst_checksum: config.sst.checksum,
+            separation_threshold: config.value_log.separation_threshold,
+        }
+    }
+
+    /// Describe each setting `other` differs in, named as in `Config`
+    pub fn mismatches(&self, other: &Self) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        let checksums = [
+            ("wal.checksum", self.wal_checksum, other.wal_checksum),
+            ("value_log.checksum", self.value_log_checksum, other.value_log_checksum),
+            ("sst.checksum", self.sst_checksum, other.sst_checksum),
+        ];
+        for (name, stored, configured) in checksums {
+            if stored != configured {
+                mismatches.push(format!("{} (database uses {:?}, configured {:?})", name, stored, configured));
+            }
+        }
+        if self.separation_threshold != other.separation_threshold {
+            mismatches.push(format!(
+                "value_log.separation_threshold (database uses {}, configured {})",
+                self.separation_threshold, other.separation_threshold
+            ));
+        }
+        mismatches
+    }
+}
+
+/// Fail unless this build can open a database of format `version`
+fn check_version(version: u32, db_path: &Path) -> Result<()> {
+    if version > MANIFEST_VERSION {
+        return Err(Error::Config(format!(
+            "Database at {} has format version {}, newer than the {} this build supports; upgrade AuraDB to open it",
+            db_path.display(),
+            version,
+            MANIFEST_VERSION
+        )));
+    }
+    if version < MIN_MANIFEST_VERSION {
+        return Err(Error::Config(format!(
+            "Database at {} has format version {}, older than the {} this build opens; upgrade it with AuraEngine::migrate",
+            db_path.display(),
+            version,
+            MIN_MANIFEST_VERSION
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        assert!(Manifest::load(temp_dir.path()).unwrap().is_none());
+
+        let mut manifest = Manifest::new("auradb.BytewiseComparator");
+        manifest.next_file_number = 7;
+        manifest.log_number = 3;
+        manifest.save(temp_dir.path()).unwrap();
+
+        let loaded = Manifest::load(temp_dir.path()).unwrap().unwrap();
+        assert_eq!(loaded.comparator, manifest.comparator);
+        assert_eq!(loaded.next_file_number, 7);
+        assert_eq!(loaded.log_number, 3);
+    }
+
+    #[test]
+    fn test_newer_format_is_refused() {
+        let temp_dir = tempdir().unwrap();
+        let mut manifest = Manifest::new("auradb.BytewiseComparator");
+        manifest.version = MANIFEST_VERSION + 1;
+        manifest.save(temp_dir.path()).unwrap();
+        let err = Manifest::load(temp_dir.path()).unwrap_err().to_string();
+        assert!(err.contains("newer"), "{}", err);
+    }
+}
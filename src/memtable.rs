@@ -1,27 +1,50 @@
-use crate::error::{Error, Result};
-use crate::storage::{Entry, Key, Value, ValuePointer};
-use crossbeam::epoch::{self, Atomic, Owned, Shared};
+use crate::comparator::Comparator;
+use crate::error::Result;
+use crate::storage::{self, Entry, Key, RangeTombstone, ValuePointer};
+use crossbeam::epoch::{self, Atomic, Guard, Owned, Shared};
 use parking_lot::RwLock;
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
-use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
-use tracing::{debug, trace};
+use std::ops::Bound;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 
-/// Memtable implementation trait
+/// Memtable implementation trait.
+///
+/// Writing a key again keeps its older versions: lookups see the version
+/// with the highest sequence number at or below the sequence they read
+/// at, so a reader can ignore writes newer than its view while they are
+/// inserted.
 pub trait MemtableImpl: Send + Sync {
-    /// Insert an entry into the memtable
-    fn insert(&mut self, entry: Entry) -> Result<()>;
-    
-    /// Get an entry by key
-    fn get(&self, key: &Key) -> Result<Option<Entry>>;
+    /// Insert an entry as a new version of its key. Safe to call from
+    /// several threads at once.
+    fn insert(&self, entry: Entry) -> Result<()>;
+
+    /// Get the newest entry by key
+    fn get(&self, key: &Key) -> Result<Option<Entry>> {
+        self.get_at(key, u64::MAX)
+    }
+
+    /// Get the newest entry by key with a sequence number at or below
+    /// `sequence`
+    fn get_at(&self, key: &Key, sequence: u64) -> Result<Option<Entry>>;
     
-    /// Delete an entry by key
-    fn delete(&mut self, key: &Key, sequence: u64) -> Result<()>;
+    /// Record a tombstone for a key
+    fn delete(&self, key: &Key, sequence: u64) -> Result<()> {
+        self.insert(Entry::delete(key.clone(), sequence))
+    }
     
-    /// Get all entries in sorted order
-    fn iter(&self) -> Box<dyn Iterator<Item = Entry> + '_>;
+    /// Get the newest entry of every key in comparator order
+    fn iter(&self) -> Box<dyn Iterator<Item = Entry> + '_> {
+        self.iter_from(None, u64::MAX)
+    }
+
+    /// Get, in comparator order from the first key at or after `start`
+    /// (from the first key if `None`), the newest entry of every key with
+    /// a sequence number at or below `sequence`; keys with only newer
+    /// versions are skipped
+    fn iter_from(&self, start: Option<&[u8]>, sequence: u64) -> Box<dyn Iterator<Item = Entry> + '_>;
     
-    /// Get the number of entries
+    /// Get the number of keys
     fn len(&self) -> usize;
     
     /// Check if memtable is empty
@@ -34,205 +57,316 @@ pub trait MemtableImpl: Send + Sync {
     fn clear(&mut self);
 }
 
-/// Skip list node for lock-free memtable
-#[derive(Debug, Clone)]
-struct SkipListNode {
-    /// Entry data
+/// Approximate memory footprint of an entry
+fn entry_size(entry: &Entry) -> usize {
+    entry.key.len()
+        + entry.value.as_ref().map_or(0, |v| v.len())
+        + entry.value_pointer.as_ref().map_or(0, |_| std::mem::size_of::<ValuePointer>())
+        + std::mem::size_of::<Entry>()
+}
+
+/// Maximum skip list height
+const MAX_LEVEL: usize = 12;
+
+/// One version of a key, linked to the next older one
+struct EntryVersion {
     entry: Entry,
-    /// Next pointers at different levels
-    next: Vec<Atomic<SkipListNode>>,
-    /// Node level
-    level: usize,
+    /// Version with the next lower sequence number, if any
+    older: Atomic<EntryVersion>,
 }
 
-impl SkipListNode {
-    /// Create a new skip list node
-    fn new(entry: Entry, level: usize) -> Self {
-        let mut next = Vec::with_capacity(level + 1);
-        for _ in 0..=level {
-            next.push(Atomic::null());
-        }
-        
-        Self {
-            entry,
-            next,
-            level,
-        }
-    }
-    
-    /// Get the next node at a specific level
-    fn next_at(&self, level: usize) -> &Atomic<SkipListNode> {
-        &self.next[level]
-    }
+/// Skip list node
+struct SkipListNode {
+    /// Key of the entry, fixed for the node's lifetime
+    key: Vec<u8>,
+    /// Versions of the entry, highest sequence number first
+    versions: Atomic<EntryVersion>,
+    /// Next node at each level this node participates in
+    next: Box<[Atomic<SkipListNode>]>,
 }
 
-/// Lock-free skip list memtable implementation
+/// Lock-free skip list memtable implementation.
+///
+/// Inserts take `&self` and may run concurrently: a new node is linked
+/// into each level with a compare-and-swap on its predecessor's link,
+/// searching again when another insert got there first. Writing a key
+/// again links a new version into the node's version list, by sequence
+/// number, the same way. Neither nodes nor versions are unlinked while the
+/// list is shared, only freed by [`MemtableImpl::clear`] and drop, which
+/// need `&mut self`, so readers follow links without pinning.
 pub struct SkipListMemtable {
-    /// Head node
-    head: Atomic<SkipListNode>,
-    /// Maximum level
-    max_level: usize,
-    /// Current number of entries
-    entry_count: AtomicU64,
+    /// First node at each level
+    head: [Atomic<SkipListNode>; MAX_LEVEL],
+    /// Key ordering
+    comparator: Comparator,
+    /// Number of nodes
+    len: AtomicUsize,
     /// Memory usage estimate
-    memory_usage: AtomicU64,
+    memory_usage: AtomicUsize,
 }
 
+/// Last node before a key at every level (`None` for the head), and the
+/// link each of them holds
+type Position<'a> = ([Option<&'a SkipListNode>; MAX_LEVEL], [Shared<'a, SkipListNode>; MAX_LEVEL]);
+
 impl SkipListMemtable {
-    /// Create a new skip list memtable
+    /// Create a new skip list memtable ordered bytewise
     pub fn new() -> Self {
-        // Create head node with maximum level
-        let head_entry = Entry::new(
-            Key::new(vec![]), // Empty key as sentinel
-            Value::new(vec![]),
-            0,
-        );
-        let head = Owned::new(SkipListNode::new(head_entry, 32));
-        
+        Self::with_comparator(Comparator::default())
+    }
+
+    /// Create a new skip list memtable with a custom key order
+    pub fn with_comparator(comparator: Comparator) -> Self {
         Self {
-            head: Atomic::from(head),
-            max_level: 32,
-            entry_count: AtomicU64::new(0),
-            memory_usage: AtomicU64::new(0),
+            head: std::array::from_fn(|_| Atomic::null()),
+            comparator,
+            len: AtomicUsize::new(0),
+            memory_usage: AtomicUsize::new(0),
         }
     }
     
-    /// Generate a random level for new nodes
-    fn random_level(&self) -> usize {
-        let mut level = 0;
-        let mut rng = fastrand::Rng::new();
-        
-        while level < self.max_level && rng.u64(..100) < 25 {
+    /// Generate a random level for new nodes (p = 1/4)
+    fn random_level() -> usize {
+        let mut level = 1;
+        while level < MAX_LEVEL && fastrand::u32(..4) == 0 {
             level += 1;
         }
-        
         level
     }
-    
-    /// Find the node with the given key and its predecessors
-    fn find_node(&self, key: &Key) -> (Vec<Shared<SkipListNode>>, Vec<Shared<SkipListNode>>) {
-        let mut preds = Vec::with_capacity(self.max_level + 1);
-        let mut currs = Vec::with_capacity(self.max_level + 1);
-        
-        // Initialize with head
-        for _ in 0..=self.max_level {
-            preds.push(Shared::null());
-            currs.push(Shared::null());
+
+    /// Guard for following links. Nodes and versions outlive every shared
+    /// borrow of the list, so links need no epoch protection.
+    fn links() -> &'static Guard {
+        // SAFETY: nodes and versions are only freed through `&mut self`,
+        // when no other reference to the list, and so to them, exists
+        unsafe { epoch::unprotected() }
+    }
+
+    /// Link following `pred` at `level` (the head when `pred` is `None`)
+    fn link<'a>(&'a self, pred: Option<&'a SkipListNode>, level: usize) -> &'a Atomic<SkipListNode> {
+        match pred {
+            Some(node) => &node.next[level],
+            None => &self.head[level],
         }
-        
-        let guard = epoch::pin();
-        let mut pred = self.head.load(AtomicOrdering::Acquire, &guard);
-        
-        // Search from top level down
-        for level in (0..=self.max_level).rev() {
-            let mut curr = pred;
-            
-            // Traverse at current level
-            while let Some(curr_ref) = curr.as_ref() {
-                let next = curr_ref.next_at(level).load(AtomicOrdering::Acquire, &guard);
-                
-                if let Some(next_ref) = next.as_ref() {
-                    match next_ref.entry.key.cmp(key) {
-                        Ordering::Less => {
-                            pred = next;
-                            curr = next;
-                        }
-                        Ordering::Equal => {
-                            preds[level] = pred;
-                            currs[level] = curr;
-                            break;
-                        }
-                        Ordering::Greater => {
-                            break;
-                        }
+    }
+
+    /// Node a link points to
+    fn node<'a>(&'a self, link: Shared<'_, SkipListNode>) -> Option<&'a SkipListNode> {
+        // SAFETY: see `links`; the node lives as long as the borrow of `self`
+        unsafe { link.as_raw().as_ref() }
+    }
+
+    /// Version a link points to
+    fn version<'a>(&'a self, link: Shared<'_, EntryVersion>) -> Option<&'a EntryVersion> {
+        // SAFETY: see `links`; versions live as long as their node
+        unsafe { link.as_raw().as_ref() }
+    }
+
+    /// Find the last node before `key` at every level and its successor
+    fn find_position(&self, key: &[u8]) -> Position<'_> {
+        let mut preds = [None; MAX_LEVEL];
+        let mut succs = [Shared::null(); MAX_LEVEL];
+        let mut pred = None;
+        for level in (0..MAX_LEVEL).rev() {
+            loop {
+                let next = self.link(pred, level).load(AtomicOrdering::Acquire, Self::links());
+                match self.node(next) {
+                    Some(node) if self.comparator.compare(&node.key, key) == Ordering::Less => pred = Some(node),
+                    _ => {
+                        succs[level] = next;
+                        break;
                     }
-                } else {
-                    break;
                 }
             }
-            
             preds[level] = pred;
-            currs[level] = curr;
         }
-        
-        (preds, currs)
+        (preds, succs)
     }
-}
 
-impl MemtableImpl for SkipListMemtable {
-    fn insert(&mut self, entry: Entry) -> Result<()> {
-        let level = self.random_level();
-        let new_node = Owned::new(SkipListNode::new(entry.clone(), level));
-        
-        let guard = epoch::pin();
-        let (preds, currs) = self.find_node(&entry.key);
-        
-        // Check if key already exists
-        if let Some(curr) = currs[0].as_ref() {
-            if curr.entry.key == entry.key {
-                // Update existing entry
-                // In a real implementation, you'd want to handle this more carefully
+    /// Node holding `key`, if `succ` is it
+    fn matching<'a>(&'a self, succ: Shared<'_, SkipListNode>, key: &[u8]) -> Option<&'a SkipListNode> {
+        self.node(succ).filter(|node| self.comparator.compare(&node.key, key) == Ordering::Equal)
+    }
+
+    /// Copy of the node's newest entry with a sequence number at or below
+    /// `sequence`, if any
+    fn load_entry(&self, node: &SkipListNode, sequence: u64) -> Option<Entry> {
+        let mut link = node.versions.load(AtomicOrdering::Acquire, Self::links());
+        while let Some(version) = self.version(link) {
+            if version.entry.sequence <= sequence {
+                return Some(version.entry.clone());
+            }
+            link = version.older.load(AtomicOrdering::Acquire, Self::links());
+        }
+        None
+    }
+
+    /// Link `version` into `node`'s versions after every version with a
+    /// higher sequence number, so whichever entry has the higher sequence
+    /// stays in front whatever order concurrent writers arrive in. An
+    /// entry at the same sequence as an existing one goes in front of it.
+    fn add_version(&self, node: &SkipListNode, version: Owned<EntryVersion>) {
+        let size = entry_size(&version.entry);
+        let sequence = version.entry.sequence;
+        let version = version.into_shared(Self::links());
+        let new = self.version(version).expect("new version is not null");
+        let mut link = &node.versions;
+        loop {
+            let current = link.load(AtomicOrdering::Acquire, Self::links());
+            match self.version(current) {
+                Some(newer) if newer.entry.sequence > sequence => link = &newer.older,
+                _ => {
+                    new.older.store(current, AtomicOrdering::Relaxed);
+                    let linked = link.compare_exchange(
+                        current,
+                        version,
+                        AtomicOrdering::Release,
+                        AtomicOrdering::Relaxed,
+                        Self::links(),
+                    );
+                    // Otherwise another version was linked here first;
+                    // look at it before trying again
+                    if linked.is_ok() {
+                        break;
+                    }
+                }
+            }
+        }
+        self.memory_usage.fetch_add(size, AtomicOrdering::Relaxed);
+    }
+
+    /// Insert an entry as a new version of its key. Safe to call from
+    /// several threads at once.
+    pub fn insert(&self, entry: Entry) -> Result<()> {
+        let key = entry.key.data.clone();
+        let (mut preds, mut succs) = self.find_position(&key);
+        let version = Owned::new(EntryVersion {
+            entry,
+            older: Atomic::null(),
+        });
+        if let Some(existing) = self.matching(succs[0], &key) {
+            self.add_version(existing, version);
+            return Ok(());
+        }
+
+        let height = Self::random_level();
+        let size = entry_size(&version.entry) + key.len() + height * std::mem::size_of::<Atomic<SkipListNode>>();
+        let node = Owned::new(SkipListNode {
+            key,
+            versions: Atomic::from(version),
+            next: (0..height).map(|_| Atomic::null()).collect(),
+        })
+        .into_shared(Self::links());
+        let new = self.node(node).expect("new node is not null");
+
+        // Linking at level 0 decides whether the key joins the list
+        loop {
+            new.next[0].store(succs[0], AtomicOrdering::Relaxed);
+            let linked = self.link(preds[0], 0).compare_exchange(
+                succs[0],
+                node,
+                AtomicOrdering::Release,
+                AtomicOrdering::Relaxed,
+                Self::links(),
+            );
+            if linked.is_ok() {
+                break;
+            }
+            (preds, succs) = self.find_position(&new.key);
+            if let Some(existing) = self.matching(succs[0], &new.key) {
+                // Another insert added the key first; this node was never
+                // published, so it can be taken apart here
+                // SAFETY: no other thread has seen `node`
+                let node = unsafe { node.into_owned() }.into_box();
+                let version = unsafe { node.versions.into_owned() };
+                self.add_version(existing, version);
                 return Ok(());
             }
         }
-        
-        // Link the new node
-        for i in 0..=level {
-            if let Some(pred) = preds[i].as_ref() {
-                new_node.next[i].store(
-                    pred.next_at(i).load(AtomicOrdering::Acquire, &guard),
+        self.len.fetch_add(1, AtomicOrdering::Relaxed);
+        self.memory_usage.fetch_add(size, AtomicOrdering::Relaxed);
+
+        // Higher levels only speed up searches
+        for level in 1..height {
+            loop {
+                new.next[level].store(succs[level], AtomicOrdering::Relaxed);
+                let linked = self.link(preds[level], level).compare_exchange(
+                    succs[level],
+                    node,
                     AtomicOrdering::Release,
+                    AtomicOrdering::Relaxed,
+                    Self::links(),
                 );
-                pred.next_at(i).store(new_node.clone(), AtomicOrdering::Release);
+                if linked.is_ok() {
+                    break;
+                }
+                (preds, succs) = self.find_position(&new.key);
             }
         }
-        
-        // Update counters
-        self.entry_count.fetch_add(1, AtomicOrdering::Relaxed);
-        self.memory_usage.fetch_add(
-            entry.key.len() as u64 + entry.value.as_ref().map_or(0, |v| v.len() as u64),
-            AtomicOrdering::Relaxed,
-        );
-        
         Ok(())
     }
-    
-    fn get(&self, key: &Key) -> Result<Option<Entry>> {
-        let guard = epoch::pin();
-        let (_, currs) = self.find_node(key);
-        
-        if let Some(curr) = currs[0].as_ref() {
-            if curr.entry.key == *key {
-                return Ok(Some(curr.entry.clone()));
+
+    /// Free every node and version, leaving the list empty
+    fn free_nodes(&mut self) {
+        let mut next = self.head[0].load(AtomicOrdering::Relaxed, Self::links());
+        while !next.is_null() {
+            // SAFETY: `&mut self` means no other reference to a node or
+            // version exists
+            let node = unsafe { next.into_owned() }.into_box();
+            next = node.next[0].load(AtomicOrdering::Relaxed, Self::links());
+            let mut version = node.versions.load(AtomicOrdering::Relaxed, Self::links());
+            while !version.is_null() {
+                let owned = unsafe { version.into_owned() }.into_box();
+                version = owned.older.load(AtomicOrdering::Relaxed, Self::links());
             }
         }
-        
-        Ok(None)
-    }
-    
-    fn delete(&mut self, key: &Key, sequence: u64) -> Result<()> {
-        let guard = epoch::pin();
-        let (preds, currs) = self.find_node(key);
-        
-        if let Some(curr) = currs[0].as_ref() {
-            if curr.entry.key == *key {
-                // Mark as deleted by setting a tombstone
-                let delete_entry = Entry::delete(key.clone(), sequence);
-                let _ = self.insert(delete_entry);
-                return Ok(());
-            }
+        for link in &self.head {
+            link.store(Shared::null(), AtomicOrdering::Relaxed);
         }
-        
-        Ok(())
+        self.len.store(0, AtomicOrdering::Relaxed);
+        self.memory_usage.store(0, AtomicOrdering::Relaxed);
     }
-    
-    fn iter(&self) -> Box<dyn Iterator<Item = Entry> + '_> {
-        // This is a simplified iterator - in practice you'd want a proper iterator
-        Box::new(std::iter::empty())
+}
+
+impl Default for SkipListMemtable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SkipListMemtable {
+    fn drop(&mut self) {
+        self.free_nodes();
+    }
+}
+
+impl MemtableImpl for SkipListMemtable {
+    fn insert(&self, entry: Entry) -> Result<()> {
+        SkipListMemtable::insert(self, entry)
+    }
+    
+    fn get_at(&self, key: &Key, sequence: u64) -> Result<Option<Entry>> {
+        let (_, succs) = self.find_position(&key.data);
+        Ok(self.matching(succs[0], &key.data).and_then(|node| self.load_entry(node, sequence)))
+    }
+    
+    fn iter_from(&self, start: Option<&[u8]>, sequence: u64) -> Box<dyn Iterator<Item = Entry> + '_> {
+        let first = match start {
+            Some(start) => self.find_position(start).1[0],
+            None => self.head[0].load(AtomicOrdering::Acquire, Self::links()),
+        };
+        let mut current = self.node(first);
+        Box::new(std::iter::from_fn(move || loop {
+            let node = current?;
+            current = self.node(node.next[0].load(AtomicOrdering::Acquire, Self::links()));
+            if let Some(entry) = self.load_entry(node, sequence) {
+                return Some(entry);
+            }
+        }))
     }
     
     fn len(&self) -> usize {
-        self.entry_count.load(AtomicOrdering::Relaxed) as usize
+        self.len.load(AtomicOrdering::Relaxed)
     }
     
     fn is_empty(&self) -> bool {
@@ -240,86 +374,181 @@ impl MemtableImpl for SkipListMemtable {
     }
     
     fn memory_usage(&self) -> usize {
-        self.memory_usage.load(AtomicOrdering::Relaxed) as usize
+        self.memory_usage.load(AtomicOrdering::Relaxed)
     }
     
     fn clear(&mut self) {
-        // In a lock-free structure, clearing is complex
-        // For now, we'll just reset counters
-        self.entry_count.store(0, AtomicOrdering::Relaxed);
-        self.memory_usage.store(0, AtomicOrdering::Relaxed);
+        self.free_nodes();
+    }
+}
+
+/// Map key ordered by a runtime comparator
+#[derive(Clone)]
+struct OrderedKey {
+    data: Vec<u8>,
+    comparator: Comparator,
+}
+
+impl PartialEq for OrderedKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OrderedKey {}
+
+impl PartialOrd for OrderedKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.comparator.compare(&self.data, &other.data)
+    }
+}
+
+/// Comparator-ordered map shared by the tree-based memtables
+struct OrderedMap {
+    /// Versions of each key, highest sequence number first, keyed by
+    /// comparator order
+    map: BTreeMap<OrderedKey, Vec<Entry>>,
+    /// Key ordering
+    comparator: Comparator,
+    /// Memory usage estimate
+    memory_usage: usize,
+}
+
+impl OrderedMap {
+    fn new(comparator: Comparator) -> Self {
+        Self {
+            map: BTreeMap::new(),
+            comparator,
+            memory_usage: 0,
+        }
+    }
+
+    fn key(&self, data: &[u8]) -> OrderedKey {
+        OrderedKey {
+            data: data.to_vec(),
+            comparator: self.comparator.clone(),
+        }
+    }
+
+    /// Add `entry` after every version of its key with a higher sequence
+    fn insert(&mut self, entry: Entry) {
+        self.memory_usage += entry_size(&entry);
+        let versions = self.map.entry(self.key(&entry.key.data)).or_default();
+        let position = versions.partition_point(|version| version.sequence > entry.sequence);
+        versions.insert(position, entry);
+    }
+
+    fn get_at(&self, key: &Key, sequence: u64) -> Option<Entry> {
+        let versions = self.map.get(&self.key(&key.data))?;
+        versions.iter().find(|version| version.sequence <= sequence).cloned()
+    }
+
+    /// First key after `from` with a version at or below `sequence`, and
+    /// that version
+    fn next_at(&self, from: Bound<OrderedKey>, sequence: u64) -> Option<(OrderedKey, Entry)> {
+        self.map.range((from, Bound::Unbounded)).find_map(|(key, versions)| {
+            let entry = versions.iter().find(|version| version.sequence <= sequence)?;
+            Some((key.clone(), entry.clone()))
+        })
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.memory_usage = 0;
+    }
+}
+
+/// Memtable over an [`OrderedMap`] behind a lock. Iterators take the lock
+/// for each step, looking up the key after the last one yielded, so they
+/// never hold it between steps.
+struct LockedMap(RwLock<OrderedMap>);
+
+impl LockedMap {
+    fn new(comparator: Comparator) -> Self {
+        Self(RwLock::new(OrderedMap::new(comparator)))
+    }
+
+    fn insert(&self, entry: Entry) -> Result<()> {
+        self.0.write().insert(entry);
+        Ok(())
+    }
+
+    fn get_at(&self, key: &Key, sequence: u64) -> Result<Option<Entry>> {
+        Ok(self.0.read().get_at(key, sequence))
+    }
+
+    fn iter_from(&self, start: Option<&[u8]>, sequence: u64) -> Box<dyn Iterator<Item = Entry> + '_> {
+        let mut from = match start {
+            Some(start) => Bound::Included(self.0.read().key(start)),
+            None => Bound::Unbounded,
+        };
+        Box::new(std::iter::from_fn(move || {
+            let (key, entry) = self.0.read().next_at(from.clone(), sequence)?;
+            from = Bound::Excluded(key);
+            Some(entry)
+        }))
+    }
+
+    fn len(&self) -> usize {
+        self.0.read().map.len()
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.0.read().memory_usage
+    }
+
+    fn clear(&mut self) {
+        self.0.get_mut().clear();
     }
 }
 
 /// B-tree memtable implementation
 pub struct BTreeMemtable {
     /// Internal B-tree map
-    map: RwLock<BTreeMap<Key, Entry>>,
-    /// Memory usage estimate
-    memory_usage: AtomicU64,
+    map: LockedMap,
 }
 
 impl BTreeMemtable {
-    /// Create a new B-tree memtable
+    /// Create a new B-tree memtable ordered bytewise
     pub fn new() -> Self {
+        Self::with_comparator(Comparator::default())
+    }
+
+    /// Create a new B-tree memtable with a custom key order
+    pub fn with_comparator(comparator: Comparator) -> Self {
         Self {
-            map: RwLock::new(BTreeMap::new()),
-            memory_usage: AtomicU64::new(0),
+            map: LockedMap::new(comparator),
         }
     }
 }
 
-impl MemtableImpl for BTreeMemtable {
-    fn insert(&mut self, entry: Entry) -> Result<()> {
-        let mut map = self.map.write();
-        let old_entry = map.insert(entry.key.clone(), entry.clone());
-        
-        // Update memory usage
-        if let Some(old) = old_entry {
-            self.memory_usage.fetch_sub(
-                old.key.len() as u64 + old.value.as_ref().map_or(0, |v| v.len() as u64),
-                AtomicOrdering::Relaxed,
-            );
-        }
-        
-        self.memory_usage.fetch_add(
-            entry.key.len() as u64 + entry.value.as_ref().map_or(0, |v| v.len() as u64),
-            AtomicOrdering::Relaxed,
-        );
-        
-        Ok(())
+impl Default for BTreeMemtable {
+    fn default() -> Self {
+        Self::new()
     }
-    
-    fn get(&self, key: &Key) -> Result<Option<Entry>> {
-        let map = self.map.read();
-        Ok(map.get(key).cloned())
+}
+
+impl MemtableImpl for BTreeMemtable {
+    fn insert(&self, entry: Entry) -> Result<()> {
+        self.map.insert(entry)
     }
     
-    fn delete(&mut self, key: &Key, sequence: u64) -> Result<()> {
-        let mut map = self.map.write();
-        
-        if let Some(old_entry) = map.remove(key) {
-            // Update memory usage
-            self.memory_usage.fetch_sub(
-                old_entry.key.len() as u64 + old_entry.value.as_ref().map_or(0, |v| v.len() as u64),
-                AtomicOrdering::Relaxed,
-            );
-            
-            // Insert tombstone
-            let delete_entry = Entry::delete(key.clone(), sequence);
-            let _ = map.insert(key.clone(), delete_entry);
-        }
-        
-        Ok(())
+    fn get_at(&self, key: &Key, sequence: u64) -> Result<Option<Entry>> {
+        self.map.get_at(key, sequence)
     }
     
-    fn iter(&self) -> Box<dyn Iterator<Item = Entry> + '_> {
-        let map = self.map.read();
-        Box::new(map.values().cloned().collect::<Vec<_>>().into_iter())
+    fn iter_from(&self, start: Option<&[u8]>, sequence: u64) -> Box<dyn Iterator<Item = Entry> + '_> {
+        self.map.iter_from(start, sequence)
     }
     
     fn len(&self) -> usize {
-        self.map.read().len()
+        self.map.len()
     }
     
     fn is_empty(&self) -> bool {
@@ -327,13 +556,11 @@ impl MemtableImpl for BTreeMemtable {
     }
     
     fn memory_usage(&self) -> usize {
-        self.memory_usage.load(AtomicOrdering::Relaxed) as usize
+        self.map.memory_usage()
     }
     
     fn clear(&mut self) {
-        let mut map = self.map.write();
-        map.clear();
-        self.memory_usage.store(0, AtomicOrdering::Relaxed);
+        self.map.clear();
     }
 }
 
@@ -341,72 +568,44 @@ impl MemtableImpl for BTreeMemtable {
 /// This is a simplified version - a full ART implementation would be much more complex
 pub struct ArtMemtable {
     /// Internal map (simplified for now)
-    map: RwLock<BTreeMap<Key, Entry>>,
-    /// Memory usage estimate
-    memory_usage: AtomicU64,
+    map: LockedMap,
 }
 
 impl ArtMemtable {
-    /// Create a new ART memtable
+    /// Create a new ART memtable ordered bytewise
     pub fn new() -> Self {
+        Self::with_comparator(Comparator::default())
+    }
+
+    /// Create a new ART memtable with a custom key order
+    pub fn with_comparator(comparator: Comparator) -> Self {
         Self {
-            map: RwLock::new(BTreeMap::new()),
-            memory_usage: AtomicU64::new(0),
+            map: LockedMap::new(comparator),
         }
     }
 }
 
-impl MemtableImpl for ArtMemtable {
-    fn insert(&mut self, entry: Entry) -> Result<()> {
-        let mut map = self.map.write();
-        let old_entry = map.insert(entry.key.clone(), entry.clone());
-        
-        // Update memory usage
-        if let Some(old) = old_entry {
-            self.memory_usage.fetch_sub(
-                old.key.len() as u64 + old.value.as_ref().map_or(0, |v| v.len() as u64),
-                AtomicOrdering::Relaxed,
-            );
-        }
-        
-        self.memory_usage.fetch_add(
-            entry.key.len() as u64 + entry.value.as_ref().map_or(0, |v| v.len() as u64),
-            AtomicOrdering::Relaxed,
-        );
-        
-        Ok(())
+impl Default for ArtMemtable {
+    fn default() -> Self {
+        Self::new()
     }
-    
-    fn get(&self, key: &Key) -> Result<Option<Entry>> {
-        let map = self.map.read();
-        Ok(map.get(key).cloned())
+}
+
+impl MemtableImpl for ArtMemtable {
+    fn insert(&self, entry: Entry) -> Result<()> {
+        self.map.insert(entry)
     }
     
-    fn delete(&mut self, key: &Key, sequence: u64) -> Result<()> {
-        let mut map = self.map.write();
-        
-        if let Some(old_entry) = map.remove(key) {
-            // Update memory usage
-            self.memory_usage.fetch_sub(
-                old_entry.key.len() as u64 + old_entry.value.as_ref().map_or(0, |v| v.len() as u64),
-                AtomicOrdering::Relaxed,
-            );
-            
-            // Insert tombstone
-            let delete_entry = Entry::delete(key.clone(), sequence);
-            let _ = map.insert(key.clone(), delete_entry);
-        }
-        
-        Ok(())
+    fn get_at(&self, key: &Key, sequence: u64) -> Result<Option<Entry>> {
+        self.map.get_at(key, sequence)
     }
     
-    fn iter(&self) -> Box<dyn Iterator<Item = Entry> + '_> {
-        let map = self.map.read();
-        Box::new(map.values().cloned().collect::<Vec<_>>().into_iter())
+    fn iter_from(&self, start: Option<&[u8]>, sequence: u64) -> Box<dyn Iterator<Item = Entry> + '_> {
+        self.map.iter_from(start, sequence)
     }
     
     fn len(&self) -> usize {
-        self.map.read().len()
+        self.map.len()
     }
     
     fn is_empty(&self) -> bool {
@@ -414,24 +613,34 @@ impl MemtableImpl for ArtMemtable {
     }
     
     fn memory_usage(&self) -> usize {
-        self.memory_usage.load(AtomicOrdering::Relaxed) as usize
+        self.map.memory_usage()
     }
     
     fn clear(&mut self) {
-        let mut map = self.map.write();
-        map.clear();
-        self.memory_usage.store(0, AtomicOrdering::Relaxed);
+        self.map.clear();
     }
 }
 
-/// Main memtable that wraps the implementation
+/// Main memtable that wraps the implementation.
+///
+/// Range deletes are kept here, beside the implementation's point entries,
+/// and applied to point lookups. Inserts take `&self`, like the
+/// implementation's.
 pub struct Memtable {
     /// Implementation
     implementation: Box<dyn MemtableImpl>,
+    /// Key order of the implementation
+    comparator: Comparator,
+    /// Range tombstones, in insertion order
+    range_tombstones: RwLock<Vec<RangeTombstone>>,
+    /// Memory taken by the range tombstones
+    range_tombstone_bytes: AtomicUsize,
     /// Maximum size in bytes
     max_size: usize,
     /// Flush threshold
     flush_threshold: f64,
+    /// Entry count that triggers a flush, if any
+    max_entries: Option<usize>,
 }
 
 impl Memtable {
@@ -439,59 +648,121 @@ impl Memtable {
     pub fn new(implementation: Box<dyn MemtableImpl>, max_size: usize, flush_threshold: f64) -> Self {
         Self {
             implementation,
+            comparator: Comparator::default(),
+            range_tombstones: RwLock::new(Vec::new()),
+            range_tombstone_bytes: AtomicUsize::new(0),
             max_size,
             flush_threshold,
+            max_entries: None,
         }
     }
-    
-    /// Insert an entry
-    pub fn insert(&mut self, entry: Entry) -> Result<()> {
+
+    /// Order range tombstone bounds with `comparator`, the implementation's
+    pub fn with_comparator(mut self, comparator: Comparator) -> Self {
+        self.comparator = comparator;
+        self
+    }
+
+    /// Also flush once the memtable holds `max_entries` entries
+    pub fn with_max_entries(mut self, max_entries: Option<usize>) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+    
+    /// Insert an entry. A range delete is kept as a range tombstone.
+    pub fn insert(&self, entry: Entry) -> Result<()> {
+        if let Some(tombstone) = RangeTombstone::from_entry(&entry) {
+            self.range_tombstone_bytes.fetch_add(tombstone.size(), AtomicOrdering::Relaxed);
+            self.range_tombstones.write().push(tombstone);
+            return Ok(());
+        }
         self.implementation.insert(entry)
     }
     
-    /// Get an entry by key
+    /// Get the newest entry by key; a tombstone if a later range delete
+    /// covers the key
     pub fn get(&self, key: &Key) -> Result<Option<Entry>> {
-        self.implementation.get(key)
+        self.get_at(key, u64::MAX)
+    }
+
+    /// Get the newest entry by key as of `sequence`: the newest version at
+    /// or below it, or a tombstone if a later range delete, also at or
+    /// below it, covers the key
+    pub fn get_at(&self, key: &Key, sequence: u64) -> Result<Option<Entry>> {
+        let entry = self.implementation.get_at(key, sequence)?;
+        let deleted_at = storage::newest_covering(&self.range_tombstones_at(sequence), &key.data, &self.comparator);
+        Ok(storage::shadow_entry(&key.data, entry, deleted_at))
+    }
+
+    /// Range tombstones, in insertion order
+    pub fn range_tombstones(&self) -> Vec<RangeTombstone> {
+        self.range_tombstones.read().clone()
+    }
+
+    /// Range tombstones with a sequence number at or below `sequence`, in
+    /// insertion order
+    pub fn range_tombstones_at(&self, sequence: u64) -> Vec<RangeTombstone> {
+        self.range_tombstones.read().iter().filter(|t| t.sequence <= sequence).cloned().collect()
     }
     
     /// Delete an entry by key
-    pub fn delete(&mut self, key: &Key, sequence: u64) -> Result<()> {
+    pub fn delete(&self, key: &Key, sequence: u64) -> Result<()> {
         self.implementation.delete(key, sequence)
     }
     
-    /// Get all entries in sorted order
+    /// Get the newest entry of every key in sorted order
     pub fn iter(&self) -> Box<dyn Iterator<Item = Entry> + '_> {
         self.implementation.iter()
     }
+
+    /// Get, in sorted order from `start` (the first key if `None`), the
+    /// newest entry of every key as of `sequence`; see
+    /// [`MemtableImpl::iter_from`]
+    pub fn iter_from(&self, start: Option<&[u8]>, sequence: u64) -> Box<dyn Iterator<Item = Entry> + '_> {
+        self.implementation.iter_from(start, sequence)
+    }
     
-    /// Get the number of entries
+    /// Get the number of keys
     pub fn len(&self) -> usize {
         self.implementation.len()
     }
     
-    /// Check if memtable is empty
+    /// Check if memtable holds neither entries nor range tombstones
     pub fn is_empty(&self) -> bool {
-        self.implementation.is_empty()
+        self.implementation.is_empty() && self.range_tombstones.read().is_empty()
     }
     
-    /// Get the memory usage
+    /// Get the memory usage, older versions of rewritten keys included
     pub fn memory_usage(&self) -> usize {
-        self.implementation.memory_usage()
+        self.implementation.memory_usage() + self.range_tombstone_bytes.load(AtomicOrdering::Relaxed)
     }
     
-    /// Check if memtable should be flushed
+    /// Memory usage at which the memtable should be flushed
+    pub fn flush_threshold_bytes(&self) -> usize {
+        (self.max_size as f64 * self.flush_threshold) as usize
+    }
+
+    /// Entry count at which the memtable should be flushed, if limited
+    pub fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
+    /// Check if memtable should be flushed: its memory usage reached the
+    /// flush threshold or its entry count reached `max_entries`
     pub fn should_flush(&self) -> bool {
-        self.memory_usage() >= (self.max_size as f64 * self.flush_threshold) as usize
+        self.memory_usage() >= self.flush_threshold_bytes() || self.max_entries.is_some_and(|max| self.len() >= max)
     }
     
     /// Check if memtable is full
     pub fn is_full(&self) -> bool {
-        self.memory_usage() >= self.max_size
+        self.memory_usage() >= self.max_size || self.max_entries.is_some_and(|max| self.len() >= max)
     }
     
-    /// Clear all entries
+    /// Clear all entries and range tombstones
     pub fn clear(&mut self) {
         self.implementation.clear();
+        self.range_tombstones.get_mut().clear();
+        *self.range_tombstone_bytes.get_mut() = 0;
     }
     
     /// Take all entries and clear the memtable
@@ -505,26 +776,27 @@ impl Memtable {
 /// Factory function to create memtables
 pub fn create_memtable(
     implementation: crate::config::MemtableImpl,
+    comparator: Comparator,
     max_size: usize,
     flush_threshold: f64,
 ) -> Memtable {
     let impl_box: Box<dyn MemtableImpl> = match implementation {
-        crate::config::MemtableImpl::SkipList => Box::new(SkipListMemtable::new()),
-        crate::config::MemtableImpl::Art => Box::new(ArtMemtable::new()),
-        crate::config::MemtableImpl::BTree => Box::new(BTreeMemtable::new()),
+        crate::config::MemtableImpl::SkipList => Box::new(SkipListMemtable::with_comparator(comparator.clone())),
+        crate::config::MemtableImpl::Art => Box::new(ArtMemtable::with_comparator(comparator.clone())),
+        crate::config::MemtableImpl::BTree => Box::new(BTreeMemtable::with_comparator(comparator.clone())),
     };
     
-    Memtable::new(impl_box, max_size, flush_threshold)
+    Memtable::new(impl_box, max_size, flush_threshold).with_comparator(comparator)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::storage::{Entry, Key, Value, OpType};
+    use crate::storage::{Entry, Key, Value};
     
     #[test]
     fn test_btree_memtable_insert_get() {
-        let mut memtable = BTreeMemtable::new();
+        let memtable = BTreeMemtable::new();
         let key = Key::new(b"test_key".to_vec());
         let value = Value::new(b"test_value".to_vec());
         let entry = Entry::new(key.clone(), value, 1);
@@ -537,7 +809,7 @@ mod tests {
     
     #[test]
     fn test_btree_memtable_delete() {
-        let mut memtable = BTreeMemtable::new();
+        let memtable = BTreeMemtable::new();
         let key = Key::new(b"test_key".to_vec());
         let value = Value::new(b"test_value".to_vec());
         let entry = Entry::new(key.clone(), value, 1);
@@ -549,11 +821,129 @@ mod tests {
         let retrieved = memtable.get(&key).unwrap().unwrap();
         assert!(retrieved.is_delete());
     }
+
+    #[test]
+    fn test_skiplist_memtable_ordering() {
+        let memtable = SkipListMemtable::with_comparator(Comparator::reverse());
+        for (i, key) in ["b", "d", "a", "c", "b"].iter().enumerate() {
+            let entry = Entry::new(Key::from(*key), Value::new(vec![i as u8]), i as u64);
+            memtable.insert(entry).unwrap();
+        }
+
+        let keys: Vec<Vec<u8>> = memtable.iter().map(|e| e.key.data).collect();
+        assert_eq!(keys, vec![b"d".to_vec(), b"c".to_vec(), b"b".to_vec(), b"a".to_vec()]);
+        let updated = memtable.get(&Key::from("b")).unwrap().unwrap();
+        assert_eq!(updated.value.unwrap().data, vec![4]);
+    }
     
+    #[test]
+    fn test_skiplist_insert_replaces_existing_key() {
+        let memtable = SkipListMemtable::new();
+        memtable.insert(Entry::new(Key::from("a"), Value::from("1"), 1)).unwrap();
+        memtable.insert(Entry::new(Key::from("a"), Value::from("2"), 2)).unwrap();
+
+        let entry = memtable.get(&Key::from("a")).unwrap().unwrap();
+        assert_eq!((entry.value.unwrap().data, entry.sequence), (b"2".to_vec(), 2));
+        assert_eq!(memtable.len(), 1);
+        assert_eq!(memtable.iter().count(), 1);
+    }
+
+    #[test]
+    fn test_higher_sequence_wins_whatever_the_insert_order() {
+        let memtables: [Box<dyn MemtableImpl>; 2] = [Box::new(SkipListMemtable::new()), Box::new(BTreeMemtable::new())];
+        for memtable in memtables {
+            for sequence in [5, 3, 7, 1] {
+                let value = Value::new(vec![sequence as u8]);
+                memtable.insert(Entry::new(Key::from("a"), value, sequence)).unwrap();
+            }
+            let value_at = |sequence| memtable.get_at(&Key::from("a"), sequence).unwrap().map(|e| e.sequence);
+            assert_eq!(memtable.get(&Key::from("a")).unwrap().unwrap().sequence, 7);
+            assert_eq!(value_at(6), Some(5));
+            assert_eq!(value_at(4), Some(3));
+            assert_eq!(value_at(0), None);
+            assert_eq!(memtable.len(), 1);
+
+            // Keys with only newer versions are skipped
+            memtable.insert(Entry::new(Key::from("b"), Value::from("b"), 9)).unwrap();
+            let keys: Vec<_> = memtable.iter_from(None, 8).map(|e| e.key.data).collect();
+            assert_eq!(keys, vec![b"a".to_vec()]);
+            let keys: Vec<_> = memtable.iter_from(Some(b"b"), 9).map(|e| e.key.data).collect();
+            assert_eq!(keys, vec![b"b".to_vec()]);
+        }
+    }
+
+    #[test]
+    fn test_skiplist_concurrent_rewrites_keep_highest_sequence() {
+        const THREADS: u64 = 8;
+        const WRITES: u64 = 1000;
+        let memtable = std::sync::Arc::new(SkipListMemtable::new());
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let memtable = std::sync::Arc::clone(&memtable);
+                std::thread::spawn(move || {
+                    // Sequences interleave across threads, so versions
+                    // arrive out of order, some racing to create the key
+                    for i in 0..WRITES {
+                        let sequence = i * THREADS + t + 1;
+                        let key = format!("key_{}", i % 10);
+                        memtable.insert(Entry::new(Key::from(key.as_str()), Value::new(vec![t as u8]), sequence)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(memtable.len(), 10);
+        for k in 0..10 {
+            let key = Key::from(format!("key_{}", k).as_str());
+            let newest = (0..WRITES).filter(|i| i % 10 == k).map(|i| i * THREADS + THREADS).max().unwrap();
+            assert_eq!(memtable.get(&key).unwrap().unwrap().sequence, newest);
+            assert_eq!(memtable.get_at(&key, newest - 1).unwrap().unwrap().sequence, newest - 1);
+        }
+    }
+
+    #[test]
+    fn test_skiplist_concurrent_inserts() {
+        const THREADS: usize = 8;
+        const KEYS: usize = 2000;
+        let memtable = std::sync::Arc::new(SkipListMemtable::new());
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let memtable = std::sync::Arc::clone(&memtable);
+                std::thread::spawn(move || {
+                    for i in 0..KEYS {
+                        // Threads interleave their keys, and every thread
+                        // also writes the shared keys
+                        let key = format!("key_{:06}", i * THREADS + t);
+                        memtable.insert(Entry::new(Key::from(key.as_str()), Value::new(vec![t as u8]), i as u64)).unwrap();
+                        let shared = format!("shared_{:04}", i % 100);
+                        memtable.insert(Entry::new(Key::from(shared.as_str()), Value::new(vec![t as u8]), i as u64)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(memtable.len(), THREADS * KEYS + 100);
+        let keys: Vec<Vec<u8>> = memtable.iter().map(|e| e.key.data).collect();
+        assert_eq!(keys.len(), THREADS * KEYS + 100);
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        for i in 0..THREADS * KEYS {
+            let key = Key::from(format!("key_{:06}", i).as_str());
+            let entry = memtable.get(&key).unwrap().unwrap();
+            assert_eq!(entry.value.unwrap().data, vec![(i % THREADS) as u8]);
+        }
+    }
+
     #[test]
     fn test_memtable_factory() {
         let memtable = create_memtable(
             crate::config::MemtableImpl::BTree,
+            Comparator::default(),
             1024,
             0.8,
         );
@@ -562,4 +952,17 @@ mod tests {
         assert_eq!(memtable.max_size, 1024);
         assert_eq!(memtable.flush_threshold, 0.8);
     }
+
+    #[test]
+    fn test_should_flush_on_entry_count() {
+        let memtable =
+            create_memtable(crate::config::MemtableImpl::SkipList, Comparator::default(), 1024 * 1024, 0.8)
+                .with_max_entries(Some(3));
+        for (i, key) in ["a", "b", "c"].into_iter().enumerate() {
+            assert!(!memtable.should_flush());
+            memtable.insert(Entry::new(Key::from(key), Value::new(vec![1]), i as u64)).unwrap();
+        }
+        assert!(memtable.should_flush());
+        assert!(memtable.memory_usage() < memtable.flush_threshold_bytes());
+    }
 }
@@ -1,41 +1,360 @@
 //! Metrics module for performance measurement
 //! 
-//! This module will implement histogram and counter collection.
-//! 
-//! Planned for M2 milestone.
+//! [`MetricsCollector`] accumulates named counters and histograms.
+//! [`Histogram`] is a lock-free log-linear (HDR-style) histogram of
+//! integer values with about 1.5% relative error, cheap enough to record
+//! every engine operation's latency into. [`crate::AuraEngine::metrics`]
+//! reports the engine's counters, such as block and value log cache hits
+//! and misses, its per-operation latency histograms and the key and value
+//! sizes of its writes ([`WriteSizes`]) as a [`MetricsSnapshot`].
 
-use crate::error::{Error, Result};
+use crate::error::Result;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::Span;
 
 /// Metrics collector
+#[derive(Debug, Default)]
 pub struct MetricsCollector {
-    // TODO: Implement metrics functionality
+    /// Histograms by name
+    histograms: BTreeMap<String, Histogram>,
+    /// Counter values by name
+    counters: BTreeMap<String, u64>,
 }
 
 impl MetricsCollector {
     /// Create a new metrics collector
     pub fn new() -> Self {
-        Self {}
+        Self::default()
     }
     
-    /// Record histogram value
-    pub fn record_histogram(&mut self, _name: &str, _value: f64) -> Result<()> {
-        // TODO: Implement
+    /// Record histogram value, rounded to a whole number (negative values
+    /// count as 0)
+    pub fn record_histogram(&mut self, name: &str, value: f64) -> Result<()> {
+        self.histograms
+            .entry(name.to_string())
+            .or_default()
+            .record(value.max(0.0).round() as u64);
+        Ok(())
+    }
+
+    /// Add every value recorded in `histogram` to the named histogram
+    pub fn add_histogram(&mut self, name: &str, histogram: &Histogram) -> Result<()> {
+        self.histograms.entry(name.to_string()).or_default().merge(histogram);
         Ok(())
     }
     
     /// Increment counter
-    pub fn increment_counter(&mut self, _name: &str) -> Result<()> {
-        // TODO: Implement
+    pub fn increment_counter(&mut self, name: &str) -> Result<()> {
+        self.add_counter(name, 1)
+    }
+
+    /// Add `delta` to a counter
+    pub fn add_counter(&mut self, name: &str, delta: u64) -> Result<()> {
+        *self.counters.entry(name.to_string()).or_insert(0) += delta;
         Ok(())
     }
     
-    /// Get metrics snapshot
+    /// Get metrics snapshot, ordered by name
     pub fn snapshot(&self) -> MetricsSnapshot {
-        // TODO: Implement
-        MetricsSnapshot::default()
+        MetricsSnapshot {
+            histograms: self
+                .histograms
+                .iter()
+                .map(|(name, histogram)| histogram.to_metric(name))
+                .collect(),
+            counters: self
+                .counters
+                .iter()
+                .map(|(name, value)| CounterMetric {
+                    name: name.clone(),
+                    value: *value,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Values below this are counted exactly, one bucket each
+const LINEAR_BUCKETS: u64 = 128;
+/// Each power of two above `LINEAR_BUCKETS` is split into 2^SUB_BUCKET_BITS buckets
+const SUB_BUCKET_BITS: u32 = 6;
+/// Buckets per power of two above `LINEAR_BUCKETS`
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+/// Total buckets, covering every `u64`
+const NUM_BUCKETS: usize = (LINEAR_BUCKETS + (64 - SUB_BUCKET_BITS as u64 - 1) * SUB_BUCKETS) as usize;
+
+/// Lock-free log-linear histogram of `u64` values.
+///
+/// Recording is a handful of relaxed atomic operations, so any number of
+/// threads can record concurrently. Values below 128 are exact; larger
+/// ones fall in buckets 1/64th of a power of two wide.
+#[derive(Debug)]
+pub struct Histogram {
+    /// Count per bucket
+    buckets: Box<[AtomicU64]>,
+    /// Values recorded
+    count: AtomicU64,
+    /// Sum of the values recorded
+    sum: AtomicU64,
+    /// Smallest value recorded (`u64::MAX` when empty)
+    min: AtomicU64,
+    /// Largest value recorded
+    max: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: (0..NUM_BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Histogram {
+    /// Create an empty histogram
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one value
+    pub fn record(&self, value: u64) {
+        self.buckets[bucket_index(value)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+        self.min.fetch_min(value, Ordering::Relaxed);
+        self.max.fetch_max(value, Ordering::Relaxed);
+    }
+
+    /// Add every value recorded in `other`
+    pub fn merge(&self, other: &Histogram) {
+        for (bucket, count) in self.buckets.iter().zip(other.buckets.iter()) {
+            let count = count.load(Ordering::Relaxed);
+            if count > 0 {
+                bucket.fetch_add(count, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(other.count(), Ordering::Relaxed);
+        self.sum.fetch_add(other.sum.load(Ordering::Relaxed), Ordering::Relaxed);
+        self.min.fetch_min(other.min.load(Ordering::Relaxed), Ordering::Relaxed);
+        self.max.fetch_max(other.max.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+
+    /// Number of values recorded
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Value at `quantile` (0 to 1) of the recorded distribution, or `None`
+    /// if nothing was recorded
+    pub fn percentile(&self, quantile: f64) -> Option<u64> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let min = self.min.load(Ordering::Relaxed);
+        let max = self.max.load(Ordering::Relaxed);
+        let rank = ((quantile.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket.load(Ordering::Relaxed);
+            if seen >= rank {
+                return Some(bucket_value(index).clamp(min, max));
+            }
+        }
+        Some(max)
+    }
+
+    /// Summary of the histogram under `name`
+    pub fn to_metric(&self, name: &str) -> HistogramMetric {
+        let count = self.count();
+        let quantile = |q| self.percentile(q).unwrap_or(0) as f64;
+        HistogramMetric {
+            name: name.to_string(),
+            count,
+            sum: self.sum.load(Ordering::Relaxed) as f64,
+            min: if count == 0 { 0.0 } else { self.min.load(Ordering::Relaxed) as f64 },
+            max: self.max.load(Ordering::Relaxed) as f64,
+            p50: quantile(0.5),
+            p95: quantile(0.95),
+            p99: quantile(0.99),
+            p999: quantile(0.999),
+        }
+    }
+}
+
+/// Bucket holding `value`
+fn bucket_index(value: u64) -> usize {
+    if value < LINEAR_BUCKETS {
+        return value as usize;
+    }
+    // `value >> shift` falls in [SUB_BUCKETS, 2 * SUB_BUCKETS)
+    let shift = 63 - value.leading_zeros() - SUB_BUCKET_BITS;
+    let sub_bucket = (value >> shift) - SUB_BUCKETS;
+    (LINEAR_BUCKETS + (shift as u64 - 1) * SUB_BUCKETS + sub_bucket) as usize
+}
+
+/// Midpoint of the values in a bucket
+fn bucket_value(index: usize) -> u64 {
+    let index = index as u64;
+    if index < LINEAR_BUCKETS {
+        return index;
+    }
+    let offset = index - LINEAR_BUCKETS;
+    let shift = offset / SUB_BUCKETS + 1;
+    let lower = (offset % SUB_BUCKETS + SUB_BUCKETS) << shift;
+    lower + ((1u64 << shift) >> 1)
+}
+
+/// Engine operations that have their own latency histogram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Point lookups
+    Get,
+    /// Single puts
+    Put,
+    /// Single deletes
+    Delete,
+    /// Range scans
+    Scan,
+    /// Write batches
+    Batch,
+}
+
+impl Operation {
+    /// Every operation, in histogram order
+    pub const ALL: [Operation; 5] = [
+        Operation::Get,
+        Operation::Put,
+        Operation::Delete,
+        Operation::Scan,
+        Operation::Batch,
+    ];
+
+    /// Name of the operation's latency histogram
+    pub fn metric_name(self) -> &'static str {
+        match self {
+            Operation::Get => "auradb.get.latency",
+            Operation::Put => "auradb.put.latency",
+            Operation::Delete => "auradb.delete.latency",
+            Operation::Scan => "auradb.scan.latency",
+            Operation::Batch => "auradb.batch.latency",
+        }
+    }
+}
+
+/// Latency histograms, in nanoseconds, for each [`Operation`]
+#[derive(Debug, Default)]
+pub struct OperationLatencies {
+    /// One histogram per operation, indexed like [`Operation::ALL`]
+    histograms: [Histogram; Operation::ALL.len()],
+}
+
+impl OperationLatencies {
+    /// Start timing `op`; the latency is recorded when the timer drops
+    pub fn start(&self, op: Operation) -> LatencyTimer<'_> {
+        LatencyTimer {
+            histogram: self.histogram(op),
+            started: Instant::now(),
+        }
+    }
+
+    /// Histogram of `op`'s latencies
+    pub fn histogram(&self, op: Operation) -> &Histogram {
+        &self.histograms[op as usize]
+    }
+
+    /// Latency at `quantile` (0 to 1) for `op`, or `None` before the first
+    /// `op` completes
+    pub fn percentile(&self, op: Operation, quantile: f64) -> Option<Duration> {
+        self.histogram(op).percentile(quantile).map(Duration::from_nanos)
     }
 }
 
+/// Sizes of the keys and values written, and how many values were
+/// separated into the value log
+#[derive(Debug, Default)]
+pub struct WriteSizes {
+    /// Key sizes in bytes
+    keys: Histogram,
+    /// Value sizes in bytes
+    values: Histogram,
+    /// Values written to the value log rather than inline
+    separated: AtomicU64,
+}
+
+impl WriteSizes {
+    /// Name of the key size histogram
+    pub const KEY_METRIC: &'static str = "auradb.key-size";
+    /// Name of the value size histogram
+    pub const VALUE_METRIC: &'static str = "auradb.value-size";
+    /// Name of the separated value counter
+    pub const SEPARATED_METRIC: &'static str = "auradb.values-separated";
+
+    /// Record a put of a `key_len` byte key and a `value_len` byte value
+    pub fn record(&self, key_len: usize, value_len: usize, separated: bool) {
+        self.keys.record(key_len as u64);
+        self.values.record(value_len as u64);
+        if separated {
+            self.separated.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Histogram of key sizes
+    pub fn keys(&self) -> &Histogram {
+        &self.keys
+    }
+
+    /// Histogram of value sizes
+    pub fn values(&self) -> &Histogram {
+        &self.values
+    }
+
+    /// Values written to the value log
+    pub fn separated(&self) -> u64 {
+        self.separated.load(Ordering::Relaxed)
+    }
+
+    /// Fraction of values written to the value log, or 0 before any write
+    pub fn separated_ratio(&self) -> f64 {
+        match self.values.count() {
+            0 => 0.0,
+            count => self.separated() as f64 / count as f64,
+        }
+    }
+}
+
+/// Records the time since it was started into a histogram when dropped
+pub struct LatencyTimer<'a> {
+    /// Histogram to record into
+    histogram: &'a Histogram,
+    /// When the operation started
+    started: Instant,
+}
+
+impl Drop for LatencyTimer<'_> {
+    fn drop(&mut self) {
+        self.histogram.record(self.started.elapsed().as_nanos() as u64);
+    }
+}
+
+/// Run `f` inside `span`, recording how long it took in the span's
+/// `duration_us` field. Disabled spans skip the clock.
+pub(crate) fn in_timed_span<T>(span: &Span, f: impl FnOnce() -> T) -> T {
+    if span.is_disabled() {
+        return f();
+    }
+    let started = Instant::now();
+    let result = span.in_scope(f);
+    span.record("duration_us", started.elapsed().as_micros() as u64);
+    result
+}
+
 /// Metrics snapshot
 #[derive(Debug, Clone, Default)]
 pub struct MetricsSnapshot {
@@ -45,6 +364,49 @@ pub struct MetricsSnapshot {
     pub counters: Vec<CounterMetric>,
 }
 
+impl MetricsSnapshot {
+    /// Value of the named counter, if present
+    pub fn counter(&self, name: &str) -> Option<u64> {
+        self.counters.iter().find(|c| c.name == name).map(|c| c.value)
+    }
+
+    /// Render the snapshot in the Prometheus text exposition format.
+    /// Counters become `counter` metrics and histograms `summary` metrics
+    /// with their percentiles as quantiles; names have `.` and `-`
+    /// replaced by `_` (`auradb.wal-bytes-written` becomes
+    /// `auradb_wal_bytes_written`).
+    pub fn to_prometheus(&self) -> String {
+        use std::fmt::Write as _;
+
+        let mut out = String::new();
+        for counter in &self.counters {
+            let name = prometheus_name(&counter.name);
+            let _ = writeln!(out, "# TYPE {} counter\n{} {}", name, name, counter.value);
+        }
+        for histogram in &self.histograms {
+            let name = prometheus_name(&histogram.name);
+            let _ = writeln!(out, "# TYPE {} summary", name);
+            for (quantile, value) in [
+                ("0.5", histogram.p50),
+                ("0.95", histogram.p95),
+                ("0.99", histogram.p99),
+                ("0.999", histogram.p999),
+            ] {
+                let _ = writeln!(out, "{}{{quantile=\"{}\"}} {}", name, quantile, value);
+            }
+            let _ = writeln!(out, "{}_sum {}\n{}_count {}", name, histogram.sum, name, histogram.count);
+        }
+        out
+    }
+}
+
+/// Metric name with the characters Prometheus does not allow replaced
+fn prometheus_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == ':' { c } else { '_' })
+        .collect()
+}
+
 /// Histogram metric
 #[derive(Debug, Clone)]
 pub struct HistogramMetric {
@@ -58,6 +420,25 @@ pub struct HistogramMetric {
     pub min: f64,
     /// Max value
     pub max: f64,
+    /// Median
+    pub p50: f64,
+    /// 95th percentile
+    pub p95: f64,
+    /// 99th percentile
+    pub p99: f64,
+    /// 99.9th percentile
+    pub p999: f64,
+}
+
+impl HistogramMetric {
+    /// One-line summary of the count and percentiles, e.g.
+    /// `count=3 min=1 p50=2 p95=9 p99=9 p999=9 max=9`
+    pub fn summary(&self) -> String {
+        format!(
+            "count={} min={} p50={} p95={} p99={} p999={} max={}",
+            self.count, self.min, self.p50, self.p95, self.p99, self.p999, self.max
+        )
+    }
 }
 
 /// Counter metric
@@ -79,4 +460,51 @@ mod tests {
         let snapshot = collector.snapshot();
         assert!(snapshot.histograms.is_empty());
     }
+
+    #[test]
+    fn test_counters_and_histograms() {
+        let mut collector = MetricsCollector::new();
+        collector.increment_counter("hits").unwrap();
+        collector.add_counter("hits", 2).unwrap();
+        collector.record_histogram("latency", 4.0).unwrap();
+        collector.record_histogram("latency", 1.0).unwrap();
+
+        let snapshot = collector.snapshot();
+        assert_eq!(snapshot.counter("hits"), Some(3));
+        assert_eq!(snapshot.counter("misses"), None);
+        let latency = &snapshot.histograms[0];
+        assert_eq!((latency.count, latency.sum, latency.min, latency.max), (2, 5.0, 1.0, 4.0));
+        assert_eq!((latency.p50, latency.p99), (1.0, 4.0));
+    }
+
+    #[test]
+    fn test_prometheus_export() {
+        let mut collector = MetricsCollector::new();
+        collector.add_counter("auradb.wal-bytes-written", 42).unwrap();
+        collector.record_histogram("auradb.get.latency", 7.0).unwrap();
+
+        let text = collector.snapshot().to_prometheus();
+        assert!(text.contains("# TYPE auradb_wal_bytes_written counter\nauradb_wal_bytes_written 42\n"));
+        assert!(text.contains("auradb_get_latency{quantile=\"0.99\"} 7\n"));
+        assert!(text.contains("auradb_get_latency_sum 7\nauradb_get_latency_count 1\n"));
+    }
+
+    #[test]
+    fn test_histogram_percentiles() {
+        let histogram = Histogram::new();
+        assert_eq!(histogram.percentile(0.5), None);
+        for value in 1..=10_000 {
+            histogram.record(value * 1000);
+        }
+        for (quantile, expected) in [(0.5, 5_000_000.0), (0.99, 9_900_000.0), (1.0, 10_000_000.0)] {
+            let value = histogram.percentile(quantile).unwrap() as f64;
+            assert!((value - expected).abs() / expected < 0.02, "p{} = {}", quantile, value);
+        }
+
+        // Bucket boundaries map back into their own bucket
+        for value in [127, 128, 129, 1 << 20, u64::MAX] {
+            assert_eq!(bucket_index(bucket_value(bucket_index(value))), bucket_index(value));
+        }
+        assert_eq!(bucket_index(u64::MAX), NUM_BUCKETS - 1);
+    }
 }
@@ -0,0 +1,160 @@
+//! On-disk format migration
+//!
+//! [`migrate`] upgrades a closed database one format version at a time.
+//! Each step writes the new files it needs, then commits by saving the
+//! manifest stamped with the next version. Nothing the old format refers to
+//! is deleted until every step has committed: the starting manifest is
+//! backed up to `MANIFEST.v<from>` first, and the backup and the files it
+//! alone references are removed only at the end. An interrupted migration
+//! is resumed by running it again, from the version the manifest records;
+//! until it completes, restoring the backup returns the database to its
+//! original format.
+//!
+//! Format history:
+//!
+//! - 1: SST data blocks may be plain runs of entries without restart points
+//! - 2: every live SST file uses restart points, so point lookups binary
+//!   search within blocks
+
+use crate::config::{Config, SstConfig};
+use crate::error::{Error, Result};
+use crate::io::FileAccess;
+use crate::manifest::{Manifest, MANIFEST_FILE, MANIFEST_VERSION};
+use crate::sst::{parse_sst_file_number, sst_file_name, SstReader, SstWriter};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// Oldest format version [`migrate`] upgrades from
+pub const MIN_MIGRATION_VERSION: u32 = 1;
+
+/// Upgrade the closed database at `db_path` from format version `from` to
+/// `to`, which must not exceed the current [`MANIFEST_VERSION`]. A database
+/// already part way there, after an interrupted run, continues from where
+/// it stopped; one already at `to` is left as is, apart from removing what
+/// an interrupted run left behind.
+pub fn migrate(db_path: &Path, from: u32, to: u32) -> Result<()> {
+    if from < MIN_MIGRATION_VERSION || from >= to || to > MANIFEST_VERSION {
+        return Err(Error::Config(format!(
+            "Cannot migrate from format version {} to {}: this build migrates from {} up to {}",
+            from, to, MIN_MIGRATION_VERSION, MANIFEST_VERSION
+        )));
+    }
+    let mut manifest = Manifest::load_any_version(db_path)?
+        .ok_or_else(|| Error::Config(format!("No database at {}", db_path.display())))?;
+    if manifest.version < from || manifest.version > to {
+        return Err(Error::Config(format!(
+            "Database at {} has format version {}, outside {}..={}",
+            db_path.display(),
+            manifest.version,
+            from,
+            to
+        )));
+    }
+
+    let backup = db_path.join(format!("{}.v{}", MANIFEST_FILE, from));
+    if manifest.version == from && !backup.exists() {
+        back_up_manifest(db_path, &backup)?;
+    }
+    let sst_dir = Config::default().with_db_path(db_path.to_path_buf()).sst.sst_path;
+    while manifest.version < to {
+        match manifest.version {
+            1 => rewrite_plain_tables(&sst_dir, &mut manifest)?,
+            version => {
+                return Err(Error::Config(format!("No migration step from format version {}", version)));
+            }
+        }
+        manifest.version += 1;
+        manifest.save(db_path)?;
+        info!("Migrated {} to format version {}", db_path.display(), manifest.version);
+    }
+
+    // Every step has committed; the old format's files can go
+    remove_unreferenced_tables(&sst_dir, &manifest)?;
+    if backup.exists() {
+        fs::remove_file(&backup)?;
+        crate::io::sync_dir(db_path)?;
+    }
+    Ok(())
+}
+
+/// Durably copy the manifest to `backup`
+fn back_up_manifest(db_path: &Path, backup: &Path) -> Result<()> {
+    let tmp_path = backup.with_file_name(format!("{}.tmp", backup.file_name().unwrap_or_default().to_string_lossy()));
+    fs::copy(db_path.join(MANIFEST_FILE), &tmp_path)?;
+    fs::File::open(&tmp_path)?.sync_all()?;
+    fs::rename(&tmp_path, backup)?;
+    crate::io::sync_dir(db_path)
+}
+
+/// Version 1 to 2: rewrite every SST file without restart points under a
+/// new file number, keeping its level and checksum algorithm
+fn rewrite_plain_tables(sst_dir: &Path, manifest: &mut Manifest) -> Result<()> {
+    for i in 0..manifest.files.len() {
+        let old = &manifest.files[i];
+        let reader = SstReader::open(&sst_dir.join(sst_file_name(old.id)), FileAccess::Buffered)?;
+        if reader.has_restart_points() {
+            continue;
+        }
+        let id = manifest.next_file_number;
+        manifest.next_file_number += 1;
+        let config = SstConfig {
+            checksum: reader.checksum_type(),
+            ..SstConfig::default()
+        };
+        let mut writer = SstWriter::new(&sst_dir.join(sst_file_name(id)), &config, FileAccess::Buffered)?;
+        for entry in reader.entries()? {
+            writer.add(&entry)?;
+        }
+        let mut file = writer.finish()?;
+        file.level = old.level;
+        manifest.files[i] = file;
+    }
+    Ok(())
+}
+
+/// Delete the SST files `manifest` does not name
+fn remove_unreferenced_tables(sst_dir: &Path, manifest: &Manifest) -> Result<()> {
+    let live: HashSet<u64> = manifest.files.iter().map(|file| file.id).collect();
+    for entry in fs::read_dir(sst_dir)? {
+        let entry = entry?;
+        if parse_sst_file_number(&entry.file_name().to_string_lossy()).is_some_and(|id| !live.contains(&id)) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{Engine, EngineBuilder};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_migrate_from_version_1() {
+        let temp_dir = tempdir().unwrap();
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        engine.put_str("key", "value").unwrap();
+        engine.flush().unwrap();
+        engine.close().await.unwrap();
+        drop(engine);
+
+        let mut manifest = Manifest::load(temp_dir.path()).unwrap().unwrap();
+        manifest.version = 1;
+        manifest.save(temp_dir.path()).unwrap();
+        assert!(EngineBuilder::new().path(temp_dir.path()).build().is_err());
+        assert!(migrate(temp_dir.path(), 2, 3).is_err());
+
+        migrate(temp_dir.path(), 1, 2).unwrap();
+        assert!(!temp_dir.path().join("MANIFEST.v1").exists());
+        // Running it again is a no-op
+        migrate(temp_dir.path(), 1, 2).unwrap();
+
+        let engine = EngineBuilder::new().path(temp_dir.path()).build().unwrap();
+        assert_eq!(engine.storage_format_version(), 2);
+        assert_eq!(engine.get_str("key").unwrap().as_deref(), Some("value"));
+        engine.close().await.unwrap();
+    }
+}
@@ -0,0 +1,401 @@
+//! Salvaging a damaged database
+//!
+//! [`repair`] is the explicit, never automatic, way back from corruption
+//! that keeps a database from opening. It works on a closed database and
+//! gives up whatever it cannot read:
+//!
+//! - A WAL file is cut off before its first record that does not decode;
+//!   later records in that file are lost. A file whose header is invalid
+//!   is quarantined whole.
+//! - An SST file that fails to open or fails [`SstReader::verify`] is
+//!   quarantined whole, along with every entry it held.
+//! - The manifest is rewritten without the files that were quarantined or
+//!   are missing. An unreadable manifest is rebuilt from every SST file
+//!   that verifies. Their levels are unknown, so all of them go to level 0,
+//!   where files with higher numbers take precedence; compaction sorts them
+//!   back into levels.
+//!
+//! Quarantined files are moved to `<db_path>/lost` rather than deleted.
+//! The [`RepairReport`] lists everything given up, with the offset of the
+//! damage that caused it.
+
+use crate::comparator::Comparator;
+use crate::config::Config;
+use crate::error::{Corruption, Error, Result};
+use crate::io::FileAccess;
+use crate::manifest::{Manifest, MANIFEST_FILE, MANIFEST_VERSION, MIN_MANIFEST_VERSION};
+use crate::sst::{parse_sst_file_number, sst_file_name, SstFile, SstReader};
+use crate::wal::{check_log, parse_log_number};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Directory under the database path that quarantined files are moved to
+pub const LOST_DIR: &str = "lost";
+
+/// Options for [`repair`]
+#[derive(Debug, Clone, Default)]
+pub struct RepairOptions {
+    /// Comparator the database was created with
+    pub comparator: Comparator,
+    /// Only report what would be given up, changing nothing
+    pub dry_run: bool,
+}
+
+impl RepairOptions {
+    /// Set the comparator the database was created with
+    pub fn with_comparator(mut self, comparator: Comparator) -> Self {
+        self.comparator = comparator;
+        self
+    }
+
+    /// Set whether to only report what would be given up
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+}
+
+/// A file given up by [`repair`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LostFile {
+    /// Where the file was
+    pub path: PathBuf,
+    /// Where it was moved to; `None` in a dry run
+    pub moved_to: Option<PathBuf>,
+    /// The damage found in it
+    pub damage: Vec<Corruption>,
+}
+
+/// The end of a WAL file cut off by [`repair`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LostWalTail {
+    /// The first record that does not decode; the file now ends at its
+    /// offset
+    pub damage: Corruption,
+    /// Bytes cut off, that record's and every later one's
+    pub bytes: u64,
+    /// Records kept before it
+    pub records_kept: u64,
+}
+
+/// What [`repair`] salvaged and what it gave up
+#[derive(Debug, Clone, Default)]
+pub struct RepairReport {
+    /// SST files in the repaired manifest
+    pub sst_files_kept: u64,
+    /// Damaged SST files, quarantined
+    pub lost_sst_files: Vec<LostFile>,
+    /// SST files the manifest listed that do not exist
+    pub missing_sst_files: Vec<PathBuf>,
+    /// WAL files with an invalid header, quarantined
+    pub lost_wal_files: Vec<LostFile>,
+    /// WAL files cut off before their first damaged record
+    pub lost_wal_tails: Vec<LostWalTail>,
+    /// Why the manifest had to be rebuilt from the SST files, if it did
+    pub manifest_rebuilt: Option<String>,
+}
+
+impl RepairReport {
+    /// Whether nothing was given up
+    pub fn is_clean(&self) -> bool {
+        self.lost_sst_files.is_empty()
+            && self.missing_sst_files.is_empty()
+            && self.lost_wal_files.is_empty()
+            && self.lost_wal_tails.is_empty()
+            && self.manifest_rebuilt.is_none()
+    }
+}
+
+/// Repair the closed database at `db_path`, giving up what cannot be read;
+/// see the [module docs](self). Fails without changing anything if there
+/// is no database there, if its format version needs a different build or
+/// a migration, or if it was created with a different comparator.
+pub fn repair(db_path: &Path, options: RepairOptions) -> Result<RepairReport> {
+    let layout = Config::default().with_db_path(db_path.to_path_buf());
+    let sst_dir = layout.sst.sst_path;
+    let wal_dir = layout.wal.wal_path;
+    if !db_path.join(MANIFEST_FILE).exists() && !sst_dir.exists() {
+        return Err(Error::Config(format!("No database at {}", db_path.display())));
+    }
+
+    let mut report = RepairReport::default();
+    let manifest = match Manifest::load_any_version(db_path) {
+        Ok(Some(manifest)) => Some(manifest),
+        Ok(None) => {
+            report.manifest_rebuilt = Some("The manifest is missing".to_string());
+            None
+        }
+        Err(e) => {
+            report.manifest_rebuilt = Some(e.to_string());
+            None
+        }
+    };
+    if let Some(manifest) = &manifest {
+        if !(MIN_MANIFEST_VERSION..=MANIFEST_VERSION).contains(&manifest.version) {
+            return Err(Error::Config(format!(
+                "Database at {} has format version {}; this build repairs versions {} to {}",
+                db_path.display(),
+                manifest.version,
+                MIN_MANIFEST_VERSION,
+                MANIFEST_VERSION
+            )));
+        }
+        if manifest.comparator != options.comparator.name() {
+            return Err(Error::Config(format!(
+                "Database at {} uses comparator '{}', repair was given '{}'",
+                db_path.display(),
+                manifest.comparator,
+                options.comparator.name()
+            )));
+        }
+    }
+    let lost_dir = db_path.join(LOST_DIR);
+    let quarantine = |path: &Path, damage: Vec<Corruption>| -> Result<LostFile> {
+        warn!("Quarantining damaged file {}", path.display());
+        let moved_to = if options.dry_run {
+            None
+        } else {
+            fs::create_dir_all(&lost_dir)?;
+            let target = lost_dir.join(path.file_name().unwrap_or_default());
+            fs::rename(path, &target)?;
+            Some(target)
+        };
+        Ok(LostFile { path: path.to_path_buf(), moved_to, damage })
+    };
+
+    // SST files: the manifest's, or every one on disk if it is unreadable
+    let candidates: Vec<(u64, Option<SstFile>)> = match &manifest {
+        Some(manifest) => manifest.files.iter().map(|file| (file.id, Some(file.clone()))).collect(),
+        None => {
+            let mut ids = Vec::new();
+            if sst_dir.exists() {
+                for entry in fs::read_dir(&sst_dir)? {
+                    if let Some(id) = parse_sst_file_number(&entry?.file_name().to_string_lossy()) {
+                        ids.push((id, None));
+                    }
+                }
+            }
+            ids.sort_by_key(|&(id, _)| id);
+            ids
+        }
+    };
+    let (mut kept, mut last_sequence) = (Vec::new(), 0);
+    for (id, listed) in candidates {
+        let path = sst_dir.join(sst_file_name(id));
+        if !path.exists() {
+            report.missing_sst_files.push(path);
+            continue;
+        }
+        match check_table(&path, id, &options.comparator) {
+            Ok((rebuilt, max_sequence)) => {
+                kept.push(listed.unwrap_or(rebuilt));
+                last_sequence = last_sequence.max(max_sequence);
+            }
+            Err(damage) => report.lost_sst_files.push(quarantine(&path, damage)?),
+        }
+    }
+    if !options.dry_run && !report.lost_sst_files.is_empty() {
+        crate::io::sync_dir(&sst_dir)?;
+    }
+
+    // WAL files, in log number order
+    let mut logs = Vec::new();
+    if wal_dir.exists() {
+        for entry in fs::read_dir(&wal_dir)? {
+            let entry = entry?;
+            if let Some(number) = parse_log_number(&entry.file_name().to_string_lossy()) {
+                logs.push((number, entry.path()));
+            }
+        }
+    }
+    logs.sort();
+    for (_, path) in logs {
+        match check_log(&path) {
+            Ok(check) => {
+                let Some(damage) = check.damage else { continue };
+                let len = fs::metadata(&path)?.len();
+                warn!("Cutting off WAL {} at offset {}", path.display(), check.valid_len);
+                if !options.dry_run {
+                    let file = fs::OpenOptions::new().write(true).open(&path)?;
+                    file.set_len(check.valid_len)?;
+                    file.sync_all()?;
+                }
+                report.lost_wal_tails.push(LostWalTail {
+                    damage,
+                    bytes: len - check.valid_len,
+                    records_kept: check.records,
+                });
+            }
+            Err(Error::WalCorruption(message)) => {
+                let damage = vec![Corruption { path: path.clone(), offset: 0, message }];
+                report.lost_wal_files.push(quarantine(&path, damage)?);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    if !options.dry_run && !report.lost_wal_files.is_empty() {
+        crate::io::sync_dir(&wal_dir)?;
+    }
+
+    report.sst_files_kept = kept.len() as u64;
+    let changed = report.manifest_rebuilt.is_some()
+        || !report.lost_sst_files.is_empty()
+        || !report.missing_sst_files.is_empty();
+    if changed && !options.dry_run {
+        let manifest = match manifest {
+            Some(mut manifest) => {
+                manifest.files = kept;
+                manifest
+            }
+            None => {
+                // Records the SST files hold are told apart from newer
+                // ones by sequence, so no WAL file is taken as flushed
+                let mut manifest = Manifest::new(options.comparator.name());
+                manifest.next_file_number = kept.iter().map(|file| file.id + 1).max().unwrap_or(1);
+                manifest.last_sequence = last_sequence;
+                manifest.files = kept;
+                manifest
+            }
+        };
+        manifest.save(db_path)?;
+    }
+    info!(
+        "Repaired {}: kept {} SST files, quarantined {} SST and {} WAL files, cut {} WAL tails",
+        db_path.display(),
+        report.sst_files_kept,
+        report.lost_sst_files.len(),
+        report.lost_wal_files.len(),
+        report.lost_wal_tails.len()
+    );
+    Ok(report)
+}
+
+/// Open and verify the SST file `id` at `path`, returning level 0 metadata
+/// for it and its highest sequence, or the damage found
+fn check_table(
+    path: &Path,
+    id: u64,
+    comparator: &Comparator,
+) -> std::result::Result<(SstFile, u64), Vec<Corruption>> {
+    let unreadable = |e: Error| vec![Corruption { path: path.to_path_buf(), offset: 0, message: e.to_string() }];
+    let reader = SstReader::open(path, FileAccess::Buffered)
+        .map_err(unreadable)?
+        .with_comparator(comparator.clone());
+    let size = fs::metadata(path).map_err(|e| unreadable(e.into()))?.len();
+
+    let mut bounds: Option<(Vec<u8>, Vec<u8>)> = None;
+    let mut widen = |key: &[u8]| match &mut bounds {
+        Some((smallest, largest)) => {
+            if comparator.compare(key, smallest) == Ordering::Less {
+                *smallest = key.to_vec();
+            }
+            if comparator.compare(key, largest) == Ordering::Greater {
+                *largest = key.to_vec();
+            }
+        }
+        None => bounds = Some((key.to_vec(), key.to_vec())),
+    };
+    let (mut deletion_count, mut max_sequence) = (0, 0);
+    let damage = reader.verify(|entry| {
+        widen(&entry.key.data);
+        deletion_count += u64::from(entry.is_delete());
+        max_sequence = max_sequence.max(entry.sequence);
+    });
+    if !damage.is_empty() {
+        return Err(damage);
+    }
+    for tombstone in reader.range_tombstones() {
+        widen(&tombstone.start.data);
+        widen(&tombstone.end.data);
+        max_sequence = max_sequence.max(tombstone.sequence);
+    }
+    let (smallest_key, largest_key) = bounds.unwrap_or_default();
+    let file = SstFile {
+        id,
+        path: path.to_path_buf(),
+        size,
+        level: 0,
+        entry_count: reader.entry_count(),
+        deletion_count,
+        smallest_key,
+        largest_key,
+        bloom_fpr: reader.bloom_fpr(),
+    };
+    Ok((file, max_sequence))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{AuraEngine, Engine, EngineBuilder};
+    use crate::config::WalSyncPolicy;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_repair_quarantines_damage() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path();
+        let engine = EngineBuilder::new().path(db_path).build().unwrap();
+        for prefix in ["a", "b"] {
+            for i in 0..20 {
+                engine.put_str(&format!("{}_{:02}", prefix, i), "value").unwrap();
+            }
+            engine.flush().unwrap();
+        }
+        engine.close().await.unwrap();
+        drop(engine);
+
+        // Crashing leaves a WAL file behind
+        let mut config = Config::default().with_db_path(db_path.to_path_buf());
+        config.wal.sync_policy = WalSyncPolicy::EveryWrite;
+        config.wal.async_writes = false;
+        let engine = AuraEngine::new(config).unwrap();
+        engine.put_str("c", "value").unwrap();
+        std::mem::forget(engine);
+
+        // Damage the first SST file's first block and append an
+        // undecodable record to the newest WAL file
+        let manifest = Manifest::load(db_path).unwrap().unwrap();
+        let first = manifest.files.iter().map(|file| file.id).min().unwrap();
+        let sst_path = db_path.join("sst").join(sst_file_name(first));
+        let mut data = fs::read(&sst_path).unwrap();
+        data[10] ^= 0xff;
+        fs::write(&sst_path, data).unwrap();
+        let wal_path = fs::read_dir(db_path.join("wal")).unwrap().map(|e| e.unwrap().path()).max().unwrap();
+        let wal_len = fs::metadata(&wal_path).unwrap().len();
+        fs::OpenOptions::new().append(true).open(&wal_path).unwrap().write_all(&[5, 0, 0, 0, 0, 0, 0, 0, 0xee, 1, 2, 3, 4]).unwrap();
+
+        // A dry run reports the damage without touching anything
+        let preview = repair(db_path, RepairOptions::default().with_dry_run(true)).unwrap();
+        assert_eq!(preview.lost_sst_files.len(), 1);
+        assert_eq!(preview.lost_sst_files[0].moved_to, None);
+        assert!(sst_path.exists());
+
+        let report = repair(db_path, RepairOptions::default()).unwrap();
+        assert_eq!(report.sst_files_kept, 1);
+        let lost = &report.lost_sst_files[0];
+        assert_eq!((lost.path.as_path(), lost.damage[0].offset), (sst_path.as_path(), 0));
+        assert!(lost.moved_to.as_ref().unwrap().starts_with(db_path.join(LOST_DIR)));
+        assert!(!sst_path.exists());
+        let tail = &report.lost_wal_tails[0];
+        assert_eq!((tail.damage.offset, tail.bytes), (wal_len, 13));
+        assert_eq!(fs::metadata(&wal_path).unwrap().len(), wal_len);
+        assert!(report.manifest_rebuilt.is_none());
+        assert!(repair(db_path, RepairOptions::default()).unwrap().is_clean());
+
+        // Without a manifest, the surviving file is adopted into level 0
+        fs::remove_file(db_path.join(MANIFEST_FILE)).unwrap();
+        let report = repair(db_path, RepairOptions::default()).unwrap();
+        assert!(report.manifest_rebuilt.is_some());
+        assert_eq!(report.sst_files_kept, 1);
+
+        let engine = EngineBuilder::new().path(db_path).build().unwrap();
+        assert_eq!(engine.get_str("a_05").unwrap(), None);
+        assert_eq!(engine.get_str("b_05").unwrap().as_deref(), Some("value"));
+        assert_eq!(engine.scan_str("a", "b_99").unwrap().len(), 20);
+        engine.close().await.unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+//! Engine-maintained secondary indexes
+//!
+//! A [`SecondaryIndex`] maps an attribute extracted from each value back to
+//! the primary keys holding it. The engine keeps it up to date on every
+//! write: entries live in the LSM tree itself, under a reserved key prefix,
+//! so they are logged, flushed and compacted like any other key. An index
+//! entry's key is
+//!
+//! ```text
+//! INDEX_KEY_PREFIX | name | 0x00 | index_key length (u32 BE) | index_key | primary_key
+//! ```
+//!
+//! and its value is empty. Length-prefixing the index key keeps one index
+//! key from matching the start of another, so the entries for a single
+//! index key form one contiguous prefix.
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Reserved prefix of every index entry's key. User writes to keys
+/// starting with it are rejected, and scans leave such keys out.
+pub const INDEX_KEY_PREFIX: &[u8] = b"\xff\xffauradb.index\x00";
+
+/// Function extracting the indexed attribute from a key and its value
+type ExtractFn = dyn Fn(&[u8], &[u8]) -> Option<Vec<u8>> + Send + Sync;
+
+/// A named secondary index over an attribute of each key/value pair.
+///
+/// Returning `None` from the extractor leaves the pair out of the index.
+/// The extractor must be deterministic: stale entries are found by running
+/// it again on the value being replaced.
+#[derive(Clone)]
+pub struct SecondaryIndex {
+    /// Index name, unique per engine
+    name: Arc<str>,
+    /// Extraction function
+    extractor: Arc<ExtractFn>,
+}
+
+impl SecondaryIndex {
+    /// Create an index from a name and an extraction function
+    pub fn new(
+        name: impl Into<String>,
+        extractor: impl Fn(&[u8], &[u8]) -> Option<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: Arc::from(name.into()),
+            extractor: Arc::new(extractor),
+        }
+    }
+
+    /// Index name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Indexed attribute of a pair, or `None` if it is not indexed
+    pub fn extract(&self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        (self.extractor)(key, value)
+    }
+
+    /// Key prefix shared by every entry for `index_key`
+    pub(crate) fn lookup_prefix(&self, index_key: &[u8]) -> Vec<u8> {
+        let mut prefix = Vec::with_capacity(INDEX_KEY_PREFIX.len() + self.name.len() + 5 + index_key.len());
+        prefix.extend_from_slice(INDEX_KEY_PREFIX);
+        prefix.extend_from_slice(self.name.as_bytes());
+        prefix.push(0);
+        prefix.extend_from_slice(&(index_key.len() as u32).to_be_bytes());
+        prefix.extend_from_slice(index_key);
+        prefix
+    }
+
+    /// Key of the entry mapping `index_key` to `primary_key`
+    pub(crate) fn entry_key(&self, index_key: &[u8], primary_key: &[u8]) -> Vec<u8> {
+        let mut key = self.lookup_prefix(index_key);
+        key.extend_from_slice(primary_key);
+        key
+    }
+}
+
+impl fmt::Debug for SecondaryIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecondaryIndex").field("name", &self.name).finish()
+    }
+}
+
+/// Whether `key` belongs to an index rather than to user data
+pub fn is_index_key(key: &[u8]) -> bool {
+    key.starts_with(INDEX_KEY_PREFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_keys_group_by_index_key() {
+        let index = SecondaryIndex::new("city", |_, value| Some(value.to_vec()));
+        let prefix = index.lookup_prefix(b"ab");
+        let entry = index.entry_key(b"ab", b"user1");
+        assert!(is_index_key(&entry));
+        assert!(entry.starts_with(&prefix));
+        assert!(entry.ends_with(b"user1"));
+        // A longer index key sharing the same first bytes is a different prefix
+        assert!(!index.entry_key(b"abc", b"user1").starts_with(&prefix));
+        assert!(!SecondaryIndex::new("cit", |_, _| None).entry_key(b"ab", b"user1").starts_with(&prefix));
+    }
+}
@@ -1,35 +1,136 @@
 //! SST (Sorted String Table) management module
 //! 
-//! This module will implement multi-level LSM structure with block-based storage,
-//! compression, and Bloom/Ribbon filters.
-//! 
-//! Planned for M2 milestone.
+//! SST files are immutable sorted runs made of checksummed data blocks,
+//! followed by an optional filter block, an optional range tombstone
+//! block, a block index and a fixed-size footer:
+//!
+//! ```text
+//! [data block][checksum] ... [filters][checksum][range tombstones][checksum][index][checksum]
+//! [filter offset|filter size|index offset|index size|entry count|checksum type|compression|
+//!  range tombstone offset|range tombstone size|magic]
+//! ```
+//!
+//! Data blocks are prefix compressed, as in LevelDB. Every
+//! `block_restart_interval` keys a restart point stores its key in full;
+//! the keys in between store only the suffix they don't share with the
+//! previous key. The restart offsets follow the entries, so a point lookup
+//! binary searches them and decodes at most one interval:
+//!
+//! ```text
+//! [shared|unshared|record size|key suffix|record] ... [restart offset] ... [restart count]
+//! ```
+//!
+//! The checksum and compression algorithms are recorded in the footer, so
+//! a file is always read with the ones it was written with. Each data
+//! block is compressed whole, restart points included, and its checksum
+//! covers the compressed bytes; filters and the index are not compressed.
+//! The writer's algorithm is chosen per level (see
+//! [`SstConfig::compression_for_level`]). Point lookups cache blocks
+//! decompressed; with a compressed tier as well, blocks evicted from the
+//! block cache are recompressed into it, so a later lookup only pays
+//! decompression instead of a read. The filter block holds the
+//! whole-key and prefix Bloom filters (see [`crate::filter`]). The range
+//! tombstone block holds the file's [`RangeTombstone`]s, which are loaded
+//! with the index; the file's key range spans them as well as its entries.
+//! Files with older footers are still readable: the 64-byte footer before
+//! range tombstones implies none, the 56-byte footer before compression
+//! implies uncompressed blocks, the original 32-byte footer has no checksum
+//! type (CRC32 is implied) and neither it nor the 40-byte one has filters.
+//! Files whose footer predates restart points have blocks of full,
+//! length-prefixed keys and records.
+//!
+//! Ribbon filters are planned for the M2 milestone.
+
+use crate::cache::{CacheStats, UnifiedCache};
+use crate::checksum::{checksum, verify, ChecksumType};
+use crate::comparator::Comparator;
+use crate::compression;
+use crate::config::{CompressionAlgorithm, SstConfig};
+use crate::error::{Corruption, Error, Result};
+use crate::filter::{BloomFilter, BloomFilterBuilder, PrefixExtractor};
+use crate::io::{Advice, FileAccess, RandomAccessFile, WritableFile};
+use crate::storage::{self, Entry, Key, OpType, RangeTombstone, Value, ValuePointer};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Magic number at the end of SST files with a CRC32-only footer ("AURASST1")
+const SST_MAGIC_V1: u64 = 0x4155_5241_5353_5431;
+
+/// Magic number at the end of SST files recording their checksum type ("AURASST2")
+const SST_MAGIC_V2: u64 = 0x4155_5241_5353_5432;
+
+/// Magic number at the end of SST files with a filter block ("AURASST3")
+const SST_MAGIC_V3: u64 = 0x4155_5241_5353_5433;
+
+/// Magic number at the end of SST files with prefix compressed blocks ("AURASST4")
+const SST_MAGIC_V4: u64 = 0x4155_5241_5353_5434;
+
+/// Magic number at the end of SST files recording their block compression ("AURASST5")
+const SST_MAGIC_V5: u64 = 0x4155_5241_5353_5435;
+
+/// Magic number at the end of SST files with a range tombstone block ("AURASST6")
+const SST_MAGIC: u64 = 0x4155_5241_5353_5436;
+
+/// Original footer layout: index offset, index size, entry count, magic
+const FOOTER_SIZE_V1: usize = 32;
+
+/// Second footer layout: index offset, index size, entry count, checksum type, magic
+const FOOTER_SIZE_V2: usize = 40;
+
+/// Third footer layout: filter offset, filter size, then as in the second layout
+const FOOTER_SIZE_V4: usize = 56;
 
-use crate::error::{Error, Result};
+/// Fourth footer layout: as in the third, with the block compression before the magic
+const FOOTER_SIZE_V5: usize = 64;
+
+/// Footer layout: as in the fourth, with the range tombstone block location
+/// before the magic
+const FOOTER_SIZE: usize = 80;
+
+/// Size of each fixed-width field in a data block
+const U32_SIZE: usize = 4;
+
+/// Size of the checksum trailer after each block
+const BLOCK_TRAILER_SIZE: usize = 4;
 
 /// SST file metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SstFile {
-    /// File path
-    pub path: String,
+    /// File number
+    pub id: u64,
+    /// File path (derived from the file number and SST directory)
+    #[serde(skip)]
+    pub path: PathBuf,
     /// File size in bytes
     pub size: u64,
     /// Level in LSM tree
     pub level: u32,
     /// Number of entries
     pub entry_count: u64,
+    /// Number of tombstones among the entries
+    #[serde(default)]
+    pub deletion_count: u64,
     /// Smallest key
     pub smallest_key: Vec<u8>,
     /// Largest key
     pub largest_key: Vec<u8>,
+    /// Expected false positive rate of the key Bloom filter, if any
+    #[serde(default)]
+    pub bloom_fpr: Option<f64>,
 }
 
 /// SST block information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SstBlock {
     /// Block offset in file
     pub offset: u64,
-    /// Block size in bytes
+    /// Block size in bytes (excluding the checksum trailer)
     pub size: u32,
     /// Number of entries in block
     pub entry_count: u32,
@@ -37,77 +138,1576 @@ pub struct SstBlock {
     pub checksum: u32,
 }
 
-/// SST reader for reading data from SST files
+/// Index entry pointing at a data block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    /// Largest key stored in the block
+    last_key: Vec<u8>,
+    /// Block location
+    block: SstBlock,
+}
+
+/// Bloom filters stored in an SST's filter block
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FilterBlock {
+    /// Filter over whole keys
+    key_filter: Option<BloomFilter>,
+    /// Name of the extractor the prefix filter was built with
+    prefix_extractor: Option<String>,
+    /// Filter over extracted key prefixes
+    prefix_filter: Option<BloomFilter>,
+}
+
+/// Decoded SST footer
+struct Footer {
+    /// Filter block location; size 0 when the file has none
+    filter_offset: u64,
+    filter_size: u64,
+    /// Range tombstone block location; size 0 when the file has none
+    range_del_offset: u64,
+    range_del_size: u64,
+    /// Index block location
+    index_offset: u64,
+    index_size: u64,
+    /// Number of entries in the file
+    entry_count: u64,
+    /// Checksum algorithm of every block
+    checksum_type: ChecksumType,
+    /// Compression of the data blocks
+    compression: CompressionAlgorithm,
+    /// Layout of the data blocks
+    block_format: BlockFormat,
+}
+
+impl Footer {
+    /// Read the footer of any supported layout
+    fn read(file: &RandomAccessFile) -> Result<Self> {
+        let path = file.path();
+        if file.len() < FOOTER_SIZE_V1 as u64 {
+            return Err(Error::SstCorruption(format!("File too small: {}", path.display())));
+        }
+
+        let magic = read_u64(&file.read_at(file.len() - 8, 8)?, 0);
+        let (footer_size, filter_fields) = match magic {
+            SST_MAGIC => (FOOTER_SIZE, 16),
+            SST_MAGIC_V5 => (FOOTER_SIZE_V5, 16),
+            SST_MAGIC_V4 | SST_MAGIC_V3 => (FOOTER_SIZE_V4, 16),
+            SST_MAGIC_V2 => (FOOTER_SIZE_V2, 0),
+            SST_MAGIC_V1 => (FOOTER_SIZE_V1, 0),
+            _ => return Err(Error::SstCorruption(format!("Bad magic number in {}", path.display()))),
+        };
+        if file.len() < footer_size as u64 {
+            return Err(Error::SstCorruption(format!("File too small: {}", path.display())));
+        }
+        let footer = file.read_at(file.len() - footer_size as u64, footer_size)?;
+
+        let checksum_type = if magic == SST_MAGIC_V1 {
+            ChecksumType::Crc32
+        } else {
+            let tag = read_u64(&footer, filter_fields + 24);
+            u8::try_from(tag).ok().and_then(ChecksumType::from_u8).ok_or_else(|| {
+                Error::SstCorruption(format!("Unknown checksum type {} in {}", tag, path.display()))
+            })?
+        };
+        let compression = if magic == SST_MAGIC || magic == SST_MAGIC_V5 {
+            let tag = read_u64(&footer, filter_fields + 32);
+            u8::try_from(tag).ok().and_then(CompressionAlgorithm::from_u8).ok_or_else(|| {
+                Error::SstCorruption(format!("Unknown compression {} in {}", tag, path.display()))
+            })?
+        } else {
+            CompressionAlgorithm::None
+        };
+        let (filter_offset, filter_size) = if filter_fields > 0 {
+            (read_u64(&footer, 0), read_u64(&footer, 8))
+        } else {
+            (0, 0)
+        };
+        let (range_del_offset, range_del_size) = if magic == SST_MAGIC {
+            (read_u64(&footer, filter_fields + 40), read_u64(&footer, filter_fields + 48))
+        } else {
+            (0, 0)
+        };
+
+        Ok(Self {
+            filter_offset,
+            filter_size,
+            range_del_offset,
+            range_del_size,
+            index_offset: read_u64(&footer, filter_fields),
+            index_size: read_u64(&footer, filter_fields + 8),
+            entry_count: read_u64(&footer, filter_fields + 16),
+            checksum_type,
+            compression,
+            block_format: if magic == SST_MAGIC || magic == SST_MAGIC_V5 || magic == SST_MAGIC_V4 {
+                BlockFormat::Restarts
+            } else {
+                BlockFormat::Plain
+            },
+        })
+    }
+
+    /// Encode in the current layout
+    fn encode(&self) -> Vec<u8> {
+        let mut footer = Vec::with_capacity(FOOTER_SIZE);
+        footer.extend_from_slice(&self.filter_offset.to_le_bytes());
+        footer.extend_from_slice(&self.filter_size.to_le_bytes());
+        footer.extend_from_slice(&self.index_offset.to_le_bytes());
+        footer.extend_from_slice(&self.index_size.to_le_bytes());
+        footer.extend_from_slice(&self.entry_count.to_le_bytes());
+        footer.extend_from_slice(&u64::from(self.checksum_type.as_u8()).to_le_bytes());
+        footer.extend_from_slice(&u64::from(self.compression.as_u8()).to_le_bytes());
+        footer.extend_from_slice(&self.range_del_offset.to_le_bytes());
+        footer.extend_from_slice(&self.range_del_size.to_le_bytes());
+        footer.extend_from_slice(&SST_MAGIC.to_le_bytes());
+        footer
+    }
+}
+
+/// Everything stored for an entry besides its key
+#[derive(Debug, Serialize, Deserialize)]
+struct BlockRecord {
+    op_type: OpType,
+    sequence: u64,
+    timestamp: u64,
+    value: Option<Value>,
+    value_pointer: Option<ValuePointer>,
+}
+
+impl BlockRecord {
+    fn from_entry(entry: &Entry) -> Self {
+        Self {
+            op_type: entry.op_type.clone(),
+            sequence: entry.sequence,
+            timestamp: entry.timestamp,
+            value: entry.value.clone(),
+            value_pointer: entry.value_pointer.clone(),
+        }
+    }
+
+    fn into_entry(self, key: Vec<u8>) -> Entry {
+        Entry {
+            key: Key::new(key),
+            value: self.value,
+            value_pointer: self.value_pointer,
+            sequence: self.sequence,
+            op_type: self.op_type,
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+/// Read a little-endian u32 length prefix at `pos`
+fn read_u32(data: &[u8], pos: usize) -> Result<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| Error::SstCorruption(format!("Truncated length at offset {}", pos)))
+}
+
+/// Read a little-endian u64 at `pos`
+fn read_u64(data: &[u8], pos: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[pos..pos + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Read a length-prefixed byte string at `pos`, returning it and the next position
+fn read_slice(data: &[u8], pos: usize) -> Result<(&[u8], usize)> {
+    let len = read_u32(data, pos)? as usize;
+    let start = pos + 4;
+    data.get(start..start + len)
+        .map(|slice| (slice, start + len))
+        .ok_or_else(|| Error::SstCorruption(format!("Truncated record at offset {}", pos)))
+}
+
+/// How the entries of a data block are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockFormat {
+    /// Full length-prefixed keys and records, without restart points
+    Plain,
+    /// Prefix compressed keys with restart points
+    Restarts,
+}
+
+/// Decode all entries of a data block
+fn decode_block(data: &[u8], format: BlockFormat) -> Result<Vec<Entry>> {
+    match format {
+        BlockFormat::Plain => decode_plain_block(data),
+        BlockFormat::Restarts => RestartBlock::parse(data)?.entries(),
+    }
+}
+
+/// Find `key` in a data block
+fn search_block(data: &[u8], format: BlockFormat, key: &[u8], comparator: &Comparator) -> Result<Option<Entry>> {
+    match format {
+        BlockFormat::Plain => Ok(decode_plain_block(data)?.into_iter().find(|entry| entry.key.data == key)),
+        BlockFormat::Restarts => RestartBlock::parse(data)?.get(key, comparator),
+    }
+}
+
+/// Decode all entries of a block without restart points
+fn decode_plain_block(data: &[u8]) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let (key, next) = read_slice(data, pos)?;
+        let (record, next) = read_slice(data, next)?;
+        let record: BlockRecord = bincode::deserialize(record)?;
+        entries.push(record.into_entry(key.to_vec()));
+        pos = next;
+    }
+    Ok(entries)
+}
+
+/// A prefix compressed data block
+struct RestartBlock<'a> {
+    /// Encoded entries
+    entries: &'a [u8],
+    /// Restart offsets into `entries`, as little-endian u32s
+    restarts: &'a [u8],
+}
+
+impl<'a> RestartBlock<'a> {
+    /// Split a block into its entries and restart array
+    fn parse(data: &'a [u8]) -> Result<Self> {
+        let corrupt = || Error::SstCorruption("Truncated restart array in data block".to_string());
+        let count_pos = data.len().checked_sub(U32_SIZE).ok_or_else(corrupt)?;
+        let count = read_u32(data, count_pos)? as usize;
+        let restarts_pos = count
+            .checked_mul(U32_SIZE)
+            .and_then(|size| count_pos.checked_sub(size))
+            .ok_or_else(corrupt)?;
+        Ok(Self {
+            entries: &data[..restarts_pos],
+            restarts: &data[restarts_pos..count_pos],
+        })
+    }
+
+    /// Number of restart points
+    fn restart_count(&self) -> usize {
+        self.restarts.len() / U32_SIZE
+    }
+
+    /// Offset of restart point `i`
+    fn restart(&self, i: usize) -> Result<usize> {
+        Ok(read_u32(self.restarts, i * U32_SIZE)? as usize)
+    }
+
+    /// Decode the entry at `pos`, whose key shares a prefix with `key`,
+    /// leaving its full key in `key`. Returns the encoded record and the
+    /// position of the next entry.
+    fn decode_at(&self, pos: usize, key: &mut Vec<u8>) -> Result<(&'a [u8], usize)> {
+        let data = self.entries;
+        let shared = read_u32(data, pos)? as usize;
+        let unshared = read_u32(data, pos + U32_SIZE)? as usize;
+        let record_len = read_u32(data, pos + 2 * U32_SIZE)? as usize;
+        let start = pos + 3 * U32_SIZE;
+        let truncated = || Error::SstCorruption(format!("Truncated record at offset {}", pos));
+        if shared > key.len() {
+            return Err(Error::SstCorruption(format!("Bad shared key length at offset {}", pos)));
+        }
+        let suffix = data.get(start..start + unshared).ok_or_else(truncated)?;
+        let record = data
+            .get(start + unshared..start + unshared + record_len)
+            .ok_or_else(truncated)?;
+        key.truncate(shared);
+        key.extend_from_slice(suffix);
+        Ok((record, start + unshared + record_len))
+    }
+
+    /// Decode every entry in order
+    fn entries(&self) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        let mut key = Vec::new();
+        let mut pos = 0;
+        while pos < self.entries.len() {
+            let (record, next) = self.decode_at(pos, &mut key)?;
+            let record: BlockRecord = bincode::deserialize(record)?;
+            entries.push(record.into_entry(key.clone()));
+            pos = next;
+        }
+        Ok(entries)
+    }
+
+    /// Find `target`: binary search the restart points for the last one
+    /// at or before it, then decode forward from there
+    fn get(&self, target: &[u8], comparator: &Comparator) -> Result<Option<Entry>> {
+        let (mut low, mut high) = (0, self.restart_count());
+        while low < high {
+            let mid = (low + high) / 2;
+            let mut key = Vec::new();
+            self.decode_at(self.restart(mid)?, &mut key)?;
+            if comparator.compare(&key, target) == Ordering::Greater {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        // `low` restart points have keys at or before the target
+        let Some(first) = low.checked_sub(1) else {
+            return Ok(None);
+        };
+
+        let mut key = Vec::new();
+        let mut pos = self.restart(first)?;
+        while pos < self.entries.len() {
+            let (record, next) = self.decode_at(pos, &mut key)?;
+            match comparator.compare(&key, target) {
+                Ordering::Less => pos = next,
+                Ordering::Equal => {
+                    let record: BlockRecord = bincode::deserialize(record)?;
+                    return Ok(Some(record.into_entry(key)));
+                }
+                Ordering::Greater => break,
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// SST reader for reading data from SST files.
+///
+/// With `FileAccess::Mmap` the reader maps the whole file for its lifetime
+/// and decodes blocks straight out of the mapping. A mapped file must not
+/// be truncated or rewritten while a reader is alive, so compaction may only
+/// remove an SST once every reader for it has been dropped.
+#[derive(Debug)]
 pub struct SstReader {
-    // TODO: Implement SST reading functionality
+    /// Underlying file
+    file: RandomAccessFile,
+    /// Block index, ordered by key
+    index: Vec<IndexEntry>,
+    /// Number of entries in the file
+    entry_count: u64,
+    /// Checksum algorithm the file was written with
+    checksum_type: ChecksumType,
+    /// Compression of the data blocks
+    compression: CompressionAlgorithm,
+    /// Layout of the data blocks
+    block_format: BlockFormat,
+    /// Whole-key and prefix Bloom filters
+    filters: FilterBlock,
+    /// Range tombstones stored in the file
+    range_tombstones: Vec<RangeTombstone>,
+    /// Order the file was written in
+    comparator: Comparator,
+    /// Shared block cache and this file's number, used by point lookups
+    block_cache: Option<(Arc<Mutex<UnifiedCache>>, u64)>,
+    /// Shared cache of blocks demoted from the block cache, compressed
+    compressed_cache: Option<Arc<Mutex<UnifiedCache>>>,
+    /// Counter of block bytes point lookups read from the file
+    read_bytes: Option<Arc<AtomicU64>>,
+    /// Whether to pass access pattern hints to the OS
+    fadvise: bool,
 }
 
 impl SstReader {
-    /// Create a new SST reader
-    pub fn new(_path: &str) -> Result<Self> {
-        // TODO: Implement
-        Err(Error::Unknown("SST reader not implemented yet".to_string()))
+    /// Open an SST file and load its index and filters
+    pub fn open(path: &Path, access: FileAccess) -> Result<Self> {
+        let file = RandomAccessFile::open(path, access)?;
+        let footer = Footer::read(&file)?;
+
+        let index_data = Self::read_checked(&file, footer.index_offset, footer.index_size as usize, footer.checksum_type)?;
+        let index: Vec<IndexEntry> = bincode::deserialize(&index_data)?;
+        let filters: FilterBlock = if footer.filter_size > 0 {
+            let data = Self::read_checked(&file, footer.filter_offset, footer.filter_size as usize, footer.checksum_type)?;
+            bincode::deserialize(&data)?
+        } else {
+            FilterBlock::default()
+        };
+        let range_tombstones: Vec<RangeTombstone> = if footer.range_del_size > 0 {
+            let data =
+                Self::read_checked(&file, footer.range_del_offset, footer.range_del_size as usize, footer.checksum_type)?;
+            bincode::deserialize(&data)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            file,
+            index,
+            entry_count: footer.entry_count,
+            checksum_type: footer.checksum_type,
+            compression: footer.compression,
+            block_format: footer.block_format,
+            filters,
+            range_tombstones,
+            comparator: Comparator::default(),
+            block_cache: None,
+            compressed_cache: None,
+            read_bytes: None,
+            fadvise: false,
+        })
     }
-    
-    /// Read a block from the SST file
-    pub fn read_block(&mut self, _block: &SstBlock) -> Result<Vec<u8>> {
-        // TODO: Implement
-        Err(Error::Unknown("SST block reading not implemented yet".to_string()))
+
+    /// Pass access pattern hints for the file to the OS (see
+    /// [`RandomAccessFile::advise`]): random for point lookups, and
+    /// sequential while [`Self::entries`] reads the whole file
+    pub fn with_fadvise(mut self, enabled: bool) -> Self {
+        self.fadvise = enabled;
+        if enabled {
+            self.file.advise(Advice::Random, 0, 0);
+        }
+        self
+    }
+
+    /// Add the bytes of every block [`Self::get`] reads from the file
+    /// (rather than the block cache) to `counter`
+    pub fn with_read_counter(mut self, counter: Arc<AtomicU64>) -> Self {
+        self.read_bytes = Some(counter);
+        self
+    }
+
+    fn count_read(&self, block: &SstBlock) {
+        if let Some(counter) = &self.read_bytes {
+            counter.fetch_add(block.size as u64, AtomicOrdering::Relaxed);
+        }
+    }
+
+    /// Cache blocks read by [`Self::get`] in `cache`, keyed by `file_id`
+    pub fn with_block_cache(mut self, cache: Arc<Mutex<UnifiedCache>>, file_id: u64) -> Self {
+        self.block_cache = Some((cache, file_id));
+        self
+    }
+
+    /// Demote blocks evicted from the block cache into `cache`, compressed
+    /// with [`DEMOTED_BLOCK_COMPRESSION`], and look there on a block cache
+    /// miss before reading the file
+    pub fn with_compressed_cache(mut self, cache: Arc<Mutex<UnifiedCache>>) -> Self {
+        self.compressed_cache = Some(cache);
+        self
+    }
+
+    /// Set the comparator the file was written with
+    pub fn with_comparator(mut self, comparator: Comparator) -> Self {
+        self.comparator = comparator;
+        self
+    }
+
+    /// Checksum algorithm the file was written with
+    pub fn checksum_type(&self) -> ChecksumType {
+        self.checksum_type
+    }
+
+    /// Compression the data blocks were written with
+    pub fn compression(&self) -> CompressionAlgorithm {
+        self.compression
+    }
+
+    /// Whether the data blocks use restart points, as every file written
+    /// by this build does; older layouts are plain runs of entries
+    pub fn has_restart_points(&self) -> bool {
+        self.block_format == BlockFormat::Restarts
+    }
+
+    /// Whether the file may hold `key`; false only if its Bloom filter
+    /// rules the key out
+    pub fn may_contain(&self, key: &[u8]) -> bool {
+        self.filters.key_filter.as_ref().is_none_or(|filter| filter.may_contain(key))
+    }
+
+    /// Bytes of Bloom filters held in memory for this file
+    pub fn filter_memory(&self) -> usize {
+        [&self.filters.key_filter, &self.filters.prefix_filter]
+            .into_iter()
+            .flatten()
+            .map(BloomFilter::memory_usage)
+            .sum()
+    }
+
+    /// Expected false positive rate of the key Bloom filter, if the file
+    /// has one
+    pub fn bloom_fpr(&self) -> Option<f64> {
+        self.filters.key_filter.as_ref().map(|filter| filter.false_positive_rate(self.entry_count))
     }
+
+    /// Whether the file may hold a key starting with `prefix`. False only
+    /// if the file has a prefix filter built by `extractor` that rules out
+    /// the prefix `extractor` takes from `prefix`.
+    pub fn may_contain_prefix(&self, prefix: &[u8], extractor: &PrefixExtractor) -> bool {
+        let Some(filter) = &self.filters.prefix_filter else {
+            return true;
+        };
+        if self.filters.prefix_extractor.as_deref() != Some(extractor.name()) {
+            return true;
+        }
+        extractor.extract(prefix).is_none_or(|p| filter.may_contain(p))
+    }
+
+    /// Read `size` bytes at `offset` and verify the checksum trailer
+    fn read_checked(
+        file: &RandomAccessFile,
+        offset: u64,
+        size: usize,
+        algo: ChecksumType,
+    ) -> Result<Cow<'_, [u8]>> {
+        // Sizes come from the footer and index, so bound them by the file
+        // before trusting them with an allocation
+        let len = size
+            .checked_add(BLOCK_TRAILER_SIZE)
+            .filter(|&len| offset.checked_add(len as u64).is_some_and(|end| end <= file.len()))
+            .ok_or_else(|| {
+                Error::SstCorruption(format!(
+                    "Block of {} bytes at offset {} extends past the end of {} ({} bytes)",
+                    size,
+                    offset,
+                    file.path().display(),
+                    file.len()
+                ))
+            })?;
+        let data = file.read_bytes(offset, len)?;
+        let expected = read_u32(&data, size)?;
+        let data = match data {
+            Cow::Borrowed(bytes) => Cow::Borrowed(&bytes[..size]),
+            Cow::Owned(mut bytes) => {
+                bytes.truncate(size);
+                Cow::Owned(bytes)
+            }
+        };
+        if !verify(&data, expected, algo) {
+            return Err(Error::SstCorruption(format!(
+                "Block checksum mismatch at offset {} in {}: expected {}, got {}",
+                offset,
+                file.path().display(),
+                expected,
+                checksum(&data, algo)
+            )));
+        }
+        Ok(data)
+    }
+
+    /// Read a block from the SST file, verifying its checksum
+    pub fn read_block(&self, block: &SstBlock) -> Result<Vec<u8>> {
+        Ok(self.block_bytes(block)?.into_owned())
+    }
+
+    /// Checked and decompressed block contents, borrowed from the mapping
+    /// when mmap'd and uncompressed
+    fn block_bytes(&self, block: &SstBlock) -> Result<Cow<'_, [u8]>> {
+        let data = Self::read_checked(&self.file, block.offset, block.size as usize, self.checksum_type)?;
+        if self.compression == CompressionAlgorithm::None {
+            return Ok(data);
+        }
+        compression::decompress(&data, self.compression).map(Cow::Owned).map_err(|e| {
+            Error::SstCorruption(format!(
+                "Undecodable block at offset {} in {}: {}",
+                block.offset,
+                self.file.path().display(),
+                e
+            ))
+        })
+    }
+
+    /// Range tombstones stored in the file
+    pub fn range_tombstones(&self) -> &[RangeTombstone] {
+        &self.range_tombstones
+    }
+
+    /// Look up a key: its entry, or a tombstone if one of the file's range
+    /// tombstones covers it and is newer
+    pub fn get(&self, key: &[u8]) -> Result<Option<Entry>> {
+        let deleted_at = storage::newest_covering(&self.range_tombstones, key, &self.comparator);
+        let entry = self.get_entry(key)?;
+        Ok(storage::shadow_entry(key, entry, deleted_at))
+    }
+
+    /// Look up a key's entry
+    fn get_entry(&self, key: &[u8]) -> Result<Option<Entry>> {
+        if !self.may_contain(key) {
+            return Ok(None);
+        }
+        // First block whose last key is >= the target
+        let idx = self
+            .index
+            .partition_point(|entry| self.comparator.compare(&entry.last_key, key) == Ordering::Less);
+        let Some(entry) = self.index.get(idx) else {
+            return Ok(None);
+        };
+
+        match &self.block_cache {
+            Some((cache, file_id)) => {
+                let data = self.cached_block(cache, *file_id, &entry.block)?;
+                search_block(&data, self.block_format, key, &self.comparator)
+            }
+            None => {
+                self.count_read(&entry.block);
+                search_block(&self.block_bytes(&entry.block)?, self.block_format, key, &self.comparator)
+            }
+        }
+    }
+
+    /// Checked block contents, served from and added to the block cache.
+    /// A miss there is looked up in the compressed tier, whose hits move
+    /// back into the block cache, before the file is read.
+    fn cached_block(&self, cache: &Mutex<UnifiedCache>, file_id: u64, block: &SstBlock) -> Result<Arc<[u8]>> {
+        let cache_key = block_cache_key(file_id, block);
+        if let Some(data) = cache.lock().get(&cache_key) {
+            return Ok(data);
+        }
+        let data: Arc<[u8]> = match self.compressed_cache.as_ref().and_then(|tier| tier.lock().take(&cache_key)) {
+            Some(packed) => Arc::from(compression::decompress(&packed, DEMOTED_BLOCK_COMPRESSION)?),
+            None => {
+                self.count_read(block);
+                Arc::from(&*self.block_bytes(block)?)
+            }
+        };
+        self.cache_block(cache, cache_key, Arc::clone(&data))?;
+        Ok(data)
+    }
+
+    /// Add a block to the block cache, demoting the blocks it evicts into
+    /// the compressed tier, if any. Blocks that don't shrink are dropped.
+    fn cache_block(&self, cache: &Mutex<UnifiedCache>, cache_key: Vec<u8>, data: Arc<[u8]>) -> Result<()> {
+        let evicted = cache.lock().put_evicting(cache_key, data)?;
+        let Some(tier) = &self.compressed_cache else {
+            return Ok(());
+        };
+        for (key, data) in evicted {
+            let packed = compression::compress(&data, DEMOTED_BLOCK_COMPRESSION)?;
+            if packed.len() < data.len() {
+                tier.lock().put(key, Arc::from(packed))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the blocks that may hold keys in `[start, end]` (every block
+    /// if `range` is `None`) into the block cache. Returns false, having
+    /// stopped early, once the next block no longer fits in the cache.
+    /// Files without a block cache have nothing to warm.
+    pub fn warm_blocks(&self, range: Option<(&[u8], &[u8])>) -> Result<bool> {
+        let Some((cache, file_id)) = &self.block_cache else {
+            return Ok(true);
+        };
+        for entry in &self.index[self.covering_blocks(range)] {
+            let cache_key = block_cache_key(*file_id, &entry.block);
+            {
+                let cache = cache.lock();
+                if cache.contains(&cache_key) {
+                    continue;
+                }
+                let stats = cache.stats();
+                if stats.size + entry.block.size as usize > stats.capacity {
+                    return Ok(false);
+                }
+            }
+            let data: Arc<[u8]> = Arc::from(&*self.block_bytes(&entry.block)?);
+            self.cache_block(cache, cache_key, data)?;
+        }
+        Ok(true)
+    }
+
+    /// Indexes of the blocks that may hold keys in `[start, end]` (every
+    /// block if `range` is `None`)
+    fn covering_blocks(&self, range: Option<(&[u8], &[u8])>) -> std::ops::Range<usize> {
+        let Some((start, end)) = range else {
+            return 0..self.index.len();
+        };
+        let ends_before = |entry: &IndexEntry, bound: &[u8]| self.comparator.compare(&entry.last_key, bound) == Ordering::Less;
+        let first = self.index.partition_point(|entry| ends_before(entry, start));
+        // Blocks up to and including the first one ending at or after `end`
+        let last = first + self.index[first..].partition_point(|entry| ends_before(entry, end)) + 1;
+        first..last.min(self.index.len())
+    }
+
+    /// Pin the blocks that may hold keys in `[start, end]` in the block
+    /// cache, reading those not cached yet. On failure the blocks pinned
+    /// by this call are unpinned again. Files without a block cache have
+    /// nothing to pin.
+    pub fn pin_blocks(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        let Some((cache, file_id)) = &self.block_cache else {
+            return Ok(());
+        };
+        let blocks = &self.index[self.covering_blocks(Some((start, end)))];
+        for (pinned, entry) in blocks.iter().enumerate() {
+            let load = || {
+                self.count_read(&entry.block);
+                Ok(Arc::from(&*self.block_bytes(&entry.block)?))
+            };
+            let result = cache.lock().pin(block_cache_key(*file_id, &entry.block), load);
+            if let Err(e) = result {
+                let mut cache = cache.lock();
+                for entry in &blocks[..pinned] {
+                    cache.unpin(&block_cache_key(*file_id, &entry.block));
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Undo one [`pin_blocks`](Self::pin_blocks) of the same range
+    pub fn unpin_blocks(&self, start: &[u8], end: &[u8]) {
+        let Some((cache, file_id)) = &self.block_cache else {
+            return;
+        };
+        let mut cache = cache.lock();
+        for entry in &self.index[self.covering_blocks(Some((start, end)))] {
+            cache.unpin(&block_cache_key(*file_id, &entry.block));
+        }
+    }
+
+    /// Drop every cached block of the file, pinned or not, from both tiers
+    fn evict_blocks(&self) {
+        let Some((cache, file_id)) = &self.block_cache else {
+            return;
+        };
+        let mut cache = cache.lock();
+        let mut tier = self.compressed_cache.as_ref().map(|tier| tier.lock());
+        for entry in &self.index {
+            let key = block_cache_key(*file_id, &entry.block);
+            if let Some(tier) = tier.as_mut() {
+                tier.remove(&key);
+            }
+            cache.remove(&key);
+        }
+    }
+
+    /// Read every entry in key order, bypassing the block cache
+    pub fn entries(&self) -> Result<Vec<Entry>> {
+        if self.fadvise {
+            self.file.advise(Advice::Sequential, 0, 0);
+        }
+        let mut entries = Vec::with_capacity(self.entry_count as usize);
+        let result = self.index.iter().try_for_each(|index_entry| {
+            let block = self.block_bytes(&index_entry.block)?;
+            entries.extend(decode_block(&block, self.block_format)?);
+            Ok(())
+        });
+        if self.fadvise {
+            // Whole-file reads don't fill the block cache, and the blocks
+            // are unlikely to be read again soon (the index and filters
+            // were loaded at open); point lookups resume random access
+            self.file.advise(Advice::DontNeed, 0, 0);
+            self.file.advise(Advice::Random, 0, 0);
+        }
+        result.map(|()| entries)
+    }
+
+    /// Iterate over the entries with keys at or after `start` (every entry
+    /// if `None`) in key order, bypassing the block cache. Only the index
+    /// is searched up front; each block is read and decoded when the
+    /// iterator reaches it, so stopping early leaves the rest unread.
+    pub fn iter_from(&self, start: Option<&[u8]>) -> TableIter<'_> {
+        let first = start.map_or(0, |start| {
+            self.index
+                .partition_point(|entry| self.comparator.compare(&entry.last_key, start) == Ordering::Less)
+        });
+        TableIter {
+            reader: self,
+            blocks: self.index[first..].iter(),
+            entries: Vec::new().into_iter(),
+            start: start.map(<[u8]>::to_vec),
+            read_counter: None,
+        }
+    }
+
+    /// Number of entries in the file
+    pub fn entry_count(&self) -> u64 {
+        self.entry_count
+    }
+
+    /// Data blocks in key order
+    pub fn blocks(&self) -> impl Iterator<Item = &SstBlock> {
+        self.index.iter().map(|entry| &entry.block)
+    }
+
+    /// Check every data block: its checksum, that it decodes to the entry
+    /// count the index records, that keys strictly increase across the
+    /// file and end each block at its index key, and that the key Bloom
+    /// filter admits every key. Each entry read is passed to `visit`. A
+    /// block that fails its checksum or does not decode is reported and
+    /// skipped.
+    pub fn verify(&self, mut visit: impl FnMut(&Entry)) -> Vec<Corruption> {
+        let path = self.file.path();
+        let mut corruptions = Vec::new();
+        let mut report = |offset: u64, message: String| {
+            corruptions.push(Corruption { path: path.to_path_buf(), offset, message });
+        };
+        let mut previous: Option<Vec<u8>> = None;
+        let mut total = 0u64;
+        for index_entry in &self.index {
+            let block = &index_entry.block;
+            let entries = match self.block_bytes(block).and_then(|data| decode_block(&data, self.block_format)) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    report(block.offset, e.to_string());
+                    total += block.entry_count as u64;
+                    continue;
+                }
+            };
+            if entries.len() != block.entry_count as usize {
+                report(block.offset, format!("Block holds {} entries, index says {}", entries.len(), block.entry_count));
+            }
+            for entry in &entries {
+                let key = &entry.key.data;
+                if previous.as_ref().is_some_and(|prev| self.comparator.compare(prev, key) != Ordering::Less) {
+                    report(block.offset, format!("Key {:?} out of order", String::from_utf8_lossy(key)));
+                }
+                if !self.may_contain(key) {
+                    report(block.offset, format!("Bloom filter rejects stored key {:?}", String::from_utf8_lossy(key)));
+                }
+                visit(entry);
+                previous = Some(key.clone());
+            }
+            if let Some(last) = entries.last() {
+                if self.comparator.compare(&last.key.data, &index_entry.last_key) != Ordering::Equal {
+                    report(block.offset, "Last key differs from the index".to_string());
+                }
+            }
+            total += entries.len() as u64;
+        }
+        if total != self.entry_count {
+            report(0, format!("Blocks hold {} entries, footer says {}", total, self.entry_count));
+        }
+        corruptions
+    }
+}
+
+/// Entries of an SST file in key order, decoded a block at a time; see
+/// [`SstReader::iter_from`]. A block that fails its checksum or does not
+/// decode ends the iteration with its error.
+pub struct TableIter<'a> {
+    reader: &'a SstReader,
+    /// Blocks not read yet
+    blocks: std::slice::Iter<'a, IndexEntry>,
+    /// Entries of the current block not yielded yet
+    entries: std::vec::IntoIter<Entry>,
+    /// Key the first block read is skipped up to
+    start: Option<Vec<u8>>,
+    /// Counts the bytes of the blocks read, if set
+    read_counter: Option<&'a AtomicU64>,
+}
+
+impl<'a> TableIter<'a> {
+    /// Add the size of every block read to `counter`
+    pub fn with_read_counter(mut self, counter: &'a AtomicU64) -> Self {
+        self.read_counter = Some(counter);
+        self
+    }
+}
+
+impl Iterator for TableIter<'_> {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Result<Entry>> {
+        loop {
+            if let Some(entry) = self.entries.next() {
+                return Some(Ok(entry));
+            }
+            let block = &self.blocks.next()?.block;
+            if let Some(counter) = self.read_counter {
+                counter.fetch_add(block.size as u64, AtomicOrdering::Relaxed);
+            }
+            let decoded = self
+                .reader
+                .block_bytes(block)
+                .and_then(|data| decode_block(&data, self.reader.block_format));
+            let mut entries = match decoded {
+                Ok(entries) => entries,
+                Err(e) => {
+                    self.blocks = [].iter();
+                    return Some(Err(e));
+                }
+            };
+            if let Some(start) = self.start.take() {
+                let comparator = &self.reader.comparator;
+                let before = entries.partition_point(|e| comparator.compare(&e.key.data, &start) == Ordering::Less);
+                entries.drain(..before);
+            }
+            self.entries = entries.into_iter();
+        }
+    }
+}
+
+/// Codec of the compressed block cache tier, fast to decompress whatever
+/// the files' own compression
+pub const DEMOTED_BLOCK_COMPRESSION: CompressionAlgorithm = CompressionAlgorithm::Lz4;
+
+/// Block cache key: file number then block offset
+fn block_cache_key(file_id: u64, block: &SstBlock) -> Vec<u8> {
+    let mut key = Vec::with_capacity(16);
+    key.extend_from_slice(&file_id.to_le_bytes());
+    key.extend_from_slice(&block.offset.to_le_bytes());
+    key
 }
 
-/// SST writer for creating new SST files
+/// SST writer for creating new SST files.
+///
+/// Entries must be added in ascending order of the database's comparator.
 pub struct SstWriter {
-    // TODO: Implement SST writing functionality
+    /// Output path
+    path: PathBuf,
+    /// Output file
+    file: WritableFile,
+    /// Target data block size
+    block_size: usize,
+    /// Checksum algorithm for blocks and the index
+    checksum_type: ChecksumType,
+    /// Compression of the data blocks
+    compression: CompressionAlgorithm,
+    /// Zstd level the data blocks are compressed at
+    compression_level: i32,
+    /// Whole-key filter being built, if enabled
+    key_filter: Option<BloomFilterBuilder>,
+    /// Prefix filter being built with its extractor, if enabled
+    prefix_filter: Option<(PrefixExtractor, BloomFilterBuilder)>,
+    /// Last prefix added to the prefix filter
+    last_prefix: Option<Vec<u8>>,
+    /// Keys between restart points
+    restart_interval: usize,
+    /// Encoded entries of the block being built
+    block: Vec<u8>,
+    /// Restart offsets of the block being built
+    restarts: Vec<u32>,
+    /// Entries in the block being built
+    block_entries: u32,
+    /// Last key added
+    last_key: Vec<u8>,
+    /// First key added
+    smallest_key: Option<Vec<u8>>,
+    /// Finished data blocks
+    index: Vec<IndexEntry>,
+    /// Total entries added
+    entry_count: u64,
+    /// Tombstones added
+    deletion_count: u64,
+    /// Range tombstones added
+    range_tombstones: Vec<RangeTombstone>,
+    /// Key order, used to widen the file's key range to its range tombstones
+    comparator: Comparator,
 }
 
 impl SstWriter {
     /// Create a new SST writer
-    pub fn new(_path: &str) -> Result<Self> {
-        // TODO: Implement
-        Err(Error::Unknown("SST writer not implemented yet".to_string()))
+    pub fn new(path: &Path, config: &SstConfig, access: FileAccess) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: WritableFile::create(path, access)?,
+            block_size: config.block_size.max(1),
+            checksum_type: config.checksum,
+            compression: config.compression,
+            compression_level: config.compression_level,
+            key_filter: config
+                .use_bloom_filters
+                .then(|| BloomFilterBuilder::new(config.bloom_bits())),
+            prefix_filter: config
+                .prefix_extractor
+                .clone()
+                .filter(|_| config.prefix_bloom)
+                .map(|extractor| (extractor, BloomFilterBuilder::new(config.bloom_bits()))),
+            last_prefix: None,
+            restart_interval: config.block_restart_interval.max(1),
+            block: Vec::new(),
+            restarts: Vec::new(),
+            block_entries: 0,
+            last_key: Vec::new(),
+            smallest_key: None,
+            index: Vec::new(),
+            entry_count: 0,
+            deletion_count: 0,
+            range_tombstones: Vec::new(),
+            comparator: Comparator::default(),
+        })
     }
-    
-    /// Write a block to the SST file
-    pub fn write_block(&mut self, _data: &[u8]) -> Result<SstBlock> {
-        // TODO: Implement
-        Err(Error::Unknown("SST block writing not implemented yet".to_string()))
+
+    /// Set the key order entries are added in
+    pub fn with_comparator(mut self, comparator: Comparator) -> Self {
+        self.comparator = comparator;
+        self
     }
-    
+
+    /// Add a range tombstone, in any order relative to the entries
+    pub fn add_range_tombstone(&mut self, tombstone: RangeTombstone) {
+        self.range_tombstones.push(tombstone);
+    }
+
+    /// Compress data blocks with `algo`, at zstd level `level`, instead of
+    /// `SstConfig::compression` (see [`SstConfig::compression_for_level`])
+    pub fn with_compression(mut self, (algo, level): (CompressionAlgorithm, i32)) -> Self {
+        self.compression = algo;
+        self.compression_level = level;
+        self
+    }
+
+    /// Add an entry
+    pub fn add(&mut self, entry: &Entry) -> Result<()> {
+        let record = bincode::serialize(&BlockRecord::from_entry(entry))?;
+        let key = &entry.key.data;
+        let shared = if (self.block_entries as usize).is_multiple_of(self.restart_interval) {
+            self.restarts.push(self.block.len() as u32);
+            0
+        } else {
+            self.last_key.iter().zip(key).take_while(|(a, b)| a == b).count()
+        };
+        self.block.extend_from_slice(&(shared as u32).to_le_bytes());
+        self.block.extend_from_slice(&((key.len() - shared) as u32).to_le_bytes());
+        self.block.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        self.block.extend_from_slice(&key[shared..]);
+        self.block.extend_from_slice(&record);
+        self.block_entries += 1;
+        self.entry_count += 1;
+        if entry.is_delete() {
+            self.deletion_count += 1;
+        }
+
+        if let Some(filter) = &mut self.key_filter {
+            filter.add(&entry.key.data);
+        }
+        if let Some((extractor, filter)) = &mut self.prefix_filter {
+            // Keys arrive sorted, so repeats of a prefix are usually adjacent
+            if let Some(prefix) = extractor.extract(&entry.key.data) {
+                if self.last_prefix.as_deref() != Some(prefix) {
+                    filter.add(prefix);
+                    self.last_prefix = Some(prefix.to_vec());
+                }
+            }
+        }
+
+        if self.smallest_key.is_none() {
+            self.smallest_key = Some(entry.key.data.clone());
+        }
+        self.last_key.clone_from(&entry.key.data);
+
+        if self.block.len() >= self.block_size {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    /// Number of entries added so far
+    pub fn entry_count(&self) -> u64 {
+        self.entry_count
+    }
+
+    /// Approximate file size so far
+    pub fn estimated_size(&self) -> u64 {
+        self.file.len() + self.block.len() as u64
+    }
+
+    /// Append `data` followed by its checksum trailer, returning the checksum
+    fn write_checked(&mut self, data: &[u8]) -> Result<u32> {
+        let checksum = checksum(data, self.checksum_type);
+        self.file.append(data)?;
+        self.file.append(&checksum.to_le_bytes())?;
+        Ok(checksum)
+    }
+
+    /// Write the block being built to the file
+    fn flush_block(&mut self) -> Result<()> {
+        if self.block_entries == 0 {
+            return Ok(());
+        }
+
+        let offset = self.file.len();
+        let mut data = std::mem::take(&mut self.block);
+        let restarts = std::mem::take(&mut self.restarts);
+        for restart in &restarts {
+            data.extend_from_slice(&restart.to_le_bytes());
+        }
+        data.extend_from_slice(&(restarts.len() as u32).to_le_bytes());
+        if self.compression != CompressionAlgorithm::None {
+            data = compression::compress_with_level(&data, self.compression, self.compression_level)?;
+        }
+        let checksum = self.write_checked(&data)?;
+        self.index.push(IndexEntry {
+            last_key: self.last_key.clone(),
+            block: SstBlock {
+                offset,
+                size: data.len() as u32,
+                entry_count: self.block_entries,
+                checksum,
+            },
+        });
+        self.block_entries = 0;
+        Ok(())
+    }
+
     /// Finalize the SST file
-    pub fn finish(&mut self) -> Result<SstFile> {
-        // TODO: Implement
-        Err(Error::Unknown("SST file finalization not implemented yet".to_string()))
+    pub fn finish(mut self) -> Result<SstFile> {
+        self.flush_block()?;
+
+        let filters = FilterBlock {
+            key_filter: self.key_filter.take().map(BloomFilterBuilder::finish),
+            prefix_extractor: self.prefix_filter.as_ref().map(|(extractor, _)| extractor.name().to_string()),
+            prefix_filter: self.prefix_filter.take().map(|(_, filter)| filter.finish()),
+        };
+        let filter_offset = self.file.len();
+        let filter_size = if filters.key_filter.is_some() || filters.prefix_filter.is_some() {
+            let filter_data = bincode::serialize(&filters)?;
+            self.write_checked(&filter_data)?;
+            filter_data.len() as u64
+        } else {
+            0
+        };
+
+        let range_del_offset = self.file.len();
+        let range_del_size = if self.range_tombstones.is_empty() {
+            0
+        } else {
+            let range_del_data = bincode::serialize(&self.range_tombstones)?;
+            self.write_checked(&range_del_data)?;
+            range_del_data.len() as u64
+        };
+
+        let index_offset = self.file.len();
+        let index_data = bincode::serialize(&self.index)?;
+        self.write_checked(&index_data)?;
+
+        let footer = Footer {
+            filter_offset,
+            filter_size,
+            range_del_offset,
+            range_del_size,
+            index_offset,
+            index_size: index_data.len() as u64,
+            entry_count: self.entry_count,
+            checksum_type: self.checksum_type,
+            compression: self.compression,
+            block_format: BlockFormat::Restarts,
+        };
+        self.file.append(&footer.encode())?;
+
+        let size = self.file.len();
+        self.file.finish()?;
+        // The file must be reachable after a crash before a manifest names it
+        crate::io::sync_parent_dir(&self.path)?;
+
+        // The key range spans the range tombstones, so reads and compactions
+        // of the keys they cover find the file
+        let mut smallest_key = self.smallest_key;
+        let mut largest_key = (self.entry_count > 0).then_some(self.last_key);
+        for tombstone in &self.range_tombstones {
+            if smallest_key
+                .as_ref()
+                .is_none_or(|smallest| self.comparator.compare(&tombstone.start.data, smallest) == Ordering::Less)
+            {
+                smallest_key = Some(tombstone.start.data.clone());
+            }
+            if largest_key
+                .as_ref()
+                .is_none_or(|largest| self.comparator.compare(&tombstone.end.data, largest) == Ordering::Greater)
+            {
+                largest_key = Some(tombstone.end.data.clone());
+            }
+        }
+
+        Ok(SstFile {
+            id: parse_sst_file_number(&self.path.file_name().unwrap_or_default().to_string_lossy()).unwrap_or(0),
+            path: self.path,
+            size,
+            level: 0,
+            entry_count: self.entry_count,
+            deletion_count: self.deletion_count,
+            smallest_key: smallest_key.unwrap_or_default(),
+            largest_key: largest_key.unwrap_or_default(),
+            bloom_fpr: filters.key_filter.as_ref().map(|filter| filter.false_positive_rate(self.entry_count)),
+        })
     }
 }
 
+/// Number of levels in the LSM tree
+pub const NUM_LEVELS: usize = 7;
+
+/// File name of the SST with the given file number
+pub fn sst_file_name(id: u64) -> String {
+    format!("{:06}.sst", id)
+}
+
+/// Parse the file number out of an SST file name
+pub fn parse_sst_file_number(file_name: &str) -> Option<u64> {
+    file_name.strip_suffix(".sst")?.parse().ok()
+}
+
+/// An open SST file shared by every version that references it.
+///
+/// Once a compaction replaces the file it is marked obsolete, and the file
+/// is deleted when the last version (and thus the last reader) drops it.
+#[derive(Debug)]
+pub struct TableHandle {
+    /// File metadata
+    pub meta: SstFile,
+    /// Open reader
+    reader: SstReader,
+    /// Whether the file should be deleted once unreferenced
+    obsolete: AtomicBool,
+}
+
+impl TableHandle {
+    /// Reader for this file
+    pub fn reader(&self) -> &SstReader {
+        &self.reader
+    }
+
+    /// Whether `key` falls within the file's key range
+    fn may_contain(&self, key: &[u8], comparator: &Comparator) -> bool {
+        comparator.compare(key, &self.meta.smallest_key) != Ordering::Less
+            && comparator.compare(key, &self.meta.largest_key) != Ordering::Greater
+    }
+
+    /// Whether the file's key range intersects `[smallest, largest]`
+    pub fn overlaps(&self, smallest: &[u8], largest: &[u8], comparator: &Comparator) -> bool {
+        comparator.compare(&self.meta.largest_key, smallest) != Ordering::Less
+            && comparator.compare(&self.meta.smallest_key, largest) != Ordering::Greater
+    }
+}
+
+impl Drop for TableHandle {
+    fn drop(&mut self) {
+        if self.obsolete.load(AtomicOrdering::Acquire) {
+            self.reader.evict_blocks();
+            if let Err(e) = std::fs::remove_file(&self.meta.path) {
+                warn!("Failed to remove obsolete SST {}: {}", self.meta.path.display(), e);
+            }
+        }
+    }
+}
+
+/// Immutable view of the SST files in each level.
+///
+/// Level 0 files may overlap and are ordered newest first; files in deeper
+/// levels are disjoint and ordered by key.
+#[derive(Debug, Clone)]
+pub struct Version {
+    /// Files per level
+    levels: Vec<Vec<Arc<TableHandle>>>,
+}
+
+impl Default for Version {
+    fn default() -> Self {
+        Self {
+            levels: vec![Vec::new(); NUM_LEVELS],
+        }
+    }
+}
+
+impl Version {
+    /// Files in a level
+    pub fn level(&self, level: usize) -> &[Arc<TableHandle>] {
+        &self.levels[level]
+    }
+
+    /// Total bytes stored in a level
+    pub fn level_size(&self, level: usize) -> u64 {
+        self.levels[level].iter().map(|t| t.meta.size).sum()
+    }
+
+    /// Every file in the version
+    pub fn files(&self) -> impl Iterator<Item = &Arc<TableHandle>> {
+        self.levels.iter().flatten()
+    }
+
+    /// Range tombstones of every file in the version
+    pub fn range_tombstones(&self) -> impl Iterator<Item = &RangeTombstone> {
+        self.files().flat_map(|t| t.reader.range_tombstones())
+    }
+
+    /// Files in `level` whose key range intersects `[smallest, largest]`
+    pub fn overlapping(
+        &self,
+        level: usize,
+        smallest: &[u8],
+        largest: &[u8],
+        comparator: &Comparator,
+    ) -> Vec<Arc<TableHandle>> {
+        self.levels[level]
+            .iter()
+            .filter(|t| t.overlaps(smallest, largest, comparator))
+            .cloned()
+            .collect()
+    }
+
+    /// Find the newest entry for `key`, searching from level 0 down
+    pub fn get(&self, key: &[u8], comparator: &Comparator) -> Result<Option<Entry>> {
+        Ok(self.get_with_level(key, comparator)?.map(|(entry, _)| entry))
+    }
+
+    /// Like [`Self::get`], also returning the level the entry was found in
+    pub fn get_with_level(&self, key: &[u8], comparator: &Comparator) -> Result<Option<(Entry, usize)>> {
+        for table in &self.levels[0] {
+            if table.may_contain(key, comparator) {
+                if let Some(entry) = table.reader.get(key)? {
+                    return Ok(Some((entry, 0)));
+                }
+            }
+        }
+
+        for (level, files) in self.levels.iter().enumerate().skip(1) {
+            // A file whose range tombstones end at the next file's first key
+            // shares that key with it, so the key may fall in two files
+            let idx = files.partition_point(|t| comparator.compare(&t.meta.largest_key, key) == Ordering::Less);
+            for table in files[idx..].iter().take_while(|t| t.may_contain(key, comparator)) {
+                if let Some(entry) = table.reader.get(key)? {
+                    return Ok(Some((entry, level)));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Changes that turn one version into the next
+#[derive(Debug, Default)]
+pub struct VersionEdit {
+    /// Files added (with their level set)
+    pub added: Vec<SstFile>,
+    /// File numbers removed
+    pub removed: Vec<u64>,
+}
+
 /// SST manager for handling multiple SST files
 pub struct SstManager {
-    // TODO: Implement SST management functionality
+    /// SST directory
+    sst_dir: PathBuf,
+    /// How SST files are opened
+    access: FileAccess,
+    /// Key ordering
+    comparator: Comparator,
+    /// Current version
+    current: RwLock<Arc<Version>>,
+    /// Block cache shared by the readers, if enabled
+    block_cache: Option<Arc<Mutex<UnifiedCache>>>,
+    /// Compressed tier behind the block cache, if enabled
+    compressed_cache: Option<Arc<Mutex<UnifiedCache>>>,
+    /// Key ranges whose blocks stay pinned in the block cache, including
+    /// in files added later
+    pinned_ranges: Mutex<Vec<(Vec<u8>, Vec<u8>)>>,
+    /// Block bytes read from files by point lookups
+    read_bytes: Arc<AtomicU64>,
+    /// Whether readers pass access pattern hints to the OS
+    fadvise: bool,
 }
 
 impl SstManager {
-    /// Create a new SST manager
-    pub fn new() -> Self {
-        Self {}
+    /// Create a new SST manager with no files
+    pub fn new(sst_dir: PathBuf, access: FileAccess, comparator: Comparator) -> Self {
+        Self {
+            sst_dir,
+            access,
+            comparator,
+            current: RwLock::new(Arc::new(Version::default())),
+            block_cache: None,
+            compressed_cache: None,
+            pinned_ranges: Mutex::new(Vec::new()),
+            read_bytes: Arc::new(AtomicU64::new(0)),
+            fadvise: false,
+        }
     }
-    
-    /// Add an SST file to the manager
-    pub fn add_file(&mut self, _file: SstFile) -> Result<()> {
-        // TODO: Implement
+
+    /// Have every reader pass access pattern hints to the OS; see
+    /// [`SstReader::with_fadvise`]
+    pub fn with_fadvise(mut self, enabled: bool) -> Self {
+        self.fadvise = enabled;
+        self
+    }
+
+    /// Block bytes point lookups have read from SST files (not the cache)
+    pub fn bytes_read(&self) -> u64 {
+        self.read_bytes.load(AtomicOrdering::Relaxed)
+    }
+
+    /// Cache point-lookup blocks of buffered and direct files in `cache`.
+    /// Memory-mapped files are read from the mapping and not cached.
+    pub fn with_block_cache(mut self, cache: UnifiedCache) -> Self {
+        self.block_cache = Some(Arc::new(Mutex::new(cache)));
+        self
+    }
+
+    /// Back the block cache with a compressed tier; see
+    /// [`SstReader::with_compressed_cache`]
+    pub fn with_compressed_cache(mut self, cache: UnifiedCache) -> Self {
+        self.compressed_cache = Some(Arc::new(Mutex::new(cache)));
+        self
+    }
+
+    /// Resize the block cache, if enabled, evicting down to `capacity`
+    pub fn set_block_cache_capacity(&self, capacity: usize) -> Result<()> {
+        match &self.block_cache {
+            Some(cache) => cache.lock().set_capacity(capacity),
+            None => Ok(()),
+        }
+    }
+
+    /// Block cache statistics, if the cache is enabled
+    pub fn block_cache_stats(&self) -> Option<CacheStats> {
+        self.block_cache.as_ref().map(|cache| cache.lock().stats())
+    }
+
+    /// Resize the compressed block cache tier, if enabled
+    pub fn set_compressed_cache_capacity(&self, capacity: usize) -> Result<()> {
+        match &self.compressed_cache {
+            Some(cache) => cache.lock().set_capacity(capacity),
+            None => Ok(()),
+        }
+    }
+
+    /// Compressed block cache tier statistics, if the tier is enabled
+    pub fn compressed_cache_stats(&self) -> Option<CacheStats> {
+        self.compressed_cache.as_ref().map(|cache| cache.lock().stats())
+    }
+
+    /// Keep the blocks that may hold keys in `[start, end]` in the block
+    /// cache, loading them if needed. Files written later are pinned as
+    /// they are added; the blocks of files compacted away are released
+    /// with the files. Fails without pinning anything if the pinned blocks
+    /// would outgrow the cache, or if there is no block cache to pin in.
+    pub fn pin_range(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        if self.block_cache.is_none() || self.access == FileAccess::Mmap {
+            return Err(Error::Config("SST blocks are not cached, so they can't be pinned".to_string()));
+        }
+        let mut ranges = self.pinned_ranges.lock();
+        let version = self.current();
+        let tables: Vec<_> = version.files().filter(|t| t.overlaps(start, end, &self.comparator)).collect();
+        for (pinned, table) in tables.iter().enumerate() {
+            if let Err(e) = table.reader.pin_blocks(start, end) {
+                for table in &tables[..pinned] {
+                    table.reader.unpin_blocks(start, end);
+                }
+                return Err(e);
+            }
+        }
+        ranges.push((start.to_vec(), end.to_vec()));
         Ok(())
     }
-    
+
+    /// Release a range pinned by [`pin_range`](Self::pin_range) with the
+    /// same bounds
+    pub fn unpin_range(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        let mut ranges = self.pinned_ranges.lock();
+        let Some(pos) = ranges.iter().position(|(s, e)| s == start && e == end) else {
+            return Err(Error::Config("Key range is not pinned".to_string()));
+        };
+        ranges.remove(pos);
+        for table in self.current().files().filter(|t| t.overlaps(start, end, &self.comparator)) {
+            table.reader.unpin_blocks(start, end);
+        }
+        Ok(())
+    }
+
+    /// Path of the SST with the given file number
+    pub fn file_path(&self, id: u64) -> PathBuf {
+        self.sst_dir.join(sst_file_name(id))
+    }
+
+    /// Delete SST files in the directory that the current version doesn't
+    /// reference: outputs of flushes and compactions interrupted by a crash,
+    /// and inputs whose removal didn't happen before one. Only safe before
+    /// the manager is shared, since an older version a reader still holds
+    /// may reference files the current one doesn't, and a flush or
+    /// compaction may be writing outputs not yet in any version.
+    /// Checkpoints hard-link into a directory of their own, so no other
+    /// database shares this one. Returns the number of files and bytes
+    /// reclaimed.
+    pub fn remove_orphans(&self) -> Result<(usize, u64)> {
+        let version = self.current();
+        let live: std::collections::HashSet<u64> = version.files().map(|t| t.meta.id).collect();
+        let (mut files, mut bytes) = (0, 0);
+        for entry in std::fs::read_dir(&self.sst_dir)? {
+            let entry = entry?;
+            let Some(id) = parse_sst_file_number(&entry.file_name().to_string_lossy()) else {
+                continue;
+            };
+            if live.contains(&id) {
+                continue;
+            }
+            let size = entry.metadata()?.len();
+            std::fs::remove_file(entry.path())?;
+            info!("Removed orphaned SST {} ({} bytes)", entry.path().display(), size);
+            files += 1;
+            bytes += size;
+        }
+        Ok((files, bytes))
+    }
+
+    /// How SST files are opened
+    pub fn access(&self) -> FileAccess {
+        self.access
+    }
+
+    /// The current version
+    pub fn current(&self) -> Arc<Version> {
+        self.current.read().clone()
+    }
+
+    /// Apply an edit, opening added files and retiring removed ones
+    pub fn apply(&self, edit: VersionEdit) -> Result<()> {
+        let mut added = Vec::with_capacity(edit.added.len());
+        for mut meta in edit.added {
+            meta.path = self.file_path(meta.id);
+            let mut reader = SstReader::open(&meta.path, self.access)?
+                .with_comparator(self.comparator.clone())
+                .with_read_counter(Arc::clone(&self.read_bytes))
+                .with_fadvise(self.fadvise);
+            if let Some(cache) = self.block_cache.as_ref().filter(|_| self.access != FileAccess::Mmap) {
+                reader = reader.with_block_cache(Arc::clone(cache), meta.id);
+                if let Some(tier) = &self.compressed_cache {
+                    reader = reader.with_compressed_cache(Arc::clone(tier));
+                }
+            }
+            added.push(Arc::new(TableHandle {
+                meta,
+                reader,
+                obsolete: AtomicBool::new(false),
+            }));
+        }
+
+        // Held until the new version is installed so `pin_range` sees
+        // every added file either pinned here or in the version
+        let ranges = self.pinned_ranges.lock();
+        for table in &added {
+            for (start, end) in ranges.iter().filter(|(s, e)| table.overlaps(s, e, &self.comparator)) {
+                if let Err(e) = table.reader.pin_blocks(start, end) {
+                    warn!("Failed to pin blocks of SST {}: {}", table.meta.id, e);
+                }
+            }
+        }
+
+        let mut current = self.current.write();
+        let mut version = Version::clone(&current);
+        for files in &mut version.levels {
+            files.retain(|table| {
+                let removed = edit.removed.contains(&table.meta.id);
+                if removed {
+                    table.obsolete.store(true, AtomicOrdering::Release);
+                }
+                !removed
+            });
+        }
+        for table in added {
+            let level = (table.meta.level as usize).min(NUM_LEVELS - 1);
+            version.levels[level].push(table);
+        }
+
+        version.levels[0].sort_by_key(|t| std::cmp::Reverse(t.meta.id));
+        for files in &mut version.levels[1..] {
+            files.sort_by(|a, b| self.comparator.compare(&a.meta.smallest_key, &b.meta.smallest_key));
+        }
+
+        *current = Arc::new(version);
+        Ok(())
+    }
+
     /// Get SST files for a given level
-    pub fn get_files_at_level(&self, _level: u32) -> Vec<&SstFile> {
-        // TODO: Implement
-        Vec::new()
+    pub fn get_files_at_level(&self, level: u32) -> Vec<SstFile> {
+        self.current()
+            .levels
+            .get(level as usize)
+            .map(|files| files.iter().map(|t| t.meta.clone()).collect())
+            .unwrap_or_default()
     }
-    
+
+    /// Metadata of every live file
+    pub fn all_files(&self) -> Vec<SstFile> {
+        self.current().files().map(|t| t.meta.clone()).collect()
+    }
+
     /// Get total size of all SST files
     pub fn total_size(&self) -> u64 {
-        // TODO: Implement
-        0
+        self.current().files().map(|t| t.meta.size).sum()
+    }
+
+    /// Bytes of Bloom filters held in memory by every live file
+    pub fn filter_memory(&self) -> usize {
+        self.current().files().map(|t| t.reader().filter_memory()).sum()
     }
 }
 
@@ -115,9 +1715,389 @@ impl SstManager {
 mod tests {
     use super::*;
     
+    use tempfile::tempdir;
+
     #[test]
     fn test_sst_manager_creation() {
-        let manager = SstManager::new();
+        let temp_dir = tempdir().unwrap();
+        let manager = SstManager::new(temp_dir.path().to_path_buf(), FileAccess::Buffered, Comparator::default());
         assert_eq!(manager.total_size(), 0);
     }
+
+    #[test]
+    fn test_sst_manager_versions() {
+        let temp_dir = tempdir().unwrap();
+        let manager = SstManager::new(temp_dir.path().to_path_buf(), FileAccess::Buffered, Comparator::default());
+        let mut file = write_test_sst(&manager.file_path(1), FileAccess::Buffered);
+        file.level = 1;
+        manager.apply(VersionEdit { added: vec![file], removed: vec![] }).unwrap();
+
+        let version = manager.current();
+        assert_eq!(manager.get_files_at_level(1).len(), 1);
+        assert!(version.get(b"key_005", &Comparator::default()).unwrap().is_some());
+
+        // The file outlives the edit that removes it until the version drops
+        manager.apply(VersionEdit { added: vec![], removed: vec![1] }).unwrap();
+        assert_eq!(manager.total_size(), 0);
+        assert!(manager.file_path(1).exists());
+        drop(version);
+        assert!(!manager.file_path(1).exists());
+    }
+
+    fn write_test_sst(path: &Path, access: FileAccess) -> SstFile {
+        write_test_sst_with(path, access, ChecksumType::Crc32)
+    }
+
+    fn write_test_sst_with(path: &Path, access: FileAccess, checksum: ChecksumType) -> SstFile {
+        let config = SstConfig {
+            block_size: 256,
+            checksum,
+            ..Default::default()
+        };
+        let mut writer = SstWriter::new(path, &config, access).unwrap();
+        for i in 0..100u64 {
+            let key = Key::new(format!("key_{:03}", i).into_bytes());
+            if i % 10 == 0 {
+                writer.add(&Entry::delete(key, i)).unwrap();
+            } else {
+                let value = Value::new(format!("value_{}", i).into_bytes());
+                writer.add(&Entry::new(key, value, i)).unwrap();
+            }
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_sst_write_and_read() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("000001.sst");
+        let file = write_test_sst(&path, FileAccess::Buffered);
+        assert_eq!(file.entry_count, 100);
+        assert_eq!(file.smallest_key, b"key_000");
+        assert_eq!(file.largest_key, b"key_099");
+
+        let reader = SstReader::open(&path, FileAccess::Buffered).unwrap();
+        assert!(reader.blocks().count() > 1);
+
+        let entry = reader.get(b"key_042").unwrap().unwrap();
+        assert_eq!(entry.value.unwrap().data, b"value_42");
+        assert!(reader.get(b"key_050").unwrap().unwrap().is_delete());
+        assert!(reader.get(b"key_100").unwrap().is_none());
+        assert_eq!(reader.entries().unwrap().len(), 100);
+    }
+
+    #[test]
+    fn test_sst_compressed_cache_tier() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("000002.sst");
+        let config = SstConfig { block_size: 1024, ..Default::default() };
+        let mut writer = SstWriter::new(&path, &config, FileAccess::Buffered).unwrap();
+        for i in 0..100u64 {
+            let key = Key::new(format!("key_{:03}", i).into_bytes());
+            writer.add(&Entry::new(key, Value::new(vec![b'v'; 64]), i)).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let policy = || crate::cache::EvictionPolicy::Lru;
+        let cache = Arc::new(Mutex::new(UnifiedCache::new(usize::MAX, policy())));
+        let tier = Arc::new(Mutex::new(UnifiedCache::new(64 * 1024, policy())));
+        let read = Arc::new(AtomicU64::new(0));
+        let reader = SstReader::open(&path, FileAccess::Buffered)
+            .unwrap()
+            .with_block_cache(Arc::clone(&cache), 2)
+            .with_compressed_cache(Arc::clone(&tier))
+            .with_read_counter(Arc::clone(&read));
+        assert!(reader.blocks().count() > 2);
+
+        // Room for one block: reading the last one demotes the first
+        reader.get(b"key_000").unwrap().unwrap();
+        let block = cache.lock().stats().size;
+        cache.lock().set_capacity(block + block / 2).unwrap();
+        reader.get(b"key_099").unwrap().unwrap();
+        let demoted = tier.lock().stats().size;
+        assert!(demoted > 0 && demoted < block, "{} vs {}", demoted, block);
+
+        // The first block comes back from the compressed tier, not the file
+        let file_reads = read.load(AtomicOrdering::Relaxed);
+        let entry = reader.get(b"key_000").unwrap().unwrap();
+        assert_eq!(entry.value.unwrap().data, vec![b'v'; 64]);
+        assert_eq!(read.load(AtomicOrdering::Relaxed), file_reads);
+        let (blocks, compressed) = (cache.lock().stats(), tier.lock().stats());
+        assert_eq!((blocks.hits, blocks.misses), (0, 3));
+        assert_eq!((compressed.hits, compressed.misses), (1, 2));
+        // Promoted back, it left the compressed tier, which now holds the
+        // last block instead
+        assert!(cache.lock().contains(&block_cache_key(2, &reader.index[0].block)));
+        assert!(!tier.lock().contains(&block_cache_key(2, &reader.index[0].block)));
+        assert!(tier.lock().contains(&block_cache_key(2, &reader.index.last().unwrap().block)));
+    }
+
+    #[test]
+    fn test_sst_iter_from_reads_blocks_lazily() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("000003.sst");
+        let config = SstConfig { block_size: 512, ..Default::default() };
+        let mut writer = SstWriter::new(&path, &config, FileAccess::Buffered).unwrap();
+        for i in 0..200u64 {
+            let key = Key::new(format!("key_{:03}", i * 2).into_bytes());
+            writer.add(&Entry::new(key, Value::new(vec![b'v'; 32]), i)).unwrap();
+        }
+        writer.finish().unwrap();
+        let reader = SstReader::open(&path, FileAccess::Buffered).unwrap();
+        let blocks = reader.blocks().count();
+        assert!(blocks > 4);
+
+        let keys = |iter: TableIter| iter.map(|e| e.unwrap().key.data).collect::<Vec<_>>();
+        let all = keys(reader.iter_from(None));
+        assert_eq!(all, reader.entries().unwrap().into_iter().map(|e| e.key.data).collect::<Vec<_>>());
+        // Starting between keys, at a key and past the last key
+        assert_eq!(keys(reader.iter_from(Some(b"key_201"))), all[101..]);
+        assert_eq!(keys(reader.iter_from(Some(b"key_202"))), all[101..]);
+        assert!(keys(reader.iter_from(Some(b"key_999"))).is_empty());
+
+        // Taking a few entries from the middle reads only their block
+        let read = AtomicU64::new(0);
+        let first: Vec<_> = reader.iter_from(Some(b"key_200")).with_read_counter(&read).take(2).collect();
+        assert_eq!(first.len(), 2);
+        let largest = reader.blocks().map(|b| b.size as u64).max().unwrap();
+        assert!(read.load(AtomicOrdering::Relaxed) <= largest);
+    }
+
+    #[test]
+    fn test_sst_direct_io_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("000002.sst");
+        write_test_sst(&path, FileAccess::Direct);
+
+        let reader = SstReader::open(&path, FileAccess::Direct).unwrap();
+        let entry = reader.get(b"key_077").unwrap().unwrap();
+        assert_eq!(entry.value.unwrap().data, b"value_77");
+    }
+
+    #[test]
+    fn test_sst_mmap_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("000004.sst");
+        write_test_sst(&path, FileAccess::Mmap);
+
+        let reader = SstReader::open(&path, FileAccess::Mmap).unwrap();
+        assert!(reader.get(b"key_030").unwrap().unwrap().is_delete());
+        assert_eq!(reader.entries().unwrap().len(), 100);
+    }
+
+    #[test]
+    fn test_sst_fadvise_hints() {
+        let temp_dir = tempdir().unwrap();
+        for (i, access) in [FileAccess::Buffered, FileAccess::Direct, FileAccess::Mmap].into_iter().enumerate() {
+            let path = temp_dir.path().join(format!("{:06}.sst", 20 + i));
+            write_test_sst(&path, access);
+
+            // Hints only steer the page cache; reads see the same data
+            let reader = SstReader::open(&path, access).unwrap().with_fadvise(true);
+            assert_eq!(reader.get(b"key_042").unwrap().unwrap().value.unwrap().data, b"value_42");
+            assert_eq!(reader.entries().unwrap().len(), 100);
+            assert!(reader.get(b"key_040").unwrap().unwrap().is_delete());
+        }
+    }
+
+    #[test]
+    fn test_sst_restart_interval() {
+        let temp_dir = tempdir().unwrap();
+        let write = |interval: usize| {
+            let path = temp_dir.path().join(format!("{:06}.sst", interval));
+            let config = SstConfig {
+                block_size: 1024,
+                block_restart_interval: interval,
+                // Let absent keys reach the block search
+                use_bloom_filters: false,
+                ..Default::default()
+            };
+            let mut writer = SstWriter::new(&path, &config, FileAccess::Buffered).unwrap();
+            for i in 0..500u64 {
+                let key = Key::new(format!("user/profile/{:06}", i * 2).into_bytes());
+                writer.add(&Entry::new(key, Value::from("v"), i)).unwrap();
+            }
+            (writer.finish().unwrap(), SstReader::open(&path, FileAccess::Buffered).unwrap())
+        };
+
+        let (full, _) = write(1);
+        let (compressed, reader) = write(16);
+        assert!(compressed.size < full.size);
+        assert_eq!(reader.entries().unwrap().len(), 500);
+        for i in 0..1000u64 {
+            let key = format!("user/profile/{:06}", i);
+            assert_eq!(reader.get(key.as_bytes()).unwrap().is_some(), i % 2 == 0, "{}", key);
+        }
+        assert!(reader.get(b"user/").unwrap().is_none());
+        assert!(reader.get(b"zzz").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_sst_detects_corrupt_block() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("000003.sst");
+        write_test_sst(&path, FileAccess::Buffered);
+
+        let mut data = std::fs::read(&path).unwrap();
+        data[10] ^= 0xFF;
+        std::fs::write(&path, data).unwrap();
+
+        let reader = SstReader::open(&path, FileAccess::Buffered).unwrap();
+        assert!(matches!(reader.get(b"key_000"), Err(Error::SstCorruption(_))));
+    }
+
+    #[test]
+    fn test_sst_rejects_index_past_end_of_file() {
+        let temp_dir = tempdir().unwrap();
+        for (name, access, index_size) in [
+            ("000003.sst", FileAccess::Buffered, u64::MAX),
+            ("000004.sst", FileAccess::Buffered, 1 << 40),
+            ("000005.sst", FileAccess::Mmap, u64::MAX),
+        ] {
+            let path = temp_dir.path().join(name);
+            write_test_sst(&path, access);
+
+            // The index size sits 24 bytes into the footer
+            let mut data = std::fs::read(&path).unwrap();
+            let at = data.len() - FOOTER_SIZE + 24;
+            data[at..at + 8].copy_from_slice(&index_size.to_le_bytes());
+            std::fs::write(&path, data).unwrap();
+
+            assert!(matches!(SstReader::open(&path, access), Err(Error::SstCorruption(_))));
+        }
+    }
+
+    #[test]
+    fn test_sst_records_checksum_type() {
+        let temp_dir = tempdir().unwrap();
+        let xxh_path = temp_dir.path().join("000005.sst");
+        write_test_sst_with(&xxh_path, FileAccess::Buffered, ChecksumType::XxHash3);
+
+        // Verified with xxHash3 from the footer, not the current default
+        let reader = SstReader::open(&xxh_path, FileAccess::Buffered).unwrap();
+        assert_eq!(reader.checksum_type(), ChecksumType::XxHash3);
+        assert_eq!(reader.entries().unwrap().len(), 100);
+
+        let mut data = std::fs::read(&xxh_path).unwrap();
+        data[10] ^= 0xFF;
+        std::fs::write(&xxh_path, &data).unwrap();
+        let reader = SstReader::open(&xxh_path, FileAccess::Buffered).unwrap();
+        assert!(matches!(reader.get(b"key_000"), Err(Error::SstCorruption(_))));
+
+        // Unchecked files skip verification entirely
+        let none_path = temp_dir.path().join("000006.sst");
+        write_test_sst_with(&none_path, FileAccess::Buffered, ChecksumType::None);
+        let reader = SstReader::open(&none_path, FileAccess::Buffered).unwrap();
+        assert_eq!(reader.checksum_type(), ChecksumType::None);
+        assert!(reader.get(b"key_042").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_sst_compression_per_level() {
+        let temp_dir = tempdir().unwrap();
+        let config = SstConfig {
+            block_size: 4096,
+            compression_per_level: vec![(0, CompressionAlgorithm::None, 3), (2, CompressionAlgorithm::Lz4, 3)],
+            ..Default::default()
+        };
+        assert_eq!(config.compression_for_level(1), (CompressionAlgorithm::None, 3));
+        assert_eq!(config.compression_for_level(6), (CompressionAlgorithm::Lz4, 3));
+
+        // The same entries as a flush to L0 and a compaction output at L5
+        let write = |level: u32| {
+            let path = temp_dir.path().join(format!("{:06}.sst", level + 10));
+            let mut writer = SstWriter::new(&path, &config, FileAccess::Buffered)
+                .unwrap()
+                .with_compression(config.compression_for_level(level));
+            for i in 0..1000u64 {
+                let key = Key::new(format!("key_{:04}", i).into_bytes());
+                let value = Value::new(format!("{{\"id\":{},\"status\":\"active\",\"tags\":[\"a\",\"b\"]}}", i).into_bytes());
+                writer.add(&Entry::new(key, value, i)).unwrap();
+            }
+            (writer.finish().unwrap(), SstReader::open(&path, FileAccess::Buffered).unwrap())
+        };
+
+        let (l0, l0_reader) = write(0);
+        let (l5, l5_reader) = write(5);
+        assert_eq!(l0_reader.compression(), CompressionAlgorithm::None);
+        assert_eq!(l5_reader.compression(), CompressionAlgorithm::Lz4);
+        assert!(l5.size < l0.size, "{} vs {}", l5.size, l0.size);
+
+        let entry = l5_reader.get(b"key_0421").unwrap().unwrap();
+        assert!(entry.value.unwrap().data.starts_with(b"{\"id\":421,"));
+        assert_eq!(l5_reader.entries().unwrap().len(), 1000);
+        assert!(l5_reader.verify(|_| {}).is_empty());
+    }
+
+    #[test]
+    fn test_sst_zstd_level_per_level() {
+        let temp_dir = tempdir().unwrap();
+        let config = SstConfig {
+            block_size: 64 * 1024,
+            compression: CompressionAlgorithm::Zstd,
+            compression_level: 5,
+            compression_per_level: vec![(1, CompressionAlgorithm::Zstd, 1), (4, CompressionAlgorithm::Zstd, 19)],
+            ..Default::default()
+        };
+        assert_eq!(config.compression_for_level(0), (CompressionAlgorithm::Zstd, 5));
+        assert_eq!(config.compression_for_level(3), (CompressionAlgorithm::Zstd, 1));
+        assert_eq!(config.compression_for_level(6), (CompressionAlgorithm::Zstd, 19));
+
+        let write = |level: u32| {
+            let path = temp_dir.path().join(format!("{:06}.sst", level + 10));
+            let mut writer = SstWriter::new(&path, &config, FileAccess::Buffered)
+                .unwrap()
+                .with_compression(config.compression_for_level(level));
+            for i in 0..2000u64 {
+                let key = Key::new(format!("key_{:04}", i).into_bytes());
+                let value = Value::new(format!("{{\"id\":{},\"name\":\"user{}\",\"tags\":[\"a\",\"b\"]}}", i, i % 37).into_bytes());
+                writer.add(&Entry::new(key, value, i)).unwrap();
+            }
+            (writer.finish().unwrap(), SstReader::open(&path, FileAccess::Buffered).unwrap())
+        };
+
+        // Readers decode whatever level wrote the blocks
+        let (fast, fast_reader) = write(2);
+        let (small, small_reader) = write(5);
+        assert!(small.size <= fast.size, "{} vs {}", small.size, fast.size);
+        for reader in [&fast_reader, &small_reader] {
+            assert_eq!(reader.entries().unwrap().len(), 2000);
+            assert!(reader.get(b"key_1234").unwrap().unwrap().value.unwrap().data.starts_with(b"{\"id\":1234,"));
+        }
+
+        for (default_level, override_level) in [(0, 3), (3, 20)] {
+            let mut engine_config = crate::config::Config::default();
+            engine_config.sst.compression_level = default_level;
+            engine_config.sst.compression_per_level = vec![(2, CompressionAlgorithm::Zstd, override_level)];
+            assert!(engine_config.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_sst_bloom_and_prefix_filters() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("000007.sst");
+        let config = SstConfig {
+            prefix_bloom: true,
+            prefix_extractor: Some(PrefixExtractor::fixed(4)),
+            ..Default::default()
+        };
+        let mut writer = SstWriter::new(&path, &config, FileAccess::Buffered).unwrap();
+        for i in 0..100u64 {
+            let key = Key::new(format!("key_{:03}", i).into_bytes());
+            writer.add(&Entry::new(key, Value::from("v"), i)).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let reader = SstReader::open(&path, FileAccess::Buffered).unwrap();
+        assert!(reader.may_contain(b"key_042"));
+        assert!(reader.get(b"key_042").unwrap().is_some());
+        let extractor = PrefixExtractor::fixed(4);
+        assert!(reader.may_contain_prefix(b"key_", &extractor));
+        assert!(!reader.may_contain_prefix(b"zzz_", &extractor));
+        // Too short to extract a prefix from, so the filter can't tell
+        assert!(reader.may_contain_prefix(b"zz", &extractor));
+        // Filters built by another extractor are ignored
+        assert!(reader.may_contain_prefix(b"zzz_", &PrefixExtractor::fixed(3)));
+    }
 }
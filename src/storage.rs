@@ -1,3 +1,4 @@
+use crate::comparator::Comparator;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
@@ -41,14 +42,83 @@ impl Key {
     pub fn is_empty(&self) -> bool {
         self.data.is_empty()
     }
+
+    /// Encode an unsigned integer big-endian so bytewise order matches
+    /// numeric order
+    pub fn from_u64_be(n: u64) -> Self {
+        Self::new(n.to_be_bytes().to_vec())
+    }
+
+    /// Decode a key built with [`Key::from_u64_be`]
+    pub fn to_u64_be(&self) -> Option<u64> {
+        let bytes: [u8; 8] = self.data.as_slice().try_into().ok()?;
+        Some(u64::from_be_bytes(bytes))
+    }
+
+    /// Encode a signed integer with its sign bit flipped, so negative
+    /// numbers sort before positive ones bytewise
+    pub fn from_i64_ordered(n: i64) -> Self {
+        Self::from_u64_be((n as u64) ^ (1 << 63))
+    }
+
+    /// Decode a key built with [`Key::from_i64_ordered`]
+    pub fn to_i64_ordered(&self) -> Option<i64> {
+        self.to_u64_be().map(|n| (n ^ (1 << 63)) as i64)
+    }
+
+    /// Join parts into one key that compares part by part. Each part has
+    /// its zero bytes escaped as `00 FF` and ends with `00 01`, so a part
+    /// that is a prefix of another sorts first.
+    pub fn compound(parts: &[&[u8]]) -> Self {
+        let mut data = Vec::with_capacity(parts.iter().map(|p| p.len() + 2).sum());
+        for part in parts {
+            for &byte in *part {
+                data.push(byte);
+                if byte == COMPOUND_ESCAPE {
+                    data.push(COMPOUND_ESCAPED_ZERO);
+                }
+            }
+            data.extend_from_slice(&[COMPOUND_ESCAPE, COMPOUND_TERMINATOR]);
+        }
+        Self::new(data)
+    }
+
+    /// Split a key built with [`Key::compound`] back into its parts, or
+    /// `None` if it is not a valid compound encoding
+    pub fn compound_parts(&self) -> Option<Vec<Vec<u8>>> {
+        let mut parts = Vec::new();
+        let mut current = Vec::new();
+        let mut bytes = self.data.iter();
+        while let Some(&byte) = bytes.next() {
+            if byte != COMPOUND_ESCAPE {
+                current.push(byte);
+                continue;
+            }
+            match bytes.next() {
+                Some(&COMPOUND_ESCAPED_ZERO) => current.push(COMPOUND_ESCAPE),
+                Some(&COMPOUND_TERMINATOR) => parts.push(std::mem::take(&mut current)),
+                _ => return None,
+            }
+        }
+        current.is_empty().then_some(parts)
+    }
 }
 
+/// Escape byte of the [`Key::compound`] encoding
+const COMPOUND_ESCAPE: u8 = 0x00;
+/// Follows an escape byte for a literal zero byte
+const COMPOUND_ESCAPED_ZERO: u8 = 0xFF;
+/// Follows an escape byte at the end of a part
+const COMPOUND_TERMINATOR: u8 = 0x01;
+
 impl PartialOrd for Key {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
+/// Bytewise ordering. The engine orders keys with the configured
+/// [`Comparator`](crate::comparator::Comparator) instead.
 impl Ord for Key {
     fn cmp(&self, other: &Self) -> Ordering {
         self.data.cmp(&other.data)
@@ -273,6 +343,14 @@ impl Entry {
         self.value_pointer.is_some()
     }
 
+    /// Create a range delete of the keys in `[start, end)`. The end key is
+    /// carried as the entry's value.
+    pub fn range_delete(start: Key, end: Key, sequence: u64) -> Self {
+        let mut entry = Self::new(start, Value::new(end.data), sequence);
+        entry.op_type = OpType::RangeDelete;
+        entry
+    }
+
     /// Check if this is a delete operation
     pub fn is_delete(&self) -> bool {
         matches!(self.op_type, OpType::Delete)
@@ -288,8 +366,96 @@ pub enum OpType {
     Delete,
     /// Merge operation
     Merge,
+    /// Delete of every key from the entry's key up to, but excluding, the
+    /// key held in its value
+    RangeDelete,
+}
+
+/// A deletion of every key in `[start, end)` written before `sequence`.
+///
+/// Range tombstones are kept apart from point entries, in the memtable and
+/// in a block of their own in SST files, because they cover keys they
+/// don't share a file with. Reads and compactions check every key against
+/// the newest tombstone covering it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeTombstone {
+    /// First key deleted
+    pub start: Key,
+    /// First key past the deleted range
+    pub end: Key,
+    /// Sequence number of the delete
+    pub sequence: u64,
+}
+
+impl RangeTombstone {
+    /// Create a range tombstone
+    pub fn new(start: Key, end: Key, sequence: u64) -> Self {
+        Self { start, end, sequence }
+    }
+
+    /// The tombstone a range delete entry writes, if it is one
+    pub fn from_entry(entry: &Entry) -> Option<Self> {
+        if entry.op_type != OpType::RangeDelete {
+            return None;
+        }
+        let end = entry.value.as_ref()?;
+        Some(Self::new(entry.key.clone(), Key::new(end.data.clone()), entry.sequence))
+    }
+
+    /// Whether `key` lies in `[start, end)`
+    pub fn covers(&self, key: &[u8], comparator: &Comparator) -> bool {
+        comparator.compare(key, &self.start.data) != Ordering::Less
+            && comparator.compare(key, &self.end.data) == Ordering::Less
+    }
+
+    /// Whether the range holds no key at all
+    pub fn is_empty(&self, comparator: &Comparator) -> bool {
+        comparator.compare(&self.start.data, &self.end.data) != Ordering::Less
+    }
+
+    /// The part of the tombstone within `[lower, upper)`, `None` leaving
+    /// that side unbounded, if any
+    pub fn clip(&self, lower: Option<&[u8]>, upper: Option<&[u8]>, comparator: &Comparator) -> Option<Self> {
+        let mut clipped = self.clone();
+        if let Some(lower) = lower.filter(|lower| comparator.compare(lower, &self.start.data) == Ordering::Greater) {
+            clipped.start = Key::new(lower.to_vec());
+        }
+        if let Some(upper) = upper.filter(|upper| comparator.compare(upper, &self.end.data) == Ordering::Less) {
+            clipped.end = Key::new(upper.to_vec());
+        }
+        (!clipped.is_empty(comparator)).then_some(clipped)
+    }
+
+    /// Approximate memory footprint
+    pub fn size(&self) -> usize {
+        self.start.len() + self.end.len() + std::mem::size_of::<Self>()
+    }
+}
+
+/// Sequence of the newest of `tombstones` covering `key`, if any
+pub fn newest_covering(tombstones: &[RangeTombstone], key: &[u8], comparator: &Comparator) -> Option<u64> {
+    tombstones
+        .iter()
+        .filter(|tombstone| tombstone.covers(key, comparator))
+        .map(|tombstone| tombstone.sequence)
+        .max()
+}
+
+/// `entry`, the newest point entry for `key` in a source, unless a range
+/// tombstone of the same source written after it covers the key, in which
+/// case a tombstone at the range tombstone's sequence stands for it
+pub fn shadow_entry(key: &[u8], entry: Option<Entry>, deleted_at: Option<u64>) -> Option<Entry> {
+    match (entry, deleted_at) {
+        (Some(entry), Some(sequence)) if entry.sequence < sequence => Some(Entry::delete(Key::new(key.to_vec()), sequence)),
+        (None, Some(sequence)) => Some(Entry::delete(Key::new(key.to_vec()), sequence)),
+        (entry, _) => entry,
+    }
 }
 
+/// Bytes a batch operation takes in the WAL besides its key and value:
+/// the record tag, two length prefixes, the sequence and the timestamp
+const BATCH_ENTRY_OVERHEAD: usize = 4 + 8 + 8 + 8 + 8;
+
 /// A batch of operations
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Batch {
@@ -297,7 +463,8 @@ pub struct Batch {
     pub operations: Vec<Entry>,
     /// Batch sequence number
     pub sequence: u64,
-    /// Whether this batch should be synced
+    /// Whether to fsync the WAL, and the value log if the batch separates
+    /// values, before the batch is applied
     pub sync: bool,
 }
 
@@ -316,6 +483,42 @@ impl Batch {
         self.operations.push(operation);
     }
 
+    /// Add a put. The sequence number is assigned when the batch commits.
+    pub fn put(&mut self, key: impl Into<Key>, value: impl Into<Value>) -> &mut Self {
+        self.add(Entry::new(key.into(), value.into(), 0));
+        self
+    }
+
+    /// Add a delete
+    pub fn delete(&mut self, key: impl Into<Key>) -> &mut Self {
+        self.add(Entry::delete(key.into(), 0));
+        self
+    }
+
+    /// Add a delete of every key in `[start, end)`
+    pub fn delete_range(&mut self, start: impl Into<Key>, end: impl Into<Key>) -> &mut Self {
+        self.add(Entry::range_delete(start.into(), end.into(), 0));
+        self
+    }
+
+    /// Add a merge operand
+    pub fn merge(&mut self, key: impl Into<Key>, operand: impl Into<Value>) -> &mut Self {
+        let mut entry = Entry::new(key.into(), operand.into(), 0);
+        entry.op_type = OpType::Merge;
+        self.add(entry);
+        self
+    }
+
+    /// Estimated bytes the batch takes once encoded in the WAL, for
+    /// capping batch size. Separated values count at full length, though
+    /// only a pointer to them is logged.
+    pub fn approximate_size(&self) -> usize {
+        self.operations
+            .iter()
+            .map(|op| BATCH_ENTRY_OVERHEAD + op.key.len() + op.value.as_ref().map_or(0, Value::len))
+            .sum()
+    }
+
     /// Set the batch sequence number
     pub fn with_sequence(mut self, sequence: u64) -> Self {
         self.sequence = sequence;
@@ -372,3 +575,75 @@ impl Range {
         self
     }
 }
+
+/// A committed write, as delivered to change subscribers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEvent {
+    /// The key written
+    pub key: Vec<u8>,
+    /// Operation applied to the key
+    pub op: OpType,
+    /// Sequence number of the write
+    pub sequence: u64,
+}
+
+impl ChangeEvent {
+    /// Describe the change an entry makes
+    pub fn from_entry(entry: &Entry) -> Self {
+        Self {
+            key: entry.key.data.clone(),
+            op: entry.op_type.clone(),
+            sequence: entry.sequence,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_builders_and_size() {
+        let mut batch = Batch::new();
+        batch.put("a", "12345").delete("b").merge("c", "+1");
+        let ops: Vec<_> = batch.operations.iter().map(|op| op.op_type.clone()).collect();
+        assert_eq!(ops, [OpType::Put, OpType::Delete, OpType::Merge]);
+        assert!(batch.operations.iter().all(|op| op.sequence == 0));
+        assert_eq!(batch.approximate_size(), 3 * BATCH_ENTRY_OVERHEAD + 3 + 5 + 2);
+    }
+
+    #[test]
+    fn test_numeric_keys_sort_numerically() {
+        let unsigned = [0u64, 9, 10, 255, 256, u64::MAX];
+        let keys: Vec<Key> = unsigned.iter().map(|&n| Key::from_u64_be(n)).collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        let decoded: Vec<u64> = keys.iter().map(|k| k.to_u64_be().unwrap()).collect();
+        assert_eq!(decoded, unsigned);
+
+        let signed = [i64::MIN, -10, -9, -1, 0, 1, 9, 10, i64::MAX];
+        let keys: Vec<Key> = signed.iter().map(|&n| Key::from_i64_ordered(n)).collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        let decoded: Vec<i64> = keys.iter().map(|k| k.to_i64_ordered().unwrap()).collect();
+        assert_eq!(decoded, signed);
+        assert_eq!(Key::from("short").to_u64_be(), None);
+    }
+
+    #[test]
+    fn test_compound_keys_compare_by_part() {
+        let ordered: [&[&[u8]]; 6] = [
+            &[b"a"],
+            &[b"a", b""],
+            &[b"a", b"b"],
+            &[b"a\0", b"a"],
+            &[b"ab", b""],
+            &[b"b"],
+        ];
+        let keys: Vec<Key> = ordered.iter().map(|parts| Key::compound(parts)).collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        for (key, parts) in keys.iter().zip(ordered) {
+            assert_eq!(key.compound_parts().unwrap(), parts);
+        }
+        assert_eq!(Key::from(&b"a\0"[..]).compound_parts(), None);
+        assert_eq!(Key::from("dangling").compound_parts(), None);
+    }
+}
@@ -1,17 +1,23 @@
+use crate::checksum::{checksum, verify, ChecksumType, Hasher};
+use crate::compression;
 use crate::config::{CompressionAlgorithm, ValueLogConfig};
-use crate::error::{Error, Result};
+use crate::error::{Corruption, Error, Result};
+use crate::io::{FileAccess, RandomAccessFile};
 use crate::storage::{Value, ValuePointer};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
-use std::path::PathBuf;
+use std::io::{BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
-use tracing::{debug, error, info, warn};
+use tracing::{error, info, warn};
+
+/// Write buffer capacity for each open segment
+const SEGMENT_BUFFER_SIZE: usize = 64 * 1024;
 
 /// Value log segment header
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,28 +30,37 @@ pub struct VlogHeader {
     pub created_at: u64,
     /// Compression algorithm used
     pub compression: CompressionAlgorithm,
-    /// Checksum of the header
+    /// Checksum algorithm for the segment's entries
+    pub checksum_type: ChecksumType,
+    /// CRC32 checksum of the header
     pub checksum: u32,
 }
 
 impl VlogHeader {
     const MAGIC: [u8; 8] = [0x41, 0x55, 0x52, 0x41, 0x44, 0x42, 0x56, 0x4C]; // "AURADBVL"
-    const VERSION: u32 = 1;
+    const VERSION: u32 = 2;
+    /// Headers before the checksum type was recorded; entries are CRC32
+    const VERSION_V1: u32 = 1;
+    /// Upper bound on the encoded header size
+    const MAX_SIZE: u64 = 64;
 
     /// Create a new value log header
-    pub fn new(compression: CompressionAlgorithm) -> Self {
+    pub fn new(compression: CompressionAlgorithm, checksum_type: ChecksumType) -> Self {
         let created_at = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis() as u64;
 
-        Self {
+        let mut header = Self {
             magic: Self::MAGIC,
             version: Self::VERSION,
             created_at,
             compression,
-            checksum: 0, // Will be calculated
-        }
+            checksum_type,
+            checksum: 0,
+        };
+        header.checksum = header.calculate_checksum();
+        header
     }
 
     /// Calculate checksum for the header
@@ -56,6 +71,7 @@ impl VlogHeader {
         hasher.update(&self.version.to_le_bytes());
         hasher.update(&self.created_at.to_le_bytes());
         hasher.update(&(self.compression as u8).to_le_bytes());
+        hasher.update(&[self.checksum_type.as_u8()]);
         hasher.finalize()
     }
 
@@ -105,8 +121,10 @@ pub struct VlogWriter {
     segments: Vec<Arc<RwLock<VlogSegment>>>,
     /// Configuration
     config: ValueLogConfig,
-    /// Next segment ID
-    next_segment_id: AtomicU64,
+    /// Next segment ID, shared with the write queues
+    next_segment_id: Arc<AtomicU64>,
+    /// Round-robin cursor for synchronous writes
+    next_sync_segment: AtomicU64,
     /// Value log directory
     vlog_dir: PathBuf,
     /// Write queues for parallel writes
@@ -115,6 +133,8 @@ pub struct VlogWriter {
     background_handles: Vec<JoinHandle<()>>,
     /// Segment metadata cache
     segment_metadata: HashMap<u64, VlogSegmentMeta>,
+    /// Bytes written to segments, shared with every segment
+    bytes_written: Arc<AtomicU64>,
 }
 
 impl VlogWriter {
@@ -123,18 +143,27 @@ impl VlogWriter {
         let vlog_dir = config.vlog_path.clone();
         std::fs::create_dir_all(&vlog_dir)?;
 
+        // Continue numbering after any segments left by a previous run
+        let next_segment_id = Self::max_segment_id(&vlog_dir)? + 1;
+
         let mut writer = Self {
             segments: Vec::new(),
             config,
-            next_segment_id: AtomicU64::new(1),
+            next_segment_id: Arc::new(AtomicU64::new(next_segment_id)),
+            next_sync_segment: AtomicU64::new(0),
             vlog_dir,
             write_queues: Vec::new(),
             background_handles: Vec::new(),
             segment_metadata: HashMap::new(),
+            bytes_written: Arc::new(AtomicU64::new(0)),
         };
 
         // Initialize write queues and background tasks
-        writer.initialize_write_queues()?;
+        if tokio::runtime::Handle::try_current().is_ok() {
+            writer.initialize_write_queues()?;
+        } else {
+            warn!("No Tokio runtime available, value log async write queues disabled");
+        }
         
         // Create initial segments
         for _ in 0..writer.config.write_queues {
@@ -144,7 +173,21 @@ impl VlogWriter {
         Ok(writer)
     }
 
-    /// Initialize write queues and background tasks
+    /// Find the highest segment ID present in the value log directory
+    fn max_segment_id(vlog_dir: &Path) -> Result<u64> {
+        let mut max_id = 0;
+        for entry in std::fs::read_dir(vlog_dir)? {
+            let name = entry?.file_name();
+            if let Some(id) = parse_segment_id(&name.to_string_lossy()) {
+                max_id = max_id.max(id);
+            }
+        }
+        Ok(max_id)
+    }
+
+    /// Initialize write queues and background tasks. Each task appends to
+    /// a segment of its own, numbered like every other segment, and on
+    /// shutdown writes what it has buffered and closes that segment.
     fn initialize_write_queues(&mut self) -> Result<()> {
         for queue_id in 0..self.config.write_queues {
             let (tx, mut rx) = mpsc::unbounded_channel();
@@ -152,32 +195,45 @@ impl VlogWriter {
 
             let vlog_dir = self.vlog_dir.clone();
             let config = self.config.clone();
-            let queue_id = queue_id;
+            let bytes_written = Arc::clone(&self.bytes_written);
+            let next_segment_id = Arc::clone(&self.next_segment_id);
 
             let handle = tokio::spawn(async move {
                 let mut current_segment = None;
                 let mut write_buffer = Vec::new();
+                let flush = |current_segment: &mut Option<VlogSegment>, write_buffer: &mut Vec<_>| {
+                    Self::flush_values(current_segment, &vlog_dir, &config, write_buffer, &next_segment_id, &bytes_written)
+                };
 
                 while let Some(request) = rx.recv().await {
                     match request {
                         WriteRequest::Write { value, callback } => {
                             write_buffer.push((value, callback));
-                            
-                            // Flush if buffer is full
-                            if write_buffer.len() >= 100 {
-                                if let Err(e) = Self::flush_values(&mut current_segment, &vlog_dir, &config, &mut write_buffer, queue_id).await {
+
+                            // Flush once the buffer is full or no more writes are waiting,
+                            // so writers awaiting their pointer aren't held up
+                            if write_buffer.len() >= 100 || rx.is_empty() {
+                                if let Err(e) = flush(&mut current_segment, &mut write_buffer) {
                                     error!("Failed to flush values in queue {}: {}", queue_id, e);
                                 }
                             }
                         }
                         WriteRequest::Sync => {
-                            if let Err(e) = Self::flush_values(&mut current_segment, &vlog_dir, &config, &mut write_buffer, queue_id).await {
+                            let result = flush(&mut current_segment, &mut write_buffer)
+                                .and_then(|()| current_segment.as_mut().map_or(Ok(()), VlogSegment::sync));
+                            if let Err(e) = result {
                                 error!("Failed to sync values in queue {}: {}", queue_id, e);
                             }
                         }
                         WriteRequest::Shutdown => break,
                     }
                 }
+
+                let result = flush(&mut current_segment, &mut write_buffer)
+                    .and_then(|()| current_segment.as_mut().map_or(Ok(()), VlogSegment::close));
+                if let Err(e) = result {
+                    error!("Failed to close segment of queue {}: {}", queue_id, e);
+                }
             });
 
             self.background_handles.push(handle);
@@ -186,13 +242,15 @@ impl VlogWriter {
         Ok(())
     }
 
-    /// Flush values to segment (async helper)
-    async fn flush_values(
+    /// Write buffered values to a write queue's segment, starting a new
+    /// one if there is none or the current one is full
+    fn flush_values(
         current_segment: &mut Option<VlogSegment>,
-        vlog_dir: &PathBuf,
+        vlog_dir: &Path,
         config: &ValueLogConfig,
-        write_buffer: &mut Vec<(Value, Option<WriteCallback>)>,
-        queue_id: usize,
+        write_buffer: &mut Vec<(Value, WriteCallback)>,
+        next_segment_id: &AtomicU64,
+        bytes_written: &Arc<AtomicU64>,
     ) -> Result<()> {
         if write_buffer.is_empty() {
             return Ok(());
@@ -200,7 +258,8 @@ impl VlogWriter {
 
         // Ensure we have a current segment
         if current_segment.is_none() {
-            *current_segment = Some(VlogSegment::new(vlog_dir, config, queue_id as u64)?);
+            let segment_id = next_segment_id.fetch_add(1, Ordering::SeqCst);
+            *current_segment = Some(VlogSegment::new(vlog_dir, config, segment_id, bytes_written)?);
         }
 
         let segment = current_segment.as_mut().unwrap();
@@ -210,20 +269,14 @@ impl VlogWriter {
             match segment.write_value(&value) {
                 Ok(vptr) => {
                     // Notify callback with success
-                    if let Some(cb) = &callback {
-                        match cb {
-                            WriteCallback::Channel(sender) => { let _ = sender.send(Ok(vptr)); }
-                            WriteCallback::None => {}
-                        }
+                    if let WriteCallback::Channel(sender) = &callback {
+                        let _ = sender.try_send(Ok(vptr));
                     }
                 }
                 Err(e) => {
                     // Notify callback with error
-                    if let Some(cb) = &callback {
-                        match cb {
-                            WriteCallback::Channel(sender) => { let _ = sender.send(Err(e)); }
-                            WriteCallback::None => {}
-                        }
+                    if let WriteCallback::Channel(sender) = &callback {
+                        let _ = sender.try_send(Err(e));
                     }
                 }
             }
@@ -232,7 +285,8 @@ impl VlogWriter {
         // Check if segment is full and rotate if needed
         if segment.should_rotate() {
             segment.close()?;
-            *current_segment = Some(VlogSegment::new(vlog_dir, config, queue_id as u64)?);
+            let segment_id = next_segment_id.fetch_add(1, Ordering::SeqCst);
+            *current_segment = Some(VlogSegment::new(vlog_dir, config, segment_id, bytes_written)?);
         }
 
         Ok(())
@@ -264,15 +318,38 @@ impl VlogWriter {
 
     /// Write a value synchronously (for small values or when async is disabled)
     pub fn write_value_sync(&mut self, value: Value) -> Result<ValuePointer> {
+        if self.segments.is_empty() {
+            return Err(Error::Concurrency("No available segments".to_string()));
+        }
+
         // Choose a segment (round-robin)
-        let segment_id = self.next_segment_id.fetch_add(1, Ordering::SeqCst) % self.segments.len() as u64;
-        
-        if let Some(segment) = self.segments.get(segment_id as usize) {
-            let mut segment = segment.write();
-            segment.write_value(&value)
-        } else {
-            Err(Error::Concurrency("No available segments".to_string()))
+        let slot = (self.next_sync_segment.fetch_add(1, Ordering::Relaxed) % self.segments.len() as u64) as usize;
+
+        let vptr = self.segments[slot].write().write_value(&value)?;
+
+        // Replace the segment once it is full
+        if self.segments[slot].read().should_rotate() {
+            self.segments[slot].write().close()?;
+            let segment_id = self.next_segment_id.fetch_add(1, Ordering::SeqCst);
+            let segment = VlogSegment::new(&self.vlog_dir, &self.config, segment_id, &self.bytes_written)?;
+            self.segments[slot] = Arc::new(RwLock::new(segment));
         }
+
+        Ok(vptr)
+    }
+
+    /// Start writing a value of unknown length to a segment of its own.
+    /// The returned writer does not borrow this one, so other values can
+    /// be written while it copies.
+    pub fn start_stream(&self) -> Result<VlogStreamWriter> {
+        let segment_id = self.next_segment_id.fetch_add(1, Ordering::SeqCst);
+        VlogStreamWriter::new(&self.vlog_dir, &self.config, segment_id, &self.bytes_written)
+    }
+
+    /// Total bytes written to segments, including headers and per-value
+    /// metadata
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
     }
 
     /// Choose a write queue for the value
@@ -289,7 +366,7 @@ impl VlogWriter {
     /// Create a new segment
     fn create_new_segment(&mut self) -> Result<()> {
         let segment_id = self.next_segment_id.fetch_add(1, Ordering::SeqCst);
-        let segment = VlogSegment::new(&self.vlog_dir, &self.config, segment_id)?;
+        let segment = VlogSegment::new(&self.vlog_dir, &self.config, segment_id, &self.bytes_written)?;
         
         self.segments.push(Arc::new(RwLock::new(segment)));
         Ok(())
@@ -307,6 +384,19 @@ impl VlogWriter {
         Ok(())
     }
 
+    /// Flush and fsync all segments owned by this writer
+    pub fn sync_segments(&self) -> Result<()> {
+        for segment in &self.segments {
+            segment.write().sync()?;
+        }
+        Ok(())
+    }
+
+    /// IDs of the segments this writer appends to
+    pub fn active_segment_ids(&self) -> Vec<u64> {
+        self.segments.iter().map(|segment| segment.read().id).collect()
+    }
+
     /// Close the value log writer
     pub async fn close(&mut self) -> Result<()> {
         // Send shutdown signal to all queues
@@ -356,6 +446,8 @@ pub enum WriteCallback {
 
 /// Individual value log segment
 pub struct VlogSegment {
+    /// Segment ID
+    id: u64,
     /// File handle
     file: BufWriter<File>,
     /// Segment metadata
@@ -364,11 +456,13 @@ pub struct VlogSegment {
     current_offset: u64,
     /// Configuration
     config: ValueLogConfig,
+    /// Writer-wide count of bytes written
+    bytes_written: Arc<AtomicU64>,
 }
 
 impl VlogSegment {
     /// Create a new value log segment
-    fn new(vlog_dir: &PathBuf, config: &ValueLogConfig, segment_id: u64) -> Result<Self> {
+    fn new(vlog_dir: &Path, config: &ValueLogConfig, segment_id: u64, bytes_written: &Arc<AtomicU64>) -> Result<Self> {
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
@@ -379,17 +473,18 @@ impl VlogSegment {
 
         let file = OpenOptions::new()
             .create(true)
-            .write(true)
             .append(true)
             .open(&path)?;
 
-        let mut buf_writer = BufWriter::with_capacity(config.cache_size, file);
+        let mut buf_writer = BufWriter::with_capacity(SEGMENT_BUFFER_SIZE, file);
 
         // Write header
-        let header = VlogHeader::new(config.compression_algorithm.clone());
+        let header = VlogHeader::new(config.compression_algorithm, config.checksum);
         let header_bytes = bincode::serialize(&header)?;
         buf_writer.write_all(&header_bytes)?;
         buf_writer.flush()?;
+        crate::io::sync_dir(vlog_dir)?;
+        bytes_written.fetch_add(header_bytes.len() as u64, Ordering::Relaxed);
 
         let meta = VlogSegmentMeta {
             path: path.clone(),
@@ -402,10 +497,12 @@ impl VlogSegment {
         };
 
         Ok(Self {
+            id: segment_id,
             file: buf_writer,
             meta,
             current_offset: header_bytes.len() as u64,
             config: config.clone(),
+            bytes_written: Arc::clone(bytes_written),
         })
     }
 
@@ -434,19 +531,21 @@ impl VlogSegment {
         self.file.write_all(&(entry_bytes.len() as u32).to_le_bytes())?;
         self.file.write_all(&entry_bytes)?;
 
-        // Write value data
+        // Write value data and make it visible to readers
         self.file.write_all(&compressed_data)?;
+        self.file.flush()?;
 
         // Update metadata
         let entry_size = 4 + entry_bytes.len() + compressed_data.len();
         let vptr = ValuePointer::with_checksum(
-            self.meta.path.file_name().unwrap().to_string_lossy().parse::<u64>().unwrap_or(0),
+            self.id,
             self.current_offset,
             compressed_data.len() as u32,
             checksum,
         );
 
         self.current_offset += entry_size as u64;
+        self.bytes_written.fetch_add(entry_size as u64, Ordering::Relaxed);
         self.meta.size = self.current_offset;
         self.meta.entry_count += 1;
         self.meta.last_offset = self.current_offset;
@@ -454,16 +553,24 @@ impl VlogSegment {
         Ok(vptr)
     }
 
-    /// Compress a value
+    /// Compress a value with the configured algorithm, storing it as is
+    /// when that doesn't make it smaller. The checksum covers the
+    /// uncompressed bytes, so reads verify what they return.
     fn compress_value(&self, data: &[u8]) -> Result<(Vec<u8>, CompressionAlgorithm, u32)> {
-        // TODO: Re-implement compression when dependencies are available
         let checksum = self.calculate_checksum(data);
+        let algo = self.config.compression_algorithm;
+        if algo != CompressionAlgorithm::None {
+            let compressed = compression::compress_with_level(data, algo, self.config.compression_level)?;
+            if compressed.len() < data.len() {
+                return Ok((compressed, algo, checksum));
+            }
+        }
         Ok((data.to_vec(), CompressionAlgorithm::None, checksum))
     }
 
     /// Calculate checksum for data
     fn calculate_checksum(&self, data: &[u8]) -> u32 {
-        crc32fast::hash(data)
+        checksum(data, self.config.checksum)
     }
 
     /// Check if segment should be rotated
@@ -471,21 +578,213 @@ impl VlogSegment {
         self.meta.size >= self.config.max_segment_size
     }
 
-    /// Close the segment
-    fn close(&mut self) -> Result<()> {
+    /// Flush buffered data and fsync the segment
+    fn sync(&mut self) -> Result<()> {
         self.file.flush()?;
         self.file.get_ref().sync_all()?;
+        Ok(())
+    }
+
+    /// Close the segment
+    fn close(&mut self) -> Result<()> {
+        self.sync()?;
         self.meta.closed = true;
         Ok(())
     }
 }
 
+/// A single value copied into its own segment as it is read, so memory use
+/// is capped at `stream_chunk_size` whatever the value's size. The value's
+/// entry metadata is written as a placeholder first and filled in once its
+/// length and checksum are known.
+pub struct VlogStreamWriter {
+    /// Segment ID
+    id: u64,
+    /// Segment file path
+    path: PathBuf,
+    /// Segment file, opened for writing rather than appending so the entry
+    /// metadata can be rewritten
+    file: BufWriter<File>,
+    /// Offset of the value's entry
+    entry_offset: u64,
+    /// Bytes copied at a time
+    chunk_size: usize,
+    /// Checksum algorithm for the value
+    checksum_type: ChecksumType,
+    /// Writer-wide count of bytes written
+    bytes_written: Arc<AtomicU64>,
+}
+
+impl VlogStreamWriter {
+    /// Create the segment and write its header
+    fn new(vlog_dir: &Path, config: &ValueLogConfig, segment_id: u64, bytes_written: &Arc<AtomicU64>) -> Result<Self> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let path = vlog_dir.join(format!("vlog_{:016x}_{:016x}.seg", segment_id, timestamp));
+        let file = OpenOptions::new().create_new(true).write(true).open(&path)?;
+        let mut file = BufWriter::with_capacity(SEGMENT_BUFFER_SIZE, file);
+
+        let header_bytes = bincode::serialize(&VlogHeader::new(CompressionAlgorithm::None, config.checksum))?;
+        file.write_all(&header_bytes)?;
+        file.flush()?;
+        crate::io::sync_dir(vlog_dir)?;
+        bytes_written.fetch_add(header_bytes.len() as u64, Ordering::Relaxed);
+
+        Ok(Self {
+            id: segment_id,
+            path,
+            file,
+            entry_offset: header_bytes.len() as u64,
+            chunk_size: config.stream_chunk_size,
+            checksum_type: config.checksum,
+            bytes_written: Arc::clone(bytes_written),
+        })
+    }
+
+    /// Copy `reader` to its end into the segment, failing once more than
+    /// `max_len` bytes have been read. The segment is fsynced before the
+    /// pointer is returned, and deleted if the copy fails.
+    pub fn write_from(mut self, reader: impl Read, max_len: usize) -> Result<ValuePointer> {
+        let result = self.copy(reader, max_len);
+        if result.is_err() {
+            if let Err(e) = std::fs::remove_file(&self.path) {
+                warn!("Failed to remove partial value log segment {}: {}", self.path.display(), e);
+            }
+        }
+        result
+    }
+
+    /// Write the entry and value, then fill in the entry's length and
+    /// checksum
+    fn copy(&mut self, mut reader: impl Read, max_len: usize) -> Result<ValuePointer> {
+        let mut entry = VlogEntry {
+            length: 0,
+            compression: CompressionAlgorithm::None,
+            checksum: 0,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        };
+        let placeholder = bincode::serialize(&entry)?;
+        self.file.write_all(&(placeholder.len() as u32).to_le_bytes())?;
+        self.file.write_all(&placeholder)?;
+
+        let mut hasher = Hasher::new(self.checksum_type);
+        let mut chunk = vec![0u8; self.chunk_size];
+        let mut length = 0usize;
+        loop {
+            let n = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            length += n;
+            if length > max_len {
+                return Err(Error::Config(format!(
+                    "Streamed value exceeds max_value_size of {} bytes",
+                    max_len
+                )));
+            }
+            hasher.update(&chunk[..n]);
+            self.file.write_all(&chunk[..n])?;
+        }
+
+        // Integers are encoded at fixed width, so the real metadata takes
+        // exactly the placeholder's space
+        entry.length = length as u32;
+        entry.checksum = hasher.finish();
+        let entry_bytes = bincode::serialize(&entry)?;
+        debug_assert_eq!(entry_bytes.len(), placeholder.len());
+        self.file.seek(SeekFrom::Start(self.entry_offset + 4))?;
+        self.file.write_all(&entry_bytes)?;
+        self.file.flush()?;
+        self.file.get_ref().sync_all()?;
+
+        self.bytes_written.fetch_add((4 + entry_bytes.len() + length) as u64, Ordering::Relaxed);
+        Ok(ValuePointer::with_checksum(self.id, self.entry_offset, entry.length, entry.checksum))
+    }
+}
+
+/// A value read back a chunk at a time. Separated values are read straight
+/// from their segment, with the checksum checked when the end is reached;
+/// a mismatch or a value cut short fails the final read with
+/// [`std::io::ErrorKind::InvalidData`] or
+/// [`std::io::ErrorKind::UnexpectedEof`].
+pub struct ValueStream {
+    /// Source of the value's bytes
+    reader: Box<dyn Read + Send>,
+    /// Bytes of the value not yet read
+    remaining: u64,
+    /// Running checksum and the value it must reach, if verifying
+    check: Option<(Hasher, u32)>,
+}
+
+impl ValueStream {
+    /// Stream a value already in memory
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Self {
+            remaining: data.len() as u64,
+            reader: Box::new(Cursor::new(data)),
+            check: None,
+        }
+    }
+
+    /// Total bytes of the value not yet read
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+}
+
+impl Read for ValueStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.remaining -= n as u64;
+        if let Some((hasher, _)) = &mut self.check {
+            hasher.update(&buf[..n]);
+        }
+        if n == 0 && !buf.is_empty() {
+            if self.remaining > 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    format!("Value ended {} bytes short", self.remaining),
+                ));
+            }
+            if let Some((hasher, expected)) = self.check.take() {
+                if hasher.finish() != expected {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        Error::ValueLogCorruption(format!(
+                            "Checksum mismatch: expected {}, got {}",
+                            expected,
+                            hasher.finish()
+                        )),
+                    ));
+                }
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Batches at least this large read segments in parallel
+const PARALLEL_READ_MIN_VALUES: usize = 64;
+
 /// Value log reader for reading values from segments
 pub struct VlogReader {
     /// Value log directory
     vlog_dir: PathBuf,
     /// Open segment handles
     segments: HashMap<u64, VlogSegmentReader>,
+    /// How segment files are opened
+    access: FileAccess,
+    /// Bytes read from segment files
+    bytes_read: u64,
+    /// Whether reads verify value checksums by default
+    verify_checksums: bool,
 }
 
 impl VlogReader {
@@ -494,80 +793,356 @@ impl VlogReader {
         Ok(Self {
             vlog_dir,
             segments: HashMap::new(),
+            access: FileAccess::Buffered,
+            bytes_read: 0,
+            verify_checksums: true,
         })
     }
 
+    /// Whether `read_value` and `read_values` verify value checksums
+    pub fn with_verify_checksums(mut self, verify: bool) -> Self {
+        self.verify_checksums = verify;
+        self
+    }
+
+    /// Set how segment files are opened (e.g. direct I/O). Segments are
+    /// appended to while readers are open, so memory mapping is not used
+    /// for them and falls back to buffered reads.
+    pub fn with_file_access(mut self, access: FileAccess) -> Self {
+        self.access = match access {
+            FileAccess::Mmap => FileAccess::Buffered,
+            other => other,
+        };
+        self
+    }
+
+    /// Reader for a segment, opened on first use
+    fn segment(&mut self, segment_id: u64) -> Result<&VlogSegmentReader> {
+        if !self.segments.contains_key(&segment_id) {
+            let reader = VlogSegmentReader::new(&self.vlog_dir, segment_id, self.access)?;
+            self.segments.insert(segment_id, reader);
+        }
+        Ok(&self.segments[&segment_id])
+    }
+
     /// Read a value using a value pointer
     pub fn read_value(&mut self, vptr: &ValuePointer) -> Result<Value> {
-        // Get or create segment reader
-        let segment_reader = if let Some(reader) = self.segments.get_mut(&vptr.segment_id) {
-            reader
+        self.read_value_with_verify(vptr, self.verify_checksums)
+    }
+
+    /// Read a value, verifying its checksum only if `verify` is set
+    pub fn read_value_with_verify(&mut self, vptr: &ValuePointer, verify: bool) -> Result<Value> {
+        let (value, bytes_read) = self.segment(vptr.segment_id)?.read_value_at(vptr.offset, vptr.length, verify)?;
+        self.bytes_read += bytes_read;
+        Ok(value)
+    }
+
+    /// Open a value as a stream read `chunk_size` bytes at a time. The
+    /// segment gets a file handle of its own, so the stream does not
+    /// borrow the reader. A compressed value is read and decompressed in
+    /// full first.
+    pub fn read_value_stream(&mut self, vptr: &ValuePointer, verify: bool, chunk_size: usize) -> Result<ValueStream> {
+        let segment = self.segment(vptr.segment_id)?;
+        let (entry, data_offset) = segment.read_entry(vptr.offset, vptr.length)?;
+        if entry.compression != CompressionAlgorithm::None {
+            let data = segment.read_entry_value(&entry, data_offset, verify)?;
+            self.bytes_read += data_offset - vptr.offset + entry.length as u64;
+            return Ok(ValueStream::from_bytes(data));
+        }
+        let check = (verify && segment.checksum_type != ChecksumType::None)
+            .then(|| (Hasher::new(segment.checksum_type), entry.checksum));
+
+        let mut file = File::open(segment.file.path())?;
+        file.seek(SeekFrom::Start(data_offset))?;
+        self.bytes_read += data_offset - vptr.offset + entry.length as u64;
+        Ok(ValueStream {
+            reader: Box::new(BufReader::with_capacity(chunk_size, file).take(entry.length as u64)),
+            remaining: entry.length as u64,
+            check,
+        })
+    }
+
+    /// Read `len` bytes of a value starting at byte `start`, clamped to the
+    /// value's length. An uncompressed value is read in place, so only the
+    /// bytes asked for leave the file; its checksum covers the whole value
+    /// and is only checked when the range covers all of it. A compressed
+    /// value has to be read and decompressed in full first.
+    pub fn read_value_range(&mut self, vptr: &ValuePointer, start: usize, len: usize) -> Result<Vec<u8>> {
+        let verify = self.verify_checksums;
+        let (data, bytes_read) =
+            self.segment(vptr.segment_id)?.read_value_range(vptr.offset, vptr.length, start, len, verify)?;
+        self.bytes_read += bytes_read;
+        Ok(data)
+    }
+
+    /// Read the values of several pointers, returned in the order of
+    /// `ptrs`. Pointers are grouped by segment and each segment is read in
+    /// offset order, so a scan's values are fetched front to back instead
+    /// of at random. Large batches spanning several segments read the
+    /// segments on parallel threads.
+    pub fn read_values(&mut self, ptrs: &[ValuePointer]) -> Result<Vec<Value>> {
+        let mut by_segment: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+        for (i, vptr) in ptrs.iter().enumerate() {
+            by_segment.entry(vptr.segment_id).or_default().push(i);
+        }
+        for (segment_id, indexes) in &mut by_segment {
+            self.segment(*segment_id)?;
+            indexes.sort_by_key(|&i| ptrs[i].offset);
+        }
+
+        let verify = self.verify_checksums;
+        let read_segment = |segment_id: &u64, indexes: &Vec<usize>| -> Result<Vec<(usize, Value, u64)>> {
+            let reader = &self.segments[segment_id];
+            indexes
+                .iter()
+                .map(|&i| {
+                    let (value, bytes_read) = reader.read_value_at(ptrs[i].offset, ptrs[i].length, verify)?;
+                    Ok((i, value, bytes_read))
+                })
+                .collect()
+        };
+        let groups: Vec<Vec<(usize, Value, u64)>> = if by_segment.len() > 1 && ptrs.len() >= PARALLEL_READ_MIN_VALUES {
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = by_segment
+                    .iter()
+                    .map(|(segment_id, indexes)| scope.spawn(move || read_segment(segment_id, indexes)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("value log read thread panicked"))
+                    .collect::<Result<_>>()
+            })?
         } else {
-            let reader = VlogSegmentReader::new(&self.vlog_dir, vptr.segment_id)?;
-            self.segments.insert(vptr.segment_id, reader);
-            self.segments.get_mut(&vptr.segment_id).unwrap()
+            by_segment
+                .iter()
+                .map(|(segment_id, indexes)| read_segment(segment_id, indexes))
+                .collect::<Result<_>>()?
         };
 
-        // Read the value
-        segment_reader.read_value_at(vptr.offset, vptr.length)
+        let mut values: Vec<Option<Value>> = vec![None; ptrs.len()];
+        for (i, value, bytes_read) in groups.into_iter().flatten() {
+            self.bytes_read += bytes_read;
+            values[i] = Some(value);
+        }
+        Ok(values.into_iter().map(|value| value.expect("every pointer was read")).collect())
+    }
+
+    /// Total bytes read from segment files by `read_value`
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
     }
 
     /// Close the reader
     pub fn close(&mut self) -> Result<()> {
-        for (_, reader) in self.segments.drain() {
+        for (_, mut reader) in self.segments.drain() {
             reader.close()?;
         }
         Ok(())
     }
 }
 
+/// Delete segments in `vlog_dir` that hold no values: ones left empty or
+/// with a torn header by a crash while they were created, and ones opened
+/// by an earlier run that never received a write. No value pointer can
+/// refer to them. Must run before a [`VlogWriter`] opens the directory.
+/// Returns the number of files and bytes reclaimed.
+pub fn remove_empty_segments(vlog_dir: &Path) -> Result<(usize, u64)> {
+    // Every complete value follows a header at least this long
+    let header_size = bincode::serialized_size(&VlogHeader::new(CompressionAlgorithm::None, ChecksumType::Crc32))?;
+    let (mut files, mut bytes) = (0, 0);
+    for entry in std::fs::read_dir(vlog_dir)? {
+        let entry = entry?;
+        if parse_segment_id(&entry.file_name().to_string_lossy()).is_none() {
+            continue;
+        }
+        let size = entry.metadata()?.len();
+        if size > header_size {
+            continue;
+        }
+        std::fs::remove_file(entry.path())?;
+        info!("Removed empty value log segment {} ({} bytes)", entry.path().display(), size);
+        files += 1;
+        bytes += size;
+    }
+    Ok((files, bytes))
+}
+
+/// Check every segment in `vlog_dir`: each header, and each entry's
+/// metadata and value checksum. Returns the number of segments checked and
+/// the damage found.
+pub fn verify_segments(vlog_dir: &Path, access: FileAccess) -> Result<(u64, Vec<Corruption>)> {
+    let (mut segments, mut corruptions) = (0, Vec::new());
+    for entry in std::fs::read_dir(vlog_dir)? {
+        let path = entry?.path();
+        if path.file_name().and_then(|name| parse_segment_id(&name.to_string_lossy())).is_none() {
+            continue;
+        }
+        segments += 1;
+        match VlogSegmentReader::open(&path, access) {
+            Ok(reader) => corruptions.extend(reader.verify()),
+            Err(e) => corruptions.push(Corruption { path, offset: 0, message: e.to_string() }),
+        }
+    }
+    Ok((segments, corruptions))
+}
+
+/// Path of the segment file with ID `segment_id`
+pub fn segment_path(vlog_dir: &Path, segment_id: u64) -> Result<PathBuf> {
+    Ok(std::fs::read_dir(vlog_dir)?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| parse_segment_id(&entry.file_name().to_string_lossy()) == Some(segment_id))
+        .ok_or_else(|| Error::InvalidValuePointer(format!("Segment {} not found", segment_id)))?
+        .path())
+}
+
 /// Value log segment reader
 struct VlogSegmentReader {
     /// File handle
-    file: File,
-    /// Segment path
-    path: PathBuf,
+    file: RandomAccessFile,
+    /// Checksum algorithm from the segment header
+    checksum_type: ChecksumType,
+    /// Offset of the first entry, just past the header
+    data_start: u64,
+}
+
+/// Parse the segment ID out of a `vlog_<id>_<timestamp>.seg` file name
+pub fn parse_segment_id(file_name: &str) -> Option<u64> {
+    let rest = file_name.strip_prefix("vlog_")?.strip_suffix(".seg")?;
+    let (id, _timestamp) = rest.split_once('_')?;
+    u64::from_str_radix(id, 16).ok()
 }
 
 impl VlogSegmentReader {
     /// Create a new segment reader
-    fn new(vlog_dir: &PathBuf, segment_id: u64) -> Result<Self> {
-        // Find segment file by ID
-        let entries = std::fs::read_dir(vlog_dir)?;
-        let segment_path = entries
-            .filter_map(|entry| entry.ok())
-            .find(|entry| {
-                entry.path().to_string_lossy().contains(&format!("vlog_{:016x}", segment_id))
-            })
-            .ok_or_else(|| Error::InvalidValuePointer(format!("Segment {} not found", segment_id)))?
-            .path();
-
-        let file = OpenOptions::new().read(true).open(&segment_path)?;
+    fn new(vlog_dir: &Path, segment_id: u64, access: FileAccess) -> Result<Self> {
+        Self::open(&segment_path(vlog_dir, segment_id)?, access)
+    }
 
-        Ok(Self {
-            file,
-            path: segment_path,
-        })
+    /// Open the segment file at `path`
+    fn open(path: &Path, access: FileAccess) -> Result<Self> {
+        let file = RandomAccessFile::open(path, access)?;
+        let (checksum_type, data_start) = Self::read_header(&file)?;
+        Ok(Self { file, checksum_type, data_start })
     }
 
-    /// Read a value at a specific offset
-    fn read_value_at(&mut self, offset: u64, length: u32) -> Result<Value> {
-        // Seek to the offset
-        self.file.seek(SeekFrom::Start(offset))?;
+    /// Read the segment header and return its checksum algorithm and
+    /// encoded size
+    fn read_header(file: &RandomAccessFile) -> Result<(ChecksumType, u64)> {
+        let bytes = file.read_at(0, file.len().min(VlogHeader::MAX_SIZE) as usize)?;
+        let corrupt = || Error::ValueLogCorruption(format!("Invalid segment header in {}", file.path().display()));
+        let version = bytes.get(8..12).ok_or_else(corrupt)?;
+        if u32::from_le_bytes([version[0], version[1], version[2], version[3]]) == VlogHeader::VERSION_V1 {
+            // Version 1 headers lack the checksum type field
+            let size = bincode::serialized_size(&VlogHeader::new(CompressionAlgorithm::None, ChecksumType::Crc32))?
+                - bincode::serialized_size(&ChecksumType::Crc32)?;
+            return Ok((ChecksumType::Crc32, size));
+        }
+
+        let header: VlogHeader = bincode::deserialize(&bytes).map_err(|_| corrupt())?;
+        if !header.validate() {
+            return Err(corrupt());
+        }
+        Ok((header.checksum_type, bincode::serialized_size(&header)?))
+    }
 
+    /// Read the metadata of the entry at `offset`, returning it with the
+    /// offset of the value data
+    fn read_entry_meta(&self, offset: u64) -> Result<(VlogEntry, u64)> {
         // Read entry metadata length
-        let mut len_bytes = [0u8; 4];
-        self.file.read_exact(&mut len_bytes)?;
-        let entry_len = u32::from_le_bytes(len_bytes) as usize;
+        let len_bytes = self.file.read_at(offset, 4)?;
+        let entry_len = u32::from_le_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]]) as usize;
 
         // Read entry metadata
-        let mut entry_bytes = vec![0u8; entry_len];
-        self.file.read_exact(&mut entry_bytes)?;
+        let entry_bytes = self.file.read_at(offset + 4, entry_len)?;
         let entry: VlogEntry = bincode::deserialize(&entry_bytes)?;
+        Ok((entry, offset + 4 + entry_len as u64))
+    }
 
-        // Read value data
-        let mut value_data = vec![0u8; entry.length as usize];
-        self.file.read_exact(&mut value_data)?;
+    /// Read the metadata of the entry at `offset`, checking it against the
+    /// pointer's `length`. Returns it with the offset of the value data.
+    fn read_entry(&self, offset: u64, length: u32) -> Result<(VlogEntry, u64)> {
+        let (entry, data_offset) = self.read_entry_meta(offset)?;
+        if entry.length != length {
+            return Err(Error::InvalidValuePointer(format!(
+                "Length mismatch at offset {} in {}: pointer says {}, entry has {}",
+                offset,
+                self.file.path().display(),
+                length,
+                entry.length
+            )));
+        }
+        Ok((entry, data_offset))
+    }
+
+    /// Walk the entries from the end of the header, checking that each
+    /// one's metadata decodes and its value matches its checksum. The walk
+    /// stops at the first entry whose metadata cannot be read or that runs
+    /// past the end of the file, since nothing after it can be located.
+    fn verify(&self) -> Vec<Corruption> {
+        let mut corruptions = Vec::new();
+        let mut report = |offset: u64, message: String| {
+            corruptions.push(Corruption { path: self.file.path().to_path_buf(), offset, message });
+        };
+        let end = self.file.len();
+        let mut offset = self.data_start;
+        while offset < end {
+            let (entry, data_offset) = match self.read_entry_meta(offset) {
+                Ok(meta) => meta,
+                Err(e) => {
+                    report(offset, format!("Unreadable entry metadata: {}", e));
+                    break;
+                }
+            };
+            if data_offset + entry.length as u64 > end {
+                report(offset, "Entry runs past the end of the segment".to_string());
+                break;
+            }
+            if let Err(e) = self.read_entry_value(&entry, data_offset, true) {
+                report(offset, e.to_string());
+            }
+            offset = data_offset + entry.length as u64;
+        }
+        corruptions
+    }
+
+    /// Read `len` bytes of the value at `offset` from byte `start`, clamped
+    /// to its length, returning them with the number of bytes read from
+    /// the file. See [`VlogReader::read_value_range`].
+    fn read_value_range(
+        &self,
+        offset: u64,
+        length: u32,
+        start: usize,
+        len: usize,
+        verify_checksum: bool,
+    ) -> Result<(Vec<u8>, u64)> {
+        let (entry, data_offset) = self.read_entry(offset, length)?;
+        let whole = start == 0 && len >= entry.length as usize;
+        if entry.compression != CompressionAlgorithm::None || whole {
+            let data = self.read_entry_value(&entry, data_offset, verify_checksum)?;
+            let start = start.min(data.len());
+            let end = start.saturating_add(len).min(data.len());
+            return Ok((data[start..end].to_vec(), data_offset - offset + entry.length as u64));
+        }
+
+        let start = start.min(entry.length as usize);
+        let end = start.saturating_add(len).min(entry.length as usize);
+        let data = self.file.read_at(data_offset + start as u64, end - start)?;
+        Ok((data, (data_offset - offset) + (end - start) as u64))
+    }
+
+    /// Read a value at a specific offset, returning it with the number of
+    /// bytes read from the file. The checksum is only checked if `verify_checksum`.
+    fn read_value_at(&self, offset: u64, length: u32, verify_checksum: bool) -> Result<(Value, u64)> {
+        let (entry, data_offset) = self.read_entry(offset, length)?;
+        let data = self.read_entry_value(&entry, data_offset, verify_checksum)?;
+        Ok((Value::new(data), data_offset - offset + entry.length as u64))
+    }
+
+    /// Read, decompress and optionally verify the whole value of `entry`,
+    /// whose data starts at `data_offset`
+    fn read_entry_value(&self, entry: &VlogEntry, data_offset: u64, verify_checksum: bool) -> Result<Vec<u8>> {
+        let value_data = self.file.read_at(data_offset, entry.length as usize)?;
 
         // Decompress if needed
         let decompressed_data = if entry.compression != CompressionAlgorithm::None {
@@ -576,41 +1151,22 @@ impl VlogSegmentReader {
             value_data
         };
 
-        // Verify checksum
-        let calculated_checksum = self.calculate_checksum(&decompressed_data);
-        if calculated_checksum != entry.checksum {
+        // Verify checksum with the segment's algorithm
+        if verify_checksum && !verify(&decompressed_data, entry.checksum, self.checksum_type) {
             return Err(Error::ValueLogCorruption(format!(
                 "Checksum mismatch: expected {}, got {}",
-                entry.checksum, calculated_checksum
+                entry.checksum,
+                checksum(&decompressed_data, self.checksum_type)
             )));
         }
 
-        Ok(Value::new(decompressed_data))
+        Ok(decompressed_data)
     }
 
-    /// Decompress a value
+    /// Decompress a value stored with `compression`
     fn decompress_value(&self, data: &[u8], compression: &CompressionAlgorithm) -> Result<Vec<u8>> {
-        match compression {
-            CompressionAlgorithm::Lz4 => {
-                // TODO: Re-implement decompression when dependencies are available
-                Ok(data.to_vec())
-            }
-            CompressionAlgorithm::Zstd => {
-                // TODO: Re-implement decompression when dependencies are available
-                Ok(data.to_vec())
-            }
-            CompressionAlgorithm::Snappy => {
-                // Note: snappy crate doesn't have a simple decompress function
-                // For now, return as-is
-                Ok(data.to_vec())
-            }
-            CompressionAlgorithm::None => Ok(data.to_vec()),
-        }
-    }
-
-    /// Calculate checksum for data
-    fn calculate_checksum(&self, data: &[u8]) -> u32 {
-        crc32fast::hash(data)
+        compression::decompress(data, *compression)
+            .map_err(|e| Error::ValueLogCorruption(format!("Undecodable {:?} value: {}", compression, e)))
     }
 
     /// Close the segment reader
@@ -622,8 +1178,14 @@ impl VlogSegmentReader {
 
 impl Drop for VlogWriter {
     fn drop(&mut self) {
-        // Try to close gracefully
-        let _ = tokio::runtime::Handle::current().block_on(self.close());
+        // Blocking on `close` would panic both inside and outside a runtime,
+        // so stop the queues and close owned segments synchronously instead
+        for sender in &self.write_queues {
+            let _ = sender.send(WriteRequest::Shutdown);
+        }
+        for segment in &self.segments {
+            let _ = segment.write().close();
+        }
     }
 }
 
@@ -646,19 +1208,161 @@ mod tests {
 
     #[test]
     fn test_vlog_header_validation() {
-        let header = VlogHeader::new(CompressionAlgorithm::Lz4);
+        let mut header = VlogHeader::new(CompressionAlgorithm::Lz4, ChecksumType::XxHash3);
         assert!(header.validate());
+        header.checksum_type = ChecksumType::None;
+        assert!(!header.validate());
     }
 
-    #[test]
-    fn test_compression_decompression() {
-        let data = b"Hello, World! This is a test string for compression testing.";
-        let config = ValueLogConfig::default();
-        
-        // Test LZ4 compression
-        // TODO: Re-implement compression when dependencies are available
-        let compressed = data.to_vec();
-        let decompressed = data.to_vec();
-        assert_eq!(data, &decompressed[..]);
+    #[tokio::test]
+    async fn test_segment_checksum_type_from_header() {
+        let temp_dir = tempdir().unwrap();
+        let config = ValueLogConfig {
+            vlog_path: temp_dir.path().to_path_buf(),
+            checksum: ChecksumType::XxHash3,
+            ..Default::default()
+        };
+        let mut writer = VlogWriter::new(config).unwrap();
+        let vptr = writer.write_value_sync(Value::new(b"xxhash value".to_vec())).unwrap();
+        assert_eq!(vptr.checksum, Some(checksum(b"xxhash value", ChecksumType::XxHash3)));
+
+        // The reader takes the algorithm from the header, not a config
+        let mut reader = VlogReader::new(temp_dir.path().to_path_buf()).unwrap();
+        assert_eq!(reader.read_value(&vptr).unwrap().data, b"xxhash value");
+    }
+
+    #[tokio::test]
+    async fn test_read_values_keeps_pointer_order() {
+        let temp_dir = tempdir().unwrap();
+        let config = ValueLogConfig {
+            vlog_path: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        // Each writer starts a new segment
+        let mut ptrs = Vec::new();
+        for segment in 0..2 {
+            let mut writer = VlogWriter::new(config.clone()).unwrap();
+            for i in 0..40 {
+                ptrs.push(writer.write_value_sync(Value::new(format!("{}-{}", segment, i).into_bytes())).unwrap());
+            }
+            writer.close().await.unwrap();
+        }
+        assert_ne!(ptrs[0].segment_id, ptrs[40].segment_id);
+
+        // Interleave the segments, reverse offsets and repeat a pointer
+        let mut wanted: Vec<_> = ptrs.iter().rev().cloned().collect();
+        wanted.push(ptrs[3].clone());
+        let mut reader = VlogReader::new(temp_dir.path().to_path_buf()).unwrap();
+        let values = reader.read_values(&wanted).unwrap();
+        assert_eq!(values.len(), wanted.len());
+        for (vptr, value) in wanted.iter().zip(&values) {
+            assert_eq!(value.data, reader.read_value(vptr).unwrap().data);
+        }
+        assert_eq!(values[0].data, b"1-39");
+        assert_eq!(values[80].data, b"0-3");
+    }
+
+    #[tokio::test]
+    async fn test_write_queues_close_their_segments() {
+        let temp_dir = tempdir().unwrap();
+        let config = ValueLogConfig {
+            vlog_path: temp_dir.path().to_path_buf(),
+            write_queues: 3,
+            ..Default::default()
+        };
+        let mut writer = VlogWriter::new(config.clone()).unwrap();
+        let sync_ptr = writer.write_value_sync(Value::new(b"sync".to_vec())).unwrap();
+        let mut ptrs = Vec::new();
+        for i in 0..30 {
+            ptrs.push(writer.write_value(Value::new(format!("queued-{}", i).into_bytes())).await.unwrap());
+        }
+        // Queue segments are numbered after the writer's own
+        let own = writer.active_segment_ids();
+        assert!(ptrs.iter().all(|vptr| !own.contains(&vptr.segment_id)));
+        writer.close().await.unwrap();
+
+        // A later writer starts after every segment the queues used
+        let reopened = VlogWriter::new(config).unwrap();
+        let max_queued = ptrs.iter().map(|vptr| vptr.segment_id).max().unwrap();
+        assert!(reopened.active_segment_ids().iter().all(|&id| id > max_queued));
+
+        let mut reader = VlogReader::new(temp_dir.path().to_path_buf()).unwrap();
+        assert_eq!(reader.read_value(&sync_ptr).unwrap().data, b"sync");
+        for (i, vptr) in ptrs.iter().enumerate() {
+            assert_eq!(reader.read_value(vptr).unwrap().data, format!("queued-{}", i).into_bytes());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_zstd_levels_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let config = |level: i32| ValueLogConfig {
+            vlog_path: temp_dir.path().to_path_buf(),
+            write_queues: 1,
+            compression_algorithm: CompressionAlgorithm::Zstd,
+            compression_level: level,
+            ..Default::default()
+        };
+        let blob: Vec<u8> = (0..2000)
+            .flat_map(|i| format!(r#"{{"id":{},"name":"user{}","tags":["a","b"]}},"#, i, i % 37).into_bytes())
+            .collect();
+
+        let mut pointers = Vec::new();
+        for level in [1, 19] {
+            let mut writer = VlogWriter::new(config(level)).unwrap();
+            pointers.push(writer.write_value_sync(Value::new(blob.clone())).unwrap());
+            writer.close().await.unwrap();
+        }
+        assert!(pointers[1].length <= pointers[0].length);
+        assert!((pointers[0].length as usize) < blob.len() / 4);
+
+        // Frames decode the same whatever level wrote them
+        let mut reader = VlogReader::new(temp_dir.path().to_path_buf()).unwrap();
+        for vptr in &pointers {
+            assert_eq!(reader.read_value(vptr).unwrap().data, blob);
+        }
+
+        for level in [0, 20] {
+            let mut engine_config = crate::config::Config::default();
+            engine_config.value_log.compression_level = level;
+            assert!(engine_config.validate().is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compressed_values_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let config = ValueLogConfig {
+            vlog_path: temp_dir.path().to_path_buf(),
+            write_queues: 1,
+            ..Default::default()
+        };
+        let mut writer = VlogWriter::new(config.clone()).unwrap();
+        let compressible = b"auradb value log ".repeat(4096);
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let noise: Vec<u8> = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let packed = writer.write_value_sync(Value::new(compressible.clone())).unwrap();
+        let raw = writer.write_value_sync(Value::new(noise.clone())).unwrap();
+        writer.close().await.unwrap();
+
+        // Incompressible values are stored as they are
+        assert!((packed.length as usize) < compressible.len() / 10);
+        assert_eq!(raw.length as usize, noise.len());
+        let segments: u64 = std::fs::read_dir(temp_dir.path()).unwrap().map(|e| e.unwrap().metadata().unwrap().len()).sum();
+        assert!(segments < compressible.len() as u64);
+
+        let mut reader = VlogReader::new(temp_dir.path().to_path_buf()).unwrap();
+        assert_eq!(reader.read_value(&packed).unwrap().data, compressible);
+        assert_eq!(reader.read_value(&raw).unwrap().data, noise);
+        let mut streamed = Vec::new();
+        reader.read_value_stream(&packed, true, 1024).unwrap().read_to_end(&mut streamed).unwrap();
+        assert_eq!(streamed, compressible);
     }
 }
@@ -1,17 +1,22 @@
-use crate::config::{WalConfig, WalSyncPolicy};
-use crate::error::{Error, Result};
-use crate::storage::{Entry, ValuePointer};
-use parking_lot::RwLock;
+use crate::checksum::{self, ChecksumType};
+use crate::compression;
+use crate::config::{CompressionAlgorithm, WalConfig, WalSyncPolicy};
+use crate::error::{Corruption, Error, Result};
+use crate::io;
+use crate::storage::{Entry, Key, OpType, Value, ValuePointer};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Write};
-use std::path::PathBuf;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
-use tokio::sync::mpsc;
-use tokio::time::{self, Duration};
-use tracing::{debug, error, info, warn};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// Bytes before each record's data: its length and checksum
+const RECORD_HEADER_LEN: usize = 8;
 
 /// WAL record types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +47,62 @@ pub enum WalRecord {
         sequence: u64,
         timestamp: u64,
     },
+    /// Range delete of `[start, end)`
+    DeleteRange {
+        start: Vec<u8>,
+        end: Vec<u8>,
+        sequence: u64,
+        timestamp: u64,
+    },
+}
+
+impl WalRecord {
+    /// Sequence number of the record; for a batch, that of its last operation
+    pub fn sequence(&self) -> u64 {
+        match self {
+            WalRecord::Put { sequence, .. }
+            | WalRecord::PutPointer { sequence, .. }
+            | WalRecord::Delete { sequence, .. }
+            | WalRecord::Batch { sequence, .. }
+            | WalRecord::DeleteRange { sequence, .. } => *sequence,
+        }
+    }
+
+    /// The record that persists `entry`
+    pub fn from_entry(entry: &Entry) -> Self {
+        let (key, sequence, timestamp) = (entry.key.data.clone(), entry.sequence, entry.timestamp);
+        if entry.op_type == OpType::RangeDelete {
+            let end = entry.value.as_ref().map(|v| v.data.clone()).unwrap_or_default();
+            WalRecord::DeleteRange { start: key, end, sequence, timestamp }
+        } else if let Some(value_pointer) = &entry.value_pointer {
+            WalRecord::PutPointer { key, value_pointer: value_pointer.clone(), sequence, timestamp }
+        } else if let Some(value) = &entry.value {
+            WalRecord::Put { key, value: value.data.clone(), sequence, timestamp }
+        } else {
+            WalRecord::Delete { key, sequence, timestamp }
+        }
+    }
+
+    /// The entries the record writes, in order
+    pub fn into_entries(self) -> Vec<Entry> {
+        let (mut entry, timestamp) = match self {
+            WalRecord::Put { key, value, sequence, timestamp } => {
+                (Entry::new(Key::new(key), Value::new(value), sequence), timestamp)
+            }
+            WalRecord::PutPointer { key, value_pointer, sequence, timestamp } => {
+                (Entry::with_pointer(Key::new(key), value_pointer, sequence), timestamp)
+            }
+            WalRecord::Delete { key, sequence, timestamp } => (Entry::delete(Key::new(key), sequence), timestamp),
+            WalRecord::DeleteRange { start, end, sequence, timestamp } => {
+                (Entry::range_delete(Key::new(start), Key::new(end), sequence), timestamp)
+            }
+            WalRecord::Batch { operations, .. } => {
+                return operations.into_iter().flat_map(WalRecord::into_entries).collect();
+            }
+        };
+        entry.timestamp = timestamp;
+        vec![entry]
+    }
 }
 
 /// WAL file header
@@ -53,27 +114,36 @@ pub struct WalHeader {
     pub version: u32,
     /// File creation timestamp
     pub created_at: u64,
-    /// Checksum of the header
+    /// Checksum algorithm for the file's records
+    pub checksum_type: ChecksumType,
+    /// CRC32 checksum of the header
     pub checksum: u32,
 }
 
 impl WalHeader {
     const MAGIC: [u8; 8] = [0x41, 0x55, 0x52, 0x41, 0x44, 0x42, 0x57, 0x41]; // "AURADBWA"
-    const VERSION: u32 = 1;
+    const VERSION: u32 = 4;
+    /// Files before records were tagged with their compression algorithm
+    const VERSION_UNTAGGED: u32 = 2;
+    /// Files before records carried a checksum
+    const VERSION_UNCHECKSUMMED: u32 = 3;
 
     /// Create a new WAL header
-    pub fn new() -> Self {
+    pub fn new(checksum_type: ChecksumType) -> Self {
         let created_at = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis() as u64;
 
-        Self {
+        let mut header = Self {
             magic: Self::MAGIC,
             version: Self::VERSION,
             created_at,
-            checksum: 0, // Will be calculated
-        }
+            checksum_type,
+            checksum: 0,
+        };
+        header.checksum = header.calculate_checksum();
+        header
     }
 
     /// Calculate checksum for the header
@@ -83,15 +153,33 @@ impl WalHeader {
         hasher.update(&self.magic);
         hasher.update(&self.version.to_le_bytes());
         hasher.update(&self.created_at.to_le_bytes());
+        hasher.update(&[self.checksum_type.as_u8()]);
         hasher.finalize()
     }
 
     /// Validate the header
     pub fn validate(&self) -> bool {
         self.magic == Self::MAGIC
-            && self.version == Self::VERSION
+            && (Self::VERSION_UNTAGGED..=Self::VERSION).contains(&self.version)
             && self.checksum == self.calculate_checksum()
     }
+
+    /// Whether each record starts with its compression tag
+    fn tags_records(&self) -> bool {
+        self.version > Self::VERSION_UNTAGGED
+    }
+
+    /// Algorithm of the checksum following each record's length prefix,
+    /// if records carry one
+    fn record_checksum(&self) -> Option<ChecksumType> {
+        (self.version > Self::VERSION_UNCHECKSUMMED).then_some(self.checksum_type)
+    }
+}
+
+impl Default for WalHeader {
+    fn default() -> Self {
+        Self::new(ChecksumType::default())
+    }
 }
 
 /// WAL file metadata
@@ -111,20 +199,139 @@ pub struct WalFileMeta {
     pub closed: bool,
 }
 
+impl WalFileMeta {
+    /// Read the metadata of a WAL file from disk. Sequence ranges come from
+    /// reading every record, so this costs a full read of the file.
+    fn read(path: PathBuf, closed: bool) -> Result<Self> {
+        let size = std::fs::metadata(&path)?.len();
+        let mut reader = WalFileReader::new(path.clone())?;
+        let (mut first_sequence, mut last_sequence) = (None, 0);
+        while let Some(record) = reader.read_record()? {
+            first_sequence.get_or_insert(record.sequence());
+            last_sequence = record.sequence();
+        }
+        Ok(Self {
+            path,
+            size,
+            first_sequence: first_sequence.unwrap_or(0),
+            last_sequence,
+            created_at: reader.created_at,
+            closed,
+        })
+    }
+}
+
+/// Snapshot of the WAL's state
+#[derive(Debug, Clone)]
+pub struct WalInfo {
+    /// Last assigned sequence number
+    pub sequence: u64,
+    /// File new records are written to; with async writes it is created
+    /// when the first record is written out
+    pub active_file: PathBuf,
+    /// Total size of the WAL files in bytes
+    pub total_bytes: u64,
+    /// Every WAL file on disk, in log number order
+    pub segments: Vec<WalFileMeta>,
+}
+
+/// File name of the WAL file with the given log number
+fn log_file_name(log_number: u64) -> String {
+    format!("wal_{:016x}.log", log_number)
+}
+
+/// Parse the log number out of a `wal_<number>.log` file name
+pub fn parse_log_number(file_name: &str) -> Option<u64> {
+    let number = file_name.strip_prefix("wal_")?.strip_suffix(".log")?;
+    u64::from_str_radix(number, 16).ok()
+}
+
+/// Highest log number present in `wal_dir` (0 if there are none)
+fn max_log_number(wal_dir: &Path) -> Result<u64> {
+    let mut max = 0;
+    for entry in std::fs::read_dir(wal_dir)? {
+        if let Some(number) = parse_log_number(&entry?.file_name().to_string_lossy()) {
+            max = max.max(number);
+        }
+    }
+    Ok(max)
+}
+
+/// Obsolete WAL files kept around for reuse when `recycle_wal` is set
+const MAX_RECYCLED_LOGS: usize = 4;
+
+/// Extension of WAL files waiting to be reused
+const RECYCLE_EXTENSION: &str = "recycle";
+
+/// Retire WAL files whose log number is below `log_number`, returning how
+/// many were retired. Called once their contents are safely in SSTs. With
+/// `recycle_wal` up to [`MAX_RECYCLED_LOGS`] files are renamed aside for
+/// reuse by the next new WAL file; the rest are deleted.
+pub fn retire_logs_before(config: &WalConfig, log_number: u64) -> Result<usize> {
+    let mut recycled = if config.recycle_wal {
+        recycled_logs(&config.wal_path)?.len()
+    } else {
+        MAX_RECYCLED_LOGS
+    };
+
+    let mut retired = 0;
+    for entry in std::fs::read_dir(&config.wal_path)? {
+        let entry = entry?;
+        let path = entry.path();
+        if parse_log_number(&entry.file_name().to_string_lossy()).is_none_or(|n| n >= log_number) {
+            continue;
+        }
+        if recycled < MAX_RECYCLED_LOGS {
+            std::fs::rename(&path, path.with_extension(RECYCLE_EXTENSION))?;
+            recycled += 1;
+        } else {
+            std::fs::remove_file(&path)?;
+        }
+        retired += 1;
+    }
+    if retired > 0 {
+        io::sync_dir(&config.wal_path)?;
+    }
+    Ok(retired)
+}
+
+/// WAL files waiting to be reused
+fn recycled_logs(wal_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut logs = Vec::new();
+    for entry in std::fs::read_dir(wal_dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == RECYCLE_EXTENSION) {
+            logs.push(path);
+        }
+    }
+    Ok(logs)
+}
+
 /// WAL writer that handles writing records to WAL files
 pub struct WalWriter {
     /// Current WAL file
     current_file: Option<WalFile>,
+    /// Log number of the current WAL file
+    log_number: u64,
     /// WAL configuration
     config: WalConfig,
-    /// Current sequence number
+    /// Last assigned sequence number
     sequence: AtomicU64,
+    /// Sequence of the last record handed to a WAL file
+    logged_sequence: u64,
     /// WAL directory path
     wal_dir: PathBuf,
     /// Async write channel
-    async_sender: Option<mpsc::UnboundedSender<AsyncWriteRequest>>,
-    /// Background task handle
-    background_handle: Option<tokio::task::JoinHandle<()>>,
+    async_sender: Option<mpsc::Sender<AsyncWriteRequest>>,
+    /// Background writer thread; returns the result of its final sync
+    background_handle: Option<std::thread::JoinHandle<Result<()>>>,
+    /// Bytes written to WAL files, shared with the async writer
+    bytes_written: Arc<AtomicU64>,
+    /// Whether records are dropped instead of written, for in-memory
+    /// engines
+    in_memory: bool,
+    /// Fsyncs shared by the writers waiting for their records to be durable
+    commit: Arc<GroupCommit>,
 }
 
 impl WalWriter {
@@ -135,149 +342,158 @@ impl WalWriter {
 
         let mut writer = Self {
             current_file: None,
+            log_number: max_log_number(&wal_dir)?,
             config,
             sequence: AtomicU64::new(0),
+            logged_sequence: 0,
             wal_dir,
             async_sender: None,
             background_handle: None,
+            bytes_written: Arc::new(AtomicU64::new(0)),
+            in_memory: false,
+            commit: Arc::new(GroupCommit::default()),
         };
 
         if writer.config.async_writes {
+            writer.log_number += 1;
             writer.start_async_writer()?;
+            return Ok(writer);
         }
 
         writer.rotate_file()?;
         Ok(writer)
     }
 
-    /// Start the async writer background task
+    /// Create a writer that only numbers writes: records are accepted and
+    /// dropped, and no file or directory is touched
+    pub fn in_memory(config: WalConfig) -> Self {
+        Self {
+            current_file: None,
+            log_number: 0,
+            wal_dir: config.wal_path.clone(),
+            config,
+            sequence: AtomicU64::new(0),
+            logged_sequence: 0,
+            async_sender: None,
+            background_handle: None,
+            bytes_written: Arc::new(AtomicU64::new(0)),
+            in_memory: true,
+            commit: Arc::new(GroupCommit::default()),
+        }
+    }
+
+    /// Continue numbering after `sequence`, the highest sequence already
+    /// persisted elsewhere
+    pub fn with_last_sequence(mut self, sequence: u64) -> Self {
+        self.sequence.store(sequence, Ordering::SeqCst);
+        self.logged_sequence = sequence;
+        self
+    }
+
+    /// Start the async writer background thread
     fn start_async_writer(&mut self) -> Result<()> {
-        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (tx, rx) = mpsc::channel();
         self.async_sender = Some(tx);
 
         let wal_dir = self.wal_dir.clone();
         let config = self.config.clone();
-        let handle = tokio::spawn(async move {
-            let mut current_file = None;
-            let mut write_buffer = Vec::new();
-
-            while let Some(request) = rx.recv().await {
-                match request {
-                    AsyncWriteRequest::Write(record) => {
-                        write_buffer.push(record);
-                        
-                        // Flush if buffer is full or sync is requested
-                        if write_buffer.len() >= 1000 {
-                            if let Err(e) = Self::flush_records(&mut current_file, &wal_dir, &config, &mut write_buffer).await {
-                                error!("Failed to flush WAL records: {}", e);
-                            }
-                        }
-                    }
-                    AsyncWriteRequest::Sync => {
-                        if let Err(e) = Self::flush_records(&mut current_file, &wal_dir, &config, &mut write_buffer).await {
-                            error!("Failed to sync WAL records: {}", e);
-                        }
-                    }
-                    AsyncWriteRequest::Shutdown => break,
-                }
-            }
-        });
+        let log_number = self.log_number;
+        let bytes_written = Arc::clone(&self.bytes_written);
+        let handle = std::thread::Builder::new()
+            .name("auradb-wal".to_string())
+            .spawn(move || AsyncWalWriter::new(wal_dir, config, log_number, bytes_written).run(rx))?;
 
         self.background_handle = Some(handle);
         Ok(())
     }
 
-    /// Flush records to WAL file (async helper)
-    async fn flush_records(
-        current_file: &mut Option<WalFile>,
-        wal_dir: &PathBuf,
-        config: &WalConfig,
-        records: &mut Vec<WalRecord>,
-    ) -> Result<()> {
-        if records.is_empty() {
-            return Ok(());
-        }
+    /// Allocate the next sequence number. Callers hold the writer across
+    /// allocation and [`write_record`](Self::write_record), so records
+    /// reach the log in sequence order.
+    pub fn next_sequence(&mut self) -> u64 {
+        self.reserve_sequences(1)
+    }
 
-        // Ensure we have a current file
-        if current_file.is_none() {
-            *current_file = Some(WalFile::new(wal_dir, config)?);
-        }
+    /// Allocate `count` consecutive sequence numbers at once, returning the
+    /// first, as for [`next_sequence`](Self::next_sequence)
+    pub fn reserve_sequences(&mut self, count: u64) -> u64 {
+        self.sequence.fetch_add(count, Ordering::SeqCst) + 1
+    }
 
-        let file = current_file.as_mut().unwrap();
-        
-        // Write all records
-        for record in records.drain(..) {
-            file.write_record(&record)?;
-        }
-
-        // Sync based on policy
-        match config.sync_policy {
-            WalSyncPolicy::EveryWrite => file.sync()?,
-            WalSyncPolicy::EveryNWrites(n) if file.record_count() % n == 0 => file.sync()?,
-            WalSyncPolicy::EveryNMs(ms) => {
-                // This is simplified - in practice you'd want more sophisticated timing
-                time::sleep(Duration::from_millis(ms)).await;
-                file.sync()?;
-            }
-            _ => {}
+    /// Fsyncs shared by writers, for waiting on a record's durability
+    /// without holding the writer
+    pub fn group_commit(&self) -> Arc<GroupCommit> {
+        Arc::clone(&self.commit)
+    }
+
+    /// Sync later writes according to `policy`
+    pub fn set_sync_policy(&mut self, policy: WalSyncPolicy) {
+        if let Some(sender) = &self.async_sender {
+            let _ = sender.send(AsyncWriteRequest::SetSyncPolicy(policy.clone()));
         }
+        self.config.sync_policy = policy;
+    }
 
-        Ok(())
+    /// Make later sequence numbers sort after `sequence`
+    pub fn advance_sequence(&mut self, sequence: u64) {
+        self.sequence.fetch_max(sequence, Ordering::SeqCst);
     }
 
-    /// Write a record to the WAL
+    /// Write a record to the WAL, returning its sequence number. Records
+    /// must arrive in sequence order; one below the last record written is
+    /// rejected rather than persisted out of order. A record the sync
+    /// policy wants on disk is handed to the OS here and fsynced by
+    /// [`GroupCommit::wait`], which the writer calls once it has let go
+    /// of the `WalWriter`.
     pub fn write_record(&mut self, record: &WalRecord) -> Result<u64> {
-        let sequence = self.sequence.fetch_add(1, Ordering::SeqCst);
+        let sequence = record.sequence();
+        if sequence < self.logged_sequence {
+            return Err(Error::Config(format!(
+                "WAL record sequence {} is below the last logged sequence {}",
+                sequence, self.logged_sequence
+            )));
+        }
+        self.logged_sequence = sequence;
+        self.sequence.fetch_max(sequence, Ordering::SeqCst);
 
+        if self.in_memory {
+            return Ok(sequence);
+        }
         if self.config.async_writes {
             if let Some(sender) = &self.async_sender {
                 let _ = sender.send(AsyncWriteRequest::Write(record.clone()));
             }
         } else {
             self.ensure_current_file()?;
-            self.current_file.as_mut().unwrap().write_record(record)?;
-            
-            // Handle sync policy
-            match self.config.sync_policy {
-                WalSyncPolicy::EveryWrite => self.sync()?,
-                WalSyncPolicy::EveryNWrites(n) if sequence % n == 0 => self.sync()?,
-                _ => {}
+            let file = self.current_file.as_mut().unwrap();
+            file.write_record(record)?;
+            let written = file.record_count();
+
+            // Handle sync policy, writing records out when a group flush
+            // limit is reached otherwise
+            let sync = match self.config.sync_policy {
+                WalSyncPolicy::EveryWrite => true,
+                WalSyncPolicy::EveryNWrites(n) => written.is_multiple_of(n.max(1)),
+                _ => false,
+            };
+            if sync || file.flush_due() {
+                file.flush()?;
+                self.commit.written(sequence, sync);
             }
         }
 
         Ok(sequence)
     }
 
-    /// Write a batch of operations
+    /// Write a batch of operations as one record. The entries carry their
+    /// own sequence numbers, in order; the batch takes that of the last.
     pub fn write_batch(&mut self, entries: &[Entry]) -> Result<u64> {
-        let sequence = self.sequence.fetch_add(1, Ordering::SeqCst);
-        
-        let records: Vec<WalRecord> = entries
-            .iter()
-            .map(|entry| {
-                if let Some(value) = &entry.value {
-                    WalRecord::Put {
-                        key: entry.key.data.clone(),
-                        value: value.data.clone(),
-                        sequence: entry.sequence,
-                        timestamp: entry.timestamp,
-                    }
-                } else if let Some(vptr) = &entry.value_pointer {
-                    WalRecord::PutPointer {
-                        key: entry.key.data.clone(),
-                        value_pointer: vptr.clone(),
-                        sequence: entry.sequence,
-                        timestamp: entry.timestamp,
-                    }
-                } else {
-                    WalRecord::Delete {
-                        key: entry.key.data.clone(),
-                        sequence: entry.sequence,
-                        timestamp: entry.timestamp,
-                    }
-                }
-            })
-            .collect();
+        let sequence = entries.last().map_or(self.logged_sequence, |entry| entry.sequence);
+        if entries.windows(2).any(|pair| pair[1].sequence <= pair[0].sequence) {
+            return Err(Error::Config("WAL batch entries are not in sequence order".to_string()));
+        }
+
+        let records: Vec<WalRecord> = entries.iter().map(WalRecord::from_entry).collect();
 
         let batch_record = WalRecord::Batch {
             operations: records,
@@ -288,8 +504,7 @@ impl WalWriter {
                 .as_millis() as u64,
         };
 
-        self.write_record(&batch_record)?;
-        Ok(sequence)
+        self.write_record(&batch_record)
     }
 
     /// Ensure we have a current WAL file
@@ -300,6 +515,12 @@ impl WalWriter {
         Ok(())
     }
 
+    /// Total bytes written to WAL files, including file headers. With async
+    /// writes, records still buffered by the writer thread are not counted.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
+    }
+
     /// Check if we should rotate to a new WAL file
     fn should_rotate(&self) -> bool {
         if let Some(file) = &self.current_file {
@@ -315,48 +536,198 @@ impl WalWriter {
         if let Some(mut file) = self.current_file.take() {
             file.close()?;
         }
+        self.commit.synced(self.logged_sequence);
 
         // Create new file
-        let file = WalFile::new(&self.wal_dir, &self.config)?;
+        self.log_number += 1;
+        let file = WalFile::new(&self.wal_dir, &self.config, self.log_number, &self.bytes_written)?;
+        self.commit.switch_file(file.file.get_ref().try_clone()?);
         self.current_file = Some(file);
         
-        info!("Rotated to new WAL file");
+        info!("Rotated to new WAL file {}", self.log_number);
         Ok(())
     }
 
-    /// Sync the current WAL file
+    /// Start a new WAL file and return its log number. Records written
+    /// before the call live in files with lower log numbers.
+    pub fn rotate(&mut self) -> Result<u64> {
+        if self.config.async_writes || self.in_memory {
+            self.log_number += 1;
+            if let Some(sender) = &self.async_sender {
+                let _ = sender.send(AsyncWriteRequest::Rotate(self.log_number));
+            }
+        } else {
+            self.rotate_file()?;
+        }
+        Ok(self.log_number)
+    }
+
+    /// Log number of the file currently being written
+    pub fn log_number(&self) -> u64 {
+        self.log_number
+    }
+
+    /// Metadata of every WAL file on disk, in log number order, including
+    /// files left by earlier runs that haven't been retired yet. Only the
+    /// active file is open. With async writes, records still buffered by
+    /// the writer thread are not reflected.
+    pub fn list_segments(&mut self) -> Result<Vec<WalFileMeta>> {
+        if self.in_memory {
+            return Ok(Vec::new());
+        }
+        if let Some(file) = &mut self.current_file {
+            file.flush()?;
+        }
+        let mut logs = Vec::new();
+        for entry in std::fs::read_dir(&self.wal_dir)? {
+            let entry = entry?;
+            if let Some(number) = parse_log_number(&entry.file_name().to_string_lossy()) {
+                logs.push((number, entry.path()));
+            }
+        }
+        logs.sort();
+        logs.into_iter()
+            .map(|(number, path)| WalFileMeta::read(path, number != self.log_number))
+            .collect()
+    }
+
+    /// Current sequence, active file and WAL files on disk
+    pub fn info(&mut self) -> Result<WalInfo> {
+        let segments = self.list_segments()?;
+        Ok(WalInfo {
+            sequence: self.current_sequence(),
+            active_file: self.wal_dir.join(log_file_name(self.log_number)),
+            total_bytes: segments.iter().map(|s| s.size).sum(),
+            segments,
+        })
+    }
+
+    /// Flush buffered records and fsync the current WAL file, regardless
+    /// of the sync policy. In async mode this waits for the background
+    /// writer to drain every record queued before the call.
     pub fn sync(&mut self) -> Result<()> {
+        if let Some(sender) = &self.async_sender {
+            let (ack, done) = mpsc::channel();
+            sender
+                .send(AsyncWriteRequest::Sync(ack))
+                .map_err(|_| Error::Config("WAL writer is closed".to_string()))?;
+            return done
+                .recv()
+                .map_err(|_| Error::Config("WAL writer stopped before syncing".to_string()))?;
+        }
         if let Some(file) = &mut self.current_file {
             file.sync()?;
         }
+        self.commit.synced(self.logged_sequence);
         Ok(())
     }
 
-    /// Get the current sequence number
+    /// Have [`GroupCommit::wait`] fsync every record written so far,
+    /// whatever the sync policy. With async writes there is no one to
+    /// wait, so this syncs right away, like [`sync`](Self::sync).
+    pub fn request_sync(&mut self) -> Result<()> {
+        if self.async_sender.is_some() {
+            return self.sync();
+        }
+        if let Some(file) = &mut self.current_file {
+            file.flush()?;
+            self.commit.written(self.logged_sequence, true);
+        }
+        Ok(())
+    }
+
+    /// Last assigned sequence number
     pub fn current_sequence(&self) -> u64 {
         self.sequence.load(Ordering::SeqCst)
     }
 
-    /// Close the WAL writer
+    /// Close the WAL writer. In async mode this waits for the background
+    /// writer to write and fsync every queued record before returning.
+    /// Closing twice is a no-op.
     pub fn close(&mut self) -> Result<()> {
-        // Send shutdown signal to async writer
-        if let Some(sender) = &self.async_sender {
+        if let Some(sender) = self.async_sender.take() {
             let _ = sender.send(AsyncWriteRequest::Shutdown);
         }
-
-        // Wait for background task to finish
         if let Some(handle) = self.background_handle.take() {
-            // In a real implementation, you'd want to handle this more gracefully
-            let _ = std::panic::catch_unwind(|| {
-                // This is simplified - in practice you'd want proper shutdown coordination
-            });
+            handle
+                .join()
+                .map_err(|_| Error::Concurrency("WAL writer thread panicked".to_string()))??;
         }
 
-        // Close current file
         if let Some(mut file) = self.current_file.take() {
             file.close()?;
         }
+        Ok(())
+    }
+}
 
+/// WAL fsyncs shared between writers. A writer hands its record to the
+/// OS while holding the [`WalWriter`], then calls [`wait`](Self::wait)
+/// after letting go of it. One writer at a time runs an fsync, which
+/// covers every record handed over before it started; the writers that
+/// arrive meanwhile share the next one instead of syncing in turn.
+#[derive(Default)]
+pub struct GroupCommit {
+    /// What the next fsync covers
+    target: Mutex<SyncTarget>,
+    /// Held by the writer running an fsync
+    syncing: Mutex<()>,
+    /// Highest sequence number known to be on disk
+    synced: AtomicU64,
+}
+
+/// File the records are handed to and how far they go
+#[derive(Default)]
+struct SyncTarget {
+    /// Current WAL file, if any
+    file: Option<Arc<File>>,
+    /// Highest sequence number handed to the OS
+    written: u64,
+    /// Highest sequence number whose writer waits for an fsync
+    required: u64,
+}
+
+impl GroupCommit {
+    /// Note that the records up to `sequence` were handed to the OS, and
+    /// with `sync` that they must be fsynced before `wait` returns
+    fn written(&self, sequence: u64, sync: bool) {
+        let mut target = self.target.lock();
+        target.written = target.written.max(sequence);
+        if sync {
+            target.required = target.required.max(sequence);
+        }
+    }
+
+    /// Note that the records up to `sequence` are on disk
+    fn synced(&self, sequence: u64) {
+        self.synced.fetch_max(sequence, Ordering::AcqRel);
+    }
+
+    /// Direct later fsyncs at a new WAL file. The previous one was synced
+    /// when it was closed.
+    fn switch_file(&self, file: File) {
+        self.target.lock().file = Some(Arc::new(file));
+    }
+
+    /// Return once the record with `sequence` is on disk, if its write
+    /// asked for that, running the fsync unless another writer's covers it
+    pub fn wait(&self, sequence: u64) -> Result<()> {
+        if self.synced.load(Ordering::Acquire) >= sequence || self.target.lock().required < sequence {
+            return Ok(());
+        }
+        let _syncing = self.syncing.lock();
+        // The fsync that just finished may have covered this record
+        if self.synced.load(Ordering::Acquire) >= sequence {
+            return Ok(());
+        }
+        let (file, written) = {
+            let target = self.target.lock();
+            (target.file.clone(), target.written)
+        };
+        if let Some(file) = file {
+            file.sync_all()?;
+        }
+        self.synced(written);
         Ok(())
     }
 }
@@ -366,46 +737,212 @@ impl WalWriter {
 pub enum AsyncWriteRequest {
     /// Write a record
     Write(WalRecord),
-    /// Sync the current file
-    Sync,
+    /// Flush and fsync the current file, then report the result
+    Sync(mpsc::Sender<Result<()>>),
+    /// Close the current file and continue in the file with this log number
+    Rotate(u64),
+    /// Sync later writes according to this policy
+    SetSyncPolicy(WalSyncPolicy),
     /// Shutdown the async writer
     Shutdown,
 }
 
-/// Individual WAL file
+/// State owned by the async writer thread
+struct AsyncWalWriter {
+    wal_dir: PathBuf,
+    config: WalConfig,
+    log_number: u64,
+    current_file: Option<WalFile>,
+    last_sync: Instant,
+    bytes_written: Arc<AtomicU64>,
+}
+
+impl AsyncWalWriter {
+    fn new(wal_dir: PathBuf, config: WalConfig, log_number: u64, bytes_written: Arc<AtomicU64>) -> Self {
+        Self {
+            wal_dir,
+            config,
+            log_number,
+            current_file: None,
+            last_sync: Instant::now(),
+            bytes_written,
+        }
+    }
+
+    /// Process requests until shutdown. Buffered records are written out
+    /// once a group flush limit is reached or the oldest has waited the
+    /// flush interval; on shutdown everything still queued is written and
+    /// fsynced.
+    fn run(mut self, rx: mpsc::Receiver<AsyncWriteRequest>) -> Result<()> {
+        loop {
+            let received = match self.current_file.as_ref().and_then(WalFile::flush_deadline) {
+                Some(deadline) => rx.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            };
+            let request = match received {
+                Ok(request) => request,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if let Err(e) = self.flush_records() {
+                        error!("Failed to flush WAL records: {}", e);
+                    }
+                    continue;
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => return self.close(),
+            };
+
+            match request {
+                AsyncWriteRequest::Write(record) => {
+                    if let Err(e) = self.write(&record) {
+                        error!("Failed to write WAL record: {}", e);
+                    }
+                }
+                AsyncWriteRequest::Sync(ack) => {
+                    let _ = ack.send(self.sync());
+                }
+                AsyncWriteRequest::Rotate(next) => {
+                    // Pending records belong to the file being retired
+                    if let Err(e) = self.flush_records() {
+                        error!("Failed to flush WAL records before rotation: {}", e);
+                    }
+                    if let Some(mut file) = self.current_file.take() {
+                        if let Err(e) = file.close() {
+                            error!("Failed to close WAL file: {}", e);
+                        }
+                    }
+                    self.log_number = next;
+                }
+                AsyncWriteRequest::SetSyncPolicy(policy) => self.config.sync_policy = policy,
+                AsyncWriteRequest::Shutdown => return self.close(),
+            }
+        }
+    }
+
+    /// Write and fsync the buffered records and close the current file
+    fn close(&mut self) -> Result<()> {
+        self.flush_records()?;
+        if let Some(mut file) = self.current_file.take() {
+            file.close()?;
+        }
+        Ok(())
+    }
+
+    /// Buffer a record in the current file, creating it if needed, and
+    /// write the buffer out if that makes it due
+    fn write(&mut self, record: &WalRecord) -> Result<()> {
+        if self.current_file.is_none() {
+            self.current_file = Some(WalFile::new(&self.wal_dir, &self.config, self.log_number, &self.bytes_written)?);
+        }
+        let file = self.current_file.as_mut().unwrap();
+        file.write_record(record)?;
+        if file.flush_due() {
+            self.flush_records()?;
+        }
+        Ok(())
+    }
+
+    /// Write buffered records out, syncing according to the policy
+    fn flush_records(&mut self) -> Result<()> {
+        let Some(file) = self.current_file.as_mut() else {
+            return Ok(());
+        };
+        if file.pending_records == 0 {
+            return Ok(());
+        }
+        let before = file.record_count() - file.pending_records as u64;
+
+        let should_sync = match self.config.sync_policy {
+            WalSyncPolicy::EveryWrite => true,
+            WalSyncPolicy::EveryNWrites(n) => before / n.max(1) != file.record_count() / n.max(1),
+            WalSyncPolicy::EveryNMs(ms) => self.last_sync.elapsed() >= Duration::from_millis(ms),
+            WalSyncPolicy::Manual => false,
+        };
+        if should_sync {
+            file.sync()?;
+            self.last_sync = Instant::now();
+        } else {
+            file.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write buffered records and fsync unconditionally
+    fn sync(&mut self) -> Result<()> {
+        self.flush_records()?;
+        if let Some(file) = &mut self.current_file {
+            file.sync()?;
+            self.last_sync = Instant::now();
+        }
+        Ok(())
+    }
+}
+
+/// When a WAL file's buffered records are written out, from the
+/// `group_flush_*` settings
+#[derive(Debug, Clone, Copy)]
+struct GroupFlush {
+    records: usize,
+    bytes: usize,
+    interval: Duration,
+}
+
+impl GroupFlush {
+    fn from_config(config: &WalConfig) -> Self {
+        Self {
+            records: config.group_flush_records,
+            bytes: config.group_flush_bytes,
+            interval: Duration::from_millis(config.group_flush_interval_ms),
+        }
+    }
+}
+
+/// Individual WAL file. Each record is written as a 4-byte length, a
+/// compression tag byte and the (possibly compressed) encoded record; the
+/// length covers the tag and payload.
 struct WalFile {
     /// File handle
     file: BufWriter<File>,
+    /// Algorithm for payloads of at least `compression_threshold` bytes
+    compression: Option<CompressionAlgorithm>,
+    /// Smallest payload worth compressing
+    compression_threshold: usize,
+    /// Algorithm of the record checksums, recorded in the header
+    checksum: ChecksumType,
     /// File metadata
     meta: WalFileMeta,
     /// Record count
     record_count: u64,
+    /// Writer-wide count of bytes written
+    bytes_written: Arc<AtomicU64>,
+    /// Limits for writing buffered records out
+    group: GroupFlush,
+    /// Records buffered since the last write-out
+    pending_records: usize,
+    /// Bytes buffered since the last write-out
+    pending_bytes: usize,
+    /// When the oldest buffered record was written, if any is buffered
+    pending_since: Option<Instant>,
 }
 
 impl WalFile {
     /// Create a new WAL file
-    fn new(wal_dir: &PathBuf, config: &WalConfig) -> Result<Self> {
+    fn new(wal_dir: &Path, config: &WalConfig, log_number: u64, bytes_written: &Arc<AtomicU64>) -> Result<Self> {
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis() as u64;
 
-        let filename = format!("wal_{:016x}.log", timestamp);
-        let path = wal_dir.join(filename);
-
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .append(true)
-            .open(&path)?;
+        let path = wal_dir.join(log_file_name(log_number));
+        let file = Self::open_file(wal_dir, &path, config)?;
 
-        let mut buf_writer = BufWriter::with_capacity(config.buffer_size, file);
+        let mut buf_writer = BufWriter::with_capacity(config.buffer_size.max(config.group_flush_bytes), file);
 
         // Write header
-        let header = WalHeader::new();
+        let header = WalHeader::new(config.checksum);
         let header_bytes = bincode::serialize(&header)?;
         buf_writer.write_all(&header_bytes)?;
         buf_writer.flush()?;
+        io::sync_dir(wal_dir)?;
+        bytes_written.fetch_add(header_bytes.len() as u64, Ordering::Relaxed);
 
         let meta = WalFileMeta {
             path: path.clone(),
@@ -418,29 +955,114 @@ impl WalFile {
 
         Ok(Self {
             file: buf_writer,
+            compression: config.compress_records,
+            compression_threshold: config.compression_threshold,
+            checksum: config.checksum,
             meta,
             record_count: 0,
+            bytes_written: Arc::clone(bytes_written),
+            group: GroupFlush::from_config(config),
+            pending_records: 0,
+            pending_bytes: 0,
+            pending_since: None,
         })
     }
 
-    /// Write a record to the file
+    /// Open the file for a new log, reusing a recycled file when enabled.
+    /// A recycled file is emptied so stale records can't be replayed, but
+    /// keeps its inode, avoiding a create/unlink pair per rotation.
+    fn open_file(wal_dir: &Path, path: &Path, config: &WalConfig) -> Result<File> {
+        let recycled = if config.recycle_wal {
+            recycled_logs(wal_dir)?.into_iter().next()
+        } else {
+            None
+        };
+
+        let file = match recycled {
+            Some(old) => {
+                std::fs::rename(&old, path)?;
+                let file = OpenOptions::new().append(true).open(path)?;
+                file.set_len(0)?;
+                file
+            }
+            None => OpenOptions::new().create(true).append(true).open(path)?,
+        };
+
+        if config.preallocate {
+            if let Err(e) = io::preallocate(&file, config.max_file_size) {
+                warn!("Failed to preallocate WAL file {}: {}", path.display(), e);
+            }
+        }
+        Ok(file)
+    }
+
+    /// Write a record to the file, compressed if it is large enough and
+    /// compression makes it smaller
     fn write_record(&mut self, record: &WalRecord) -> Result<()> {
         let record_bytes = bincode::serialize(record)?;
-        let record_len = record_bytes.len() as u32;
-        
-        // Write record length and data
+        let (algo, payload) = match self.compression {
+            Some(algo) if algo != CompressionAlgorithm::None && record_bytes.len() >= self.compression_threshold => {
+                let compressed = compression::compress(&record_bytes, algo)?;
+                if compressed.len() < record_bytes.len() {
+                    (algo, compressed)
+                } else {
+                    (CompressionAlgorithm::None, record_bytes)
+                }
+            }
+            _ => (CompressionAlgorithm::None, record_bytes),
+        };
+        let record_len = 1 + payload.len() as u32;
+        let mut hasher = checksum::Hasher::new(self.checksum);
+        hasher.update(&[algo.as_u8()]);
+        hasher.update(&payload);
+
+        // Write record length, checksum, compression tag and data
         self.file.write_all(&record_len.to_le_bytes())?;
-        self.file.write_all(&record_bytes)?;
-        
-        self.meta.size += 4 + record_bytes.len() as u64;
+        self.file.write_all(&hasher.finish().to_le_bytes())?;
+        self.file.write_all(&[algo.as_u8()])?;
+        self.file.write_all(&payload)?;
+
+        let size = RECORD_HEADER_LEN as u64 + record_len as u64;
+        self.meta.size += size;
+        if self.record_count == 0 {
+            self.meta.first_sequence = record.sequence();
+        }
+        self.meta.last_sequence = record.sequence();
         self.record_count += 1;
-        
+        self.bytes_written.fetch_add(size, Ordering::Relaxed);
+        self.pending_records += 1;
+        self.pending_bytes += size as usize;
+        self.pending_since.get_or_insert_with(Instant::now);
+
+        Ok(())
+    }
+
+    /// When the buffered records must be written out at the latest, if any
+    /// are buffered
+    fn flush_deadline(&self) -> Option<Instant> {
+        self.pending_since.map(|since| since + self.group.interval)
+    }
+
+    /// Whether the buffered records are due to be written out: either group
+    /// flush limit is reached or the oldest has waited the flush interval
+    fn flush_due(&self) -> bool {
+        self.pending_records >= self.group.records
+            || self.pending_bytes >= self.group.bytes
+            || self.flush_deadline().is_some_and(|deadline| deadline <= Instant::now())
+    }
+
+    /// Hand buffered bytes to the OS without fsyncing
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()?;
+        self.pending_records = 0;
+        self.pending_bytes = 0;
+        self.pending_since = None;
         Ok(())
     }
 
     /// Sync the file to disk
     fn sync(&mut self) -> Result<()> {
-        self.file.flush()?;
+        self.flush()?;
         self.file.get_ref().sync_all()?;
         Ok(())
     }
@@ -463,10 +1085,42 @@ impl WalFile {
     }
 }
 
+/// How much of a WAL file is intact; see [`check_log`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogCheck {
+    /// Records before the first damaged one
+    pub records: u64,
+    /// Length of the file up to the end of those records
+    pub valid_len: u64,
+    /// The first record that does not decode; nothing after it is read
+    pub damage: Option<Corruption>,
+}
+
+/// Read the WAL file at `path` up to its first damaged record. A torn
+/// final record ends the file, as in recovery, and is not damage. Fails if
+/// the file header is invalid.
+pub fn check_log(path: &Path) -> Result<LogCheck> {
+    let mut reader = WalFileReader::new(path.to_path_buf())?;
+    let mut records = 0;
+    loop {
+        let offset = reader.offset;
+        match reader.read_record() {
+            Ok(Some(_)) => records += 1,
+            Ok(None) => return Ok(LogCheck { records, valid_len: reader.offset, damage: None }),
+            Err(Error::WalCorruption(message)) => {
+                return Ok(LogCheck {
+                    records,
+                    valid_len: offset,
+                    damage: Some(Corruption { path: path.to_path_buf(), offset, message }),
+                })
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 /// WAL reader for recovery
 pub struct WalReader {
-    /// WAL directory path
-    wal_dir: PathBuf,
     /// Current file being read
     current_file: Option<WalFileReader>,
     /// File list to read
@@ -479,21 +1133,31 @@ impl WalReader {
         let mut files: Vec<PathBuf> = std::fs::read_dir(&wal_dir)?
             .filter_map(|entry| entry.ok())
             .filter(|entry| {
-                entry.path().extension().map_or(false, |ext| ext == "log")
+                entry.path().extension().is_some_and(|ext| ext == "log")
             })
             .map(|entry| entry.path())
             .collect();
 
-        // Sort files by creation time (filename contains timestamp)
+        // Replay in log number order (zero-padded, so names sort numerically)
         files.sort();
 
         Ok(Self {
-            wal_dir,
             current_file: None,
             files: files.into(),
         })
     }
 
+    /// Leave out the files numbered below `log_number`, whose records are
+    /// already in SST files
+    pub fn skip_logs_before(mut self, log_number: u64) -> Self {
+        self.files.retain(|path| {
+            path.file_name()
+                .and_then(|name| parse_log_number(&name.to_string_lossy()))
+                .is_none_or(|number| number >= log_number)
+        });
+        self
+    }
+
     /// Read the next record from the WAL
     pub fn read_next(&mut self) -> Result<Option<WalRecord>> {
         loop {
@@ -521,39 +1185,144 @@ impl WalReader {
     }
 }
 
-/// WAL file reader for recovery
+/// WAL file reader for recovery.
+///
+/// A crash can leave the last record of a file half written. A file that
+/// ends inside a record's length prefix, checksum or body is treated as
+/// ending before that record: the partial bytes are discarded with a
+/// warning and reading moves on to the next file. Only complete records
+/// that fail their checksum or don't decode are reported as corruption.
 struct WalFileReader {
-    /// File handle
-    file: std::io::BufReader<File>,
+    /// File handle, or `None` once the end of the log has been reached
+    file: Option<std::io::BufReader<File>>,
     /// File path
     path: PathBuf,
+    /// Offset of the next record
+    offset: u64,
+    /// Creation time from the header, 0 if the header is torn
+    created_at: u64,
+    /// Whether records start with a compression tag
+    tagged: bool,
+    /// Algorithm of the record checksums, `None` for files from before
+    /// records carried one
+    checksum: Option<ChecksumType>,
 }
 
 impl WalFileReader {
-    /// Create a new WAL file reader
+    /// Create a new WAL file reader, validating the file header
     fn new(path: PathBuf) -> Result<Self> {
         let file = OpenOptions::new().read(true).open(&path)?;
-        let reader = std::io::BufReader::new(file);
+        let len = file.metadata()?.len();
+        let mut reader = std::io::BufReader::new(file);
+
+        let header: WalHeader = match bincode::deserialize_from(&mut reader) {
+            Ok(header) => header,
+            Err(e) if matches!(&*e, bincode::ErrorKind::Io(io) if io.kind() == std::io::ErrorKind::UnexpectedEof) => {
+                // Crashed while creating the file, before any record
+                warn!("Discarding {} bytes of torn header in WAL {}", len, path.display());
+                return Ok(Self { file: None, path, offset: len, created_at: 0, tagged: true, checksum: None });
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !header.validate() {
+            return Err(Error::WalCorruption(format!(
+                "Invalid WAL header in {}",
+                path.display()
+            )));
+        }
 
-        Ok(Self { file: reader, path })
+        let offset = bincode::serialized_size(&header)?;
+        Ok(Self {
+            file: Some(reader),
+            path,
+            offset,
+            created_at: header.created_at,
+            tagged: header.tags_records(),
+            checksum: header.record_checksum(),
+        })
     }
 
-    /// Read a record from the file
+    /// Read up to `len` bytes, fewer only at the end of the file
+    fn read_up_to(file: &mut std::io::BufReader<File>, len: usize) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(len.min(1 << 20));
+        file.take(len as u64).read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Stop reading at a record cut short by the end of the file
+    fn discard_torn(&mut self, bytes: usize) -> Result<Option<WalRecord>> {
+        warn!(
+            "Discarding {} bytes of torn record at offset {} at the end of WAL {}",
+            bytes,
+            self.offset,
+            self.path.display()
+        );
+        self.file = None;
+        Ok(None)
+    }
+
+    /// Read the next record from the file, or `None` at the end of the log
     fn read_record(&mut self) -> Result<Option<WalRecord>> {
+        let Some(file) = &mut self.file else {
+            return Ok(None);
+        };
+
         // Read record length
-        let mut len_bytes = [0u8; 4];
-        if self.file.read_exact(&mut len_bytes).is_err() {
+        let len_bytes = Self::read_up_to(file, 4)?;
+        if len_bytes.is_empty() {
             return Ok(None); // End of file
         }
+        if len_bytes.len() < 4 {
+            return self.discard_torn(len_bytes.len());
+        }
+        let record_len = u32::from_le_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]]) as usize;
+        let expected = match self.checksum {
+            Some(_) => {
+                let checksum_bytes = Self::read_up_to(file, 4)?;
+                if checksum_bytes.len() < 4 {
+                    return self.discard_torn(4 + checksum_bytes.len());
+                }
+                Some(u32::from_le_bytes([checksum_bytes[0], checksum_bytes[1], checksum_bytes[2], checksum_bytes[3]]))
+            }
+            None => None,
+        };
+        let prefix_len = if expected.is_some() { RECORD_HEADER_LEN } else { 4 };
 
-        let record_len = u32::from_le_bytes(len_bytes) as usize;
-        
         // Read record data
-        let mut record_bytes = vec![0u8; record_len];
-        self.file.read_exact(&mut record_bytes)?;
-        
-        // Deserialize record
-        let record: WalRecord = bincode::deserialize(&record_bytes)?;
+        let record_bytes = Self::read_up_to(file, record_len)?;
+        if record_bytes.len() < record_len {
+            return self.discard_torn(prefix_len + record_bytes.len());
+        }
+        if let (Some(algo), Some(expected)) = (self.checksum, expected) {
+            if !checksum::verify(&record_bytes, expected, algo) {
+                return Err(Error::WalCorruption(format!(
+                    "Checksum mismatch in record at offset {} in {}",
+                    self.offset,
+                    self.path.display()
+                )));
+            }
+        }
+
+        // Decompress and deserialize record
+        let corrupt = |message: String| {
+            Error::WalCorruption(format!(
+                "Undecodable record at offset {} in {}: {}",
+                self.offset,
+                self.path.display(),
+                message
+            ))
+        };
+        let record_bytes = match (self.tagged, record_bytes.split_first()) {
+            (false, _) => record_bytes,
+            (true, None) => return Err(corrupt("missing compression tag".to_string())),
+            (true, Some((&tag, payload))) => {
+                let algo = CompressionAlgorithm::from_u8(tag)
+                    .ok_or_else(|| corrupt(format!("unknown compression tag {}", tag)))?;
+                compression::decompress(payload, algo).map_err(|e| corrupt(e.to_string()))?
+            }
+        };
+        let record: WalRecord = bincode::deserialize(&record_bytes).map_err(|e| corrupt(e.to_string()))?;
+        self.offset += (prefix_len + record_len) as u64;
         Ok(Some(record))
     }
 }
@@ -603,4 +1372,294 @@ mod tests {
             _ => panic!("Unexpected record type"),
         }
     }
+
+    #[test]
+    fn test_recycled_wal_files_are_reused() {
+        let temp_dir = tempdir().unwrap();
+        let config = WalConfig {
+            wal_path: temp_dir.path().to_path_buf(),
+            async_writes: false,
+            recycle_wal: true,
+            max_file_size: 1024 * 1024,
+            ..Default::default()
+        };
+        let put = |n: u64| WalRecord::Delete { key: vec![n as u8], sequence: n, timestamp: 0 };
+
+        let mut writer = WalWriter::new(config.clone()).unwrap();
+        writer.write_record(&put(1)).unwrap();
+        let next = writer.rotate().unwrap();
+        assert_eq!(retire_logs_before(&config, next).unwrap(), 1);
+        assert_eq!(recycled_logs(temp_dir.path()).unwrap().len(), 1);
+
+        // The next new log takes over the recycled file, emptied
+        writer.rotate().unwrap();
+        writer.write_record(&put(2)).unwrap();
+        writer.close().unwrap();
+        assert!(recycled_logs(temp_dir.path()).unwrap().is_empty());
+
+        let mut reader = WalReader::new(temp_dir.path().to_path_buf()).unwrap();
+        assert!(matches!(reader.read_next().unwrap(), Some(WalRecord::Delete { sequence: 2, .. })));
+        assert!(reader.read_next().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_close_flushes_queued_async_writes() {
+        let temp_dir = tempdir().unwrap();
+        let config = WalConfig {
+            wal_path: temp_dir.path().to_path_buf(),
+            async_writes: true,
+            sync_policy: WalSyncPolicy::Manual,
+            ..Default::default()
+        };
+
+        let mut writer = WalWriter::new(config).unwrap();
+        for n in 0..5000u64 {
+            writer.write_record(&WalRecord::Delete { key: n.to_le_bytes().to_vec(), sequence: n, timestamp: 0 }).unwrap();
+            if n == 2500 {
+                writer.rotate().unwrap();
+            }
+        }
+        writer.close().unwrap();
+        writer.close().unwrap();
+
+        let mut reader = WalReader::new(temp_dir.path().to_path_buf()).unwrap();
+        let mut sequences = Vec::new();
+        while let Some(record) = reader.read_next().unwrap() {
+            if let WalRecord::Delete { sequence, .. } = record {
+                sequences.push(sequence);
+            }
+        }
+        assert_eq!(sequences, (0..5000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_group_flush_limits() {
+        let on_disk = |dir: &Path| -> u64 {
+            std::fs::read_dir(dir).unwrap().map(|e| e.unwrap().metadata().unwrap().len()).sum()
+        };
+        let delete = |n: u64, len: usize| WalRecord::Delete { key: vec![n as u8; len], sequence: n, timestamp: 0 };
+        let config = |dir: &Path, async_writes| WalConfig {
+            wal_path: dir.to_path_buf(),
+            async_writes,
+            sync_policy: WalSyncPolicy::Manual,
+            preallocate: false,
+            group_flush_records: 3,
+            group_flush_bytes: 1024,
+            group_flush_interval_ms: 60_000,
+            ..Default::default()
+        };
+
+        // Records are written out on the third record, or once 1KB is held
+        let records_dir = tempdir().unwrap();
+        let mut writer = WalWriter::new(config(records_dir.path(), false)).unwrap();
+        let header = on_disk(records_dir.path());
+        writer.write_record(&delete(1, 8)).unwrap();
+        writer.write_record(&delete(2, 8)).unwrap();
+        assert_eq!(on_disk(records_dir.path()), header);
+        writer.write_record(&delete(3, 8)).unwrap();
+        assert!(on_disk(records_dir.path()) > header);
+        let written = on_disk(records_dir.path());
+        writer.write_record(&delete(4, 2048)).unwrap();
+        assert!(on_disk(records_dir.path()) > written + 2048);
+
+        // The async writer writes a lone record out after the interval
+        let interval_dir = tempdir().unwrap();
+        let mut writer = WalWriter::new(WalConfig {
+            group_flush_interval_ms: 20,
+            ..config(interval_dir.path(), true)
+        })
+        .unwrap();
+        writer.write_record(&delete(1, 8)).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while WalReader::new(interval_dir.path().to_path_buf()).unwrap().read_next().unwrap().is_none() {
+            assert!(Instant::now() < deadline, "record was never written out");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn test_torn_tail_ends_log() {
+        let temp_dir = tempdir().unwrap();
+        let config = WalConfig {
+            wal_path: temp_dir.path().to_path_buf(),
+            async_writes: false,
+            ..Default::default()
+        };
+        let delete = |n: u64| WalRecord::Delete { key: vec![n as u8; 8], sequence: n, timestamp: 0 };
+        let mut writer = WalWriter::new(config.clone()).unwrap();
+        for n in 0..3 {
+            writer.write_record(&delete(n)).unwrap();
+        }
+        writer.close().unwrap();
+        let path = temp_dir.path().join(log_file_name(1));
+        let len = std::fs::metadata(&path).unwrap().len();
+
+        let sequences = || {
+            let mut reader = WalReader::new(temp_dir.path().to_path_buf()).unwrap();
+            let mut sequences = Vec::new();
+            while let Some(record) = reader.read_next().unwrap() {
+                if let WalRecord::Delete { sequence, .. } = record {
+                    sequences.push(sequence);
+                }
+            }
+            sequences
+        };
+
+        // Cut inside the last record's body, then inside its length prefix
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(len - 3).unwrap();
+        assert_eq!(sequences(), vec![0, 1]);
+        // Length prefix, checksum, compression tag and record
+        let record_len = RECORD_HEADER_LEN as u64 + 1 + bincode::serialized_size(&delete(2)).unwrap();
+        file.set_len(len - record_len + 2).unwrap();
+        assert_eq!(sequences(), vec![0, 1]);
+
+        // A complete record that doesn't decode is still corruption
+        file.set_len(len - record_len).unwrap();
+        let mut garbage = OpenOptions::new().append(true).open(&path).unwrap();
+        garbage.write_all(&4u32.to_le_bytes()).unwrap();
+        garbage.write_all(&checksum::checksum(&[0xff; 4], ChecksumType::Crc32).to_le_bytes()).unwrap();
+        garbage.write_all(&[0xff; 4]).unwrap();
+        let mut reader = WalReader::new(temp_dir.path().to_path_buf()).unwrap();
+        reader.read_next().unwrap();
+        reader.read_next().unwrap();
+        assert!(matches!(reader.read_next(), Err(Error::WalCorruption(_))));
+    }
+
+    #[test]
+    fn test_flipped_byte_fails_record_checksum() {
+        let temp_dir = tempdir().unwrap();
+        let config = WalConfig {
+            wal_path: temp_dir.path().to_path_buf(),
+            async_writes: false,
+            ..Default::default()
+        };
+        let put = |n: u64| WalRecord::Put { key: vec![n as u8], value: vec![b'v'; 32], sequence: n, timestamp: 0 };
+        let mut writer = WalWriter::new(config).unwrap();
+        for n in 1..=3 {
+            writer.write_record(&put(n)).unwrap();
+        }
+        writer.close().unwrap();
+
+        // Flip the last value byte of the second record
+        let path = temp_dir.path().join(log_file_name(1));
+        let header_len = bincode::serialized_size(&WalHeader::default()).unwrap();
+        let record_len = RECORD_HEADER_LEN as u64 + 1 + bincode::serialized_size(&put(1)).unwrap();
+        let second = header_len + record_len;
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[(second + record_len - 1) as usize] ^= 0x01;
+        std::fs::write(&path, bytes).unwrap();
+
+        let mut reader = WalReader::new(temp_dir.path().to_path_buf()).unwrap();
+        assert_eq!(reader.read_next().unwrap().map(|r| r.sequence()), Some(1));
+        let Err(Error::WalCorruption(message)) = reader.read_next() else {
+            panic!("expected a checksum mismatch");
+        };
+        assert!(message.contains(&format!("offset {}", second)) && message.contains(&path.display().to_string()));
+        let check = check_log(&path).unwrap();
+        assert_eq!((check.records, check.valid_len), (1, second));
+        assert_eq!(check.damage.unwrap().offset, second);
+    }
+
+    #[test]
+    fn test_compressed_records_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let config = WalConfig {
+            wal_path: temp_dir.path().to_path_buf(),
+            async_writes: false,
+            compress_records: Some(CompressionAlgorithm::Lz4),
+            ..Default::default()
+        };
+        let put = |n: u64, len: usize| WalRecord::Put { key: vec![n as u8], value: vec![b'x'; len], sequence: n, timestamp: 0 };
+        let mut writer = WalWriter::new(config).unwrap();
+        // Compressible, below the threshold, and incompressible
+        writer.write_record(&put(1, 64 * 1024)).unwrap();
+        writer.write_record(&put(2, 16)).unwrap();
+        let noise: Vec<u8> = (0..4096u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        writer.write_record(&WalRecord::Put { key: vec![3], value: noise.clone(), sequence: 3, timestamp: 0 }).unwrap();
+        writer.close().unwrap();
+        assert!(writer.bytes_written() < 16 * 1024);
+
+        let mut reader = WalReader::new(temp_dir.path().to_path_buf()).unwrap();
+        let mut values = Vec::new();
+        while let Some(record) = reader.read_next().unwrap() {
+            if let WalRecord::Put { value, .. } = record {
+                values.push(value);
+            }
+        }
+        assert_eq!(values, vec![vec![b'x'; 64 * 1024], vec![b'x'; 16], noise]);
+    }
+
+    #[test]
+    fn test_sequences_follow_write_order() {
+        let temp_dir = tempdir().unwrap();
+        let config = WalConfig {
+            wal_path: temp_dir.path().to_path_buf(),
+            async_writes: false,
+            ..Default::default()
+        };
+        let mut writer = WalWriter::new(config).unwrap().with_last_sequence(10);
+        let sequence = writer.next_sequence();
+        assert_eq!(sequence, 11);
+        writer.write_record(&WalRecord::Delete { key: b"a".to_vec(), sequence, timestamp: 0 }).unwrap();
+
+        let entries: Vec<_> = (0..3)
+            .map(|_| Entry::delete(crate::storage::Key::from("b"), writer.next_sequence()))
+            .collect();
+        assert_eq!(writer.write_batch(&entries).unwrap(), 14);
+        let meta = &writer.current_file.as_ref().unwrap().meta;
+        assert_eq!((meta.first_sequence, meta.last_sequence), (11, 14));
+
+        // A record numbered before the last one is not persisted
+        let stale = WalRecord::Delete { key: b"c".to_vec(), sequence: 12, timestamp: 0 };
+        assert!(matches!(writer.write_record(&stale), Err(Error::Config(_))));
+        writer.close().unwrap();
+
+        let mut reader = WalReader::new(temp_dir.path().to_path_buf()).unwrap();
+        let mut sequences = Vec::new();
+        while let Some(record) = reader.read_next().unwrap() {
+            sequences.push(record.sequence());
+        }
+        assert_eq!(sequences, vec![11, 14]);
+    }
+
+    #[test]
+    fn test_group_commit_syncs_records_that_ask_for_it() {
+        let temp_dir = tempdir().unwrap();
+        let config = WalConfig {
+            wal_path: temp_dir.path().to_path_buf(),
+            async_writes: false,
+            sync_policy: WalSyncPolicy::EveryNWrites(2),
+            ..Default::default()
+        };
+        let mut writer = WalWriter::new(config).unwrap();
+        let commit = writer.group_commit();
+        let write = |writer: &mut WalWriter| {
+            let sequence = writer.next_sequence();
+            writer.write_record(&WalRecord::Delete { key: b"a".to_vec(), sequence, timestamp: 0 }).unwrap()
+        };
+
+        // The first record needs no fsync, the second one's covers both
+        let first = write(&mut writer);
+        commit.wait(first).unwrap();
+        assert_eq!(commit.synced.load(Ordering::Acquire), 0);
+        let second = write(&mut writer);
+        commit.wait(second).unwrap();
+        assert_eq!(commit.synced.load(Ordering::Acquire), second);
+
+        // Waiting needs no access to the writer, which takes more records
+        let third = write(&mut writer);
+        writer.request_sync().unwrap();
+        let waiter = {
+            let commit = Arc::clone(&commit);
+            std::thread::spawn(move || commit.wait(third))
+        };
+        let fourth = write(&mut writer);
+        waiter.join().unwrap().unwrap();
+        assert!(commit.synced.load(Ordering::Acquire) >= third);
+        commit.wait(fourth).unwrap();
+        assert_eq!(commit.synced.load(Ordering::Acquire), fourth);
+        writer.close().unwrap();
+    }
 }